@@ -1,6 +1,6 @@
 //! Basic command execution example
 
-use expectrust::{Pattern, Session};
+use expectrust::{cmd, Pattern, Session};
 use std::time::Duration;
 
 #[tokio::main]
@@ -9,14 +9,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", "=".repeat(50));
 
     // Spawn a simple command
-    let mut session =
-        Session::builder()
-            .timeout(Duration::from_secs(5))
-            .spawn(if cfg!(windows) {
-                "cmd /C echo Hello from ExpectRust!"
-            } else {
-                "echo Hello from ExpectRust!"
-            })?;
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&cmd::echo("Hello from ExpectRust!"))?;
 
     // Wait for the output to contain "Hello"
     let result = session.expect(Pattern::exact("Hello")).await?;
@@ -1,6 +1,6 @@
 //! Pattern matching example demonstrating different pattern types
 
-use expectrust::{Pattern, Session};
+use expectrust::{cmd, Pattern, Session};
 use std::time::Duration;
 
 #[tokio::main]
@@ -10,28 +10,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 1: Exact string matching
     println!("\n1. Exact String Matching");
-    let mut session =
-        Session::builder()
-            .timeout(Duration::from_secs(5))
-            .spawn(if cfg!(windows) {
-                "cmd /C echo Pattern: SUCCESS"
-            } else {
-                "echo Pattern: SUCCESS"
-            })?;
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&cmd::echo("Pattern: SUCCESS"))?;
 
     let result = session.expect(Pattern::exact("SUCCESS")).await?;
     println!("   ✓ Found exact match: '{}'", result.matched);
 
     // Example 2: Regex matching
     println!("\n2. Regex Pattern Matching");
-    let mut session2 =
-        Session::builder()
-            .timeout(Duration::from_secs(5))
-            .spawn(if cfg!(windows) {
-                "cmd /C echo Number: 12345"
-            } else {
-                "echo Number: 12345"
-            })?;
+    let mut session2 = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&cmd::echo("Number: 12345"))?;
 
     let result = session2.expect(Pattern::regex(r"\d+")?).await?;
     println!("   ✓ Found regex match: '{}'", result.matched);
@@ -41,14 +31,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 3: Multiple patterns (first match wins)
     println!("\n3. Multiple Pattern Matching");
-    let mut session3 =
-        Session::builder()
-            .timeout(Duration::from_secs(5))
-            .spawn(if cfg!(windows) {
-                "cmd /C echo Status: OK"
-            } else {
-                "echo Status: OK"
-            })?;
+    let mut session3 = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&cmd::echo("Status: OK"))?;
 
     let patterns = [
         Pattern::exact("ERROR"),
@@ -1,9 +1,21 @@
 //! SSH automation example demonstrating password authentication,
 //! privilege escalation, and error handling
 
-use expectrust::{Pattern, Session};
+use expectrust::{Pattern, Session, TypedPatterns};
 use std::time::Duration;
 
+/// Outcomes of connecting with SSH
+#[derive(Clone, Copy)]
+enum SshPrompt {
+    Password,
+    HostKeyFailed,
+    Denied,
+    Refused,
+    NoRoute,
+    UnknownHost,
+    Timeout,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("ExpectRust - SSH Automation Example");
@@ -25,22 +37,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Step 2: Handle SSH connection - watch for errors or password prompt
     println!("[2] Waiting for SSH prompt or errors...");
-    let ssh_patterns = [
-        Pattern::regex(r"[Pp]assword:")?, // Password prompt (index 0)
-        Pattern::exact("Host key verification failed"), // SSH error (index 1)
-        Pattern::exact("Permission denied"), // Auth error (index 2)
-        Pattern::exact("Connection refused"), // Connection error (index 3)
-        Pattern::exact("No route to host"), // Network error (index 4)
-        Pattern::regex(r"Could not resolve hostname")?, // DNS error (index 5)
-        Pattern::Timeout,                 // Timeout (index 6)
-    ];
-
-    let result = session.expect_any(&ssh_patterns).await?;
-    match result.pattern_index {
-        0 => {
+    let ssh_patterns = TypedPatterns::new()
+        .with(SshPrompt::Password, Pattern::regex(r"[Pp]assword:")?)
+        .with(
+            SshPrompt::HostKeyFailed,
+            Pattern::exact("Host key verification failed"),
+        )
+        .with(SshPrompt::Denied, Pattern::exact("Permission denied"))
+        .with(SshPrompt::Refused, Pattern::exact("Connection refused"))
+        .with(SshPrompt::NoRoute, Pattern::exact("No route to host"))
+        .with(
+            SshPrompt::UnknownHost,
+            Pattern::regex(r"Could not resolve hostname")?,
+        )
+        .with(SshPrompt::Timeout, Pattern::Timeout);
+
+    let (prompt, _) = session.expect_typed(&ssh_patterns).await?;
+    match prompt {
+        SshPrompt::Password => {
             println!("   ✓ Got password prompt");
         }
-        1 => {
+        SshPrompt::HostKeyFailed => {
             eprintln!("   ✗ ERROR: Host key verification failed");
             eprintln!(
                 "   Hint: Run 'ssh-keyscan {} >> ~/.ssh/known_hosts'",
@@ -48,27 +65,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             );
             return Err("SSH connection failed".into());
         }
-        2 => {
+        SshPrompt::Denied => {
             eprintln!("   ✗ ERROR: Permission denied");
             return Err("SSH authentication failed".into());
         }
-        3 => {
+        SshPrompt::Refused => {
             eprintln!("   ✗ ERROR: Connection refused");
             return Err("SSH server not responding".into());
         }
-        4 => {
+        SshPrompt::NoRoute => {
             eprintln!("   ✗ ERROR: No route to host");
             return Err("Network unreachable".into());
         }
-        5 => {
+        SshPrompt::UnknownHost => {
             eprintln!("   ✗ ERROR: Could not resolve hostname");
             return Err("DNS resolution failed".into());
         }
-        6 => {
+        SshPrompt::Timeout => {
             eprintln!("   ✗ ERROR: Connection timeout");
             return Err("SSH connection timed out".into());
         }
-        _ => unreachable!(),
     }
 
     // Step 3: Send user password
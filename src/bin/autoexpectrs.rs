@@ -0,0 +1,51 @@
+//! CLI tool that records an interactive session and generates replay scripts.
+
+use clap::Parser;
+use expectrust::Session;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "autoexpectrs")]
+#[command(author, version, about = "Record an interactive session as an Expect script and Rust program", long_about = None)]
+struct Args {
+    /// Command to run and record
+    #[arg(required = true, trailing_var_arg = true)]
+    command: Vec<String>,
+
+    /// Output file stem; writes `<stem>.exp` and `<stem>.rs`
+    #[arg(short, long, default_value = "script")]
+    output: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let (program, program_args) = args.command.split_first().expect("command is required");
+
+    let mut session = Session::builder()
+        .no_timeout()
+        .spawn_args(program, program_args)?;
+
+    eprintln!(
+        "autoexpectrs started, recording '{}'",
+        args.command.join(" ")
+    );
+    eprintln!("Exit the program or press Ctrl-] to finish.\n");
+    let recorder = session.teach().await?;
+
+    let exp_path = args.output.with_extension("exp");
+    let rs_path = args.output.with_extension("rs");
+    std::fs::write(&exp_path, recorder.to_expect_script(&args.command))?;
+    std::fs::write(&rs_path, recorder.to_rust(&args.command))?;
+
+    eprintln!();
+    eprintln!(
+        "✓ Recorded {} step(s) to {} and {}",
+        recorder.steps().len(),
+        exp_path.display(),
+        rs_path.display()
+    );
+    eprintln!("Review the prompt patterns before replaying: they are chosen heuristically.");
+
+    Ok(())
+}
@@ -0,0 +1,410 @@
+//! Cargo subcommand that scaffolds new automation projects.
+//!
+//! Installed as `cargo-expectrust`, it runs as `cargo expectrust new <name>`.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    /// ExpectRust project tools
+    #[command(author, version)]
+    Expectrust(Expectrust),
+}
+
+#[derive(clap::Args)]
+struct Expectrust {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a new automation project
+    New(NewArgs),
+}
+
+#[derive(clap::Args)]
+struct NewArgs {
+    /// Package name; the project is created in a directory of this name
+    name: String,
+
+    /// How the automation is written
+    #[arg(long, value_enum, default_value_t = FrontEnd::Rust)]
+    front_end: FrontEnd,
+
+    /// Parent directory of the project (default: current directory)
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FrontEnd {
+    /// Rust code using the Session API
+    Rust,
+    /// A classic Expect (Tcl) script run by the script interpreter
+    Tcl,
+    /// A TOML step file of expect/send pairs, the crate's playbook format
+    ///
+    /// Steps are written in TOML rather than YAML, which the crate does not
+    /// read.
+    #[value(alias = "playbook")]
+    Steps,
+}
+
+/// Program the generated examples automate: a login prompt that does not
+/// echo the password, standing in for ssh, a device console or an installer.
+const DEMO_COMMAND: &str =
+    "printf 'Password: '; stty -echo; read password; stty echo; echo; echo 'logged in'";
+
+/// Environment variable the secrets stub reads the password from.
+const SECRET_VAR: &str = "AUTOMATION_PASSWORD";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let Cargo::Expectrust(args) = Cargo::parse();
+    match args.command {
+        Command::New(args) => new_project(&args),
+    }
+}
+
+/// Create the project directory and its files.
+fn new_project(args: &NewArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !is_package_name(&args.name) {
+        return Err(format!(
+            "'{}' is not a valid package name: use letters, digits, '-' and '_', starting with a letter",
+            args.name
+        )
+        .into());
+    }
+
+    let dir = args
+        .path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(&args.name);
+    if dir.exists() {
+        return Err(format!("'{}' already exists", dir.display()).into());
+    }
+
+    for (path, contents) in project_files(&args.name, args.front_end) {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+    }
+
+    println!("✓ Created {}", dir.display());
+    println!("\nNext steps:");
+    println!("  cd {}", dir.display());
+    println!("  export {}=...", SECRET_VAR);
+    println!("  cargo run");
+    Ok(())
+}
+
+/// Whether `name` is usable as a Cargo package name.
+fn is_package_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The files of a new project, relative to its directory.
+fn project_files(name: &str, front_end: FrontEnd) -> Vec<(&'static Path, String)> {
+    let mut files = vec![
+        (Path::new("Cargo.toml"), cargo_toml(name, front_end)),
+        (Path::new(".gitignore"), "/target\n".to_string()),
+        (Path::new("README.md"), readme(name, front_end)),
+        (
+            Path::new(".github/workflows/automation.yml"),
+            WORKFLOW.to_string(),
+        ),
+    ];
+
+    match front_end {
+        FrontEnd::Rust => {
+            files.push((Path::new("src/main.rs"), RUST_MAIN.to_string()));
+            files.push((Path::new("src/secrets.rs"), secrets_rs()));
+        }
+        FrontEnd::Tcl => {
+            files.push((Path::new("src/main.rs"), TCL_MAIN.to_string()));
+            files.push((Path::new("src/secrets.rs"), secrets_rs()));
+            files.push((Path::new("automation.exp"), TCL_SCRIPT.to_string()));
+        }
+        FrontEnd::Steps => {
+            files.push((Path::new("src/main.rs"), STEPS_MAIN.to_string()));
+            files.push((Path::new("src/secrets.rs"), secrets_rs()));
+            files.push((Path::new("steps.toml"), STEPS_TOML.to_string()));
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|(path, contents)| (path, contents.replace("{demo}", DEMO_COMMAND)))
+        .map(|(path, contents)| (path, contents.replace("{secret}", SECRET_VAR)))
+        .collect()
+}
+
+fn cargo_toml(name: &str, front_end: FrontEnd) -> String {
+    let features = match front_end {
+        FrontEnd::Rust => "",
+        FrontEnd::Tcl => ", features = [\"script\"]",
+        FrontEnd::Steps => ", features = [\"steps\"]",
+    };
+    format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[dependencies]
+expectrust = {{ version = "{version}"{features} }}
+tokio = {{ version = "1", features = ["full"] }}
+"#,
+        version = env!("CARGO_PKG_VERSION"),
+    )
+}
+
+fn readme(name: &str, front_end: FrontEnd) -> String {
+    let (automation, secrets) = match front_end {
+        FrontEnd::Rust => (
+            "The automation is written in `src/main.rs` with the ExpectRust `Session` API.",
+            "Secrets are read from the environment by `src/secrets.rs`; set `{secret}` locally \
+             and as a repository secret for CI. Never commit them.",
+        ),
+        FrontEnd::Tcl => (
+            "The automation is the Expect script `automation.exp`, run by `src/main.rs`.",
+            "Secrets are read from the environment by `src/secrets.rs` and passed to the \
+             script as variables, so they never appear in `automation.exp`. Set `{secret}` \
+             locally and as a repository secret for CI. Never commit it.",
+        ),
+        FrontEnd::Steps => (
+            "The automation is the step file `steps.toml`, run by `src/main.rs`.",
+            "The password prompt is answered from `src/main.rs`, so the secret never appears in \
+             `steps.toml`. Set `{secret}` locally and as a repository secret for CI. Never \
+             commit it.",
+        ),
+    };
+    format!(
+        "# {name}\n\n{automation}\n\n## Running\n\n```sh\ncargo run\n```\n\n\
+         The GitHub Actions workflow in `.github/workflows/automation.yml` runs the \
+         automation on every push.\n\n## Secrets\n\n{secrets}\n"
+    )
+}
+
+const WORKFLOW: &str = r#"name: automation
+
+on:
+  push:
+  workflow_dispatch:
+
+jobs:
+  run:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: dtolnay/rust-toolchain@stable
+      - name: Run automation
+        run: cargo run --release
+        env:
+          {secret}: ${{ secrets.{secret} }}
+"#;
+
+fn secrets_rs() -> String {
+    r#"//! Secrets used by the automation
+//!
+//! Credentials are read from the environment so they stay out of the
+//! repository. Set them in your shell locally and as repository secrets in
+//! CI. Replace this with your secret store if you have one.
+
+/// Read the secret `name` from the environment.
+pub fn get(name: &str) -> Result<String, String> {
+    std::env::var(name).map_err(|_| format!("secret {} is not set", name))
+}
+"#
+    .to_string()
+}
+
+const RUST_MAIN: &str = r#"mod secrets;
+
+use expectrust::{Pattern, Session};
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let password = secrets::get("{secret}")?;
+
+    // Replace with the program to automate
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(30))
+        .spawn_shell("{demo}")?;
+
+    session.expect(Pattern::exact("Password: ")).await?;
+    session.send_secret(&password).await?;
+    session.expect(Pattern::exact("logged in")).await?;
+    session.expect(Pattern::Eof).await?;
+
+    println!("done");
+    Ok(())
+}
+"#;
+
+const TCL_MAIN: &str = r#"mod secrets;
+
+use expectrust::script::Script;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let password = secrets::get("{secret}")?;
+
+    // The script sees the secret as $password
+    let script = Script::builder()
+        .timeout(Duration::from_secs(30))
+        .variable("password", password)
+        .from_file("automation.exp")?;
+    script.execute().await?;
+
+    println!("done");
+    Ok(())
+}
+"#;
+
+const TCL_SCRIPT: &str = r#"# Replace with the program to automate
+spawn sh -c "\"{demo}\""
+expect "Password: "
+# Set by src/main.rs from the environment
+send "$password\n"
+expect "logged in"
+expect eof
+"#;
+
+const STEPS_MAIN: &str = r#"mod secrets;
+
+use expectrust::{Pattern, Session};
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let password = secrets::get("{secret}")?;
+
+    // Replace with the program to automate
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(30))
+        .spawn_shell("{demo}")?;
+
+    // Answered here so the secret never appears in steps.toml
+    session.auto_respond(Pattern::exact("Password: "), format!("{}\n", password))?;
+    session.run_steps_file("steps.toml").await?;
+
+    println!("done");
+    Ok(())
+}
+"#;
+
+const STEPS_TOML: &str = r#"# Each step waits for `expect`, `regex` or `glob`, then sends `send`
+
+[[step]]
+name = "login"
+expect = "logged in"
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The contents of `path` in a new project.
+    fn file(front_end: FrontEnd, path: &str) -> String {
+        project_files("demo", front_end)
+            .into_iter()
+            .find(|(file, _)| *file == Path::new(path))
+            .map(|(_, contents)| contents)
+            .unwrap_or_else(|| panic!("no {} in the project", path))
+    }
+
+    fn paths(front_end: FrontEnd) -> Vec<&'static str> {
+        let mut paths: Vec<_> = project_files("demo", front_end)
+            .into_iter()
+            .map(|(path, _)| path.to_str().unwrap())
+            .collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    #[test]
+    fn test_package_names() {
+        for name in ["backup", "nightly-backup", "site_2"] {
+            assert!(is_package_name(name), "{}", name);
+        }
+        for name in ["", "2fast", "-backup", "my backup", "../backup", "café"] {
+            assert!(!is_package_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_project_files() {
+        let common = [
+            ".github/workflows/automation.yml",
+            ".gitignore",
+            "Cargo.toml",
+            "README.md",
+            "src/main.rs",
+            "src/secrets.rs",
+        ];
+        let expected = |extra: &[&'static str]| {
+            let mut paths: Vec<_> = common.iter().chain(extra).copied().collect();
+            paths.sort_unstable();
+            paths
+        };
+        assert_eq!(paths(FrontEnd::Rust), expected(&[]));
+        assert_eq!(paths(FrontEnd::Tcl), expected(&["automation.exp"]));
+        assert_eq!(paths(FrontEnd::Steps), expected(&["steps.toml"]));
+    }
+
+    #[test]
+    fn test_placeholders_replaced() {
+        for front_end in [FrontEnd::Rust, FrontEnd::Tcl, FrontEnd::Steps] {
+            for (path, contents) in project_files("demo", front_end) {
+                assert!(!contents.contains("{demo}"), "{}", path.display());
+                assert!(!contents.contains("{secret}"), "{}", path.display());
+            }
+        }
+        assert!(file(FrontEnd::Tcl, ".github/workflows/automation.yml")
+            .contains("AUTOMATION_PASSWORD: ${{ secrets.AUTOMATION_PASSWORD }}"));
+    }
+
+    #[test]
+    fn test_playbook_alias() {
+        assert!(FrontEnd::from_str("playbook", false) == Ok(FrontEnd::Steps));
+    }
+
+    #[cfg(feature = "steps")]
+    #[test]
+    fn test_steps_toml_parses() {
+        let steps: expectrust::Steps = file(FrontEnd::Steps, "steps.toml").parse().unwrap();
+        assert_eq!(steps.steps.len(), 1);
+        assert!(steps.steps[0].pattern().unwrap().is_some());
+    }
+
+    #[cfg(feature = "script")]
+    #[tokio::test]
+    async fn test_tcl_script_runs() {
+        use expectrust::script::Script;
+
+        let source = file(FrontEnd::Tcl, "automation.exp");
+        Script::from_str(&source).unwrap();
+
+        if cfg!(windows) {
+            return;
+        }
+        let script = Script::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .variable("password", "hunter2")
+            .from_str(&source)
+            .unwrap();
+        script.execute().await.unwrap();
+    }
+}
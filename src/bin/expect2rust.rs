@@ -1,7 +1,7 @@
 //! CLI tool for translating Expect scripts to Rust code.
 
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "expect2rust")]
@@ -21,6 +21,10 @@ struct Args {
     /// Generate standalone executable (with main function)
     #[arg(long)]
     standalone: bool,
+
+    /// Name of the generated function when not standalone (default: input file stem)
+    #[arg(long)]
+    fn_name: Option<String>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,18 +41,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Translate the script
     println!("Translating {}...", args.input.display());
-    let generated = expectrust::script::translator::translate_file(&args.input)?;
-
-    // Format output
-    let mut output = String::new();
-
-    if args.standalone {
-        // Already includes main function from translator
-        output.push_str(&generated.code);
+    let generated = if args.standalone {
+        expectrust::script::translator::translate_file(&args.input)?
     } else {
-        // Strip the main function wrapper for library usage
-        output.push_str(&strip_main_wrapper(&generated.code));
-    }
+        let name = args
+            .fn_name
+            .clone()
+            .unwrap_or_else(|| fn_name_from_path(&args.input));
+        expectrust::script::translator::translate_file_to_fn(&name, &args.input)?
+    };
+    let output = generated.render(!args.no_warnings);
 
     // Determine output path
     let output_path = args.output.unwrap_or_else(|| {
@@ -90,62 +92,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Strip the main function wrapper from generated code.
-fn strip_main_wrapper(code: &str) -> String {
-    let lines: Vec<&str> = code.lines().collect();
-    let mut result = Vec::new();
-    let mut in_main = false;
-    let mut skip_imports = true;
-
-    for line in &lines {
-        // Skip warning header
-        if line.starts_with("//") {
-            continue;
-        }
-
-        // Skip initial imports (we'll add them back)
-        if skip_imports && (line.starts_with("use ") || line.is_empty()) {
-            continue;
-        }
-
-        if line.contains("#[tokio::main]") {
-            skip_imports = false;
-            continue;
-        }
-
-        if line.contains("async fn main()") {
-            in_main = true;
-            skip_imports = false;
-            continue;
-        }
-
-        if in_main {
-            // Skip the opening brace after main
-            if line.trim() == "{" {
-                continue;
-            }
-            // Skip Ok(()) and final closing brace
-            if line.contains("Ok(())") {
-                continue;
-            }
-            if line.trim() == "}" && result.iter().any(|l: &&str| l.contains("session")) {
-                break;
-            }
-
-            // Dedent by one level
-            if let Some(stripped) = line.strip_prefix("    ") {
-                result.push(stripped);
-            } else {
-                result.push(*line);
-            }
-        }
+/// Derive a function name from the script's file name.
+fn fn_name_from_path(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') || name == "_" {
+        name.insert_str(0, "run_");
     }
-
-    // Build output with clean imports
-    let mut output = String::new();
-    output.push_str("use expectrust::{Session, Pattern};\n");
-    output.push_str("use std::time::Duration;\n\n");
-    output.push_str(&result.join("\n"));
-    output.push('\n');
-    output
+    name
 }
@@ -0,0 +1,25 @@
+//! HTTP control server for driving sessions from other languages.
+
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "expectrust-server")]
+#[command(author, version, about = "Serve ExpectRust sessions over HTTP", long_about = None)]
+struct Args {
+    /// Address to listen on; the server is unauthenticated, so keep it local
+    #[arg(short, long, default_value = "127.0.0.1:7878")]
+    listen: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let listener = tokio::net::TcpListener::bind(&args.listen).await?;
+    eprintln!(
+        "expectrust-server listening on http://{}",
+        listener.local_addr()?
+    );
+    expectrust::server::serve(listener).await?;
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Synchronous API for programs without an async runtime
+//!
+//! [`blocking::Session`](Session) wraps an asynchronous
+//! [`Session`](crate::Session) and drives it on a private single-threaded
+//! runtime, so scripts and CLI tools can automate processes from plain
+//! `fn main`. Sessions are configured with the usual
+//! [`SessionBuilder`] and then wrapped.
+//!
+//! The blocking calls must not be made from within an async runtime, where
+//! they would stall the executor; they panic if they are. Use the async API
+//! there instead.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use expectrust::blocking::Session;
+//! use expectrust::Pattern;
+//! use std::time::Duration;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut session = Session::new(
+//!         expectrust::Session::builder()
+//!             .timeout(Duration::from_secs(30))
+//!             .spawn("python3 -i")?,
+//!     )?;
+//!
+//!     session.expect(Pattern::exact(">>> "))?;
+//!     session.send_line("print(6 * 7)")?;
+//!     let result = session.expect(Pattern::exact(">>> "))?;
+//!     println!("Output: {}", result.before.trim());
+//!
+//!     session.send_line("exit()")?;
+//!     session.wait()?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::{IntoControlChar, SessionBuilder};
+use portable_pty::ExitStatus;
+use std::ffi::OsStr;
+use tokio::runtime::{Builder, Runtime};
+
+/// A session whose operations block until they complete.
+///
+/// See the [module documentation](self).
+pub struct Session {
+    inner: crate::Session,
+    runtime: Runtime,
+}
+
+impl Session {
+    /// Wrap an asynchronous session.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::IoError` if the runtime cannot be created.
+    pub fn new(session: crate::Session) -> Result<Self, ExpectError> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self {
+            inner: session,
+            runtime,
+        })
+    }
+
+    /// Spawn a command with default settings.
+    ///
+    /// See [`SessionBuilder::spawn`].
+    pub fn spawn(command: &str) -> Result<Self, ExpectError> {
+        Self::new(SessionBuilder::new().spawn(command)?)
+    }
+
+    /// Spawn a program with an explicit argument list and default settings.
+    ///
+    /// See [`SessionBuilder::spawn_args`].
+    pub fn spawn_args<I, S>(program: impl AsRef<OsStr>, args: I) -> Result<Self, ExpectError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        Self::new(SessionBuilder::new().spawn_args(program, args)?)
+    }
+
+    /// Run a command line through the platform shell with default settings.
+    ///
+    /// See [`SessionBuilder::spawn_shell`].
+    pub fn spawn_shell(command: &str) -> Result<Self, ExpectError> {
+        Self::new(SessionBuilder::new().spawn_shell(command)?)
+    }
+
+    /// Wait for a pattern. See [`crate::Session::expect`].
+    pub fn expect(&mut self, pattern: Pattern) -> Result<MatchResult, ExpectError> {
+        self.runtime.block_on(self.inner.expect(pattern))
+    }
+
+    /// Wait for any of several patterns. See [`crate::Session::expect_any`].
+    pub fn expect_any(&mut self, patterns: &[Pattern]) -> Result<MatchResult, ExpectError> {
+        self.runtime.block_on(self.inner.expect_any(patterns))
+    }
+
+    /// Send raw bytes. See [`crate::Session::send`].
+    pub fn send(&mut self, data: &[u8]) -> Result<(), ExpectError> {
+        self.runtime.block_on(self.inner.send(data))
+    }
+
+    /// Send a line of text. See [`crate::Session::send_line`].
+    pub fn send_line(&mut self, line: &str) -> Result<(), ExpectError> {
+        self.runtime.block_on(self.inner.send_line(line))
+    }
+
+    /// Send a control character. See [`crate::Session::send_control`].
+    pub fn send_control(&mut self, key: impl IntoControlChar) -> Result<(), ExpectError> {
+        self.runtime.block_on(self.inner.send_control(key))
+    }
+
+    /// Check whether the process is still running. See
+    /// [`crate::Session::is_alive`].
+    pub fn is_alive(&mut self) -> Result<bool, ExpectError> {
+        self.inner.is_alive()
+    }
+
+    /// Wait for the process to exit. See [`crate::Session::wait`].
+    pub fn wait(&mut self) -> Result<ExitStatus, ExpectError> {
+        self.runtime.block_on(self.inner.wait())
+    }
+
+    /// Ask the process to exit, then force it. See
+    /// [`crate::Session::terminate`].
+    pub fn terminate(&mut self) -> Result<ExitStatus, ExpectError> {
+        self.runtime.block_on(self.inner.terminate())
+    }
+
+    /// Kill the process. See [`crate::Session::kill`].
+    pub fn kill(&mut self) -> Result<ExitStatus, ExpectError> {
+        self.runtime.block_on(self.inner.kill())
+    }
+
+    /// The wrapped session, for its synchronous methods such as
+    /// [`buffer`](crate::Session::buffer) and [`report`](crate::Session::report).
+    pub fn get_ref(&self) -> &crate::Session {
+        &self.inner
+    }
+
+    /// The wrapped session, mutably, for its synchronous methods such as
+    /// [`resize`](crate::Session::resize).
+    pub fn get_mut(&mut self) -> &mut crate::Session {
+        &mut self.inner
+    }
+
+    /// Unwrap the asynchronous session.
+    pub fn into_inner(self) -> crate::Session {
+        self.inner
+    }
+}
@@ -0,0 +1,81 @@
+//! Printing output with control characters made visible
+
+use std::fmt;
+
+/// Bytes formatted with control characters and invalid UTF-8 escaped.
+///
+/// Printing process output as is lets the terminal interpret it: a carriage
+/// return overwrites the diagnostic line and escape sequences change colors
+/// or move the cursor, hiding the very bytes being debugged. `Escaped`
+/// shows them as `\r`, `\n`, `\t`, `\x1b` and so on, and bytes that are not
+/// valid UTF-8 as `\xff`. Other text, including non-ASCII characters, is
+/// shown unchanged, and backslashes are doubled.
+///
+/// `Display` writes the escaped text; `Debug` also wraps it in double
+/// quotes and escapes the double quotes within.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::Escaped;
+///
+/// let output = b"\x1b[32mok\x1b[0m\r\n\xff";
+/// assert_eq!(Escaped(output).to_string(), r"\x1b[32mok\x1b[0m\r\n\xff");
+/// assert_eq!(format!("{:?}", Escaped(b"say \"hi\"")), r#""say \"hi\"""#);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Escaped<'a>(pub &'a [u8]);
+
+impl Escaped<'_> {
+    fn write(&self, f: &mut fmt::Formatter<'_>, quoted: bool) -> fmt::Result {
+        for chunk in self.0.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\\' => f.write_str("\\\\")?,
+                    '\n' => f.write_str("\\n")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\t' => f.write_str("\\t")?,
+                    '"' if quoted => f.write_str("\\\"")?,
+                    c if c.is_control() && c.is_ascii() => write!(f, "\\x{:02x}", c as u32)?,
+                    c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                    c => write!(f, "{}", c)?,
+                }
+            }
+            for byte in chunk.invalid() {
+                write!(f, "\\x{:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+impl fmt::Debug for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        self.write(f, true)?;
+        f.write_str("\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaped_controls() {
+        let escaped = Escaped("a\\b\tc\x07\u{7f}\u{85}é".as_bytes()).to_string();
+        assert_eq!(escaped, r"a\\b\tc\x07\x7f\u{85}é");
+    }
+
+    #[test]
+    fn test_escaped_invalid_utf8() {
+        assert_eq!(Escaped(b"\xe2\x82ok\xff").to_string(), r"\xe2\x82ok\xff");
+        assert_eq!(format!("{:?}", Escaped(b"")), "\"\"");
+    }
+}
@@ -1,9 +1,17 @@
 //! Buffer management for process output
 
 mod ansi;
+mod escaped;
+mod progress;
+mod raw;
 
 pub use ansi::strip_ansi;
+pub use escaped::Escaped;
+pub use raw::RawStream;
 
+pub(crate) use progress::{LineSink, ProgressCollapser};
+
+use crate::pattern::{Match, Matcher};
 use bytes::BytesMut;
 use std::io;
 
@@ -11,86 +19,303 @@ use std::io;
 /// When buffer is full, discard oldest 1/3 and keep newest 2/3.
 const DISCARD_RATIO: usize = 3;
 
-/// Manages buffering of process output with intelligent compaction
+/// What a [`BufferManager`] does when appending would exceed its maximum size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetentionPolicy {
+    /// Discard the oldest third of the buffer (or everything already
+    /// consumed, whichever is more) to make room. Very old unconsumed data
+    /// may be lost, but appending never fails.
+    #[default]
+    Compact,
+
+    /// Discard only data that has already been consumed. If the unconsumed
+    /// data plus the new data still does not fit, `append` returns an error
+    /// and the buffer is left unchanged.
+    Reject,
+}
+
+/// Buffer for streamed output with match tracking and bounded memory use.
+///
+/// `BufferManager` is the buffering layer used by [`Session`](crate::Session),
+/// and can also be used on its own to scan any chunked byte stream (log files,
+/// sockets, serial ports) for patterns. Data is appended as it arrives,
+/// searched with a [`Matcher`], and consumed up to the end of each match so
+/// the next search starts after it. Patterns split across chunk boundaries
+/// are found once the rest of the data has been appended.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::{BufferManager, Pattern};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let matcher = Pattern::exact("READY").to_matcher()?;
+/// let mut buffer = BufferManager::new(8192, false);
+///
+/// buffer.append(b"booting... RE")?;
+/// assert!(buffer.find(matcher.as_ref()).is_none());
+///
+/// buffer.append(b"ADY\n")?;
+/// let found = buffer.find(matcher.as_ref()).unwrap();
+/// buffer.consume(found.end);
+/// assert_eq!(buffer.unmatched(), b"\n");
+/// # Ok(())
+/// # }
+/// ```
 pub struct BufferManager {
     buffer: BytesMut,
     matched_position: usize,
     max_size: usize,
     strip_ansi: bool,
+    retention: RetentionPolicy,
+    /// Collapses progress lines, if enabled.
+    progress: Option<ProgressCollapser>,
+    /// Unfiltered copy of appended data, if retained.
+    raw: Option<RawStream>,
 }
 
 impl BufferManager {
-    /// Create a new buffer manager
+    /// Create a new buffer manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_size` - Maximum number of bytes to retain
+    /// * `strip_ansi` - Whether to remove ANSI escape sequences from appended data
     pub fn new(max_size: usize, strip_ansi: bool) -> Self {
         Self {
             buffer: BytesMut::with_capacity(max_size),
             matched_position: 0,
             max_size,
             strip_ansi,
+            retention: RetentionPolicy::default(),
+            progress: None,
+            raw: None,
         }
     }
 
-    /// Append data to the buffer
+    /// Set the retention policy used when the buffer is full.
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Collapse lines rewritten with carriage returns to their final text.
+    ///
+    /// Progress bars (wget, apt, pip) redraw one line by printing `\r`
+    /// followed by the updated text. With collapsing enabled, a `\r` that is
+    /// not part of `\r\n` discards the unconsumed part of the current line,
+    /// so only the last update is kept and line-oriented patterns see a
+    /// clean line. Text that was already consumed by a match is not affected.
+    ///
+    /// A `\r` at the end of the appended data is kept until the next byte
+    /// shows whether the line is redrawn, so a pattern ending in `\r` can
+    /// match before more data arrives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expectrust::BufferManager;
+    ///
+    /// # fn example() -> std::io::Result<()> {
+    /// let mut buffer = BufferManager::new(8192, false).with_progress_collapsing(true);
+    /// buffer.append(b"fetching\r\n  0%\r 50%")?;
+    /// buffer.append(b"\r100%\r\n")?;
+    /// assert_eq!(buffer.as_str(), "fetching\r\n100%\r\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_progress_collapsing(mut self, enabled: bool) -> Self {
+        self.progress = enabled.then(ProgressCollapser::default);
+        self
+    }
+
+    /// Also keep the most recent appended data as it was before filtering.
+    ///
+    /// ANSI stripping and progress collapsing change the data before it is
+    /// buffered, so [`as_bytes`](Self::as_bytes) does not show what really
+    /// arrived. With raw retention, up to `max_size` bytes of the original
+    /// data are kept and available from [`raw_tail`](Self::raw_tail), which
+    /// helps when debugging patterns that fail because of escape sequences.
+    ///
+    /// A [`Session`](crate::Session) keeps its own raw layer; see
+    /// [`Session::raw_tail`](crate::Session::raw_tail).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expectrust::BufferManager;
+    ///
+    /// # fn example() -> std::io::Result<()> {
+    /// let mut buffer = BufferManager::new(8192, true).with_raw_retention(true);
+    /// buffer.append(b"\x1b[31mFAIL\x1b[0m")?;
+    /// assert_eq!(buffer.as_bytes(), b"FAIL");
+    /// assert_eq!(buffer.raw_tail(9), b"FAIL\x1b[0m");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_raw_retention(mut self, enabled: bool) -> Self {
+        self.raw = enabled.then(|| RawStream::new(self.max_size));
+        self
+    }
+
+    /// The last `n` bytes appended, before filtering.
+    ///
+    /// Empty unless raw retention is enabled with
+    /// [`with_raw_retention`](Self::with_raw_retention).
+    pub fn raw_tail(&self, n: usize) -> &[u8] {
+        self.raw.as_ref().map_or(&[], |raw| raw.tail(n))
+    }
+
+    /// Append data to the buffer.
+    ///
+    /// # Errors
+    ///
+    /// With [`RetentionPolicy::Reject`], returns an error if the data does not
+    /// fit even after discarding consumed data.
     pub fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        if let Some(raw) = &mut self.raw {
+            raw.append(data);
+        }
         let data_to_append = if self.strip_ansi {
             strip_ansi(data)
         } else {
             data.to_vec()
         };
 
-        // Check if we need to compact before appending
-        if self.buffer.len() + data_to_append.len() > self.max_size {
-            self.compact()?;
+        match self.progress {
+            Some(mut progress) => {
+                let result = progress.feed(&data_to_append, self);
+                self.progress = Some(progress);
+                result
+            }
+            None => self.push(&data_to_append),
         }
+    }
 
-        self.buffer.extend_from_slice(&data_to_append);
-        Ok(())
+    /// Whether ANSI escape sequences are removed from appended data.
+    pub(crate) fn strips_ansi(&self) -> bool {
+        self.strip_ansi
     }
 
-    /// Get the buffer as a string slice
+    /// Search the unconsumed part of the buffer with a matcher.
+    ///
+    /// The returned match positions are absolute offsets into
+    /// [`as_bytes()`](Self::as_bytes). The buffer is not modified; call
+    /// [`consume()`](Self::consume) to advance past the match.
+    pub fn find(&self, matcher: &dyn Matcher) -> Option<Match> {
+        matcher.find(self.unmatched()).map(|m| Match {
+            start: self.matched_position + m.start,
+            end: self.matched_position + m.end,
+            ..m
+        })
+    }
+
+    /// Search only the last `window` bytes of the unconsumed buffer.
+    ///
+    /// The window is widened to start on a UTF-8 character boundary. Like
+    /// [`find()`](Self::find), positions are absolute.
+    pub fn find_within(&self, matcher: &dyn Matcher, window: usize) -> Option<Match> {
+        let searched = self.window(window);
+        let start = self.buffer.len() - searched.len();
+        matcher.find(searched).map(|m| Match {
+            start: start + m.start,
+            end: start + m.end,
+            ..m
+        })
+    }
+
+    /// The part of the buffer [`find_within()`](Self::find_within) searches.
+    pub(crate) fn window(&self, window: usize) -> &[u8] {
+        let mut start = self
+            .buffer
+            .len()
+            .saturating_sub(window)
+            .max(self.matched_position);
+        while start > self.matched_position && self.buffer[start] & 0xC0 == 0x80 {
+            start -= 1;
+        }
+        &self.buffer[start..]
+    }
+
+    /// Consume the buffer up to an absolute position.
+    ///
+    /// Subsequent searches start at this position. Positions before the
+    /// current consumed position are ignored.
+    pub fn consume(&mut self, end_position: usize) {
+        self.mark_matched(end_position.max(self.matched_position));
+    }
+
+    /// Remove the unconsumed bytes in `start..end` (absolute positions).
+    ///
+    /// Data before `start` stays unconsumed, so later searches still see it.
+    /// Parts of the range that were already consumed are left in place.
+    pub fn remove(&mut self, start: usize, end: usize) {
+        let end = end.min(self.buffer.len());
+        let start = start.max(self.matched_position);
+        if start < end {
+            self.buffer.copy_within(end.., start);
+            self.buffer.truncate(self.buffer.len() - (end - start));
+        }
+    }
+
+    /// Get the buffer as a string slice.
+    ///
+    /// Returns an empty string if the buffer is not valid UTF-8.
     pub fn as_str(&self) -> &str {
         std::str::from_utf8(&self.buffer).unwrap_or("")
     }
 
-    /// Get the buffer as bytes
+    /// Get the buffer as bytes.
     pub fn as_bytes(&self) -> &[u8] {
         &self.buffer
     }
 
-    /// Get unmatched portion of the buffer
+    /// Get the unconsumed portion of the buffer.
     pub fn unmatched(&self) -> &[u8] {
         &self.buffer[self.matched_position..]
     }
 
-    /// Mark a position as matched
+    /// Mark a position as matched.
     pub fn mark_matched(&mut self, end_position: usize) {
-        self.matched_position = end_position;
+        self.matched_position = end_position.min(self.buffer.len());
     }
 
-    /// Get the current buffer length
+    /// Get the current buffer length.
     pub fn len(&self) -> usize {
         self.buffer.len()
     }
 
-    /// Get the matched position
+    /// Check if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Get the matched (consumed) position.
     pub fn matched_position(&self) -> usize {
         self.matched_position
     }
 
-    /// Get text before a given position
-    pub fn before(&self, position: usize) -> &[u8] {
-        &self.buffer[..position.min(self.buffer.len())]
+    /// Get the maximum number of bytes retained.
+    pub fn max_size(&self) -> usize {
+        self.max_size
     }
 
-    #[cfg(test)]
-    pub fn is_empty(&self) -> bool {
-        self.buffer.is_empty()
+    /// Get text before a given position.
+    pub fn before(&self, position: usize) -> &[u8] {
+        &self.buffer[..position.min(self.buffer.len())]
     }
 
-    #[cfg(test)]
+    /// Remove all data and reset the consumed position.
     pub fn clear(&mut self) {
         self.buffer.clear();
         self.matched_position = 0;
+        self.progress = self.progress.map(|_| ProgressCollapser::default());
+    }
+
+    /// Drop all consumed data from the front of the buffer.
+    fn discard_consumed(&mut self) {
+        let _ = self.buffer.split_to(self.matched_position);
+        self.matched_position = 0;
     }
 
     /// Compact the buffer using 2/3 discard strategy
@@ -116,6 +341,41 @@ impl BufferManager {
     }
 }
 
+impl LineSink for BufferManager {
+    type Error = io::Error;
+
+    /// Append data as is, making room according to the retention policy.
+    fn push(&mut self, data_to_append: &[u8]) -> io::Result<()> {
+        // Check if we need to compact before appending
+        if self.buffer.len() + data_to_append.len() > self.max_size {
+            match self.retention {
+                RetentionPolicy::Compact => self.compact()?,
+                RetentionPolicy::Reject => {
+                    let unconsumed = self.buffer.len() - self.matched_position;
+                    if unconsumed + data_to_append.len() > self.max_size {
+                        return Err(io::Error::new(
+                            io::ErrorKind::OutOfMemory,
+                            format!("Buffer full ({} bytes)", self.max_size),
+                        ));
+                    }
+                    self.discard_consumed();
+                }
+            }
+        }
+
+        self.buffer.extend_from_slice(data_to_append);
+        Ok(())
+    }
+
+    /// Drop the unconsumed part of the current line.
+    fn rewind_line(&mut self) {
+        let unconsumed = &self.buffer[self.matched_position..];
+        let line_start = memchr::memrchr(b'\n', unconsumed)
+            .map_or(self.matched_position, |i| self.matched_position + i + 1);
+        self.buffer.truncate(line_start);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,6 +502,19 @@ mod tests {
         assert_eq!(buffer.as_str(), "Hello Red World");
     }
 
+    #[test]
+    fn test_raw_retention() {
+        let mut buffer = BufferManager::new(8, true).with_raw_retention(true);
+        buffer.append(b"\x1b[1mab\x1b[0m").unwrap();
+        assert_eq!(buffer.as_bytes(), b"ab");
+        // Bounded by the maximum size
+        assert_eq!(buffer.raw_tail(100), b"1mab\x1b[0m");
+
+        let mut buffer = BufferManager::new(8, true);
+        buffer.append(b"\x1b[1mab").unwrap();
+        assert!(buffer.raw_tail(100).is_empty());
+    }
+
     #[test]
     fn test_strip_ansi_disabled() {
         let mut buffer = BufferManager::new(1024, false);
@@ -314,6 +587,59 @@ mod tests {
         assert_eq!(buffer.as_str(), "Hello 世界! 🎉");
     }
 
+    #[test]
+    fn test_find_and_consume() {
+        let matcher = crate::Pattern::exact("ok").to_matcher().unwrap();
+        let mut buffer = BufferManager::new(1024, false);
+        buffer.append(b"ok then ok").unwrap();
+
+        let first = buffer.find(matcher.as_ref()).unwrap();
+        assert_eq!((first.start, first.end), (0, 2));
+        buffer.consume(first.end);
+
+        let second = buffer.find(matcher.as_ref()).unwrap();
+        assert_eq!((second.start, second.end), (8, 10));
+        buffer.consume(second.end);
+        assert!(buffer.find(matcher.as_ref()).is_none());
+
+        // Consuming backwards is ignored
+        buffer.consume(3);
+        assert_eq!(buffer.matched_position(), 10);
+    }
+
+    #[test]
+    fn test_find_within() {
+        let matcher = crate::Pattern::regex(r"é\S*")
+            .unwrap()
+            .to_matcher()
+            .unwrap();
+        let mut buffer = BufferManager::new(1024, false);
+        buffer.append("é1 é2 xé3".as_bytes()).unwrap();
+
+        // The window starts inside "é3" and is widened to include it
+        let found = buffer.find_within(matcher.as_ref(), 2).unwrap();
+        assert_eq!(found.captures[0], "é3");
+        assert_eq!(&buffer.as_bytes()[found.start..found.end], "é3".as_bytes());
+
+        // The window never reaches into consumed output
+        buffer.consume(found.end);
+        assert!(buffer.find_within(matcher.as_ref(), 100).is_none());
+    }
+
+    #[test]
+    fn test_reject_policy() {
+        let mut buffer = BufferManager::new(10, false).with_retention(RetentionPolicy::Reject);
+        buffer.append(b"0123456789").unwrap();
+        assert!(buffer.append(b"X").is_err());
+        assert_eq!(buffer.as_bytes(), b"0123456789");
+
+        // Consumed data can be discarded to make room
+        buffer.consume(4);
+        buffer.append(b"ABCD").unwrap();
+        assert_eq!(buffer.as_bytes(), b"456789ABCD");
+        assert_eq!(buffer.matched_position(), 0);
+    }
+
     #[test]
     fn test_invalid_utf8() {
         let mut buffer = BufferManager::new(1024, false);
@@ -326,4 +652,49 @@ mod tests {
         // But as_bytes should still return the data
         assert_eq!(buffer.as_bytes(), &[0xFF, 0xFE, 0xFD]);
     }
+
+    #[test]
+    fn test_progress_collapsing() {
+        let mut buffer = BufferManager::new(1024, false).with_progress_collapsing(true);
+        buffer.append(b"  0% [....]\r 40% [##..]\r").unwrap();
+        buffer.append(b"100% [####]\r").unwrap();
+        // A carriage return split from its line feed is kept
+        buffer.append(b"\ndone\n").unwrap();
+        assert_eq!(buffer.as_str(), "100% [####]\r\ndone\n");
+    }
+
+    #[test]
+    fn test_progress_collapsing_trailing_carriage_return() {
+        let matcher = crate::Pattern::exact("[y/N]\r").to_matcher().unwrap();
+        let mut buffer = BufferManager::new(1024, false).with_progress_collapsing(true);
+        buffer.append(b"Continue? [y/N]\r").unwrap();
+        // Matches without waiting for the byte after the carriage return
+        assert!(buffer.find(matcher.as_ref()).is_some());
+        // and is still there if nothing follows
+        assert_eq!(buffer.as_str(), "Continue? [y/N]\r");
+    }
+
+    #[test]
+    fn test_progress_collapsing_keeps_consumed_text() {
+        let mut buffer = BufferManager::new(1024, false).with_progress_collapsing(true);
+        buffer.append(b"Downloading: 10%").unwrap();
+        buffer.consume(13);
+        buffer.append(b"\r90%").unwrap();
+        assert_eq!(buffer.as_str(), "Downloading: 90%");
+        assert_eq!(buffer.unmatched(), b"90%");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut buffer = BufferManager::new(1024, false);
+        buffer.append(b"one two three").unwrap();
+        buffer.consume(2);
+        buffer.remove(4, 8);
+        assert_eq!(buffer.as_bytes(), b"one three");
+        assert_eq!(buffer.unmatched(), b"e three");
+
+        // The consumed part of the range is kept
+        buffer.remove(0, 4);
+        assert_eq!(buffer.as_bytes(), b"onthree");
+    }
 }
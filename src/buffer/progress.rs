@@ -0,0 +1,91 @@
+//! Collapsing lines redrawn with carriage returns
+
+/// Output that progress collapsing writes to.
+pub(crate) trait LineSink {
+    /// Error returned when text cannot be appended.
+    type Error;
+
+    /// Append text.
+    fn push(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Drop the current, unterminated line, or as much of it as can still
+    /// be changed.
+    fn rewind_line(&mut self);
+}
+
+/// Reduces lines redrawn with carriage returns to their final text.
+///
+/// A carriage return is passed on as soon as it arrives, so output ending in
+/// one can be matched without waiting for more; the line it ends is dropped
+/// once the next byte shows that it is redrawn rather than ended by `\r\n`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ProgressCollapser {
+    /// The last byte fed was a carriage return.
+    after_cr: bool,
+}
+
+impl ProgressCollapser {
+    /// Pass `data` on to `sink`, dropping lines that it redraws.
+    pub(crate) fn feed<S: LineSink>(&mut self, data: &[u8], sink: &mut S) -> Result<(), S::Error> {
+        let mut rest = data;
+        while let Some(&first) = rest.first() {
+            if std::mem::take(&mut self.after_cr) && first != b'\n' {
+                sink.rewind_line();
+            }
+            match memchr::memchr(b'\r', rest) {
+                Some(i) => {
+                    sink.push(&rest[..=i])?;
+                    self.after_cr = true;
+                    rest = &rest[i + 1..];
+                }
+                None => {
+                    sink.push(rest)?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LineSink for Vec<u8> {
+    type Error = std::convert::Infallible;
+
+    fn push(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn rewind_line(&mut self) {
+        let line_start = memchr::memrchr(b'\n', self).map_or(0, |i| i + 1);
+        self.truncate(line_start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collapse(chunks: &[&[u8]]) -> Vec<u8> {
+        let mut progress = ProgressCollapser::default();
+        let mut output = Vec::new();
+        for chunk in chunks {
+            let Ok(()) = progress.feed(chunk, &mut output);
+        }
+        output
+    }
+
+    #[test]
+    fn test_redrawn_lines_keep_last_text() {
+        assert_eq!(
+            collapse(&[b"a\r\n 10%\r 50%\r100%\ndone"]),
+            b"a\r\n100%\ndone"
+        );
+        assert_eq!(collapse(&[b" 10%\r", b" 50%", b"\r", b"\n"]), b" 50%\r\n");
+    }
+
+    #[test]
+    fn test_trailing_carriage_return_is_passed_on() {
+        assert_eq!(collapse(&[b"Continue? [y/N]\r"]), b"Continue? [y/N]\r");
+    }
+}
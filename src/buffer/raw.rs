@@ -0,0 +1,109 @@
+//! Bounded record of the raw output stream
+
+use bytes::{Buf, BytesMut};
+
+/// The most recent bytes of a stream, exactly as they arrived.
+///
+/// A [`Session`](crate::Session) keeps one alongside its matching buffer,
+/// which may have escape sequences stripped or progress lines collapsed.
+/// Only the last `capacity` bytes are retained, and nothing is ever consumed
+/// by matches, so it shows what the process really printed.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::RawStream;
+///
+/// let mut raw = RawStream::new(8);
+/// raw.append(b"\x1b[1mbold\x1b[0m");
+/// assert_eq!(raw.as_bytes(), b"bold\x1b[0m");
+/// assert_eq!(raw.tail(4), b"\x1b[0m");
+/// assert_eq!(raw.total_len(), 12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RawStream {
+    data: BytesMut,
+    capacity: usize,
+    total_len: u64,
+}
+
+impl RawStream {
+    /// Create an empty stream retaining at most `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: BytesMut::new(),
+            capacity,
+            total_len: 0,
+        }
+    }
+
+    /// Append bytes, discarding the oldest if over capacity.
+    pub fn append(&mut self, data: &[u8]) {
+        self.total_len += data.len() as u64;
+        self.data.extend_from_slice(data);
+        // Trim lazily, so appends are not each paying for a shift
+        if self.data.len() > self.capacity.saturating_mul(2).max(self.capacity + 1024) {
+            let excess = self.data.len() - self.capacity;
+            self.data.advance(excess);
+        }
+    }
+
+    /// The retained bytes, oldest first.
+    pub fn as_bytes(&self) -> &[u8] {
+        let start = self.data.len().saturating_sub(self.capacity);
+        &self.data[start..]
+    }
+
+    /// The last `n` retained bytes (fewer if less is retained).
+    pub fn tail(&self, n: usize) -> &[u8] {
+        let bytes = self.as_bytes();
+        &bytes[bytes.len().saturating_sub(n)..]
+    }
+
+    /// Number of bytes retained.
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    /// Whether no bytes are retained.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of bytes retained.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Total number of bytes appended, including those no longer retained.
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    /// Discard all retained bytes. The total length is kept.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_most_recent_bytes() {
+        let mut raw = RawStream::new(4);
+        for chunk in [&b"abc"[..], b"def", &[b'x'; 3000]] {
+            raw.append(chunk);
+        }
+        assert_eq!(raw.as_bytes(), b"xxxx");
+        assert_eq!(raw.len(), 4);
+        assert_eq!(raw.total_len(), 3006);
+        assert_eq!(raw.data.len(), 4);
+
+        let mut raw = RawStream::new(16);
+        raw.append(b"abc");
+        assert_eq!(raw.tail(10), b"abc");
+        assert_eq!(raw.tail(1), b"c");
+    }
+}
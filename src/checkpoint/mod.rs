@@ -0,0 +1,292 @@
+//! Checkpointing of automation progress for resumable orchestrations
+
+use crate::result::ExpectError;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Header line identifying the checkpoint file format and version.
+const HEADER: &str = "# expectrust checkpoint v1";
+
+/// Snapshot of the logical state of a multi-step automation.
+///
+/// A `Checkpoint` records which named steps have completed, any variables
+/// extracted along the way, and the transcript collected so far. It does
+/// **not** capture the spawned process itself: after a crash or restart, a
+/// new run loads the checkpoint and skips steps that already completed,
+/// which is only safe for idempotent steps.
+///
+/// Checkpoints are stored as a small line-oriented text file so they can be
+/// inspected and edited by hand.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Checkpoint, Pattern, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let path = "deploy.checkpoint";
+/// let mut checkpoint = Checkpoint::load(path).unwrap_or_default();
+/// let mut session = Session::spawn("bash")?;
+///
+/// checkpoint
+///     .step("install", async {
+///         session.send_line("./install.sh").await?;
+///         session.expect(Pattern::exact("installed")).await?;
+///         Ok(())
+///     })
+///     .await?;
+/// checkpoint.save(path)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    completed: Vec<String>,
+    variables: BTreeMap<String, String>,
+    transcript: Vec<String>,
+}
+
+impl Checkpoint {
+    /// Create an empty checkpoint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a checkpoint from a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid checkpoint.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ExpectError> {
+        let content = std::fs::read_to_string(path)?;
+        content.parse()
+    }
+
+    /// Save the checkpoint to a file, replacing any previous contents.
+    ///
+    /// The file is written to a temporary sibling first and then renamed, so
+    /// a crash while saving never leaves a truncated checkpoint behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ExpectError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, self.to_string())?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Check whether a step has already completed.
+    pub fn is_completed(&self, step: &str) -> bool {
+        self.completed.iter().any(|s| s == step)
+    }
+
+    /// Mark a step as completed.
+    pub fn mark_completed(&mut self, step: impl Into<String>) {
+        let step = step.into();
+        if !self.is_completed(&step) {
+            self.completed.push(step);
+        }
+    }
+
+    /// Names of completed steps, in completion order.
+    pub fn completed_steps(&self) -> &[String] {
+        &self.completed
+    }
+
+    /// Run a step unless it has already completed.
+    ///
+    /// The future is only polled when the step has not completed yet; on
+    /// success the step is marked as completed. Returns `true` if the step
+    /// ran and `false` if it was skipped.
+    pub async fn step<F>(&mut self, name: &str, step: F) -> Result<bool, ExpectError>
+    where
+        F: Future<Output = Result<(), ExpectError>>,
+    {
+        if self.is_completed(name) {
+            return Ok(false);
+        }
+        step.await?;
+        self.mark_completed(name);
+        Ok(true)
+    }
+
+    /// Store an extracted variable.
+    pub fn set_variable(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.variables.insert(name.into(), value.into());
+    }
+
+    /// Get a previously stored variable.
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// All stored variables.
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    /// Append output to the recorded transcript.
+    pub fn record_transcript(&mut self, text: impl Into<String>) {
+        self.transcript.push(text.into());
+    }
+
+    /// The recorded transcript.
+    pub fn transcript(&self) -> String {
+        self.transcript.concat()
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for step in &self.completed {
+            writeln!(f, "step {}", escape(step))?;
+        }
+        for (name, value) in &self.variables {
+            writeln!(f, "var {}={}", escape(name), escape(value))?;
+        }
+        for chunk in &self.transcript {
+            writeln!(f, "transcript {}", escape(chunk))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = ExpectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("missing checkpoint header"));
+        }
+
+        let mut checkpoint = Checkpoint::new();
+        for line in lines.filter(|l| !l.is_empty()) {
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match kind {
+                "step" => checkpoint.mark_completed(unescape(rest)?),
+                "var" => {
+                    let (name, value) = rest
+                        .split_once('=')
+                        .ok_or_else(|| invalid("variable entry without '='"))?;
+                    checkpoint.set_variable(unescape(name)?, unescape(value)?);
+                }
+                "transcript" => checkpoint.record_transcript(unescape(rest)?),
+                other => return Err(invalid(&format!("unknown entry '{}'", other))),
+            }
+        }
+
+        Ok(checkpoint)
+    }
+}
+
+fn invalid(message: &str) -> ExpectError {
+    ExpectError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid checkpoint: {}", message),
+    ))
+}
+
+/// Escape a value so it fits on a single line without ambiguity.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('=', "\\e")
+}
+
+fn unescape(s: &str) -> Result<String, ExpectError> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => result.push('\\'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('e') => result.push('='),
+            _ => return Err(invalid("bad escape sequence")),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.mark_completed("login");
+        checkpoint.mark_completed("upgrade");
+        checkpoint.set_variable("version", "1.2=3");
+        checkpoint.record_transcript("$ ls\r\nfile\\name\r\n");
+
+        let text = checkpoint.to_string();
+        let parsed: Checkpoint = text.parse().unwrap();
+        assert_eq!(parsed, checkpoint);
+        assert_eq!(parsed.variable("version"), Some("1.2=3"));
+        assert_eq!(parsed.transcript(), "$ ls\r\nfile\\name\r\n");
+    }
+
+    #[test]
+    fn test_mark_completed_is_idempotent() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.mark_completed("login");
+        checkpoint.mark_completed("login");
+        assert_eq!(checkpoint.completed_steps(), ["login"]);
+    }
+
+    #[test]
+    fn test_missing_header() {
+        assert!("step login\n".parse::<Checkpoint>().is_err());
+    }
+
+    #[test]
+    fn test_unknown_entry() {
+        let text = format!("{}\nbogus entry\n", HEADER);
+        assert!(text.parse::<Checkpoint>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_step_skips_completed() {
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.mark_completed("login");
+
+        let mut ran = false;
+        let executed = checkpoint
+            .step("login", async {
+                ran = true;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert!(!executed);
+        assert!(!ran);
+
+        let executed = checkpoint.step("deploy", async { Ok(()) }).await.unwrap();
+        assert!(executed);
+        assert!(checkpoint.is_completed("deploy"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_not_marked() {
+        let mut checkpoint = Checkpoint::new();
+        let result = checkpoint
+            .step("login", async { Err(ExpectError::Eof) })
+            .await;
+        assert!(result.is_err());
+        assert!(!checkpoint.is_completed("login"));
+    }
+}
@@ -0,0 +1,73 @@
+//! Platform-appropriate command lines for common test commands
+//!
+//! Tests and examples often need a process that prints some text, waits, or
+//! echoes its input back. The programs that do this differ between Unix and
+//! Windows, so each helper returns a command line for the current platform,
+//! ready to pass to [`Session::spawn`](crate::Session::spawn).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use expectrust::{cmd, Pattern, Session};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut session = Session::spawn(&cmd::echo("Hello World"))?;
+//! session.expect(Pattern::exact("Hello World")).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+/// A command that prints `text` followed by a newline, then exits.
+///
+/// The command is split into arguments when spawned, so runs of spaces in
+/// `text` are printed as a single space and quotes in it are removed. Use
+/// [`Session::spawn_shell`](crate::Session::spawn_shell) when the exact
+/// spacing matters.
+pub fn echo(text: &str) -> String {
+    if cfg!(windows) {
+        format!("cmd /C echo {}", text)
+    } else {
+        format!("echo {}", text)
+    }
+}
+
+/// A command that waits `secs` seconds without printing anything, then
+/// exits.
+pub fn sleep(secs: u64) -> String {
+    if cfg!(windows) {
+        format!("cmd /C timeout /t {} /nobreak", secs)
+    } else {
+        format!("sleep {}", secs)
+    }
+}
+
+/// A command that copies each line of its input to its output until end of
+/// input.
+pub fn cat() -> String {
+    if cfg!(windows) {
+        "findstr ^".to_string()
+    } else {
+        "cat".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unix_commands() {
+        assert_eq!(echo("Hello World"), "echo Hello World");
+        assert_eq!(sleep(2), "sleep 2");
+        assert_eq!(cat(), "cat");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_windows_commands() {
+        assert_eq!(echo("Hello World"), "cmd /C echo Hello World");
+        assert_eq!(sleep(2), "cmd /C timeout /t 2 /nobreak");
+        assert_eq!(cat(), "findstr ^");
+    }
+}
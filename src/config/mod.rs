@@ -0,0 +1,138 @@
+//! Process-wide defaults for new sessions
+//!
+//! [`SessionBuilder::new`](crate::SessionBuilder::new) starts from these
+//! defaults, so a test suite can tune every session in one place instead of
+//! at each construction site. Settings made on a builder still take
+//! precedence.
+//!
+//! The initial defaults are the built-in values, overridden by these
+//! environment variables when set to a valid value:
+//!
+//! - `EXPECTRUST_TIMEOUT`: expect timeout in seconds (fractions allowed), or
+//!   `none` to wait forever
+//! - `EXPECTRUST_BUFFER_SIZE`: maximum buffer size in bytes
+//! - `EXPECTRUST_STRIP_ANSI`: `1`/`true` or `0`/`false`
+//!
+//! The environment is read the first time the defaults are needed.
+//!
+//! # Examples
+//!
+//! ```
+//! use expectrust::config::{self, Defaults};
+//! use std::time::Duration;
+//!
+//! config::set_defaults(Defaults {
+//!     timeout: Some(Duration::from_secs(5)),
+//!     ..Defaults::default()
+//! });
+//! assert_eq!(config::defaults().timeout, Some(Duration::from_secs(5)));
+//! ```
+
+use crate::session::{DEFAULT_MAX_BUFFER_SIZE, DEFAULT_TIMEOUT_SECS};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Default settings for new sessions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Defaults {
+    /// Timeout for expect operations (`None` waits forever).
+    pub timeout: Option<Duration>,
+    /// Maximum buffer size in bytes.
+    pub buffer_size: usize,
+    /// Whether ANSI escape sequences are stripped from the buffer.
+    pub strip_ansi: bool,
+}
+
+impl Default for Defaults {
+    /// The built-in defaults, ignoring the environment.
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(DEFAULT_TIMEOUT_SECS)),
+            buffer_size: DEFAULT_MAX_BUFFER_SIZE,
+            strip_ansi: false,
+        }
+    }
+}
+
+impl Defaults {
+    /// The built-in defaults with environment variable overrides applied.
+    ///
+    /// Variables that are unset or cannot be parsed are ignored.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut defaults = Self::default();
+        if let Some(value) = lookup("EXPECTRUST_TIMEOUT") {
+            let value = value.trim();
+            if value.eq_ignore_ascii_case("none") {
+                defaults.timeout = None;
+            } else if let Some(timeout) = value
+                .parse()
+                .ok()
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            {
+                defaults.timeout = Some(timeout);
+            }
+        }
+        if let Some(size) = lookup("EXPECTRUST_BUFFER_SIZE").and_then(|v| v.trim().parse().ok()) {
+            defaults.buffer_size = size;
+        }
+        if let Some(value) = lookup("EXPECTRUST_STRIP_ANSI") {
+            match value.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" => defaults.strip_ansi = true,
+                "0" | "false" => defaults.strip_ansi = false,
+                _ => {}
+            }
+        }
+        defaults
+    }
+}
+
+static DEFAULTS: RwLock<Option<Defaults>> = RwLock::new(None);
+
+/// Replace the defaults used by sessions created from now on.
+pub fn set_defaults(defaults: Defaults) {
+    let mut current = DEFAULTS.write().unwrap_or_else(|e| e.into_inner());
+    *current = Some(defaults);
+}
+
+/// The defaults currently used for new sessions.
+pub fn defaults() -> Defaults {
+    if let Some(defaults) = DEFAULTS.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return defaults.clone();
+    }
+    let mut current = DEFAULTS.write().unwrap_or_else(|e| e.into_inner());
+    current.get_or_insert_with(Defaults::from_env).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_overrides() {
+        let env = |name: &str| match name {
+            "EXPECTRUST_TIMEOUT" => Some("2.5".to_string()),
+            "EXPECTRUST_BUFFER_SIZE" => Some("65536".to_string()),
+            "EXPECTRUST_STRIP_ANSI" => Some("true".to_string()),
+            _ => None,
+        };
+        let defaults = Defaults::from_lookup(env);
+        assert_eq!(defaults.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(defaults.buffer_size, 65536);
+        assert!(defaults.strip_ansi);
+
+        let defaults = Defaults::from_lookup(|name| {
+            (name == "EXPECTRUST_TIMEOUT").then(|| "none".to_string())
+        });
+        assert_eq!(defaults.timeout, None);
+    }
+
+    #[test]
+    fn test_invalid_env_ignored() {
+        let defaults = Defaults::from_lookup(|_| Some("bogus".to_string()));
+        assert_eq!(defaults, Defaults::default());
+    }
+}
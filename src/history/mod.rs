@@ -0,0 +1,391 @@
+//! On-disk history of a session's output and expect calls, for post-mortem
+//! inspection
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Header line identifying the history file format and version.
+const HEADER: &str = "# expectrust history v1";
+
+/// The recorded history of a session: its complete output and the buffer
+/// each expect call searched.
+///
+/// Recorded with [`SessionBuilder::history_file`](crate::SessionBuilder::history_file)
+/// and loaded afterwards, typically after a failure, to answer questions
+/// such as "what did the buffer look like when expect #7 ran" and see why
+/// a pattern fired where it did, or did not fire at all.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{ExpectOutcome, History};
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let history = History::load("target/deploy.history")?;
+/// let record = history.expect_call(7).expect("at least 7 expect calls");
+/// println!("patterns: {:?}", record.patterns);
+/// println!("buffer: {:?}", String::from_utf8_lossy(&record.buffer));
+/// if let ExpectOutcome::Matched { range, .. } = &record.outcome {
+///     println!("matched at {:?}", range);
+/// }
+/// println!(
+///     "output so far: {:?}",
+///     String::from_utf8_lossy(history.output_at(7).unwrap())
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History {
+    output: Vec<u8>,
+    expects: Vec<ExpectRecord>,
+}
+
+/// One expect call in a [`History`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectRecord {
+    /// Time since the session started when the call finished.
+    pub elapsed: Duration,
+    /// Descriptions of the patterns waited for, in order.
+    pub patterns: Vec<String>,
+    /// How the call ended.
+    pub outcome: ExpectOutcome,
+    /// The unconsumed buffer as last searched, before a match consumed it.
+    pub buffer: Vec<u8>,
+    /// Bytes of output received when the call finished.
+    pub output_len: usize,
+}
+
+/// How a recorded expect call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectOutcome {
+    /// A pattern matched.
+    Matched {
+        /// Index of the pattern that matched.
+        pattern_index: usize,
+        /// Position of the match within [`ExpectRecord::buffer`].
+        range: Range<usize>,
+    },
+    /// The call failed with this error message.
+    Failed(String),
+}
+
+impl History {
+    /// Load a history file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid
+    /// history. A file cut short by a crash loads up to its last complete
+    /// record.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ExpectError> {
+        let content = std::fs::read_to_string(path)?;
+        content.parse()
+    }
+
+    /// All output received, exactly as the process printed it.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// The recorded expect calls, in order.
+    pub fn expects(&self) -> &[ExpectRecord] {
+        &self.expects
+    }
+
+    /// Expect call number `number`, counting from 1.
+    pub fn expect_call(&self, number: usize) -> Option<&ExpectRecord> {
+        self.expects.get(number.checked_sub(1)?)
+    }
+
+    /// The output received up to the end of expect call `number`.
+    pub fn output_at(&self, number: usize) -> Option<&[u8]> {
+        self.expect_call(number)
+            .map(|record| &self.output[..record.output_len])
+    }
+}
+
+impl FromStr for History {
+    type Err = ExpectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("missing history header"));
+        }
+
+        let mut history = History::default();
+        let mut patterns = Vec::new();
+        let mut outcome = None;
+        let mut parse_line = |line: &str| -> Result<(), ExpectError> {
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match kind {
+                "output" => {
+                    let (_, data) = rest.split_once(' ').unwrap_or((rest, ""));
+                    history.output.extend(unescape(data)?);
+                }
+                "pattern" => patterns.push(String::from_utf8_lossy(&unescape(rest)?).into_owned()),
+                "match" => {
+                    let fields: Vec<&str> = rest.split(' ').collect();
+                    let [elapsed, index, start, end] = fields[..] else {
+                        return Err(invalid("malformed match entry"));
+                    };
+                    outcome = Some((
+                        parse_elapsed(elapsed)?,
+                        ExpectOutcome::Matched {
+                            pattern_index: parse_number(index)?,
+                            range: parse_number(start)?..parse_number(end)?,
+                        },
+                    ));
+                }
+                "error" => {
+                    let (elapsed, message) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let message = String::from_utf8_lossy(&unescape(message)?).into_owned();
+                    outcome = Some((parse_elapsed(elapsed)?, ExpectOutcome::Failed(message)));
+                }
+                "buffer" => {
+                    let (elapsed, outcome) = outcome
+                        .take()
+                        .ok_or_else(|| invalid("buffer entry without outcome"))?;
+                    history.expects.push(ExpectRecord {
+                        elapsed,
+                        patterns: std::mem::take(&mut patterns),
+                        outcome,
+                        buffer: unescape(rest)?,
+                        output_len: history.output.len(),
+                    });
+                }
+                other => return Err(invalid(&format!("unknown entry '{}'", other))),
+            }
+            Ok(())
+        };
+
+        // A process killed mid-write leaves a torn final line without its
+        // line feed; everything before it is still usable
+        let torn = !s.ends_with('\n');
+        let mut lines = lines.filter(|l| !l.is_empty()).peekable();
+        while let Some(line) = lines.next() {
+            if let Err(err) = parse_line(line) {
+                if torn && lines.peek().is_none() {
+                    break;
+                }
+                return Err(err);
+            }
+        }
+
+        Ok(history)
+    }
+}
+
+/// Writer recording a session's history as it happens.
+///
+/// Every line is written immediately, so the history is complete up to the
+/// moment a process hangs or the program is killed.
+#[derive(Debug)]
+pub(crate) struct HistoryLog {
+    start: Instant,
+    file: File,
+    /// Buffer searched by the current expect call when it matched, with its
+    /// absolute start position.
+    searched: Option<(usize, Vec<u8>)>,
+}
+
+impl HistoryLog {
+    /// Create `path`, replacing any previous history.
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", HEADER)?;
+        Ok(Self {
+            start: Instant::now(),
+            file,
+            searched: None,
+        })
+    }
+
+    /// Record output read from the process.
+    ///
+    /// Write errors are ignored, as for the log file.
+    pub(crate) fn output(&mut self, data: &[u8]) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let _ = writeln!(self.file, "output {:.3} {}", elapsed, escape(data));
+    }
+
+    /// Remember the buffer a match was found in, before it is consumed.
+    pub(crate) fn searched(&mut self, position: usize, buffer: &[u8]) {
+        self.searched = Some((position, buffer.to_vec()));
+    }
+
+    /// Record the end of an expect call.
+    ///
+    /// `position` and `buffer` are the unconsumed buffer now, used unless
+    /// the call remembered what it searched.
+    pub(crate) fn expect(
+        &mut self,
+        patterns: &[Pattern],
+        result: &Result<MatchResult, ExpectError>,
+        position: usize,
+        buffer: &[u8],
+    ) {
+        let (position, buffer) = match self.searched.take() {
+            Some((position, buffer)) => (position, buffer),
+            None => (position, buffer.to_vec()),
+        };
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let mut record = String::new();
+        for pattern in patterns {
+            record.push_str(&format!(
+                "pattern {}\n",
+                escape(format!("{:?}", pattern).as_bytes())
+            ));
+        }
+        match result {
+            Ok(m) => record.push_str(&format!(
+                "match {:.3} {} {} {}\n",
+                elapsed,
+                m.pattern_index,
+                m.start.saturating_sub(position),
+                m.end.saturating_sub(position)
+            )),
+            Err(e) => record.push_str(&format!(
+                "error {:.3} {}\n",
+                elapsed,
+                escape(e.to_string().as_bytes())
+            )),
+        }
+        record.push_str(&format!("buffer {}\n", escape(&buffer)));
+        let _ = self.file.write_all(record.as_bytes());
+    }
+}
+
+fn invalid(message: &str) -> ExpectError {
+    ExpectError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid history: {}", message),
+    ))
+}
+
+fn parse_number(field: &str) -> Result<usize, ExpectError> {
+    field.parse().map_err(|_| invalid("bad number"))
+}
+
+fn parse_elapsed(field: &str) -> Result<Duration, ExpectError> {
+    field
+        .parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| invalid("bad time"))
+}
+
+/// Escape bytes so they fit on a single line without ambiguity.
+fn escape(data: &[u8]) -> String {
+    let mut escaped = String::with_capacity(data.len());
+    for &byte in data {
+        match byte {
+            b'\\' => escaped.push_str("\\\\"),
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> Result<Vec<u8>, ExpectError> {
+    let bytes = s.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            result.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(b'\\') => result.push(b'\\'),
+            Some(b'n') => result.push(b'\n'),
+            Some(b'r') => result.push(b'\r'),
+            Some(b'x') => {
+                let hex = s
+                    .get(i + 2..i + 4)
+                    .ok_or_else(|| invalid("bad escape sequence"))?;
+                let byte =
+                    u8::from_str_radix(hex, 16).map_err(|_| invalid("bad escape sequence"))?;
+                result.push(byte);
+                i += 2;
+            }
+            _ => return Err(invalid("bad escape sequence")),
+        }
+        i += 2;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_round_trip() {
+        let data = b"a b\\c\r\n\x1b[0m\xff\x00~";
+        let escaped = escape(data);
+        assert!(!escaped.contains('\n'));
+        assert_eq!(unescape(&escaped).unwrap(), data);
+        assert!(unescape("\\x4").is_err());
+        assert!(unescape("\\q").is_err());
+    }
+
+    #[test]
+    fn test_parse_history() {
+        let history: History = "# expectrust history v1\n\
+            output 0.010 login:\\x20\n\
+            pattern Exact(\"login: \")\n\
+            match 0.011 0 0 7\n\
+            buffer login:\\x20\n\
+            output 0.500 Password:\n\
+            pattern Exact(\"$ \")\n\
+            error 2.000 Timeout\n\
+            buffer Password:\n\
+            pattern Eof\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(history.output(), b"login: Password:");
+        assert_eq!(history.expects().len(), 2);
+        let first = history.expect_call(1).unwrap();
+        assert_eq!(first.patterns, ["Exact(\"login: \")"]);
+        assert_eq!(
+            first.outcome,
+            ExpectOutcome::Matched {
+                pattern_index: 0,
+                range: 0..7
+            }
+        );
+        assert_eq!(history.output_at(1).unwrap(), b"login: ");
+        let second = history.expect_call(2).unwrap();
+        assert_eq!(second.outcome, ExpectOutcome::Failed("Timeout".into()));
+        assert_eq!(second.elapsed, Duration::from_secs(2));
+        assert!(history.expect_call(0).is_none());
+        assert!(history.expect_call(3).is_none());
+
+        assert!("output 0.1 x\n".parse::<History>().is_err());
+    }
+
+    #[test]
+    fn test_parse_history_skips_torn_last_line() {
+        let text = "# expectrust history v1\n\
+            output 0.010 login:\\x20\n\
+            output 0.020 Pass\\x2";
+        let history: History = text.parse().unwrap();
+        assert_eq!(history.output(), b"login: ");
+
+        // With its line feed, the line was written whole and is an error
+        assert!(format!("{}\n", text).parse::<History>().is_err());
+    }
+}
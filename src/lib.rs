@@ -44,7 +44,7 @@
 //!
 //! ExpectRust supports multiple pattern types:
 //!
-//! - **Exact**: Fast string matching using Boyer-Moore-Horspool
+//! - **Exact**: Fast string matching using SIMD-accelerated substring search
 //! - **Regex**: Full regular expression support
 //! - **Glob**: Shell-style wildcard patterns
 //! - **EOF**: Match end of file
@@ -130,18 +130,60 @@
 #![warn(missing_docs)]
 
 mod buffer;
+mod checkpoint;
+mod history;
+mod login;
+#[cfg(feature = "packs")]
+mod pack;
 mod pattern;
 mod result;
+mod screen;
 mod session;
+#[cfg(feature = "steps")]
+mod steps;
+mod teach;
+mod transcript;
+mod transfer;
+
+pub mod blocking;
+pub mod cmd;
+pub mod config;
+pub mod quote;
+pub mod service;
 
 // Optional script module
 #[cfg(feature = "script")]
 pub mod script;
 
+// Optional control server
+#[cfg(feature = "server")]
+pub mod server;
+
 // Public API exports
-pub use pattern::Pattern;
-pub use result::{ExpectError, MatchResult, PatternError};
-pub use session::{Session, SessionBuilder};
+pub use buffer::{BufferManager, Escaped, RawStream, RetentionPolicy};
+pub use checkpoint::Checkpoint;
+pub use history::{ExpectOutcome, ExpectRecord, History};
+pub use login::{Credentials, LoginError, LoginSpec, LoginStage, PromptClassifier, PromptEvent};
+pub use pattern::{search, Match, Matcher, Pattern, PatternFactory, RegexOptions, TypedPatterns};
+pub use result::{ExpectError, MatchResult, PatternError, Report};
+#[cfg(feature = "diagnostics")]
+pub use result::{MatchDiagnostics, PatternStats};
+pub use screen::{Key, Rect, Screen, ScreenPattern};
+pub use session::{
+    AutoResponse, Backoff, CommandOutput, ConnectReport, Consume, ControlChar, DropPolicy,
+    ExpectOptions, FailureCluster, GroupError, GroupSummary, HostSummary, HumanTyping,
+    IntoControlChar, Lines, MergedMatch, MergedSession, PtyEvent, Session, SessionBuilder,
+    SessionGroup, SlowStep, SoftTimeout, Unreachable,
+};
+pub use teach::{RecordedStep, Recorder};
+pub use transcript::{Direction, Origin, Transcript, TranscriptEntry};
+pub use transfer::{FtpDriver, SftpDriver, TransferError, TransferProgress};
+
+#[cfg(feature = "steps")]
+pub use steps::{Step, Steps};
+
+#[cfg(feature = "packs")]
+pub use pack::{PackPattern, PackTest, PatternPack, PACK_FORMAT};
 
 // Re-export commonly used types
-pub use portable_pty::ExitStatus;
+pub use portable_pty::{CommandBuilder, ExitStatus};
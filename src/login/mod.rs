@@ -0,0 +1,257 @@
+//! Declarative login state machine
+
+mod prompt;
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::Session;
+use std::fmt;
+use thiserror::Error;
+
+pub use prompt::{PromptClassifier, PromptEvent};
+
+/// Default number of retries after a rejected login.
+const DEFAULT_MAX_RETRIES: usize = 2;
+
+/// Description of a login dialog.
+///
+/// A `LoginSpec` lists the prompts a device shows during login and the
+/// output that tells success from failure. [`Session::login`] turns it into
+/// a state machine: it answers the username and password prompts, retries
+/// after a recognized failure, and stops once the success pattern appears.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Credentials, LoginSpec, Pattern, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let spec = LoginSpec::new(
+///     Pattern::exact("login: "),
+///     Pattern::exact("Password: "),
+///     Pattern::regex(r"[$#] $")?,
+/// )
+/// .failure(Pattern::exact("Login incorrect"))
+/// .max_retries(1);
+///
+/// let mut session = Session::spawn("telnet router")?;
+/// session.login(&spec, &Credentials::new("admin", "secret")).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoginSpec {
+    username_prompt: Pattern,
+    password_prompt: Pattern,
+    success: Pattern,
+    failures: Vec<Pattern>,
+    max_retries: usize,
+}
+
+impl LoginSpec {
+    /// Create a login spec from the username prompt, password prompt, and
+    /// the pattern that indicates a successful login (usually the shell prompt).
+    pub fn new(username_prompt: Pattern, password_prompt: Pattern, success: Pattern) -> Self {
+        Self {
+            username_prompt,
+            password_prompt,
+            success,
+            failures: Vec::new(),
+            max_retries: DEFAULT_MAX_RETRIES,
+        }
+    }
+
+    /// Add a pattern that indicates the login was rejected.
+    ///
+    /// Failure patterns are checked in the order they were added, and the
+    /// index of the one that matched is reported in [`LoginError::Rejected`].
+    pub fn failure(mut self, pattern: Pattern) -> Self {
+        self.failures.push(pattern);
+        self
+    }
+
+    /// Set how many times to retry after a rejected login (default: 2).
+    pub fn max_retries(mut self, retries: usize) -> Self {
+        self.max_retries = retries;
+        self
+    }
+}
+
+/// Username and password used by [`Session::login`].
+///
+/// The `Debug` output never includes the password.
+#[derive(Clone)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    /// Create credentials from a username and password.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// The username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// How far a login attempt got before it stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginStage {
+    /// Nothing has been sent yet in the current attempt.
+    Connecting,
+    /// The username was sent.
+    UsernameSent,
+    /// The password was sent.
+    PasswordSent,
+}
+
+/// Reasons a login did not succeed.
+#[derive(Error, Debug)]
+pub enum LoginError {
+    /// The device rejected every attempt.
+    ///
+    /// `failure` is the index of the matching failure pattern, or `None` if
+    /// the device prompted again after the password without a recognized
+    /// failure message.
+    #[error("Login rejected after {attempts} attempt(s): {output:?}")]
+    Rejected {
+        /// Number of attempts made
+        attempts: usize,
+        /// Index of the failure pattern that matched, if any
+        failure: Option<usize>,
+        /// The matched failure text
+        output: String,
+    },
+
+    /// No expected prompt or result appeared in time.
+    #[error("Login timed out at stage {stage:?}")]
+    Timeout {
+        /// Stage the attempt had reached
+        stage: LoginStage,
+    },
+
+    /// The connection closed during login.
+    #[error("Connection closed during login at stage {stage:?}")]
+    Eof {
+        /// Stage the attempt had reached
+        stage: LoginStage,
+    },
+
+    /// Any other session error.
+    #[error(transparent)]
+    Expect(#[from] ExpectError),
+}
+
+/// Pattern indices used by the login state machine.
+const SUCCESS: usize = 0;
+const FIRST_FAILURE: usize = 1;
+
+impl Session {
+    /// Log in by following a [`LoginSpec`].
+    ///
+    /// Answers username and password prompts as they appear, so devices that
+    /// skip the username prompt or show a banner first are handled the same
+    /// way. A failure pattern, or a new prompt after the password was sent,
+    /// counts as a rejected attempt and is retried up to the spec's
+    /// `max_retries`.
+    ///
+    /// # Returns
+    ///
+    /// The `MatchResult` of the success pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LoginError`] describing why the login did not succeed.
+    pub async fn login(
+        &mut self,
+        spec: &LoginSpec,
+        credentials: &Credentials,
+    ) -> Result<MatchResult, LoginError> {
+        // Outcomes first: expect_any checks patterns in order, so a failure
+        // message is seen before the prompt that follows it
+        let mut patterns = vec![spec.success.clone()];
+        patterns.extend(spec.failures.iter().cloned());
+        let password_index = patterns.len();
+        patterns.push(spec.password_prompt.clone());
+        let username_index = patterns.len();
+        patterns.push(spec.username_prompt.clone());
+
+        let mut stage = LoginStage::Connecting;
+        let mut attempts = 1;
+
+        loop {
+            let result = match self.expect_any(&patterns).await {
+                Ok(result) => result,
+                Err(ExpectError::Timeout { .. }) => return Err(LoginError::Timeout { stage }),
+                Err(ExpectError::Eof) => return Err(LoginError::Eof { stage }),
+                Err(e) => return Err(e.into()),
+            };
+
+            let index = result.pattern_index;
+            if index == SUCCESS {
+                return Ok(result);
+            }
+
+            let rejected = if index < password_index {
+                Some(Some(index - FIRST_FAILURE))
+            } else if stage == LoginStage::PasswordSent {
+                Some(None)
+            } else {
+                None
+            };
+
+            if let Some(failure) = rejected {
+                if attempts > spec.max_retries {
+                    return Err(LoginError::Rejected {
+                        attempts,
+                        failure,
+                        output: result.matched,
+                    });
+                }
+                attempts += 1;
+                stage = LoginStage::Connecting;
+                // A recognized failure message is followed by a fresh prompt
+                if failure.is_some() {
+                    continue;
+                }
+            }
+
+            if index == username_index {
+                self.send_line(&credentials.username).await?;
+                stage = LoginStage::UsernameSent;
+            } else if index == password_index {
+                self.send_secret(&credentials.password).await?;
+                stage = LoginStage::PasswordSent;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_debug_redacts_password() {
+        let credentials = Credentials::new("admin", "hunter2");
+        let debug = format!("{:?}", credentials);
+        assert!(debug.contains("admin"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
@@ -0,0 +1,239 @@
+//! Classifying password prompts
+
+use crate::pattern::{Matcher, Pattern};
+use crate::result::{ExpectError, PatternError};
+use crate::session::{Consume, Session};
+
+/// What the output says about a password prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptEvent {
+    /// A password prompt with no failure before it: the first attempt.
+    Password,
+    /// A password prompt after a failure message: the last password sent
+    /// was rejected.
+    Retry,
+    /// No prompt: the command went ahead without asking, for example
+    /// because sudo cached the credentials. Send no password.
+    NoPrompt,
+}
+
+/// Tells first password prompts from retries and from no prompt at all.
+///
+/// Sending a password blindly after a command that may or may not ask for
+/// one types it into whatever runs next, and re-sending a rejected password
+/// locks accounts. The classifier looks at the output around the prompt
+/// instead: a failure message on the line before the prompt makes it a
+/// [`PromptEvent::Retry`], and output showing the command went ahead (such
+/// as its result or the shell prompt) without a prompt first makes it
+/// [`PromptEvent::NoPrompt`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Pattern, PromptClassifier, PromptEvent, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let classifier = PromptClassifier::password().without_prompt(Pattern::exact("$ "))?;
+///
+/// let mut session = Session::spawn("bash")?;
+/// session.send_line("sudo systemctl restart nginx").await?;
+/// match session.classify_prompt(&classifier).await? {
+///     PromptEvent::Password => session.send_line("secret").await?,
+///     PromptEvent::Retry => return Err("password rejected".into()),
+///     PromptEvent::NoPrompt => {}
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct PromptClassifier {
+    prompt: Pattern,
+    prompt_matcher: Box<dyn Matcher>,
+    failures: Vec<(Pattern, Box<dyn Matcher>)>,
+    without_prompt: Vec<(Pattern, Box<dyn Matcher>)>,
+}
+
+impl PromptClassifier {
+    /// Create a classifier for a password prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or is a special pattern
+    /// such as `Pattern::Eof`.
+    pub fn new(prompt: Pattern) -> Result<Self, PatternError> {
+        Ok(Self {
+            prompt_matcher: prompt.to_matcher()?,
+            prompt,
+            failures: Vec::new(),
+            without_prompt: Vec::new(),
+        })
+    }
+
+    /// Create a classifier for the usual prompts of `sudo`, `su`, `ssh` and
+    /// `login`, with their failure messages.
+    pub fn password() -> Self {
+        let prompt = Pattern::regex(r"(?i)password[^:\r\n]*: ?$").expect("valid prompt regex");
+        let mut classifier = Self::new(prompt).expect("valid prompt pattern");
+        for failure in [
+            "Sorry, try again.",
+            "Permission denied",
+            "Login incorrect",
+            "Authentication failure",
+        ] {
+            classifier = classifier
+                .failure(Pattern::exact(failure))
+                .expect("valid failure pattern");
+        }
+        classifier
+    }
+
+    /// Add a message meaning the previous password was rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid.
+    pub fn failure(mut self, pattern: Pattern) -> Result<Self, PatternError> {
+        let matcher = pattern.to_matcher()?;
+        self.failures.push((pattern, matcher));
+        Ok(self)
+    }
+
+    /// Add output meaning the command went ahead without a prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid.
+    pub fn without_prompt(mut self, pattern: Pattern) -> Result<Self, PatternError> {
+        let matcher = pattern.to_matcher()?;
+        self.without_prompt.push((pattern, matcher));
+        Ok(self)
+    }
+
+    /// Classify output, or `None` if it shows neither a prompt nor output
+    /// meaning there will be none.
+    ///
+    /// Whichever comes first decides: a prompt, or output from
+    /// [`without_prompt`](Self::without_prompt). Failure messages only
+    /// count on the prompt's line and the last non-blank line before it, so
+    /// output left over from earlier commands does not turn a first prompt
+    /// into a retry.
+    pub fn classify(&self, output: &[u8]) -> Option<PromptEvent> {
+        self.decide(output).map(|(event, _)| event)
+    }
+
+    /// Classify output, along with the index in
+    /// [`patterns()`](Self::patterns) of the pattern that decided.
+    fn decide(&self, output: &[u8]) -> Option<(PromptEvent, usize)> {
+        let prompt = self.prompt_matcher.find(output).map(|m| m.start);
+        let proceeded = self
+            .without_prompt
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, matcher))| matcher.find(output).map(|m| (m.start, i + 1)))
+            .min();
+
+        let Some(prompt) = prompt.filter(|&p| proceeded.is_none_or(|(q, _)| p <= q)) else {
+            return proceeded.map(|(_, index)| (PromptEvent::NoPrompt, index));
+        };
+        let window = &output[reply_start(&output[..prompt])..prompt];
+        let failed = self
+            .failures
+            .iter()
+            .any(|(_, matcher)| matcher.find(window).is_some());
+        let event = if failed {
+            PromptEvent::Retry
+        } else {
+            PromptEvent::Password
+        };
+        Some((event, 0))
+    }
+
+    /// The patterns that end the wait, in order.
+    fn patterns(&self) -> Vec<Pattern> {
+        let mut patterns = vec![self.prompt.clone()];
+        patterns.extend(self.without_prompt.iter().map(|(p, _)| p.clone()));
+        patterns
+    }
+}
+
+/// Start of the last non-blank line of `output`, including the partial
+/// line at its end.
+fn reply_start(output: &[u8]) -> usize {
+    let is_break = |b: &u8| matches!(b, b'\r' | b'\n');
+    // The prompt's own line, then the line before it
+    let line_start = output.iter().rposition(is_break).map_or(0, |i| i + 1);
+    let before = output[..line_start].trim_ascii_end();
+    before.iter().rposition(is_break).map_or(0, |i| i + 1)
+}
+
+impl Session {
+    /// Wait for a password prompt, or output showing none will come, and
+    /// classify it.
+    ///
+    /// Output up to the end of the prompt, or of the output that showed the
+    /// command went ahead, whichever decided, is consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::Timeout` if neither appears within the session
+    /// timeout, and `ExpectError::Eof` if the process exits first.
+    pub async fn classify_prompt(
+        &mut self,
+        classifier: &PromptClassifier,
+    ) -> Result<PromptEvent, ExpectError> {
+        let patterns = classifier.patterns();
+        self.expect_any_consuming(&patterns, Consume::Nothing)
+            .await?;
+        let output = self.peek()?;
+        let (event, index) = classifier
+            .decide(output.as_bytes())
+            .unwrap_or((PromptEvent::NoPrompt, 0));
+        self.expect(patterns[index].clone()).await?;
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_prompts() {
+        let classifier = PromptClassifier::password()
+            .without_prompt(Pattern::exact("$ "))
+            .unwrap();
+
+        assert_eq!(
+            classifier.classify(b"sudo ls\r\n[sudo] password for admin: "),
+            Some(PromptEvent::Password)
+        );
+        assert_eq!(
+            classifier.classify(b"\r\nSorry, try again.\r\n[sudo] password for admin: "),
+            Some(PromptEvent::Retry)
+        );
+        assert_eq!(
+            classifier.classify(b"sudo ls\r\nfile.txt\r\n$ "),
+            Some(PromptEvent::NoPrompt)
+        );
+        assert_eq!(classifier.classify(b"sudo ls\r\n"), None);
+    }
+
+    #[test]
+    fn test_classify_ignores_stale_failures() {
+        let classifier = PromptClassifier::password();
+
+        // Left over from an earlier command
+        assert_eq!(
+            classifier.classify(b"rm: Permission denied\r\nsudo ls\r\n[sudo] password for admin: "),
+            Some(PromptEvent::Password)
+        );
+        assert_eq!(
+            classifier
+                .classify(b"Permission denied, please try again.\r\n\r\nadmin@host's password: "),
+            Some(PromptEvent::Retry)
+        );
+        assert_eq!(
+            classifier.classify(b"Sorry, try again. [sudo] password for admin: "),
+            Some(PromptEvent::Retry)
+        );
+    }
+}
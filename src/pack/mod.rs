@@ -0,0 +1,352 @@
+//! Shareable pattern packs loaded from TOML files
+
+use crate::pattern::{Pattern, TypedPatterns};
+use crate::result::{ExpectError, PatternError};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Newest pack format this version can read.
+pub const PACK_FORMAT: u32 = 1;
+
+/// A named collection of patterns for a family of devices or programs.
+///
+/// Packs let organizations curate prompt and error patterns once and
+/// distribute them as files. A pack is written in TOML:
+///
+/// ```toml
+/// format = 1
+/// name = "cisco-ios"
+/// version = "1.2.0"
+/// family = "Cisco IOS"
+/// description = "Prompts and errors of IOS routers and switches"
+///
+/// [metadata]
+/// maintainer = "netops@example.com"
+///
+/// [[pattern]]
+/// name = "prompt"
+/// regex = '(?m)^[\w.-]+(\(config[^)]*\))?[>#] ?$'
+/// description = "User, enable and configuration mode prompts"
+///
+/// [[pattern]]
+/// name = "invalid-input"
+/// expect = "% Invalid input detected"
+/// tags = ["error"]
+///
+/// [[test]]
+/// pattern = "prompt"
+/// sample = "Building configuration...\r\nrouter1(config-if)# "
+/// matched = "router1(config-if)# "
+///
+/// [[test]]
+/// pattern = "prompt"
+/// sample = "router1 uptime is 3 weeks"
+/// matches = false
+/// ```
+///
+/// `format` is required and versions the file format itself; packs written
+/// for a newer format are rejected rather than misread. Each pattern has a
+/// unique `name` and exactly one of `expect` (exact text), `regex` or
+/// `glob`. Tests check a pattern against a sample transcript: by default
+/// the pattern must match, and `matched` additionally checks the matched
+/// text. Run them with [`PatternPack::verify`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternPack {
+    /// Version of the pack file format.
+    pub format: u32,
+    /// Name of the pack.
+    pub name: String,
+    /// Version of the pack's contents, as chosen by its authors.
+    pub version: Option<String>,
+    /// Device or program family the patterns are for.
+    pub family: Option<String>,
+    /// Free-form description.
+    pub description: Option<String>,
+    /// Additional free-form metadata.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// The patterns, in file order.
+    #[serde(rename = "pattern", default)]
+    pub patterns: Vec<PackPattern>,
+    /// Tests checking the patterns against sample output.
+    #[serde(rename = "test", default)]
+    pub tests: Vec<PackTest>,
+}
+
+/// A named pattern in a [`PatternPack`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackPattern {
+    /// Name the pattern is looked up by.
+    pub name: String,
+    /// Exact text to match.
+    pub expect: Option<String>,
+    /// Regular expression to match.
+    pub regex: Option<String>,
+    /// Glob pattern to match.
+    pub glob: Option<String>,
+    /// Free-form description.
+    pub description: Option<String>,
+    /// Free-form tags, such as `prompt` or `error`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A check of a [`PackPattern`] against sample output.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackTest {
+    /// Name of the pattern under test.
+    pub pattern: String,
+    /// Sample transcript of process output.
+    pub sample: String,
+    /// Whether the pattern must match the sample (default) or must not.
+    #[serde(default = "default_matches")]
+    pub matches: bool,
+    /// Text the pattern must match, if given.
+    pub matched: Option<String>,
+}
+
+fn default_matches() -> bool {
+    true
+}
+
+impl PackPattern {
+    /// The pattern to match.
+    pub fn pattern(&self) -> Result<Pattern, ExpectError> {
+        match (&self.expect, &self.regex, &self.glob) {
+            (Some(text), None, None) => Ok(Pattern::exact(text)),
+            (None, Some(regex), None) => Ok(Pattern::regex(regex).map_err(PatternError::from)?),
+            (None, None, Some(glob)) => Ok(Pattern::glob(glob)),
+            _ => Err(invalid(&format!(
+                "pattern '{}' needs exactly one of expect, regex and glob",
+                self.name
+            ))),
+        }
+    }
+}
+
+impl PatternPack {
+    /// Load a pack from a TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not a valid pack, or
+    /// uses a newer format than [`PACK_FORMAT`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{PatternPack, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let ios = PatternPack::load("packs/cisco-ios.toml")?;
+    /// let mut session = Session::spawn("ssh admin@router1")?;
+    /// session.expect(ios.pattern("prompt")?).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ExpectError> {
+        let content = std::fs::read_to_string(path)?;
+        content.parse()
+    }
+
+    /// The pattern with the given name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pack has no pattern with that name.
+    pub fn pattern(&self, name: &str) -> Result<Pattern, ExpectError> {
+        self.get(name)
+            .ok_or_else(|| {
+                invalid(&format!(
+                    "pack '{}' has no pattern named '{}'",
+                    self.name, name
+                ))
+            })?
+            .pattern()
+    }
+
+    /// The pattern entry with the given name, with its description and tags.
+    pub fn get(&self, name: &str) -> Option<&PackPattern> {
+        self.patterns.iter().find(|p| p.name == name)
+    }
+
+    /// All patterns, tagged with their names, for
+    /// [`Session::expect_typed`](crate::Session::expect_typed).
+    pub fn typed(&self) -> Result<TypedPatterns<String>, ExpectError> {
+        self.patterns
+            .iter()
+            .map(|p| Ok((p.name.clone(), p.pattern()?)))
+            .collect()
+    }
+
+    /// The patterns carrying a tag, tagged with their names.
+    pub fn tagged(&self, tag: &str) -> Result<TypedPatterns<String>, ExpectError> {
+        self.patterns
+            .iter()
+            .filter(|p| p.tags.iter().any(|t| t == tag))
+            .map(|p| Ok((p.name.clone(), p.pattern()?)))
+            .collect()
+    }
+
+    /// Run the pack's tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first failing test.
+    pub fn verify(&self) -> Result<(), ExpectError> {
+        for (index, test) in self.tests.iter().enumerate() {
+            let matcher = self.pattern(&test.pattern)?.to_matcher()?;
+            let found = matcher
+                .find(test.sample.as_bytes())
+                .map(|m| &test.sample[m.start..m.end]);
+            let failure = match (found, test.matches, &test.matched) {
+                (Some(text), false, _) => format!("matched {:?} but must not match", text),
+                (None, true, _) => "did not match".to_string(),
+                (Some(text), true, Some(expected)) if text != expected => {
+                    format!("matched {:?} instead of {:?}", text, expected)
+                }
+                _ => continue,
+            };
+            return Err(invalid(&format!(
+                "test {} of pattern '{}' {}",
+                index + 1,
+                test.pattern,
+                failure
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PatternPack {
+    type Err = ExpectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Check the format first: newer packs may not parse as this version
+        #[derive(Deserialize)]
+        struct Header {
+            format: Option<u32>,
+        }
+        let header: Header = toml::from_str(s).map_err(|e| invalid(&e.to_string()))?;
+        match header.format {
+            None => return Err(invalid("missing 'format'")),
+            Some(format) if format > PACK_FORMAT => {
+                return Err(invalid(&format!(
+                    "format {} is newer than the supported format {}",
+                    format, PACK_FORMAT
+                )))
+            }
+            Some(_) => {}
+        }
+
+        let pack: PatternPack = toml::from_str(s).map_err(|e| invalid(&e.to_string()))?;
+        let mut names = HashSet::new();
+        for pattern in &pack.patterns {
+            if !names.insert(pattern.name.as_str()) {
+                return Err(invalid(&format!("duplicate pattern '{}'", pattern.name)));
+            }
+            pattern.pattern()?;
+        }
+        for test in &pack.tests {
+            if !names.contains(test.pattern.as_str()) {
+                return Err(invalid(&format!(
+                    "test of unknown pattern '{}'",
+                    test.pattern
+                )));
+            }
+        }
+        Ok(pack)
+    }
+}
+
+fn invalid(message: &str) -> ExpectError {
+    ExpectError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid pattern pack: {}", message),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = r#"
+        format = 1
+        name = "demo"
+        family = "Demo OS"
+
+        [metadata]
+        maintainer = "ops"
+
+        [[pattern]]
+        name = "prompt"
+        regex = '[\w-]+[>#] ?$'
+
+        [[pattern]]
+        name = "error"
+        expect = "% Invalid"
+        tags = ["error"]
+
+        [[test]]
+        pattern = "prompt"
+        sample = "show version\r\nrouter-1# "
+        matched = "router-1# "
+
+        [[test]]
+        pattern = "error"
+        sample = "router-1# "
+        matches = false
+    "#;
+
+    #[test]
+    fn test_parse_and_verify_pack() {
+        let pack: PatternPack = PACK.parse().unwrap();
+        assert_eq!(pack.family.as_deref(), Some("Demo OS"));
+        assert_eq!(pack.metadata["maintainer"], "ops");
+        assert!(matches!(pack.pattern("prompt").unwrap(), Pattern::Regex(_)));
+        assert!(pack.pattern("missing").is_err());
+        assert_eq!(pack.typed().unwrap().len(), 2);
+        assert_eq!(pack.tagged("error").unwrap().tag(0).unwrap(), "error");
+        pack.verify().unwrap();
+
+        let failing = PACK.replace("matched = \"router-1# \"", "matched = \"router-2# \"");
+        let error = failing
+            .parse::<PatternPack>()
+            .unwrap()
+            .verify()
+            .unwrap_err();
+        assert!(error.to_string().contains("test 1 of pattern 'prompt'"));
+    }
+
+    #[test]
+    fn test_reject_invalid_packs() {
+        let newer = PACK.replace("format = 1", "format = 2\nfuture = true");
+        assert!(newer
+            .parse::<PatternPack>()
+            .unwrap_err()
+            .to_string()
+            .contains("newer"));
+        assert!(PACK
+            .replace("format = 1", "")
+            .parse::<PatternPack>()
+            .is_err());
+        assert!(PACK
+            .replace("name = \"error\"", "name = \"prompt\"")
+            .parse::<PatternPack>()
+            .is_err());
+        assert!(PACK
+            .replace("pattern = \"error\"", "pattern = \"eror\"")
+            .parse::<PatternPack>()
+            .is_err());
+        assert!(PACK
+            .replace("expect = \"% Invalid\"", "glob = \"%*\"\nexpect = \"%\"")
+            .parse::<PatternPack>()
+            .is_err());
+    }
+}
@@ -1,7 +1,8 @@
 //! Pattern matcher implementations
 
+use super::search::{self, Finder};
 use crate::result::PatternError;
-use globset::{Glob, GlobMatcher as GlobsetMatcher};
+use globset::{Glob, GlobBuilder, GlobMatcher as GlobsetMatcher};
 use regex::Regex;
 
 /// Result of a pattern match
@@ -13,6 +14,8 @@ pub struct Match {
     pub end: usize,
     /// Captured groups (for regex)
     pub captures: Vec<String>,
+    /// Captured groups as raw bytes (for regex)
+    pub captures_bytes: Vec<Vec<u8>>,
 }
 
 /// Trait for pattern matching
@@ -26,10 +29,10 @@ pub trait Matcher: Send + Sync {
     }
 }
 
-/// Exact string matcher using Boyer-Moore-Horspool algorithm
+/// Exact string matcher backed by SIMD-accelerated substring search
 pub struct ExactMatcher {
     pattern: Vec<u8>,
-    bad_char_table: [usize; 256],
+    finder: Finder,
 }
 
 impl ExactMatcher {
@@ -41,52 +44,24 @@ impl ExactMatcher {
             return Err(PatternError::EmptyPattern);
         }
 
-        // Build bad character table for Boyer-Moore-Horspool
-        let mut bad_char_table = [pattern.len(); 256];
-        for (i, &byte) in pattern.iter().enumerate().take(pattern.len() - 1) {
-            bad_char_table[byte as usize] = pattern.len() - 1 - i;
-        }
-
-        Ok(Self {
-            pattern,
-            bad_char_table,
-        })
+        let finder = Finder::new(&pattern);
+        Ok(Self { pattern, finder })
     }
 }
 
 impl Matcher for ExactMatcher {
     fn find(&self, buffer: &[u8]) -> Option<Match> {
-        if buffer.len() < self.pattern.len() {
-            return None;
-        }
-
-        let mut pos = 0;
-        while pos + self.pattern.len() <= buffer.len() {
-            // Check if pattern matches at current position
-            if buffer[pos..pos + self.pattern.len()] == self.pattern[..] {
-                return Some(Match {
-                    start: pos,
-                    end: pos + self.pattern.len(),
-                    captures: vec![],
-                });
-            }
-
-            // Shift using bad character table
-            let shift_char = buffer[pos + self.pattern.len() - 1];
-            pos += self.bad_char_table[shift_char as usize];
-        }
-
-        None
+        self.finder.find(buffer).map(|pos| Match {
+            start: pos,
+            end: pos + self.pattern.len(),
+            captures: vec![],
+            captures_bytes: vec![],
+        })
     }
 
     fn partial_match(&self, buffer: &[u8]) -> bool {
         // Check if buffer ends with a prefix of the pattern
-        for i in 1..self.pattern.len() {
-            if buffer.len() >= i && buffer.ends_with(&self.pattern[..i]) {
-                return true;
-            }
-        }
-        false
+        search::partial_suffix_len(buffer, &self.pattern) > 0
     }
 }
 
@@ -97,10 +72,14 @@ pub struct RegexMatcher {
 
 impl RegexMatcher {
     /// Create a new regex matcher
+    #[cfg(test)]
     pub fn new(pattern: &str) -> Result<Self, PatternError> {
-        Ok(Self {
-            regex: Regex::new(pattern)?,
-        })
+        Ok(Self::from_regex(Regex::new(pattern)?))
+    }
+
+    /// Create a matcher from an already compiled regex, keeping its flags
+    pub fn from_regex(regex: Regex) -> Self {
+        Self { regex }
     }
 }
 
@@ -111,9 +90,46 @@ impl Matcher for RegexMatcher {
         let full_match = captures.get(0)?;
 
         let mut capture_strings = vec![];
+        let mut capture_bytes = vec![];
         for i in 0..captures.len() {
             if let Some(cap) = captures.get(i) {
                 capture_strings.push(cap.as_str().to_string());
+                capture_bytes.push(cap.as_str().as_bytes().to_vec());
+            }
+        }
+
+        Some(Match {
+            start: full_match.start(),
+            end: full_match.end(),
+            captures: capture_strings,
+            captures_bytes: capture_bytes,
+        })
+    }
+}
+
+/// Regex matcher over raw bytes, which does not require valid UTF-8
+pub struct BytesRegexMatcher {
+    regex: regex::bytes::Regex,
+}
+
+impl BytesRegexMatcher {
+    /// Create a matcher from an already compiled regex, keeping its flags
+    pub fn from_regex(regex: regex::bytes::Regex) -> Self {
+        Self { regex }
+    }
+}
+
+impl Matcher for BytesRegexMatcher {
+    fn find(&self, buffer: &[u8]) -> Option<Match> {
+        let captures = self.regex.captures(buffer)?;
+        let full_match = captures.get(0)?;
+
+        let mut capture_strings = vec![];
+        let mut capture_bytes = vec![];
+        for i in 0..captures.len() {
+            if let Some(cap) = captures.get(i) {
+                capture_strings.push(String::from_utf8_lossy(cap.as_bytes()).into_owned());
+                capture_bytes.push(cap.as_bytes().to_vec());
             }
         }
 
@@ -121,6 +137,7 @@ impl Matcher for RegexMatcher {
             start: full_match.start(),
             end: full_match.end(),
             captures: capture_strings,
+            captures_bytes: capture_bytes,
         })
     }
 }
@@ -148,6 +165,18 @@ impl GlobMatcher {
             matcher: glob.compile_matcher(),
         })
     }
+
+    /// Create a glob matcher that ignores letter case
+    pub fn case_insensitive(pattern: &str) -> Result<Self, PatternError> {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| PatternError::InvalidGlob(e.to_string()))?;
+
+        Ok(Self {
+            matcher: glob.compile_matcher(),
+        })
+    }
 }
 
 impl Matcher for GlobMatcher {
@@ -166,6 +195,7 @@ impl Matcher for GlobMatcher {
                         start,
                         end,
                         captures: vec![],
+                        captures_bytes: vec![],
                     });
                 }
             }
@@ -180,14 +210,52 @@ pub struct NullMatcher;
 
 impl Matcher for NullMatcher {
     fn find(&self, buffer: &[u8]) -> Option<Match> {
-        buffer.iter().position(|&b| b == 0).map(|pos| Match {
+        search::find_byte(buffer, 0).map(|pos| Match {
             start: pos,
             end: pos + 1,
             captures: vec![],
+            captures_bytes: vec![],
         })
     }
 }
 
+/// Matcher that only matches within complete lines
+///
+/// The inner matcher sees each line on its own, without the line feed and
+/// any carriage returns before it, so matches cannot span lines and anchors
+/// apply to the line.
+pub(crate) struct LineMatcher {
+    inner: Box<dyn Matcher>,
+}
+
+impl LineMatcher {
+    pub(crate) fn new(inner: Box<dyn Matcher>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Matcher for LineMatcher {
+    fn find(&self, buffer: &[u8]) -> Option<Match> {
+        let mut start = 0;
+        for end in memchr::memchr_iter(b'\n', buffer) {
+            let mut line = &buffer[start..end];
+            while let Some(stripped) = line.strip_suffix(b"\r") {
+                line = stripped;
+            }
+            if let Some(m) = self.inner.find(line) {
+                return Some(Match {
+                    start: start + m.start,
+                    end: start + m.end,
+                    captures: m.captures,
+                    captures_bytes: m.captures_bytes,
+                });
+            }
+            start = end + 1;
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -375,6 +443,26 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_bytes_regex_matcher_invalid_utf8() {
+        let matcher = BytesRegexMatcher::from_regex(
+            regex::bytes::Regex::new(r"(?-u)\x02(.)(\w+)\x03").unwrap(),
+        );
+        let buffer = b"\xff\xfenoise\x02\x80ok\x03";
+
+        let result = matcher.find(buffer).unwrap();
+        assert_eq!(result.start, 7);
+        assert_eq!(
+            result.captures_bytes,
+            [&b"\x02\x80ok\x03"[..], b"\x80", b"ok"]
+        );
+        assert_eq!(result.captures[1], "\u{fffd}");
+
+        // The text regex gives up on the same buffer
+        let text = RegexMatcher::new(r"ok").unwrap();
+        assert!(text.find(buffer).is_none());
+    }
+
     #[test]
     fn test_null_matcher_at_start() {
         let matcher = NullMatcher;
@@ -423,4 +511,21 @@ mod tests {
         assert_eq!(result.start, 6);
         assert_eq!(result.end, 9);
     }
+
+    #[test]
+    fn test_line_matcher() {
+        let regex = RegexMatcher::new(r"^(\w+) done$").unwrap();
+        let matcher = LineMatcher::new(Box::new(regex));
+        let buffer = b"build started\r\ntest done\r\nlint done";
+        let m = matcher.find(buffer).unwrap();
+        assert_eq!(&buffer[m.start..m.end], b"test done");
+        assert_eq!(m.captures[1], "test");
+
+        // A match spanning lines is not a match
+        let exact = LineMatcher::new(Box::new(ExactMatcher::new(b"started\r\ntest").unwrap()));
+        assert!(exact.find(buffer).is_none());
+        // Neither is one in the incomplete last line
+        let exact = LineMatcher::new(Box::new(ExactMatcher::new(b"lint").unwrap()));
+        assert!(exact.find(buffer).is_none());
+    }
 }
@@ -1,9 +1,14 @@
 //! Pattern matching for expect operations
 
 mod matcher;
-mod search;
+mod options;
+pub mod search;
+mod typed;
 
-pub use matcher::Matcher;
+pub(crate) use matcher::LineMatcher;
+pub use matcher::{Match, Matcher};
+pub use options::{PatternFactory, RegexOptions};
+pub use typed::TypedPatterns;
 
 use regex::Regex;
 
@@ -14,8 +19,9 @@ use regex::Regex;
 ///
 /// # Pattern Types
 ///
-/// - **Exact**: Fast exact string matching using Boyer-Moore-Horspool algorithm
+/// - **Exact**: Fast exact string matching using SIMD-accelerated substring search
 /// - **Regex**: Full regular expression support with capture groups
+/// - **RegexBytes**: Regular expressions over raw bytes, for binary output
 /// - **Glob**: Shell-style wildcard patterns (*, ?, etc.)
 /// - **Eof**: Special pattern that matches when the process exits
 /// - **Timeout**: Special pattern that matches when a timeout occurs
@@ -44,7 +50,7 @@ use regex::Regex;
 pub enum Pattern {
     /// Exact string match (most efficient).
     ///
-    /// Uses Boyer-Moore-Horspool algorithm for O(n/m) average-case performance.
+    /// Uses SIMD-accelerated substring search (via `memchr::memmem`) where available.
     /// This is the fastest pattern type and should be preferred when possible.
     Exact(String),
 
@@ -54,6 +60,14 @@ pub enum Pattern {
     /// all capture groups are returned in the `MatchResult`.
     Regex(Regex),
 
+    /// Regular expression match over raw bytes.
+    ///
+    /// Unlike [`Regex`](Self::Regex), which needs the searched output to be
+    /// valid UTF-8 and never matches otherwise, this matches anywhere in
+    /// binary output. Captures are returned as bytes in
+    /// [`MatchResult::captures_bytes`](crate::MatchResult::captures_bytes).
+    RegexBytes(regex::bytes::Regex),
+
     /// Glob pattern match (shell-style wildcards).
     ///
     /// Supports patterns like `*.txt`, `test?.log`, etc.
@@ -135,6 +149,39 @@ impl Pattern {
         Ok(Pattern::Regex(Regex::new(pattern)?))
     }
 
+    /// Create a regex pattern compiled with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns a regex error if the pattern is invalid or exceeds the size
+    /// limit.
+    pub fn regex_with(pattern: &str, options: &RegexOptions) -> Result<Self, regex::Error> {
+        Ok(Pattern::Regex(options.build(pattern)?))
+    }
+
+    /// Create a regex pattern that matches raw bytes.
+    ///
+    /// For binary protocols and output that is not valid UTF-8. Escapes
+    /// such as `\x00` match single bytes when Unicode mode is disabled with
+    /// `(?-u)`; with it enabled (the default), `.` and classes like `\w`
+    /// match whole UTF-8 characters only.
+    ///
+    /// # Errors
+    ///
+    /// Returns a regex error if the pattern is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expectrust::Pattern;
+    ///
+    /// // A frame: start byte, length byte, payload up to the end byte
+    /// let frame = Pattern::regex_bytes(r"(?-u)\x02(.)([^\x03]*)\x03").unwrap();
+    /// ```
+    pub fn regex_bytes(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Pattern::RegexBytes(regex::bytes::Regex::new(pattern)?))
+    }
+
     /// Create a glob pattern.
     ///
     /// Supports shell-style wildcards like `*`, `?`, etc.
@@ -153,11 +200,15 @@ impl Pattern {
 
     /// Convert pattern to a matcher implementation
     pub fn to_matcher(&self) -> Result<Box<dyn Matcher>, crate::result::PatternError> {
-        use matcher::{ExactMatcher, GlobMatcher as GlobMatcherImpl, NullMatcher, RegexMatcher};
+        use matcher::{
+            BytesRegexMatcher, ExactMatcher, GlobMatcher as GlobMatcherImpl, NullMatcher,
+            RegexMatcher,
+        };
 
         match self {
             Pattern::Exact(s) => Ok(Box::new(ExactMatcher::new(s.as_bytes())?)),
-            Pattern::Regex(r) => Ok(Box::new(RegexMatcher::new(r.as_str())?)),
+            Pattern::Regex(r) => Ok(Box::new(RegexMatcher::from_regex(r.clone()))),
+            Pattern::RegexBytes(r) => Ok(Box::new(BytesRegexMatcher::from_regex(r.clone()))),
             Pattern::Glob(g) => Ok(Box::new(GlobMatcherImpl::new(g)?)),
             Pattern::Null => Ok(Box::new(NullMatcher)),
             Pattern::Eof | Pattern::Timeout | Pattern::FullBuffer => {
@@ -169,8 +220,50 @@ impl Pattern {
         }
     }
 
+    /// Create a matcher that ignores letter case.
+    ///
+    /// Exact strings are matched as escaped case-insensitive regexes. Regexes
+    /// are recompiled from their source with the `i` flag, so flags set
+    /// through [`RegexOptions`] rather than inline are not kept. Null bytes
+    /// have no case.
+    pub(crate) fn to_matcher_ignoring_case(
+        &self,
+    ) -> Result<Box<dyn Matcher>, crate::result::PatternError> {
+        use matcher::{BytesRegexMatcher, GlobMatcher as GlobMatcherImpl, RegexMatcher};
+
+        let case_insensitive = |source: &str| {
+            regex::RegexBuilder::new(source)
+                .case_insensitive(true)
+                .build()
+                .map(|regex| Box::new(RegexMatcher::from_regex(regex)) as Box<dyn Matcher>)
+        };
+        match self {
+            Pattern::Exact(s) if s.is_empty() => Err(crate::result::PatternError::EmptyPattern),
+            Pattern::Exact(s) => Ok(case_insensitive(&regex::escape(s))?),
+            Pattern::Regex(r) => Ok(case_insensitive(r.as_str())?),
+            Pattern::RegexBytes(r) => Ok(Box::new(BytesRegexMatcher::from_regex(
+                regex::bytes::RegexBuilder::new(r.as_str())
+                    .case_insensitive(true)
+                    .build()?,
+            ))),
+            Pattern::Glob(g) => Ok(Box::new(GlobMatcherImpl::case_insensitive(g)?)),
+            _ => self.to_matcher(),
+        }
+    }
+
     /// Check if this is a special pattern (EOF, Timeout, FullBuffer)
     pub fn is_special(&self) -> bool {
         matches!(self, Pattern::Eof | Pattern::Timeout | Pattern::FullBuffer)
     }
+
+    /// The text the pattern was created from, or `None` for patterns that
+    /// match conditions rather than text.
+    pub(crate) fn source(&self) -> Option<&str> {
+        match self {
+            Pattern::Exact(s) | Pattern::Glob(s) => Some(s),
+            Pattern::Regex(r) => Some(r.as_str()),
+            Pattern::RegexBytes(r) => Some(r.as_str()),
+            Pattern::Eof | Pattern::Timeout | Pattern::FullBuffer | Pattern::Null => None,
+        }
+    }
 }
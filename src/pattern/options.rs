@@ -0,0 +1,185 @@
+//! Default flags for regex patterns
+
+use super::Pattern;
+use regex::RegexBuilder;
+
+/// Flags and limits used when compiling regex patterns.
+///
+/// Set once on a session with
+/// [`SessionBuilder::regex_options`](crate::SessionBuilder::regex_options)
+/// and applied to every regex created through
+/// [`Session::pattern`](crate::Session::pattern), so flags do not need to be
+/// repeated in each pattern string. Inline flags such as `(?-i)` still
+/// override these defaults.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::{Pattern, RegexOptions};
+///
+/// let options = RegexOptions::new().case_insensitive(true).multi_line(true);
+/// let pattern = Pattern::regex_with(r"^error: (.*)$", &options).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegexOptions {
+    case_insensitive: bool,
+    multi_line: bool,
+    dot_matches_new_line: bool,
+    size_limit: Option<usize>,
+    dfa_size_limit: Option<usize>,
+}
+
+impl RegexOptions {
+    /// Options matching the `regex` crate defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match letters regardless of case (`(?i)`).
+    pub fn case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    /// Make `^` and `$` match at line boundaries (`(?m)`).
+    pub fn multi_line(mut self, enabled: bool) -> Self {
+        self.multi_line = enabled;
+        self
+    }
+
+    /// Make `.` match `\n` as well (`(?s)`).
+    pub fn dot_matches_new_line(mut self, enabled: bool) -> Self {
+        self.dot_matches_new_line = enabled;
+        self
+    }
+
+    /// Limit the size of a compiled regex in bytes.
+    ///
+    /// Patterns exceeding the limit fail to compile instead of using large
+    /// amounts of memory.
+    pub fn size_limit(mut self, bytes: usize) -> Self {
+        self.size_limit = Some(bytes);
+        self
+    }
+
+    /// Limit the cache used by the lazy DFA while matching, in bytes.
+    ///
+    /// Patterns that need more fall back to slower (still linear time)
+    /// matching instead of growing the cache further.
+    pub fn dfa_size_limit(mut self, bytes: usize) -> Self {
+        self.dfa_size_limit = Some(bytes);
+        self
+    }
+
+    /// Compile a regex with these options.
+    pub(crate) fn build(&self, pattern: &str) -> Result<regex::Regex, regex::Error> {
+        let mut builder = RegexBuilder::new(pattern);
+        builder
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line);
+        if let Some(limit) = self.size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(limit);
+        }
+        builder.build()
+    }
+}
+
+/// Creates patterns using a session's default regex options.
+///
+/// Returned by [`Session::pattern`](crate::Session::pattern).
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{RegexOptions, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::builder()
+///     .regex_options(RegexOptions::new().case_insensitive(true))
+///     .spawn("ftp example.com")?;
+///
+/// // Matches "Name:", "NAME:", ...
+/// let name_prompt = session.pattern().regex(r"name.*: $")?;
+/// session.expect(name_prompt).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PatternFactory {
+    options: RegexOptions,
+}
+
+impl PatternFactory {
+    /// Create a factory using the given regex options.
+    pub fn new(options: RegexOptions) -> Self {
+        Self { options }
+    }
+
+    /// The regex options applied by this factory.
+    pub fn options(&self) -> &RegexOptions {
+        &self.options
+    }
+
+    /// Create a regex pattern with the factory's options.
+    ///
+    /// # Errors
+    ///
+    /// Returns a regex error if the pattern is invalid or exceeds the size
+    /// limit.
+    pub fn regex(&self, pattern: &str) -> Result<Pattern, regex::Error> {
+        Pattern::regex_with(pattern, &self.options)
+    }
+
+    /// Create an exact string pattern (regex options do not apply).
+    pub fn exact(&self, s: impl Into<String>) -> Pattern {
+        Pattern::exact(s)
+    }
+
+    /// Create a glob pattern (regex options do not apply).
+    pub fn glob(&self, pattern: &str) -> Pattern {
+        Pattern::glob(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(options: &RegexOptions, pattern: &str, text: &str) -> bool {
+        options.build(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn test_flags_applied() {
+        let options = RegexOptions::new()
+            .case_insensitive(true)
+            .multi_line(true)
+            .dot_matches_new_line(true);
+        assert!(is_match(&options, "^ERROR$", "ok\nerror\n"));
+        assert!(is_match(&options, "a.b", "a\nb"));
+        assert!(!is_match(&RegexOptions::new(), "^ERROR$", "ok\nerror\n"));
+    }
+
+    #[test]
+    fn test_inline_flags_override() {
+        let options = RegexOptions::new().case_insensitive(true);
+        assert!(!is_match(&options, "(?-i)ERROR", "error"));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let options = RegexOptions::new().size_limit(100);
+        assert!(options.build(r"\w{50}").is_err());
+    }
+
+    #[test]
+    fn test_flags_survive_matcher() {
+        let factory = PatternFactory::new(RegexOptions::new().case_insensitive(true));
+        let matcher = factory.regex("ready").unwrap().to_matcher().unwrap();
+        assert!(matcher.find(b"READY").is_some());
+    }
+}
@@ -1,7 +1,91 @@
-//! Partial match tracking for patterns split across buffer boundaries
+//! Substring search primitives.
+//!
+//! These functions are the building blocks used by the exact and null byte
+//! matchers. They are backed by [`memchr`], which uses SIMD instructions where
+//! the target supports them, and are exposed for callers that need fast byte
+//! searching on their own buffers.
+//!
+//! # Examples
+//!
+//! ```
+//! use expectrust::search;
+//!
+//! let haystack = b"login: admin\r\npassword: ";
+//! assert_eq!(search::find(haystack, b"password"), Some(14));
+//! assert_eq!(search::find_iter(b"a-a-a", b"a").count(), 3);
+//!
+//! // A trailing "pass" could become "password" once more data arrives
+//! assert_eq!(search::partial_suffix_len(b"enter pass", b"password"), 4);
+//! ```
+
+use memchr::memmem;
+
+/// A reusable substring searcher for a fixed needle.
+///
+/// Building a `Finder` once and reusing it avoids recomputing the search
+/// tables for every haystack.
+#[derive(Clone, Debug)]
+pub struct Finder {
+    inner: memmem::Finder<'static>,
+}
+
+impl Finder {
+    /// Create a finder for the given needle.
+    pub fn new(needle: &[u8]) -> Self {
+        Self {
+            inner: memmem::Finder::new(needle).into_owned(),
+        }
+    }
+
+    /// Find the first occurrence of the needle in `haystack`.
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        self.inner.find(haystack)
+    }
+
+    /// The needle this finder searches for.
+    pub fn needle(&self) -> &[u8] {
+        self.inner.needle()
+    }
+}
+
+/// Find the first occurrence of `needle` in `haystack`.
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memmem::find(haystack, needle)
+}
+
+/// Find the last occurrence of `needle` in `haystack`.
+pub fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memmem::rfind(haystack, needle)
+}
+
+/// Iterate over the start positions of all non-overlapping occurrences of `needle`.
+pub fn find_iter<'h, 'n>(haystack: &'h [u8], needle: &'n [u8]) -> impl Iterator<Item = usize> + 'h
+where
+    'n: 'h,
+{
+    memmem::find_iter(haystack, needle)
+}
+
+/// Find the first occurrence of a single byte in `haystack`.
+pub fn find_byte(haystack: &[u8], byte: u8) -> Option<usize> {
+    memchr::memchr(byte, haystack)
+}
+
+/// Length of the longest proper prefix of `needle` that `haystack` ends with.
+///
+/// A non-zero result means the needle may be split across a buffer boundary
+/// and could match once more data arrives.
+pub fn partial_suffix_len(haystack: &[u8], needle: &[u8]) -> usize {
+    let max = needle.len().saturating_sub(1).min(haystack.len());
+    (1..=max)
+        .rev()
+        .find(|&len| haystack.ends_with(&needle[..len]))
+        .unwrap_or(0)
+}
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::pattern::matcher::ExactMatcher;
     use crate::pattern::Matcher;
 
@@ -20,4 +104,34 @@ mod tests {
 
         assert!(!matcher.partial_match(buffer));
     }
+
+    #[test]
+    fn test_find_and_rfind() {
+        let haystack = b"abc abc abc";
+        assert_eq!(find(haystack, b"abc"), Some(0));
+        assert_eq!(rfind(haystack, b"abc"), Some(8));
+        assert_eq!(find(haystack, b"xyz"), None);
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let positions: Vec<_> = find_iter(b"aXbXc", b"X").collect();
+        assert_eq!(positions, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_finder_reuse() {
+        let finder = Finder::new(b"$ ");
+        assert_eq!(finder.find(b"user@host$ "), Some(9));
+        assert_eq!(finder.find(b"no prompt"), None);
+        assert_eq!(finder.needle(), b"$ ");
+    }
+
+    #[test]
+    fn test_partial_suffix_len() {
+        assert_eq!(partial_suffix_len(b"enter pass", b"password:"), 4);
+        assert_eq!(partial_suffix_len(b"password:", b"password:"), 0);
+        assert_eq!(partial_suffix_len(b"", b"password:"), 0);
+        assert_eq!(partial_suffix_len(b"p", b"p"), 0);
+    }
 }
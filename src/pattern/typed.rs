@@ -0,0 +1,120 @@
+//! Patterns paired with caller-defined tags
+
+use super::Pattern;
+
+/// A list of patterns, each paired with a tag identifying it.
+///
+/// Used with [`Session::expect_typed`](crate::Session::expect_typed), which
+/// returns the tag of the pattern that matched instead of its index. Tags
+/// are usually variants of an enum, so handling the outcome is an
+/// exhaustive `match` that cannot get out of step with the pattern list.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Pattern, Session, TypedPatterns};
+///
+/// #[derive(Clone, Copy)]
+/// enum Login {
+///     Password,
+///     Denied,
+///     Timeout,
+/// }
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::spawn("ssh user@host")?;
+/// let patterns = TypedPatterns::new()
+///     .with(Login::Password, Pattern::exact("password:"))
+///     .with(Login::Denied, Pattern::exact("Permission denied"))
+///     .with(Login::Timeout, Pattern::Timeout);
+///
+/// let (outcome, _) = session.expect_typed(&patterns).await?;
+/// match outcome {
+///     Login::Password => session.send_line("secret").await?,
+///     Login::Denied => return Err("access denied".into()),
+///     Login::Timeout => return Err("no response".into()),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypedPatterns<T> {
+    tags: Vec<T>,
+    patterns: Vec<Pattern>,
+}
+
+impl<T> Default for TypedPatterns<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TypedPatterns<T> {
+    /// Create an empty list.
+    pub fn new() -> Self {
+        Self {
+            tags: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Add a pattern with its tag, builder style.
+    pub fn with(mut self, tag: T, pattern: Pattern) -> Self {
+        self.push(tag, pattern);
+        self
+    }
+
+    /// Add a pattern with its tag.
+    pub fn push(&mut self, tag: T, pattern: Pattern) {
+        self.tags.push(tag);
+        self.patterns.push(pattern);
+    }
+
+    /// The patterns, in the order they were added.
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// The tag of the pattern at `index`.
+    pub fn tag(&self, index: usize) -> Option<&T> {
+        self.tags.get(index)
+    }
+
+    /// Number of patterns.
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+impl<T> FromIterator<(T, Pattern)> for TypedPatterns<T> {
+    fn from_iter<I: IntoIterator<Item = (T, Pattern)>>(iter: I) -> Self {
+        let mut patterns = Self::new();
+        for (tag, pattern) in iter {
+            patterns.push(tag, pattern);
+        }
+        patterns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_follow_patterns() {
+        let patterns: TypedPatterns<&str> =
+            [("prompt", Pattern::exact("$ ")), ("eof", Pattern::Eof)]
+                .into_iter()
+                .collect();
+
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns.tag(1), Some(&"eof"));
+        assert!(matches!(patterns.patterns()[1], Pattern::Eof));
+        assert_eq!(patterns.tag(2), None);
+    }
+}
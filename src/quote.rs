@@ -0,0 +1,294 @@
+//! Quoting values for interpolation into shell command lines
+//!
+//! Command lines composed with `format!` break, or run something else
+//! entirely, when an interpolated value contains spaces, quotes or shell
+//! metacharacters. These helpers quote a value so the shell passes it to the
+//! command as a single, literal argument.
+//!
+//! [`shell_command!`](crate::shell_command) formats a command line with every
+//! argument quoted, and [`send_commandf!`](crate::send_commandf) sends one.
+//! [`shsplit`] does the reverse, splitting a command line into arguments.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use expectrust::{send_commandf, Pattern, Session};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut session = Session::spawn("bash")?;
+//! let file = "report; rm -rf ~.txt";
+//! // Sends: wc -l 'report; rm -rf ~.txt'
+//! send_commandf!(session, "wc -l {}", file).await?;
+//! session.expect(Pattern::exact("$ ")).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+/// Quote a value for POSIX shells (`sh`, `bash`, `zsh` and compatible).
+///
+/// Values made only of characters with no special meaning are returned
+/// as is; anything else is wrapped in single quotes, inside which the shell
+/// interprets nothing. Single quotes in the value are written as `'\''`.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::quote::shquote;
+///
+/// assert_eq!(shquote("backup-2024.tar.gz"), "backup-2024.tar.gz");
+/// assert_eq!(shquote("my file.txt"), "'my file.txt'");
+/// assert_eq!(shquote("it's"), r"'it'\''s'");
+/// assert_eq!(shquote(""), "''");
+/// ```
+pub fn shquote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Quote a value for PowerShell.
+///
+/// The value is always wrapped in single quotes, inside which PowerShell
+/// expands nothing. Single quotes in the value, including the typographic
+/// ones PowerShell also accepts, are doubled.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::quote::powershell_quote;
+///
+/// assert_eq!(powershell_quote("C:\\Program Files"), "'C:\\Program Files'");
+/// assert_eq!(powershell_quote("$env:PATH"), "'$env:PATH'");
+/// assert_eq!(powershell_quote("it's"), "'it''s'");
+/// ```
+pub fn powershell_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Split a command line into arguments the way a POSIX shell would.
+///
+/// Arguments are separated by unquoted whitespace. Single quotes keep
+/// everything up to the next single quote literally; double quotes keep
+/// whitespace and single quotes, with a backslash escaping only `$`, `` ` ``,
+/// `"`, `\` and a newline; elsewhere a backslash escapes the next
+/// character. Nothing is expanded: `$HOME` and `*` are passed as written.
+///
+/// Returns `None` if a quote is not closed or the line ends with a
+/// backslash.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::quote::shsplit;
+///
+/// assert_eq!(
+///     shsplit(r#"bash -c "echo hi there""#).unwrap(),
+///     ["bash", "-c", "echo hi there"]
+/// );
+/// assert_eq!(shsplit(r"ls my\ dir ''").unwrap(), ["ls", "my dir", ""]);
+/// assert!(shsplit("echo 'unclosed").is_none());
+/// ```
+pub fn shsplit(line: &str) -> Option<Vec<String>> {
+    split_words(line, true)
+}
+
+/// Split a command line on unquoted whitespace, removing the quotes.
+///
+/// Without `escapes`, for Windows command lines, backslashes are literal
+/// (they separate paths) and only double quotes group.
+pub(crate) fn split_words(line: &str, escapes: bool) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' if escapes => match chars.next()? {
+                '\n' => {}
+                next => word.get_or_insert_with(String::new).push(next),
+            },
+            '\'' if escapes => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' if escapes => match chars.next()? {
+                            '\n' => {}
+                            c @ ('$' | '`' | '"' | '\\') => word.push(c),
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Format a POSIX shell command line, quoting every argument.
+///
+/// Takes a format string and positional arguments like `format!`. Each
+/// argument is converted with `Display` and quoted with
+/// [`shquote`](crate::quote::shquote); the format string itself is used as
+/// written. Placeholders must be plain `{}`: named, numbered and inline
+/// ones such as `{name}`, and format specs, are rejected at compile time,
+/// since they would bypass the quoting.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::shell_command;
+///
+/// let dir = "/tmp/my dir";
+/// assert_eq!(
+///     shell_command!("ls -l {} | grep {}", dir, "$USER"),
+///     "ls -l '/tmp/my dir' | grep '$USER'"
+/// );
+/// ```
+///
+/// Capturing a variable inline does not compile:
+///
+/// ```compile_fail
+/// use expectrust::shell_command;
+///
+/// let path = "/tmp/x; rm -rf ~";
+/// let command = shell_command!("rm {path}");
+/// ```
+#[macro_export]
+macro_rules! shell_command {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {{
+        const _: () = $crate::quote::check_shell_format($fmt);
+        ::std::format!($fmt $(, $crate::quote::shquote(&$arg.to_string()))*)
+    }};
+}
+
+/// Reject `shell_command!` format strings with placeholders other than
+/// `{}`, which would be formatted without quoting.
+#[doc(hidden)]
+pub const fn check_shell_format(fmt: &str) {
+    let bytes = fmt.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'{' {
+            if i + 1 < bytes.len() && matches!(bytes[i + 1], b'{' | b'}') {
+                i += 2;
+                continue;
+            }
+            panic!(
+                "shell_command! placeholders must be `{{}}`, with the value passed as an argument"
+            );
+        }
+        i += 1;
+    }
+}
+
+/// Send a POSIX shell command line with every argument quoted.
+///
+/// `send_commandf!(session, fmt, args...)` is
+/// `session.send_line(&shell_command!(fmt, args...))`; await the result.
+/// See [`shell_command!`](crate::shell_command).
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{send_commandf, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::spawn("bash")?;
+/// let name = "Quarterly Report (final).pdf";
+/// send_commandf!(session, "cp {} /srv/archive/", name).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! send_commandf {
+    ($session:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $session.send_line(&$crate::shell_command!($fmt $(, $arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shquote_metacharacters() {
+        for value in [
+            "a b", "$(id)", "`id`", "a;b", "a|b", "*", "~", "a\nb", "!x", "é",
+        ] {
+            let quoted = shquote(value);
+            assert!(
+                quoted.starts_with('\'') && quoted.ends_with('\''),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(shquote("'"), r"''\'''");
+    }
+
+    #[test]
+    fn test_shsplit_round_trips_shquote() {
+        let args = ["a  b", "it's", "$(id)", "", "back\\slash", "say \"hi\""];
+        let line: Vec<String> = args.iter().map(|arg| shquote(arg)).collect();
+        assert_eq!(shsplit(&line.join(" ")).unwrap(), args);
+    }
+
+    #[test]
+    fn test_shsplit_double_quote_escapes() {
+        assert_eq!(
+            shsplit("echo \"a \\\"b\\\" \\$c \\d\" e\\\nf").unwrap(),
+            ["echo", r#"a "b" $c \d"#, "ef"]
+        );
+        assert!(shsplit(r#"echo "unclosed"#).is_none());
+        assert!(shsplit(r"echo trailing\").is_none());
+    }
+
+    #[test]
+    fn test_shell_command_escaped_braces() {
+        assert_eq!(
+            crate::shell_command!("echo {{}} {}", "a b"),
+            "echo {} 'a b'"
+        );
+    }
+
+    #[test]
+    fn test_split_words_without_escapes() {
+        assert_eq!(
+            split_words(r#"C:\tools\app.exe "C:\My Files\x.txt" it's"#, false).unwrap(),
+            [r"C:\tools\app.exe", r"C:\My Files\x.txt", "it's"]
+        );
+    }
+
+    #[test]
+    fn test_powershell_quote_typographic_quotes() {
+        assert_eq!(powershell_quote("a\u{2019}b"), "'a\u{2019}\u{2019}b'");
+        assert_eq!(powershell_quote(""), "''");
+    }
+}
@@ -0,0 +1,63 @@
+//! Per-call pattern matching statistics
+
+use std::time::Duration;
+
+/// How much matching work an expect call did.
+///
+/// Returned by [`Session::match_diagnostics`](crate::Session::match_diagnostics)
+/// when the `diagnostics` feature is enabled. Each time output arrives, the
+/// call makes a pass over the unconsumed buffer, trying every pattern in
+/// turn until one matches. Comparing [`PatternStats`] across the patterns of
+/// a large `expect_any` shows which of them the time is going to.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Pattern, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::spawn("make")?;
+/// let patterns = [Pattern::glob("*error*"), Pattern::regex(r"\$ $")?];
+/// session.expect_any(&patterns).await?;
+///
+/// if let Some(diagnostics) = session.match_diagnostics() {
+///     for (index, stats) in diagnostics.patterns.iter().enumerate() {
+///         println!("pattern {index}: {stats:?}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchDiagnostics {
+    /// Number of passes over the buffer.
+    pub passes: usize,
+    /// Statistics for each pattern, by index into the pattern list.
+    ///
+    /// Special patterns such as `Pattern::Eof` are never searched for, so
+    /// their entries stay zero.
+    pub patterns: Vec<PatternStats>,
+}
+
+/// Matching work done for one pattern during an expect call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatternStats {
+    /// Number of times the pattern was searched for.
+    pub attempts: usize,
+    /// Total size of the buffer windows searched, in bytes.
+    ///
+    /// Unconsumed output is searched again on every pass, so this grows
+    /// with both the amount of output and the number of passes.
+    pub bytes_scanned: usize,
+    /// Total time spent searching for the pattern.
+    pub time: Duration,
+}
+
+impl MatchDiagnostics {
+    pub(crate) fn new(patterns: usize) -> Self {
+        Self {
+            passes: 0,
+            patterns: vec![PatternStats::default(); patterns],
+        }
+    }
+}
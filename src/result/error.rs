@@ -39,6 +39,10 @@ pub enum ExpectError {
     /// Returned when a pattern is not matched within the configured timeout duration.
     /// To avoid this error, either increase the timeout or use `Pattern::Timeout`
     /// in `expect_any` to handle timeouts gracefully.
+    ///
+    /// When the session has the screen model enabled,
+    /// [`Session::timeout_screen`](crate::Session::timeout_screen) holds a
+    /// text screenshot of the terminal at the moment the wait gave up.
     #[error("Timeout waiting for pattern (after {duration:?})")]
     Timeout {
         /// Duration that was waited before timeout
@@ -97,6 +101,79 @@ pub enum ExpectError {
     /// waited on (via `Session::wait()`).
     #[error("Process has already exited")]
     ProcessExited,
+
+    /// Screen model not enabled.
+    ///
+    /// Returned by screen-based operations such as `Session::expect_screen()`
+    /// when the session was built without `SessionBuilder::screen(true)`.
+    #[error("Screen model is not enabled for this session")]
+    ScreenDisabled,
+
+    /// The cursor could not be moved to the requested position.
+    ///
+    /// Returned by `Session::navigate_to()` when the arrow keys stop bringing
+    /// the cursor closer, e.g. because the target is not a selectable item.
+    #[error("Cannot move cursor to ({row}, {col}), stuck at ({}, {})", cursor.0, cursor.1)]
+    NavigationFailed {
+        /// Requested row
+        row: u16,
+        /// Requested column
+        col: u16,
+        /// Cursor position when navigation gave up
+        cursor: (u16, u16),
+    },
+
+    /// Unexpected output while silence was expected.
+    ///
+    /// Returned by `Session::assert_no_output()` when the process writes
+    /// anything during the quiet window.
+    #[error("Unexpected output: {output:?}")]
+    UnexpectedOutput {
+        /// The output that arrived during the quiet window
+        output: String,
+    },
+
+    /// Critical output appeared while waiting for a pattern.
+    ///
+    /// Returned by expect calls when output matches one of the session's
+    /// critical patterns (see `Session::add_critical_pattern()`).
+    #[error("Critical output: {matched:?}")]
+    CriticalOutput {
+        /// Index of the critical pattern that matched, in the order added
+        pattern_index: usize,
+        /// The critical output
+        matched: String,
+    },
+
+    /// The shell prompt could not be learned.
+    ///
+    /// Returned by `Session::learn_prompt()`, and by methods that learn the
+    /// prompt on first use, when the output after the newlines sent shows no
+    /// common prompt.
+    #[error("Could not learn the prompt from {samples:?}")]
+    PromptNotLearned {
+        /// Output after each newline sent
+        samples: Vec<String>,
+    },
+
+    /// A command printed more output than allowed.
+    ///
+    /// Returned by `Session::run_command_with_limit()`, which interrupts the
+    /// command with Ctrl-C when this happens.
+    #[error("Command output exceeded {limit} bytes ({received} received)")]
+    OutputLimit {
+        /// The configured limit
+        limit: usize,
+        /// Bytes received when the command was interrupted
+        received: u64,
+    },
+
+    /// The operation was cancelled.
+    ///
+    /// Returned by `Session::expect_cancellable()` when its cancellation
+    /// future completes before the pattern matches.
+    #[error("Cancelled while waiting for pattern")]
+    Cancelled,
 }
 
 /// Errors related to pattern creation or matching.
@@ -121,4 +198,17 @@ pub enum PatternError {
     /// Returned when attempting to create a pattern with an empty string.
     #[error("Pattern cannot be empty")]
     EmptyPattern,
+
+    /// Matching exceeded its time budget.
+    ///
+    /// Returned by expect operations when searching the buffer took longer
+    /// in total than the budget set with `SessionBuilder::match_budget()`,
+    /// typically because of an expensive pattern against a large buffer.
+    #[error("Pattern matching took {elapsed:?}, exceeding its budget of {budget:?}")]
+    Budget {
+        /// The configured budget
+        budget: Duration,
+        /// Time spent matching when the budget was exceeded
+        elapsed: Duration,
+    },
 }
@@ -1,8 +1,17 @@
 //! Result types for expect operations
 
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
 mod error;
+mod report;
 
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::{MatchDiagnostics, PatternStats};
 pub use error::{ExpectError, PatternError};
+pub use report::Report;
+
+use crate::buffer::Escaped;
+use std::fmt;
 
 /// Result of a successful pattern match.
 ///
@@ -48,7 +57,14 @@ pub use error::{ExpectError, PatternError};
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Debug, Clone)]
+///
+/// # Printing
+///
+/// `Debug` shows the text fields with control characters escaped (see
+/// [`Escaped`](crate::Escaped)), so printing a result does not let the
+/// terminal interpret the escape sequences in it. `Display` shows the
+/// escaped match and its position, as in `"ready\r\n" at 12..19`.
+#[derive(Clone)]
 pub struct MatchResult {
     /// Index of the pattern that matched (for `expect_any`).
     ///
@@ -94,6 +110,18 @@ pub struct MatchResult {
     /// ```
     pub before: String,
 
+    /// The matched bytes, exactly as received.
+    ///
+    /// Unlike [`matched`](Self::matched), invalid UTF-8 is kept rather than
+    /// replaced, for binary-ish output from bootloaders, serial consoles or
+    /// progress bars.
+    pub matched_bytes: Vec<u8>,
+
+    /// The bytes before the match, exactly as received.
+    ///
+    /// The raw counterpart of [`before`](Self::before).
+    pub before_bytes: Vec<u8>,
+
     /// Captured groups (for regex patterns).
     ///
     /// For regex patterns with capture groups, this vector contains:
@@ -102,4 +130,40 @@ pub struct MatchResult {
     ///
     /// For non-regex patterns, this vector is empty.
     pub captures: Vec<String>,
+
+    /// Captured groups as raw bytes.
+    ///
+    /// The raw counterpart of [`captures`](Self::captures), keeping invalid
+    /// UTF-8 captured by [`Pattern::regex_bytes`](crate::Pattern::regex_bytes).
+    pub captures_bytes: Vec<Vec<u8>>,
+}
+
+impl fmt::Debug for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let captures: Vec<_> = self
+            .captures_bytes
+            .iter()
+            .map(|capture| Escaped(capture))
+            .collect();
+        f.debug_struct("MatchResult")
+            .field("pattern_index", &self.pattern_index)
+            .field("matched", &Escaped(&self.matched_bytes))
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("before", &Escaped(&self.before_bytes))
+            .field("captures", &captures)
+            .finish()
+    }
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} at {}..{}",
+            Escaped(&self.matched_bytes),
+            self.start,
+            self.end
+        )
+    }
 }
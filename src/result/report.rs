@@ -0,0 +1,217 @@
+//! Operator-facing error reports and exit codes
+
+use super::error::ExpectError;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Maximum number of output lines shown in a report.
+const REPORT_MAX_LINES: usize = 10;
+
+impl ExpectError {
+    /// A process exit code for this error, for CLI tools built on the crate.
+    ///
+    /// | Error | Code |
+    /// |---|---|
+    /// | `Timeout` | 2 |
+    /// | `Eof` | 3 |
+    /// | `FullBuffer` | 4 |
+    /// | `UnexpectedOutput` | 5 |
+    /// | `NavigationFailed` | 6 |
+    /// | `ProcessExited` | 7 |
+    /// | `CriticalOutput` | 8 |
+    /// | `PromptNotLearned` | 9 |
+    /// | `OutputLimit` | 10 |
+    /// | `Cancelled` | 11 |
+    /// | `PatternError`, `ScreenDisabled` | 64 (usage error) |
+    /// | `IoError`, `PtyError` | 74 (I/O error) |
+    /// | `SpawnError` | 127 (command could not be run) |
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::process::ExitCode;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> ExitCode {
+    ///     let mut session = Session::spawn("./deploy.sh").unwrap();
+    ///     match session.expect(Pattern::exact("deployed")).await {
+    ///         Ok(_) => ExitCode::SUCCESS,
+    ///         Err(e) => {
+    ///             eprint!("{}", session.report(&e));
+    ///             ExitCode::from(e.exit_code())
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ExpectError::Timeout { .. } => 2,
+            ExpectError::Eof => 3,
+            ExpectError::FullBuffer { .. } => 4,
+            ExpectError::UnexpectedOutput { .. } => 5,
+            ExpectError::NavigationFailed { .. } => 6,
+            ExpectError::ProcessExited => 7,
+            ExpectError::CriticalOutput { .. } => 8,
+            ExpectError::PromptNotLearned { .. } => 9,
+            ExpectError::OutputLimit { .. } => 10,
+            ExpectError::Cancelled => 11,
+            ExpectError::PatternError(_) | ExpectError::ScreenDisabled => 64,
+            ExpectError::IoError(_) | ExpectError::PtyError(_) => 74,
+            ExpectError::SpawnError(_) => 127,
+        }
+    }
+
+    /// A readable report of the error with the output that preceded it.
+    ///
+    /// `output` is the context to show, typically the end of the session
+    /// buffer; [`Session::report`](crate::Session::report) fills it in. Only
+    /// the last few lines are shown, with control characters escaped. A
+    /// screen added with [`Report::screen`] is shown after the output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use expectrust::ExpectError;
+    ///
+    /// let report = ExpectError::Eof.report(b"Connecting...\r\nConnection refused\r\n");
+    /// let text = report.to_string();
+    /// assert!(text.starts_with("error: EOF reached before pattern matched"));
+    /// assert!(text.contains("| Connection refused"));
+    /// ```
+    pub fn report<'a>(&'a self, output: &'a [u8]) -> Report<'a> {
+        Report {
+            error: self,
+            message: None,
+            output: Cow::Borrowed(output),
+            screen: None,
+        }
+    }
+}
+
+/// Error report returned by [`ExpectError::report`]; format it with `Display`.
+#[derive(Debug)]
+pub struct Report<'a> {
+    error: &'a ExpectError,
+    message: Option<String>,
+    output: Cow<'a, [u8]>,
+    screen: Option<Cow<'a, str>>,
+}
+
+impl<'a> Report<'a> {
+    /// Replace the error message shown in the report.
+    pub(crate) fn message(mut self, message: String) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    /// Replace the output shown in the report.
+    pub(crate) fn output(mut self, output: Cow<'a, [u8]>) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Show `screen`, a text screenshot of the terminal, in the report.
+    ///
+    /// [`Session::report`](crate::Session::report) adds the current screen
+    /// for sessions with the screen model enabled. Trailing blank rows are
+    /// not shown.
+    pub fn screen(mut self, screen: impl Into<Cow<'a, str>>) -> Self {
+        self.screen = Some(screen.into());
+        self
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => writeln!(f, "error: {}", message)?,
+            None => writeln!(f, "error: {}", self.error)?,
+        }
+        writeln!(f, "  exit code: {}", self.error.exit_code())?;
+
+        let text = String::from_utf8_lossy(&self.output);
+        if text.is_empty() {
+            writeln!(f, "  no output received")?;
+        } else {
+            let lines: Vec<&str> = text.trim_end_matches(['\r', '\n']).split('\n').collect();
+            let skipped = lines.len().saturating_sub(REPORT_MAX_LINES);
+            writeln!(f, "  last output:")?;
+            if skipped > 0 {
+                writeln!(f, "    ({} earlier lines omitted)", skipped)?;
+            }
+            for line in &lines[skipped..] {
+                write_line(f, line.trim_end_matches('\r'))?;
+            }
+        }
+
+        if let Some(screen) = &self.screen {
+            let screen = screen.trim_end();
+            if screen.is_empty() {
+                writeln!(f, "  screen: blank")?;
+            } else {
+                writeln!(f, "  screen:")?;
+                for line in screen.split('\n') {
+                    write_line(f, line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write an indented context line with control characters escaped.
+fn write_line(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    write!(f, "    | ")?;
+    for c in line.chars() {
+        if c.is_control() && c != '\t' {
+            write!(f, "{}", c.escape_default())?;
+        } else {
+            write!(f, "{}", c)?;
+        }
+    }
+    writeln!(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_report_shows_last_lines() {
+        let output: String = (1..=15).map(|i| format!("line {i}\r\n")).collect();
+        let error = ExpectError::Timeout {
+            duration: Duration::from_secs(5),
+        };
+        let report = error.report(output.as_bytes()).to_string();
+
+        assert!(report.contains("exit code: 2"));
+        assert!(report.contains("(5 earlier lines omitted)"));
+        assert!(!report.contains("| line 5\n"));
+        assert!(report.contains("| line 6\n"));
+        assert!(report.ends_with("| line 15\n"));
+    }
+
+    #[test]
+    fn test_report_escapes_control_characters() {
+        let report = ExpectError::Eof.report(b"\x1b[31mfailed\x07").to_string();
+        assert!(report.contains("| \\u{1b}[31mfailed\\u{7}"));
+
+        let report = ExpectError::Eof.report(b"").to_string();
+        assert!(report.contains("no output received"));
+    }
+
+    #[test]
+    fn test_report_shows_screen() {
+        let report = ExpectError::Eof
+            .report(b"\x1b[2J\x1b[H  Main Menu")
+            .screen("  Main Menu\n\n> Settings\n\n")
+            .to_string();
+        assert!(report.ends_with("  screen:\n    |   Main Menu\n    | \n    | > Settings\n"));
+
+        let report = ExpectError::Eof.report(b"").screen("\n\n").to_string();
+        assert!(report.ends_with("  screen: blank\n"));
+        assert!(!ExpectError::Eof.report(b"").to_string().contains("screen"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Keyboard keys and the bytes a terminal sends for them
+
+/// A key that can be sent with [`Session::press`](crate::Session::press).
+///
+/// Keys are encoded the way an xterm-compatible terminal sends them.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::Key;
+///
+/// assert_eq!(Key::Down.to_bytes(false), b"\x1b[B");
+/// assert_eq!(Key::Down.to_bytes(true), b"\x1bOB");
+/// assert_eq!(Key::Ctrl('c').to_bytes(false), b"\x03");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// Up arrow
+    Up,
+    /// Down arrow
+    Down,
+    /// Left arrow
+    Left,
+    /// Right arrow
+    Right,
+    /// Home
+    Home,
+    /// End
+    End,
+    /// Page Up
+    PageUp,
+    /// Page Down
+    PageDown,
+    /// Insert
+    Insert,
+    /// Delete (forward delete)
+    Delete,
+    /// Backspace (sent as DEL, 0x7f)
+    Backspace,
+    /// Enter (sent as carriage return)
+    Enter,
+    /// Tab
+    Tab,
+    /// Shift+Tab
+    BackTab,
+    /// Escape
+    Escape,
+    /// Function keys F1 to F12; other numbers send nothing.
+    F(u8),
+    /// A character, sent as UTF-8.
+    Char(char),
+    /// A character with Ctrl held, e.g. `Ctrl('c')` for interrupt.
+    Ctrl(char),
+}
+
+impl Key {
+    /// The bytes sent for this key.
+    ///
+    /// `application_cursor` selects the encoding of arrow, Home and End keys
+    /// used when the application enabled cursor key application mode (see
+    /// [`Screen::application_cursor`](crate::Screen::application_cursor)).
+    pub fn to_bytes(self, application_cursor: bool) -> Vec<u8> {
+        let cursor = |final_byte: u8| {
+            let introducer = if application_cursor { b'O' } else { b'[' };
+            vec![0x1b, introducer, final_byte]
+        };
+
+        match self {
+            Key::Up => cursor(b'A'),
+            Key::Down => cursor(b'B'),
+            Key::Right => cursor(b'C'),
+            Key::Left => cursor(b'D'),
+            Key::Home => cursor(b'H'),
+            Key::End => cursor(b'F'),
+            Key::Insert => b"\x1b[2~".to_vec(),
+            Key::Delete => b"\x1b[3~".to_vec(),
+            Key::PageUp => b"\x1b[5~".to_vec(),
+            Key::PageDown => b"\x1b[6~".to_vec(),
+            Key::Backspace => vec![0x7f],
+            Key::Enter => vec![b'\r'],
+            Key::Tab => vec![b'\t'],
+            Key::BackTab => b"\x1b[Z".to_vec(),
+            Key::Escape => vec![0x1b],
+            Key::F(n @ 1..=4) => vec![0x1b, b'O', b'P' + (n - 1)],
+            Key::F(n @ 5..=12) => {
+                let code = [15, 17, 18, 19, 20, 21, 23, 24][(n - 5) as usize];
+                format!("\x1b[{}~", code).into_bytes()
+            }
+            Key::F(_) => Vec::new(),
+            Key::Char(ch) => ch.to_string().into_bytes(),
+            Key::Ctrl('?') => vec![0x7f],
+            Key::Ctrl(ch) if ch.is_ascii() => vec![(ch.to_ascii_uppercase() as u8) & 0x1f],
+            Key::Ctrl(ch) => ch.to_string().into_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_keys() {
+        assert_eq!(Key::F(1).to_bytes(false), b"\x1bOP");
+        assert_eq!(Key::F(4).to_bytes(false), b"\x1bOS");
+        assert_eq!(Key::F(5).to_bytes(false), b"\x1b[15~");
+        assert_eq!(Key::F(12).to_bytes(false), b"\x1b[24~");
+        assert!(Key::F(13).to_bytes(false).is_empty());
+    }
+
+    #[test]
+    fn test_ctrl_keys() {
+        assert_eq!(Key::Ctrl('a').to_bytes(false), [0x01]);
+        assert_eq!(Key::Ctrl('C').to_bytes(false), [0x03]);
+        assert_eq!(Key::Ctrl('[').to_bytes(false), [0x1b]);
+        assert_eq!(Key::Ctrl('?').to_bytes(false), [0x7f]);
+    }
+
+    #[test]
+    fn test_application_cursor_keys() {
+        assert_eq!(Key::Up.to_bytes(true), b"\x1bOA");
+        assert_eq!(Key::Home.to_bytes(false), b"\x1b[H");
+        assert_eq!(Key::PageDown.to_bytes(true), b"\x1b[6~");
+    }
+}
@@ -0,0 +1,536 @@
+//! Terminal screen model for full-screen applications
+
+mod key;
+mod parser;
+
+pub use key::Key;
+
+use crate::pattern::Pattern;
+use parser::{Action, Parser};
+
+/// Number of columns between tab stops.
+const TAB_WIDTH: u16 = 8;
+
+/// A rectangular region of the screen, in 0-based cell coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// First row of the region.
+    pub row: u16,
+    /// First column of the region.
+    pub col: u16,
+    /// Number of rows.
+    pub rows: u16,
+    /// Number of columns.
+    pub cols: u16,
+}
+
+impl Rect {
+    /// Create a region from its top-left corner and size.
+    pub fn new(row: u16, col: u16, rows: u16, cols: u16) -> Self {
+        Self {
+            row,
+            col,
+            rows,
+            cols,
+        }
+    }
+
+    /// A whole row of the screen.
+    pub fn row(row: u16) -> Self {
+        Self::new(row, 0, 1, u16::MAX)
+    }
+
+    /// A range of whole rows, `start..end`.
+    pub fn rows(start: u16, end: u16) -> Self {
+        Self::new(start, 0, end.saturating_sub(start), u16::MAX)
+    }
+}
+
+/// A pattern constrained to a region of the screen.
+///
+/// Matched against the text currently displayed in the region, rather than
+/// the output stream, so content that scrolled away or was overwritten
+/// cannot cause a match. Rows of the region are joined with `\n`, with
+/// trailing blanks removed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Pattern, Rect, ScreenPattern, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::builder().screen(true).spawn("htop")?;
+///
+/// // Only look at the status bar on the last row
+/// let status = ScreenPattern::at(Rect::row(23), Pattern::exact("F10Quit"));
+/// session.expect_screen(&status).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScreenPattern {
+    /// The region to search.
+    pub region: Rect,
+    /// The pattern to search for.
+    pub pattern: Pattern,
+}
+
+impl ScreenPattern {
+    /// Constrain a pattern to a region of the screen.
+    pub fn at(region: Rect, pattern: Pattern) -> Self {
+        Self { region, pattern }
+    }
+
+    /// Match a pattern anywhere on the visible screen.
+    pub fn anywhere(pattern: Pattern) -> Self {
+        Self::at(Rect::new(0, 0, u16::MAX, u16::MAX), pattern)
+    }
+}
+
+/// In-memory model of a terminal screen.
+///
+/// Interprets the output of a process the way a VT100-compatible terminal
+/// would (cursor movement, erasing, scrolling), so full-screen applications
+/// can be inspected by what is actually displayed. Colors and other
+/// attributes are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::Screen;
+///
+/// let mut screen = Screen::new(24, 80);
+/// screen.process(b"hello\r\n\x1b[5;10Hworld");
+/// assert_eq!(screen.row_text(0), "hello");
+/// assert_eq!(screen.row_text(4), "         world");
+/// assert_eq!(screen.cursor(), (4, 14));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Screen {
+    rows: u16,
+    cols: u16,
+    cells: Vec<Vec<char>>,
+    cursor_row: u16,
+    cursor_col: u16,
+    saved_cursor: (u16, u16),
+    scroll_top: u16,
+    scroll_bottom: u16,
+    application_cursor: bool,
+    parser: Parser,
+}
+
+impl Screen {
+    /// Create a blank screen of the given size.
+    pub fn new(rows: u16, cols: u16) -> Self {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        Self {
+            rows,
+            cols,
+            cells: vec![vec![' '; cols as usize]; rows as usize],
+            cursor_row: 0,
+            cursor_col: 0,
+            saved_cursor: (0, 0),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            application_cursor: false,
+            parser: Parser::default(),
+        }
+    }
+
+    /// Screen size as `(rows, cols)`.
+    pub fn size(&self) -> (u16, u16) {
+        (self.rows, self.cols)
+    }
+
+    /// Cursor position as 0-based `(row, col)`.
+    pub fn cursor(&self) -> (u16, u16) {
+        (self.cursor_row, self.cursor_col.min(self.cols - 1))
+    }
+
+    /// Whether the application enabled cursor key application mode (DECCKM).
+    ///
+    /// In this mode, arrow keys are sent as `ESC O A` rather than `ESC [ A`.
+    pub fn application_cursor(&self) -> bool {
+        self.application_cursor
+    }
+
+    /// Text of a row, without trailing blanks.
+    ///
+    /// Returns an empty string for rows outside the screen.
+    pub fn row_text(&self, row: u16) -> String {
+        match self.cells.get(row as usize) {
+            Some(cells) => cells.iter().collect::<String>().trim_end().to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Text of the whole screen, rows joined with `\n`.
+    pub fn contents(&self) -> String {
+        self.region_text(Rect::new(0, 0, self.rows, self.cols))
+    }
+
+    /// Text of a region, rows joined with `\n` and trailing blanks removed.
+    ///
+    /// The region is clipped to the screen.
+    pub fn region_text(&self, region: Rect) -> String {
+        let first_row = region.row.min(self.rows) as usize;
+        let last_row = (region.row as usize + region.rows as usize).min(self.rows as usize);
+        let first_col = region.col.min(self.cols) as usize;
+        let last_col = (region.col as usize + region.cols as usize).min(self.cols as usize);
+
+        self.cells[first_row..last_row]
+            .iter()
+            .map(|cells| {
+                cells[first_col..last_col]
+                    .iter()
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Feed output from the process into the screen.
+    pub fn process(&mut self, data: &[u8]) {
+        for &byte in data {
+            if let Some(action) = self.parser.advance(byte) {
+                self.apply(action);
+            }
+        }
+    }
+
+    /// Change the screen size, keeping the top-left content.
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        self.cells.resize(rows as usize, vec![' '; cols as usize]);
+        for row in &mut self.cells {
+            row.resize(cols as usize, ' ');
+        }
+        self.rows = rows;
+        self.cols = cols;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor_row = self.cursor_row.min(rows - 1);
+        self.cursor_col = self.cursor_col.min(cols - 1);
+        self.saved_cursor = (
+            self.saved_cursor.0.min(rows - 1),
+            self.saved_cursor.1.min(cols - 1),
+        );
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Print(ch) => self.print(ch),
+            Action::Control(byte) => self.control(byte),
+            Action::Csi {
+                params,
+                private,
+                final_byte,
+            } => {
+                if private {
+                    self.private_csi(&params, final_byte);
+                } else {
+                    self.csi(&params, final_byte);
+                }
+            }
+            Action::Escape(byte) => self.escape(byte),
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.cursor_col >= self.cols {
+            // Deferred wrap: the previous character filled the last column
+            self.cursor_col = 0;
+            self.line_feed();
+        }
+        self.cells[self.cursor_row as usize][self.cursor_col as usize] = ch;
+        self.cursor_col += 1;
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            b'\r' => self.cursor_col = 0,
+            b'\n' | 0x0b | 0x0c => self.line_feed(),
+            0x08 => self.cursor_col = self.cursor_col.min(self.cols - 1).saturating_sub(1),
+            b'\t' => {
+                let next = (self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor_col = next.min(self.cols - 1);
+            }
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        match byte {
+            b'7' => self.saved_cursor = (self.cursor_row, self.cursor_col),
+            b'8' => (self.cursor_row, self.cursor_col) = self.saved_cursor,
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.cursor_col = 0;
+                self.line_feed();
+            }
+            b'M' => self.reverse_line_feed(),
+            b'c' => *self = Screen::new(self.rows, self.cols),
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, params: &[u16], final_byte: u8) {
+        let arg = |i: usize, default: u16| match params.get(i) {
+            Some(&0) | None => default,
+            Some(&n) => n,
+        };
+
+        match final_byte {
+            b'A' => self.cursor_row = self.cursor_row.saturating_sub(arg(0, 1)),
+            b'B' | b'e' => {
+                self.cursor_row = self.cursor_row.saturating_add(arg(0, 1)).min(self.rows - 1)
+            }
+            b'C' | b'a' => {
+                self.cursor_col = self.cursor_col.saturating_add(arg(0, 1)).min(self.cols - 1)
+            }
+            b'D' => self.cursor_col = self.cursor_col.min(self.cols - 1).saturating_sub(arg(0, 1)),
+            b'E' => {
+                self.cursor_row = self.cursor_row.saturating_add(arg(0, 1)).min(self.rows - 1);
+                self.cursor_col = 0;
+            }
+            b'F' => {
+                self.cursor_row = self.cursor_row.saturating_sub(arg(0, 1));
+                self.cursor_col = 0;
+            }
+            b'G' | b'`' => self.cursor_col = (arg(0, 1) - 1).min(self.cols - 1),
+            b'd' => self.cursor_row = (arg(0, 1) - 1).min(self.rows - 1),
+            b'H' | b'f' => {
+                self.cursor_row = (arg(0, 1) - 1).min(self.rows - 1);
+                self.cursor_col = (arg(1, 1) - 1).min(self.cols - 1);
+            }
+            b'J' => self.erase_display(params.first().copied().unwrap_or(0)),
+            b'K' => self.erase_line(params.first().copied().unwrap_or(0)),
+            b'X' => {
+                let row = self.cursor_row as usize;
+                let start = self.cursor_col.min(self.cols - 1) as usize;
+                let end = (start + arg(0, 1) as usize).min(self.cols as usize);
+                self.cells[row][start..end].fill(' ');
+            }
+            b'P' => {
+                let row = &mut self.cells[self.cursor_row as usize];
+                let start = self.cursor_col.min(self.cols - 1) as usize;
+                let count = (arg(0, 1) as usize).min(row.len() - start);
+                row.drain(start..start + count);
+                row.extend(std::iter::repeat_n(' ', count));
+            }
+            b'@' => {
+                let cols = self.cols as usize;
+                let row = &mut self.cells[self.cursor_row as usize];
+                let start = self.cursor_col.min(self.cols - 1) as usize;
+                let count = (arg(0, 1) as usize).min(cols - start);
+                for _ in 0..count {
+                    row.insert(start, ' ');
+                }
+                row.truncate(cols);
+            }
+            b'L' if (self.scroll_top..=self.scroll_bottom).contains(&self.cursor_row) => {
+                for _ in 0..arg(0, 1) {
+                    self.scroll_down_from(self.cursor_row);
+                }
+            }
+            b'M' if (self.scroll_top..=self.scroll_bottom).contains(&self.cursor_row) => {
+                for _ in 0..arg(0, 1) {
+                    self.scroll_up_from(self.cursor_row);
+                }
+            }
+            b'S' => {
+                for _ in 0..arg(0, 1) {
+                    self.scroll_up_from(self.scroll_top);
+                }
+            }
+            b'T' => {
+                for _ in 0..arg(0, 1) {
+                    self.scroll_down_from(self.scroll_top);
+                }
+            }
+            b'r' => {
+                let top = arg(0, 1) - 1;
+                let bottom = arg(1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.cursor_row = 0;
+                    self.cursor_col = 0;
+                }
+            }
+            b's' => self.saved_cursor = (self.cursor_row, self.cursor_col),
+            b'u' => (self.cursor_row, self.cursor_col) = self.saved_cursor,
+            _ => {}
+        }
+    }
+
+    /// DEC private modes; only those affecting input are tracked.
+    fn private_csi(&mut self, params: &[u16], final_byte: u8) {
+        let enabled = match final_byte {
+            b'h' => true,
+            b'l' => false,
+            _ => return,
+        };
+        if params.contains(&1) {
+            self.application_cursor = enabled;
+        }
+    }
+
+    fn erase_display(&mut self, mode: u16) {
+        let row = self.cursor_row as usize;
+        match mode {
+            0 => {
+                self.erase_line(0);
+                for cells in &mut self.cells[row + 1..] {
+                    cells.fill(' ');
+                }
+            }
+            1 => {
+                self.erase_line(1);
+                for cells in &mut self.cells[..row] {
+                    cells.fill(' ');
+                }
+            }
+            2 | 3 => {
+                for cells in &mut self.cells {
+                    cells.fill(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, mode: u16) {
+        let col = self.cursor_col.min(self.cols - 1) as usize;
+        let cells = &mut self.cells[self.cursor_row as usize];
+        match mode {
+            0 => cells[col..].fill(' '),
+            1 => cells[..=col].fill(' '),
+            2 => cells.fill(' '),
+            _ => {}
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor_row == self.scroll_bottom {
+            self.scroll_up_from(self.scroll_top);
+        } else if self.cursor_row < self.rows - 1 {
+            self.cursor_row += 1;
+        }
+    }
+
+    fn reverse_line_feed(&mut self) {
+        if self.cursor_row == self.scroll_top {
+            self.scroll_down_from(self.scroll_top);
+        } else {
+            self.cursor_row = self.cursor_row.saturating_sub(1);
+        }
+    }
+
+    /// Remove the line at `row`, shifting the rest of the scroll region up.
+    fn scroll_up_from(&mut self, row: u16) {
+        let bottom = self.scroll_bottom as usize;
+        self.cells[row as usize..=bottom].rotate_left(1);
+        self.cells[bottom].fill(' ');
+    }
+
+    /// Insert a blank line at `row`, shifting the rest of the scroll region down.
+    fn scroll_down_from(&mut self, row: u16) {
+        let bottom = self.scroll_bottom as usize;
+        self.cells[row as usize..=bottom].rotate_right(1);
+        self.cells[row as usize].fill(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_and_wrap() {
+        let mut screen = Screen::new(3, 5);
+        screen.process(b"abcdefg");
+        assert_eq!(screen.row_text(0), "abcde");
+        assert_eq!(screen.row_text(1), "fg");
+        assert_eq!(screen.cursor(), (1, 2));
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let mut screen = Screen::new(2, 10);
+        screen.process(b"one\r\ntwo\r\nthree");
+        assert_eq!(screen.contents(), "two\nthree");
+    }
+
+    #[test]
+    fn test_cursor_movement_and_erase() {
+        let mut screen = Screen::new(5, 20);
+        screen.process(b"\x1b[2J\x1b[3;5Hmenu\x1b[1;1Htitle\x1b[3;7H\x1b[K");
+        assert_eq!(screen.row_text(0), "title");
+        assert_eq!(screen.row_text(2), "    me");
+        assert_eq!(screen.cursor(), (2, 6));
+    }
+
+    #[test]
+    fn test_region_text() {
+        let mut screen = Screen::new(3, 10);
+        screen.process(b"0123456789\x1b[2;1Habcdefghij");
+        assert_eq!(screen.region_text(Rect::new(0, 2, 2, 3)), "234\ncde");
+        assert_eq!(screen.region_text(Rect::row(1)), "abcdefghij");
+        assert_eq!(screen.region_text(Rect::new(5, 0, 1, 1)), "");
+    }
+
+    #[test]
+    fn test_ignores_attributes_and_osc() {
+        let mut screen = Screen::new(2, 20);
+        screen.process(b"\x1b]0;window title\x07\x1b[1;31mred\x1b[0m \x1b[?25lok");
+        assert_eq!(screen.row_text(0), "red ok");
+    }
+
+    #[test]
+    fn test_utf8() {
+        let mut screen = Screen::new(1, 10);
+        screen.process("héllo".as_bytes());
+        assert_eq!(screen.row_text(0), "héllo");
+        assert_eq!(screen.cursor(), (0, 5));
+    }
+
+    #[test]
+    fn test_application_cursor_mode() {
+        let mut screen = Screen::new(2, 10);
+        assert!(!screen.application_cursor());
+        screen.process(b"\x1b[?1h");
+        assert!(screen.application_cursor());
+        screen.process(b"\x1b[?1l");
+        assert!(!screen.application_cursor());
+    }
+
+    #[test]
+    fn test_scroll_region() {
+        let mut screen = Screen::new(4, 10);
+        screen.process(b"top\x1b[2;3r\x1b[2;1Ha\r\nb\r\nc\x1b[4;1Hstatus");
+        assert_eq!(screen.contents(), "top\nb\nc\nstatus");
+    }
+
+    #[test]
+    fn test_cursor_movement_saturates() {
+        let mut screen = Screen::new(4, 10);
+        screen.process(b"\x1b[2;1H\x1b[65535B\x1b[65535C");
+        assert_eq!(screen.cursor(), (3, 9));
+        screen.process(b"\x1b[65535E");
+        assert_eq!(screen.cursor(), (3, 0));
+    }
+
+    #[test]
+    fn test_resize_clamps_saved_cursor() {
+        let mut screen = Screen::new(24, 80);
+        screen.process(b"\x1b[20;70H\x1b7");
+        screen.resize(10, 40);
+        screen.process(b"\x1b8X");
+        assert_eq!(screen.row_text(9), format!("{:39}X", ""));
+    }
+}
@@ -0,0 +1,210 @@
+//! Escape sequence parser feeding the screen model
+
+/// Maximum number of CSI parameters kept; extra parameters are ignored.
+const MAX_PARAMS: usize = 16;
+
+/// An action decoded from the output stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// A printable character.
+    Print(char),
+    /// A C0 control byte.
+    Control(u8),
+    /// A control sequence (`ESC [ params final`).
+    Csi {
+        params: Vec<u16>,
+        /// Whether the sequence had a private marker (`?`, `>`, ...).
+        private: bool,
+        final_byte: u8,
+    },
+    /// A two-byte escape sequence (`ESC byte`).
+    Escape(u8),
+}
+
+#[derive(Debug, Clone, Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// `ESC (`-style designators take one more byte, which is ignored.
+    EscapeIntermediate,
+    Csi {
+        params: Vec<u16>,
+        private: bool,
+    },
+    /// Operating system command, terminated by BEL or `ESC \`.
+    Osc {
+        escape: bool,
+    },
+}
+
+/// Incremental parser; state persists between chunks.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Parser {
+    state: State,
+    utf8: Vec<u8>,
+}
+
+impl Parser {
+    /// Feed one byte, returning an action once one is complete.
+    pub(crate) fn advance(&mut self, byte: u8) -> Option<Action> {
+        match std::mem::take(&mut self.state) {
+            State::Ground => self.ground(byte),
+            State::Escape => match byte {
+                b'[' => {
+                    self.state = State::Csi {
+                        params: vec![0],
+                        private: false,
+                    };
+                    None
+                }
+                b']' => {
+                    self.state = State::Osc { escape: false };
+                    None
+                }
+                b'(' | b')' | b'*' | b'+' | b'#' | b'%' => {
+                    self.state = State::EscapeIntermediate;
+                    None
+                }
+                _ => Some(Action::Escape(byte)),
+            },
+            State::EscapeIntermediate => None,
+            State::Csi {
+                mut params,
+                mut private,
+            } => match byte {
+                b'0'..=b'9' => {
+                    if let Some(last) = params.last_mut() {
+                        *last = last.saturating_mul(10).saturating_add((byte - b'0') as u16);
+                    }
+                    self.state = State::Csi { params, private };
+                    None
+                }
+                b';' | b':' => {
+                    if params.len() < MAX_PARAMS {
+                        params.push(0);
+                    }
+                    self.state = State::Csi { params, private };
+                    None
+                }
+                b'<'..=b'?' => {
+                    private = true;
+                    self.state = State::Csi { params, private };
+                    None
+                }
+                // Intermediate bytes
+                0x20..=0x2f => {
+                    self.state = State::Csi { params, private };
+                    None
+                }
+                0x40..=0x7e => Some(Action::Csi {
+                    params,
+                    private,
+                    final_byte: byte,
+                }),
+                // Anything else aborts the sequence
+                _ => self.ground(byte),
+            },
+            State::Osc { escape } => {
+                if byte == 0x07 || (escape && byte == b'\\') {
+                    return None;
+                }
+                self.state = State::Osc {
+                    escape: byte == 0x1b,
+                };
+                None
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8) -> Option<Action> {
+        match byte {
+            0x1b => {
+                self.utf8.clear();
+                self.state = State::Escape;
+                None
+            }
+            0x00..=0x1f | 0x7f => {
+                self.utf8.clear();
+                Some(Action::Control(byte))
+            }
+            0x20..=0x7e => {
+                self.utf8.clear();
+                Some(Action::Print(byte as char))
+            }
+            _ => self.utf8_byte(byte),
+        }
+    }
+
+    fn utf8_byte(&mut self, byte: u8) -> Option<Action> {
+        if byte & 0xc0 != 0x80 {
+            // Start of a new sequence; drop any incomplete one
+            self.utf8.clear();
+        }
+        self.utf8.push(byte);
+
+        let expected = match self.utf8[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => {
+                self.utf8.clear();
+                return Some(Action::Print(char::REPLACEMENT_CHARACTER));
+            }
+        };
+        if self.utf8.len() < expected {
+            return None;
+        }
+
+        let ch = std::str::from_utf8(&self.utf8)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.utf8.clear();
+        Some(Action::Print(ch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &[u8]) -> Vec<Action> {
+        let mut parser = Parser::default();
+        data.iter().filter_map(|&b| parser.advance(b)).collect()
+    }
+
+    #[test]
+    fn test_csi_params() {
+        assert_eq!(
+            parse(b"\x1b[12;34H"),
+            vec![Action::Csi {
+                params: vec![12, 34],
+                private: false,
+                final_byte: b'H'
+            }]
+        );
+    }
+
+    #[test]
+    fn test_private_csi() {
+        assert_eq!(
+            parse(b"\x1b[?1049h"),
+            vec![Action::Csi {
+                params: vec![1049],
+                private: true,
+                final_byte: b'h'
+            }]
+        );
+    }
+
+    #[test]
+    fn test_osc_skipped() {
+        assert_eq!(parse(b"\x1b]0;title\x1b\\a"), vec![Action::Print('a')]);
+    }
+
+    #[test]
+    fn test_charset_designator_skipped() {
+        assert_eq!(parse(b"\x1b(Bx"), vec![Action::Print('x')]);
+    }
+}
@@ -1,7 +1,32 @@
 //! Abstract Syntax Tree (AST) definitions for Expect scripts.
 
 /// A block of statements.
-pub type Block = Vec<Statement>;
+pub type Block = Vec<Spanned<Statement>>;
+
+/// Location of a node in the script source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the start.
+    pub start: usize,
+    /// Byte offset just past the end.
+    pub end: usize,
+    /// Line of the start (1-based).
+    pub line: usize,
+    /// Column of the start (1-based).
+    pub col: usize,
+}
+
+/// A node together with its location in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    /// The node.
+    pub node: T,
+    /// Where the node appears in the source.
+    pub span: Span,
+    /// Text of the `#` comments directly preceding the node, or following
+    /// it on the same line, one per line.
+    pub comments: Vec<String>,
+}
 
 /// A statement in an Expect script.
 #[derive(Debug, Clone, PartialEq)]
@@ -10,7 +35,7 @@ pub enum Statement {
     Spawn(SpawnStmt),
     /// Expect one or more patterns: `expect pattern` or `expect { pattern { action } ... }`
     Expect(ExpectStmt),
-    /// Send data to the process: `send "data"`
+    /// Send data to the process: `send ?-i id? ?-break? ?--? "data"`
     Send(SendStmt),
     /// Set a variable: `set var value`
     Set(SetStmt),
@@ -24,10 +49,10 @@ pub enum Statement {
     Proc(ProcStmt),
     /// Procedure call: `name args...`
     Call(CallStmt),
-    /// Close the session: `close`
-    Close,
-    /// Wait for process exit: `wait`
-    Wait,
+    /// Close the connection to a process: `close ?-i id?`
+    Close(Option<Expression>),
+    /// Wait for process exit: `wait ?-i id?`
+    Wait(Option<Expression>),
     /// Exit the script: `exit` or `exit code`
     Exit(Option<Expression>),
 }
@@ -53,6 +78,8 @@ pub struct ExpectPattern {
     pub pattern_type: PatternType,
     /// Optional action block to execute on match.
     pub action: Option<Block>,
+    /// Location of the pattern.
+    pub span: Span,
 }
 
 /// Type of pattern to match.
@@ -70,11 +97,28 @@ pub enum PatternType {
     Timeout,
 }
 
+impl std::fmt::Display for PatternType {
+    /// Formats the pattern as it is written in a script.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternType::Exact(s) => write!(f, "{:?}", s),
+            PatternType::Regex(s) => write!(f, "-re {:?}", s),
+            PatternType::Glob(s) => write!(f, "-gl {:?}", s),
+            PatternType::Eof => write!(f, "eof"),
+            PatternType::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
 /// Send statement.
 #[derive(Debug, Clone, PartialEq)]
 pub struct SendStmt {
     /// Data to send (expression that evaluates to a string).
     pub data: Expression,
+    /// Spawn id of the target process (`-i`); the current process if `None`.
+    pub spawn_id: Option<Expression>,
+    /// Whether to send a break signal instead of data (`-break`).
+    pub break_signal: bool,
 }
 
 /// Set statement (variable assignment).
@@ -110,11 +154,11 @@ pub struct WhileStmt {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ForStmt {
     /// Initialization statement.
-    pub init: Box<Statement>,
+    pub init: Box<Spanned<Statement>>,
     /// Loop condition.
     pub condition: Expression,
     /// Increment statement.
-    pub increment: Box<Statement>,
+    pub increment: Box<Spanned<Statement>>,
     /// Loop body.
     pub body: Block,
 }
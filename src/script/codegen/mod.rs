@@ -11,10 +11,29 @@ use crate::script::ast::*;
 use std::fmt;
 
 /// Result of translating an Expect script to Rust code.
+///
+/// Besides the complete file in `code`, the generated code is available in
+/// sections, so callers can compose their own output: merge the imports
+/// with an existing module, place the helper items elsewhere, or embed the
+/// body in a function of their own.
 #[derive(Debug)]
 pub struct GeneratedCode {
-    /// The generated Rust code.
+    /// The complete generated Rust code, as rendered by
+    /// [`render(true)`](Self::render).
     pub code: String,
+    /// `use` declarations needed by the generated code, one per entry.
+    pub imports: Vec<String>,
+    /// Items placed before the entry function, such as the functions
+    /// translated from `proc` definitions.
+    pub items: Vec<String>,
+    /// Attributes and signature of the entry function, without the opening
+    /// brace.
+    pub entry: String,
+    /// Statements of the entry function, indented one level.
+    pub body: String,
+    /// Final expression of the entry function: `Ok(())`, or `Ok(0)` for a
+    /// function that returns the script's exit status.
+    pub result: String,
     /// Warnings about unsupported features or behavioral differences.
     pub warnings: Vec<TranslationWarning>,
     /// Additional crate dependencies needed.
@@ -22,13 +41,46 @@ pub struct GeneratedCode {
 }
 
 impl GeneratedCode {
-    /// Create a new generated code result.
-    pub fn new(code: String, warnings: Vec<TranslationWarning>) -> Self {
-        Self {
-            code,
-            warnings,
-            dependencies: vec!["expectrust".to_string(), "tokio".to_string()],
+    /// Render the sections as one Rust file.
+    ///
+    /// With `with_warnings`, a review notice is placed at the top and the
+    /// warnings are listed as comments at the end.
+    pub fn render(&self, with_warnings: bool) -> String {
+        let with_warnings = with_warnings && !self.warnings.is_empty();
+        let mut code = String::new();
+
+        if with_warnings {
+            code.push_str("// WARNING: This code was auto-generated from an expect script\n");
+            code.push_str("// Review and test thoroughly before using in production\n\n");
         }
+
+        for import in &self.imports {
+            code.push_str(import);
+            code.push('\n');
+        }
+        code.push('\n');
+
+        for item in &self.items {
+            code.push_str(item);
+            code.push_str("\n\n");
+        }
+
+        code.push_str(&self.entry);
+        code.push_str(" {\n");
+        code.push_str(&self.body);
+        code.push_str("    ");
+        code.push_str(&self.result);
+        code.push('\n');
+        code.push_str("}\n");
+
+        if with_warnings {
+            code.push_str("\n// Translation warnings:\n");
+            for warning in &self.warnings {
+                code.push_str(&format!("// - {}\n", warning));
+            }
+        }
+
+        code
     }
 }
 
@@ -40,8 +92,14 @@ pub struct Translator {
     indent_level: usize,
     /// Whether we're inside a procedure.
     in_procedure: bool,
-    /// Line number tracking for warnings.
+    /// Script line of the statement being translated.
     current_line: usize,
+    /// Whether `spawn` clones the `builder` parameter instead of calling
+    /// `Session::spawn`.
+    spawn_from_builder: bool,
+    /// Whether `exit` returns the exit status from the entry function
+    /// instead of ending the process.
+    return_exit_status: bool,
 }
 
 impl Translator {
@@ -52,61 +110,126 @@ impl Translator {
             indent_level: 1,
             in_procedure: false,
             current_line: 0,
+            spawn_from_builder: false,
+            return_exit_status: false,
         }
     }
 
-    /// Translate a script block to Rust code.
+    /// Translate a script block to a standalone Rust program.
     pub fn translate(block: &Block) -> Result<GeneratedCode, TranslationError> {
+        Self::new().generate(
+            block,
+            vec![
+                "use expectrust::{Session, Pattern};".to_string(),
+                "use std::time::Duration;".to_string(),
+            ],
+            "#[tokio::main]\nasync fn main() -> Result<(), Box<dyn std::error::Error>>".to_string(),
+            "Ok(())",
+        )
+    }
+
+    /// Translate a script block to a public async function named `name`.
+    ///
+    /// The function takes a `&SessionBuilder`, which every `spawn` in the
+    /// script clones, so callers choose timeouts and other session options.
+    /// It returns the script's exit status: `exit` returns from the function
+    /// rather than ending the caller's process, and a script that runs to
+    /// the end returns 0.
+    pub fn translate_fn(name: &str, block: &Block) -> Result<GeneratedCode, TranslationError> {
+        if !is_identifier(name) {
+            return Err(TranslationError::Internal(format!(
+                "Invalid function name: {}",
+                name
+            )));
+        }
+
         let mut translator = Self::new();
+        translator.spawn_from_builder = true;
+        translator.return_exit_status = true;
+        translator.generate(
+            block,
+            vec![
+                "use expectrust::{Pattern, SessionBuilder};".to_string(),
+                "use std::time::Duration;".to_string(),
+            ],
+            format!(
+                "pub async fn {}(builder: &SessionBuilder) -> Result<i32, Box<dyn std::error::Error>>",
+                name
+            ),
+            "Ok(0)",
+        )
+    }
 
+    /// Translate a script block into the sections of a [`GeneratedCode`].
+    fn generate(
+        mut self,
+        block: &Block,
+        imports: Vec<String>,
+        entry: String,
+        result: &str,
+    ) -> Result<GeneratedCode, TranslationError> {
         // Detect warnings upfront
         let detected_warnings = WarningDetector::check_script(block);
-        translator.warnings.extend(detected_warnings);
+        self.warnings.extend(detected_warnings);
 
-        // Generate main function body
+        // Top-level procedures become items before the entry function
+        let mut items = Vec::new();
         let mut body = String::new();
         for stmt in block {
-            translator.current_line += 1;
-            let code = translator.generate_statement(stmt)?;
+            if matches!(stmt.node, Statement::Proc(_)) {
+                self.indent_level = 0;
+                items.push(self.generate_statement(stmt)?);
+                self.indent_level = 1;
+                continue;
+            }
+
+            let code = self.generate_statement(stmt)?;
             if !code.is_empty() {
-                body.push_str(&translator.indent(&code));
+                body.push_str(&self.indent(&code));
                 body.push('\n');
             }
         }
 
-        // Build full code
-        let mut code = String::new();
+        let mut generated = GeneratedCode {
+            code: String::new(),
+            imports,
+            items,
+            entry,
+            body,
+            result: result.to_string(),
+            warnings: self.warnings,
+            dependencies: vec!["expectrust".to_string(), "tokio".to_string()],
+        };
+        generated.code = generated.render(true);
+        Ok(generated)
+    }
 
-        // Add warning header if there are warnings
-        if !translator.warnings.is_empty() {
-            code.push_str("// WARNING: This code was auto-generated from an expect script\n");
-            code.push_str("// Review and test thoroughly before using in production\n\n");
+    /// Generate code for a single statement.
+    fn generate_statement(
+        &mut self,
+        stmt: &Spanned<Statement>,
+    ) -> Result<String, TranslationError> {
+        self.current_line = stmt.span.line;
+        let code = self.generate_node(&stmt.node)?;
+        if stmt.comments.is_empty() {
+            return Ok(code);
         }
 
-        // Add imports
-        code.push_str("use expectrust::{Session, Pattern};\n");
-        code.push_str("use std::time::Duration;\n\n");
-
-        // Add main function
-        code.push_str("#[tokio::main]\n");
-        code.push_str("async fn main() -> Result<(), Box<dyn std::error::Error>> {\n");
-        code.push_str(&body);
-        code.push_str("    Ok(())\n");
-        code.push_str("}\n");
-
-        // Add warning comments at the end
-        if !translator.warnings.is_empty() {
-            code.push_str("\n// Translation warnings:\n");
-            for warning in &translator.warnings {
-                code.push_str(&format!("// - {}\n", warning));
+        // Carry the script's comments over to the generated code
+        let mut commented = String::new();
+        for comment in &stmt.comments {
+            if comment.is_empty() {
+                commented.push_str("//\n");
+            } else {
+                commented.push_str(&format!("// {}\n", comment));
             }
         }
-
-        Ok(GeneratedCode::new(code, translator.warnings))
+        commented.push_str(&code);
+        Ok(commented)
     }
 
-    /// Generate code for a single statement.
-    fn generate_statement(&mut self, stmt: &Statement) -> Result<String, TranslationError> {
+    /// Generate code for a statement, without its comments.
+    fn generate_node(&mut self, stmt: &Statement) -> Result<String, TranslationError> {
         match stmt {
             Statement::Spawn(s) => statement::gen_spawn(s, self),
             Statement::Expect(s) => statement::gen_expect(s, self),
@@ -117,15 +240,20 @@ impl Translator {
             Statement::For(s) => statement::gen_for(s, self),
             Statement::Proc(s) => statement::gen_proc(s, self),
             Statement::Call(s) => statement::gen_call(s, self),
-            Statement::Close => Ok("drop(session);".to_string()),
-            Statement::Wait => Ok("session.wait().await?;".to_string()),
+            Statement::Close(_) => Ok("drop(session);".to_string()),
+            Statement::Wait(_) => Ok("session.wait().await?;".to_string()),
             Statement::Exit(code) => {
-                if let Some(expr) = code {
-                    let code_expr = expression::generate_expression(expr, self)?;
-                    Ok(format!("std::process::exit({} as i32);", code_expr))
-                } else {
-                    Ok("std::process::exit(0);".to_string())
-                }
+                let code_expr = match code {
+                    Some(expr) => {
+                        format!("{} as i32", expression::generate_expression(expr, self)?)
+                    }
+                    None => "0".to_string(),
+                };
+                Ok(match (self.return_exit_status, self.in_procedure) {
+                    (false, _) => format!("std::process::exit({});", code_expr),
+                    (true, false) => format!("return Ok({});", code_expr),
+                    (true, true) => format!("return Ok(Some({}));", code_expr),
+                })
             }
         }
     }
@@ -176,6 +304,14 @@ impl Translator {
     }
 }
 
+/// Whether `name` can be used as a Rust function name.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+}
+
 impl Default for Translator {
     fn default() -> Self {
         Self::new()
@@ -10,14 +10,17 @@ pub fn gen_spawn(
 ) -> Result<String, TranslationError> {
     let cmd = expression::generate_expression(&stmt.command, translator)?;
 
+    let spawner = if translator.spawn_from_builder {
+        "builder.clone().spawn"
+    } else {
+        "Session::spawn"
+    };
+
     // Try to evaluate if it's a static string
     let code = if let Expression::String(s) = &stmt.command {
-        format!(
-            "let mut session = Session::spawn(\"{}\")?;",
-            escape_string(s)
-        )
+        format!("let mut session = {}(\"{}\")?;", spawner, escape_string(s))
     } else {
-        format!("let mut session = Session::spawn(&{})?;", cmd)
+        format!("let mut session = {}(&{})?;", spawner, cmd)
     };
 
     Ok(code)
@@ -98,6 +101,10 @@ fn gen_expect_multi(
 
 /// Generate code for send statement.
 pub fn gen_send(stmt: &SendStmt, translator: &mut Translator) -> Result<String, TranslationError> {
+    if stmt.break_signal {
+        return Ok("session.send_break(Duration::from_millis(250)).await?;".to_string());
+    }
+
     if let Expression::String(s) = &stmt.data {
         Ok(format!("session.send(b\"{}\").await?;", escape_bytes(s)))
     } else {
@@ -189,11 +196,19 @@ pub fn gen_for(stmt: &ForStmt, translator: &mut Translator) -> Result<String, Tr
 /// Generate code for procedure definition.
 pub fn gen_proc(stmt: &ProcStmt, translator: &mut Translator) -> Result<String, TranslationError> {
     let params = stmt.params.join(", ");
+    // In function mode a procedure returns the status of an `exit` in it,
+    // so the caller can return it in turn
+    let (output, result) = if translator.return_exit_status {
+        ("Option<i32>", "Ok(None)")
+    } else {
+        ("()", "Ok(())")
+    };
 
     let mut code = format!(
-        "async fn {}({}) -> Result<(), Box<dyn std::error::Error>> {{\n",
+        "async fn {}({}) -> Result<{}, Box<dyn std::error::Error>> {{\n",
         sanitize_variable_name(&stmt.name),
-        params
+        params,
+        output
     );
     translator.push_indent();
 
@@ -204,8 +219,8 @@ pub fn gen_proc(stmt: &ProcStmt, translator: &mut Translator) -> Result<String,
 
     code.push_str(&body);
 
-    // Add Ok(()) if not already present
-    code.push_str(&translator.indent("Ok(())\n"));
+    code.push_str(&translator.indent(result));
+    code.push('\n');
 
     translator.pop_indent();
     code.push_str(&translator.indent("}"));
@@ -220,17 +235,24 @@ pub fn gen_call(stmt: &CallStmt, translator: &mut Translator) -> Result<String,
         args.push(expression::generate_expression(arg, translator)?);
     }
 
-    let call = if args.is_empty() {
-        format!("{}().await?;", sanitize_variable_name(&stmt.name))
+    let call = format!(
+        "{}({}).await?",
+        sanitize_variable_name(&stmt.name),
+        args.join(", ")
+    );
+    if !translator.return_exit_status {
+        return Ok(format!("{};", call));
+    }
+
+    let exit = if translator.in_procedure {
+        "Some(code)"
     } else {
-        format!(
-            "{}({}).await?;",
-            sanitize_variable_name(&stmt.name),
-            args.join(", ")
-        )
+        "code"
     };
-
-    Ok(call)
+    Ok(format!(
+        "if let Some(code) = {} {{\n    return Ok({});\n}}",
+        call, exit
+    ))
 }
 
 /// Escape special characters in a string for Rust string literal.
@@ -10,8 +10,8 @@ pub enum TranslationWarning {
     UnsupportedFeature {
         /// The feature name
         feature: String,
-        /// The line number
-        line: usize,
+        /// Where the feature is used in the script
+        span: Span,
         /// Suggested workaround
         suggestion: String,
     },
@@ -19,8 +19,8 @@ pub enum TranslationWarning {
     BehaviorDifference {
         /// Description of the difference
         description: String,
-        /// The line number
-        line: usize,
+        /// Where the difference applies in the script
+        span: Span,
     },
     /// General performance or usage note.
     PerformanceNote {
@@ -29,22 +29,35 @@ pub enum TranslationWarning {
     },
 }
 
+impl TranslationWarning {
+    /// Where in the script the warning applies, if it is about a specific
+    /// statement.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::UnsupportedFeature { span, .. } | Self::BehaviorDifference { span, .. } => {
+                Some(*span)
+            }
+            Self::PerformanceNote { .. } => None,
+        }
+    }
+}
+
 impl fmt::Display for TranslationWarning {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::UnsupportedFeature {
                 feature,
-                line,
+                span,
                 suggestion,
             } => {
                 write!(
                     f,
                     "Line {}: '{}' not directly supported - {}",
-                    line, feature, suggestion
+                    span.line, feature, suggestion
                 )
             }
-            Self::BehaviorDifference { description, line } => {
-                write!(f, "Line {}: {}", line, description)
+            Self::BehaviorDifference { description, span } => {
+                write!(f, "Line {}: {}", span.line, description)
             }
             Self::PerformanceNote { description } => {
                 write!(f, "Note: {}", description)
@@ -56,7 +69,7 @@ impl fmt::Display for TranslationWarning {
 /// Detector for warnings in a script.
 pub struct WarningDetector {
     warnings: Vec<TranslationWarning>,
-    line: usize,
+    span: Span,
 }
 
 impl WarningDetector {
@@ -64,13 +77,12 @@ impl WarningDetector {
     pub fn check_script(script: &Block) -> Vec<TranslationWarning> {
         let mut detector = Self {
             warnings: Vec::new(),
-            line: 0,
+            span: Span::default(),
         };
 
         // Add general async warning
         detector.warnings.push(TranslationWarning::PerformanceNote {
-            description: "All generated code is async - main function uses #[tokio::main]"
-                .to_string(),
+            description: "All generated code is async and needs a Tokio runtime".to_string(),
         });
 
         detector.walk_block(script);
@@ -80,8 +92,8 @@ impl WarningDetector {
     /// Walk through a block of statements.
     fn walk_block(&mut self, block: &Block) {
         for stmt in block {
-            self.line += 1;
-            self.check_statement(stmt);
+            self.span = stmt.span;
+            self.check_statement(&stmt.node);
         }
     }
 
@@ -94,8 +106,8 @@ impl WarningDetector {
             Statement::Expect(expect_stmt) => {
                 self.check_expect(expect_stmt);
             }
-            Statement::Send(_) => {
-                // No warnings for basic send
+            Statement::Send(send_stmt) => {
+                self.check_send(send_stmt);
             }
             Statement::Set(_) => {
                 // No warnings for variable assignment
@@ -113,18 +125,24 @@ impl WarningDetector {
                 self.walk_block(&for_stmt.body);
             }
             Statement::Proc(proc_stmt) => {
-                let saved_line = self.line;
                 self.walk_block(&proc_stmt.body);
-                self.line = saved_line;
             }
             Statement::Call(_) => {
                 // No warnings for procedure calls
             }
-            Statement::Close => {
-                // No warnings for close
-            }
-            Statement::Wait => {
-                // No warnings for wait
+            Statement::Close(spawn_id) | Statement::Wait(spawn_id) => {
+                if spawn_id.is_some() {
+                    let command = if matches!(stmt, Statement::Close(_)) {
+                        "close -i"
+                    } else {
+                        "wait -i"
+                    };
+                    self.warnings.push(TranslationWarning::UnsupportedFeature {
+                        feature: command.to_string(),
+                        span: self.span,
+                        suggestion: "the single generated session is used; pass the intended Session explicitly".to_string(),
+                    });
+                }
             }
             Statement::Exit(_) => {
                 // No warnings for exit
@@ -132,6 +150,17 @@ impl WarningDetector {
         }
     }
 
+    /// Check send statement for options without a direct translation.
+    fn check_send(&mut self, send_stmt: &SendStmt) {
+        if send_stmt.spawn_id.is_some() {
+            self.warnings.push(TranslationWarning::UnsupportedFeature {
+                feature: "send -i".to_string(),
+                span: self.span,
+                suggestion: "data is sent to the single generated session; pass the intended Session explicitly".to_string(),
+            });
+        }
+    }
+
     /// Check expect statement for regex patterns.
     fn check_expect(&mut self, _expect_stmt: &ExpectStmt) {
         // Could add warnings for specific pattern types if needed
@@ -147,7 +176,10 @@ mod tests {
     fn test_format_unsupported_warning() {
         let warning = TranslationWarning::UnsupportedFeature {
             feature: "interact".to_string(),
-            line: 10,
+            span: Span {
+                line: 10,
+                ..Span::default()
+            },
             suggestion: "implement manual I/O loop".to_string(),
         };
         let text = format!("{}", warning);
@@ -162,4 +194,18 @@ mod tests {
         // Should at least have the async note
         assert!(!warnings.is_empty());
     }
+
+    #[test]
+    fn test_warning_uses_source_line() {
+        let script = "spawn cat\n\n# comment\nproc greet {} {\n    send -i $id \"hi\"\n}\n";
+        let block = crate::script::parser::parse_script(script).unwrap();
+        let warnings = WarningDetector::check_script(&block);
+        let warning = warnings
+            .iter()
+            .find(|w| matches!(w, TranslationWarning::UnsupportedFeature { .. }))
+            .unwrap();
+        let span = warning.span().unwrap();
+        assert_eq!((span.line, span.col), (5, 5));
+        assert_eq!(&script[span.start..span.end], "send -i $id \"hi\"");
+    }
 }
@@ -1,5 +1,6 @@
 //! Error types for script parsing and execution.
 
+use crate::script::ast::Span;
 use std::fmt;
 
 /// Errors that can occur during script parsing and execution.
@@ -27,6 +28,17 @@ pub enum ScriptError {
         /// Actual type.
         actual: String,
     },
+    /// An `expect` statement timed out without a `timeout` pattern.
+    StepTimeout {
+        /// Line of the statement.
+        line: usize,
+        /// Column of the statement.
+        col: usize,
+        /// The statement's patterns as written in the script.
+        patterns: String,
+        /// The underlying `ExpectError::Timeout`.
+        error: crate::ExpectError,
+    },
     /// Error from the Expect session.
     ExpectError(crate::ExpectError),
     /// I/O error.
@@ -35,6 +47,66 @@ pub enum ScriptError {
     PatternError(crate::PatternError),
     /// Script exited with a code.
     Exit(i32),
+    /// An error raised by the statement at the given location.
+    Located {
+        /// Line of the statement.
+        line: usize,
+        /// Column of the statement.
+        col: usize,
+        /// The error itself.
+        error: Box<ScriptError>,
+    },
+}
+
+impl ScriptError {
+    /// Line and column in the script where the error occurred, if known.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            ScriptError::ParseError { line, col, .. }
+            | ScriptError::StepTimeout { line, col, .. }
+            | ScriptError::Located { line, col, .. } => Some((*line, *col)),
+            _ => None,
+        }
+    }
+
+    /// The error without its location, for matching on the kind of error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::script::{Script, ScriptError};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let script = Script::from_str("send $missing\n")?;
+    /// if let Err(e) = script.execute().await {
+    ///     if let ScriptError::UndefinedVariable(name) = e.inner() {
+    ///         eprintln!("{:?}: ${} is not set", e.location(), name);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inner(&self) -> &ScriptError {
+        match self {
+            ScriptError::Located { error, .. } => error,
+            _ => self,
+        }
+    }
+
+    /// Attach the location of the statement that raised the error.
+    ///
+    /// Errors that already have a location, and `Exit`, are returned as is.
+    pub(crate) fn at(self, span: Span) -> Self {
+        match self {
+            ScriptError::Exit(_) | ScriptError::ParseError { .. } => self,
+            _ if self.location().is_some() => self,
+            error => ScriptError::Located {
+                line: span.line,
+                col: span.col,
+                error: Box::new(error),
+            },
+        }
+    }
 }
 
 impl fmt::Display for ScriptError {
@@ -57,10 +129,23 @@ impl fmt::Display for ScriptError {
             ScriptError::TypeError { expected, actual } => {
                 write!(f, "Type error: expected {}, got {}", expected, actual)
             }
+            ScriptError::StepTimeout {
+                line,
+                col,
+                patterns,
+                error,
+            } => write!(
+                f,
+                "expect at line {}, column {} waiting for {}: {}",
+                line, col, patterns, error
+            ),
             ScriptError::ExpectError(e) => write!(f, "Expect error: {}", e),
             ScriptError::IoError(e) => write!(f, "I/O error: {}", e),
             ScriptError::PatternError(e) => write!(f, "Pattern error: {}", e),
             ScriptError::Exit(code) => write!(f, "Script exited with code {}", code),
+            ScriptError::Located { line, col, error } => {
+                write!(f, "Line {}, column {}: {}", line, col, error)
+            }
         }
     }
 }
@@ -68,9 +153,11 @@ impl fmt::Display for ScriptError {
 impl std::error::Error for ScriptError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
+            ScriptError::StepTimeout { error, .. } => Some(error),
             ScriptError::ExpectError(e) => Some(e),
             ScriptError::IoError(e) => Some(e),
             ScriptError::PatternError(e) => Some(e),
+            ScriptError::Located { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -4,6 +4,10 @@ use crate::script::ast::*;
 use crate::script::error::ScriptError;
 use crate::script::runtime::Runtime;
 use crate::script::value::Value;
+use std::time::Duration;
+
+/// Length of the break condition sent by `send -break`, matching `tcsendbreak`.
+const SERIAL_BREAK_DURATION: Duration = Duration::from_millis(250);
 
 /// Execute a block of statements.
 pub fn execute_block<'a>(
@@ -19,14 +23,18 @@ pub fn execute_block<'a>(
 }
 
 /// Execute a single statement.
+///
+/// Errors are tagged with the statement's location, unless a nested
+/// statement already did so.
 pub fn execute_statement<'a>(
-    statement: &'a Statement,
+    statement: &'a Spanned<Statement>,
     runtime: &'a mut Runtime,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ScriptError>> + 'a>> {
+    let span = statement.span;
     Box::pin(async move {
-        match statement {
+        let result = match &statement.node {
             Statement::Spawn(stmt) => execute_spawn(stmt, runtime).await,
-            Statement::Expect(stmt) => execute_expect(stmt, runtime).await,
+            Statement::Expect(stmt) => execute_expect(stmt, span, runtime).await,
             Statement::Send(stmt) => execute_send(stmt, runtime).await,
             Statement::Set(stmt) => execute_set(stmt, runtime),
             Statement::If(stmt) => execute_if(stmt, runtime).await,
@@ -34,10 +42,11 @@ pub fn execute_statement<'a>(
             Statement::For(stmt) => execute_for(stmt, runtime).await,
             Statement::Proc(stmt) => execute_proc(stmt, runtime),
             Statement::Call(stmt) => execute_call(stmt, runtime).await,
-            Statement::Close => execute_close(runtime).await,
-            Statement::Wait => execute_wait(runtime).await,
+            Statement::Close(id) => execute_close(id.as_ref(), runtime).await,
+            Statement::Wait(id) => execute_wait(id.as_ref(), runtime).await,
             Statement::Exit(code_expr) => execute_exit(code_expr.as_ref(), runtime),
-        }
+        };
+        result.map_err(|e| e.at(span))
     })
 }
 
@@ -48,7 +57,11 @@ async fn execute_spawn(stmt: &SpawnStmt, runtime: &mut Runtime) -> Result<(), Sc
     Ok(())
 }
 
-async fn execute_expect(stmt: &ExpectStmt, runtime: &mut Runtime) -> Result<(), ScriptError> {
+async fn execute_expect(
+    stmt: &ExpectStmt,
+    span: Span,
+    runtime: &mut Runtime,
+) -> Result<(), ScriptError> {
     // Build patterns from the expect statement
     let mut patterns = Vec::new();
     for pattern in &stmt.patterns {
@@ -56,12 +69,36 @@ async fn execute_expect(stmt: &ExpectStmt, runtime: &mut Runtime) -> Result<(),
         patterns.push(p);
     }
 
-    // Execute expect_any to match the first pattern
-    let session = runtime.session_mut()?;
-    let result = session.expect_any(&patterns).await?;
+    // A closed session has reached end of file, as in Expect
+    let pattern_index = if runtime.is_closed() {
+        stmt.patterns
+            .iter()
+            .position(|p| p.pattern_type == PatternType::Eof)
+            .ok_or(crate::ExpectError::Eof)?
+    } else {
+        // Execute expect_any to match the first pattern
+        let session = runtime.session_mut()?;
+        match session.expect_any(&patterns).await {
+            Ok(result) => result.pattern_index,
+            Err(error @ crate::ExpectError::Timeout { .. }) => {
+                let patterns: Vec<String> = stmt
+                    .patterns
+                    .iter()
+                    .map(|p| p.pattern_type.to_string())
+                    .collect();
+                return Err(ScriptError::StepTimeout {
+                    line: span.line,
+                    col: span.col,
+                    patterns: patterns.join(", "),
+                    error,
+                });
+            }
+            Err(error) => return Err(error.into()),
+        }
+    };
 
     // If the matched pattern has an action, execute it
-    if let Some(matched_pattern) = stmt.patterns.get(result.pattern_index) {
+    if let Some(matched_pattern) = stmt.patterns.get(pattern_index) {
         if let Some(action) = &matched_pattern.action {
             execute_block(action, runtime).await?;
         }
@@ -73,7 +110,19 @@ async fn execute_expect(stmt: &ExpectStmt, runtime: &mut Runtime) -> Result<(),
 async fn execute_send(stmt: &SendStmt, runtime: &mut Runtime) -> Result<(), ScriptError> {
     let data = evaluate_expression(&stmt.data, runtime)?;
     let data_str = data.as_string();
-    let session = runtime.session_mut()?;
+    let session = match &stmt.spawn_id {
+        Some(id) => {
+            let id = evaluate_expression(id, runtime)?.as_string();
+            runtime.session_by_id(&id)?
+        }
+        None => runtime.session_mut()?,
+    };
+
+    if stmt.break_signal {
+        session.send_break(SERIAL_BREAK_DURATION).await?;
+        return Ok(());
+    }
+
     session.send(data_str.as_bytes()).await?;
     Ok(())
 }
@@ -175,12 +224,20 @@ async fn execute_call(stmt: &CallStmt, runtime: &mut Runtime) -> Result<(), Scri
     result
 }
 
-async fn execute_close(runtime: &mut Runtime) -> Result<(), ScriptError> {
-    runtime.close().await
+async fn execute_close(id: Option<&Expression>, runtime: &mut Runtime) -> Result<(), ScriptError> {
+    let id = id
+        .map(|id| evaluate_expression(id, runtime))
+        .transpose()?
+        .map(|id| id.as_string());
+    runtime.close(id).await
 }
 
-async fn execute_wait(runtime: &mut Runtime) -> Result<(), ScriptError> {
-    runtime.wait().await
+async fn execute_wait(id: Option<&Expression>, runtime: &mut Runtime) -> Result<(), ScriptError> {
+    let id = id
+        .map(|id| evaluate_expression(id, runtime))
+        .transpose()?
+        .map(|id| id.as_string());
+    runtime.wait(id).await
 }
 
 fn execute_exit(code_expr: Option<&Expression>, runtime: &mut Runtime) -> Result<(), ScriptError> {
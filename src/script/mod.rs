@@ -45,7 +45,7 @@ pub mod codegen;
 #[cfg(feature = "translator")]
 pub mod translator;
 
-pub use ast::{Block, Expression, Statement};
+pub use ast::{Block, Expression, Span, Spanned, Statement};
 pub use error::ScriptError;
 pub use value::Value;
 
@@ -68,6 +68,7 @@ pub struct Script {
     max_buffer_size: Option<usize>,
     strip_ansi: bool,
     pty_size: Option<(u16, u16)>,
+    variables: Vec<(String, Value)>,
 }
 
 impl Script {
@@ -89,6 +90,7 @@ impl Script {
             max_buffer_size: None,
             strip_ansi: false,
             pty_size: None,
+            variables: Vec::new(),
         })
     }
 
@@ -143,6 +145,9 @@ impl Script {
             self.strip_ansi,
             self.pty_size,
         );
+        for (name, value) in self.variables {
+            runtime.context_mut().set_variable(name, value);
+        }
 
         interpreter::execute_block(&self.ast, &mut runtime).await?;
 
@@ -164,6 +169,7 @@ impl std::str::FromStr for Script {
             max_buffer_size: None,
             strip_ansi: false,
             pty_size: None,
+            variables: Vec::new(),
         })
     }
 }
@@ -174,6 +180,7 @@ pub struct ScriptBuilder {
     max_buffer_size: Option<usize>,
     strip_ansi: bool,
     pty_size: Option<(u16, u16)>,
+    variables: Vec<(String, Value)>,
 }
 
 impl ScriptBuilder {
@@ -184,6 +191,7 @@ impl ScriptBuilder {
             max_buffer_size: None,
             strip_ansi: false,
             pty_size: None,
+            variables: Vec::new(),
         }
     }
 
@@ -211,6 +219,15 @@ impl ScriptBuilder {
         self
     }
 
+    /// Set a variable before the script runs.
+    ///
+    /// Passes values such as credentials in from the host program, so they
+    /// need not be written in the script.
+    pub fn variable(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.variables.push((name.into(), value.into()));
+        self
+    }
+
     /// Parse a script from a string with the configured options.
     pub fn from_str(self, input: &str) -> Result<Script, ScriptError> {
         let ast = parser::parse_script(input)?;
@@ -220,6 +237,7 @@ impl ScriptBuilder {
             max_buffer_size: self.max_buffer_size,
             strip_ansi: self.strip_ansi,
             pty_size: self.pty_size,
+            variables: self.variables,
         })
     }
 
@@ -17,15 +17,7 @@ pub fn parse_script(input: &str) -> Result<Block, ScriptError> {
     let mut statements = Vec::new();
     for pair in pairs {
         match pair.as_rule() {
-            Rule::script => {
-                for inner_pair in pair.into_inner() {
-                    if let Rule::statement = inner_pair.as_rule() {
-                        if let Some(stmt) = parse_statement(inner_pair)? {
-                            statements.push(stmt);
-                        }
-                    }
-                }
-            }
+            Rule::script => statements = parse_statements(pair, 0)?,
             Rule::EOI => {}
             _ => {}
         }
@@ -34,26 +26,88 @@ pub fn parse_script(input: &str) -> Result<Block, ScriptError> {
     Ok(statements)
 }
 
-fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Result<Option<Statement>, ScriptError> {
+/// Parse the statements inside `pair`, attaching the comments that precede
+/// each one, or follow it on the same line (`cmd ;# comment`). Comments are
+/// looked for from byte offset `start` onwards.
+fn parse_statements(pair: pest::iterators::Pair<Rule>, start: usize) -> Result<Block, ScriptError> {
+    let input = pair.as_span().get_input();
+    let mut statements: Block = Vec::new();
+    let mut gap_start = start;
+
+    for inner_pair in pair.into_inner() {
+        if let Rule::statement = inner_pair.as_rule() {
+            let end = inner_pair.as_span().end();
+            if let Some(mut stmt) = parse_statement(inner_pair)? {
+                let mut gap = &input[gap_start..stmt.span.start];
+                // The previous statement's line continues unless its
+                // terminator was a newline
+                let same_line = !input[..gap_start].ends_with('\n');
+                if let (true, Some(previous)) = (same_line, statements.last_mut()) {
+                    let line_end = gap.find('\n').unwrap_or(gap.len());
+                    let (trailing, rest) = gap.split_at(line_end);
+                    previous.comments.extend(comments_in(trailing));
+                    gap = rest;
+                }
+                stmt.comments = comments_in(gap);
+                statements.push(stmt);
+                gap_start = end;
+            }
+        }
+    }
+
+    Ok(statements)
+}
+
+/// The `#` comments in source text between two statements.
+fn comments_in(gap: &str) -> Vec<String> {
+    gap.lines()
+        .map(|line| line.trim_start_matches([' ', '\t', ';']))
+        .filter_map(|line| line.strip_prefix('#'))
+        .map(|comment| comment.trim().to_string())
+        .collect()
+}
+
+fn parse_statement(
+    pair: pest::iterators::Pair<Rule>,
+) -> Result<Option<Spanned<Statement>>, ScriptError> {
     let inner = pair.into_inner().next();
     let Some(inner) = inner else {
         return Ok(None);
     };
 
-    match inner.as_rule() {
-        Rule::spawn_stmt => Ok(Some(parse_spawn_stmt(inner)?)),
-        Rule::expect_stmt => Ok(Some(parse_expect_stmt(inner)?)),
-        Rule::send_stmt => Ok(Some(parse_send_stmt(inner)?)),
-        Rule::set_stmt => Ok(Some(parse_set_stmt(inner)?)),
-        Rule::if_stmt => Ok(Some(parse_if_stmt(inner)?)),
-        Rule::while_stmt => Ok(Some(parse_while_stmt(inner)?)),
-        Rule::for_stmt => Ok(Some(parse_for_stmt(inner)?)),
-        Rule::proc_stmt => Ok(Some(parse_proc_stmt(inner)?)),
-        Rule::close_stmt => Ok(Some(Statement::Close)),
-        Rule::wait_stmt => Ok(Some(Statement::Wait)),
-        Rule::exit_stmt => Ok(Some(parse_exit_stmt(inner)?)),
-        Rule::call_stmt => Ok(Some(parse_call_stmt(inner)?)),
-        _ => Ok(None),
+    let span = span_of(&inner);
+    let statement = match inner.as_rule() {
+        Rule::spawn_stmt => parse_spawn_stmt(inner)?,
+        Rule::expect_stmt => parse_expect_stmt(inner)?,
+        Rule::send_stmt => parse_send_stmt(inner)?,
+        Rule::set_stmt => parse_set_stmt(inner)?,
+        Rule::if_stmt => parse_if_stmt(inner)?,
+        Rule::while_stmt => parse_while_stmt(inner)?,
+        Rule::for_stmt => parse_for_stmt(inner)?,
+        Rule::proc_stmt => parse_proc_stmt(inner)?,
+        Rule::close_stmt => Statement::Close(parse_spawn_id_option(inner)?),
+        Rule::wait_stmt => Statement::Wait(parse_spawn_id_option(inner)?),
+        Rule::exit_stmt => parse_exit_stmt(inner)?,
+        Rule::call_stmt => parse_call_stmt(inner)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(Spanned {
+        node: statement,
+        span,
+        comments: Vec::new(),
+    }))
+}
+
+/// Location of a pair, excluding the command separator ending a statement.
+fn span_of(pair: &pest::iterators::Pair<Rule>) -> Span {
+    let span = pair.as_span();
+    let (line, col) = span.start_pos().line_col();
+    let text = span.as_str().trim_end_matches([' ', '\t', '\r', '\n', ';']);
+    Span {
+        start: span.start(),
+        end: span.start() + text.len(),
+        line,
+        col,
     }
 }
 
@@ -110,41 +164,83 @@ fn parse_pattern_spec(
     pair: pest::iterators::Pair<Rule>,
     action: Option<Block>,
 ) -> Result<ExpectPattern, ScriptError> {
+    // Keywords like `-re` and `timeout` are literals and produce no inner pairs,
+    // so look at the matched text to tell the pattern kinds apart
+    let span = span_of(&pair);
+    let text = pair.as_str();
     let mut inner = pair.into_inner();
-    let first = inner.next().unwrap();
 
-    let pattern_type = match first.as_str() {
-        "-re" => {
-            let word = parse_word(inner.next().unwrap())?;
-            PatternType::Regex(word)
-        }
-        "-gl" => {
-            let word = parse_word(inner.next().unwrap())?;
-            PatternType::Glob(word)
-        }
-        "timeout" => PatternType::Timeout,
-        "eof" => PatternType::Eof,
-        _ => {
-            // It's a word (exact match)
-            let word = parse_word(first)?;
-            PatternType::Exact(word)
-        }
+    let pattern_type = if text.starts_with("-re") {
+        PatternType::Regex(parse_word(inner.next().unwrap())?)
+    } else if text.starts_with("-gl") {
+        PatternType::Glob(parse_word(inner.next().unwrap())?)
+    } else if text == "timeout" {
+        PatternType::Timeout
+    } else if text == "eof" {
+        PatternType::Eof
+    } else {
+        // It's a word (exact match)
+        PatternType::Exact(parse_word(inner.next().unwrap())?)
     };
 
     Ok(ExpectPattern {
         pattern_type,
         action,
+        span,
     })
 }
 
 fn parse_send_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptError> {
-    let mut inner = pair.into_inner();
-    let word = parse_word(inner.next().unwrap())?;
+    let (line, col) = pair.as_span().start_pos().line_col();
+    let mut data = None;
+    let mut spawn_id = None;
+    let mut break_signal = false;
+
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::send_option => {
+                let option = inner.into_inner().next().unwrap();
+                match option.as_rule() {
+                    Rule::send_spawn_id => {
+                        let word = parse_word(option.into_inner().next().unwrap())?;
+                        spawn_id = Some(Expression::String(word));
+                    }
+                    _ => break_signal = true,
+                }
+            }
+            Rule::word => data = Some(Expression::String(parse_word(inner)?)),
+            _ => {}
+        }
+    }
+
+    if data.is_none() && !break_signal {
+        return Err(ScriptError::ParseError {
+            line,
+            col,
+            message: "send requires a string to send".to_string(),
+        });
+    }
+
     Ok(Statement::Send(SendStmt {
-        data: Expression::String(word),
+        data: data.unwrap_or_else(|| Expression::String(String::new())),
+        spawn_id,
+        break_signal,
     }))
 }
 
+/// Parse the optional `-i id` of a statement.
+fn parse_spawn_id_option(
+    pair: pest::iterators::Pair<Rule>,
+) -> Result<Option<Expression>, ScriptError> {
+    match pair.into_inner().next() {
+        Some(option) => {
+            let word = parse_word(option.into_inner().next().unwrap())?;
+            Ok(Some(Expression::String(word)))
+        }
+        None => Ok(None),
+    }
+}
+
 fn parse_set_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptError> {
     let mut inner = pair.into_inner();
     let name = inner.next().unwrap().as_str().to_string();
@@ -161,14 +257,12 @@ fn parse_set_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, Script
 fn parse_if_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptError> {
     let mut inner = pair.into_inner();
 
-    // First brace_block is the condition
-    let cond_block = parse_brace_block(inner.next().unwrap())?;
-    let condition = block_to_expression(cond_block);
+    let condition = parse_condition(inner.next().unwrap())?;
 
-    // Second brace_block is the then block
+    // First brace_block is the then block
     let then_block = parse_brace_block(inner.next().unwrap())?;
 
-    // Optional third brace_block is the else block
+    // Optional second brace_block is the else block
     let else_block = inner.next().map(|p| parse_brace_block(p)).transpose()?;
 
     Ok(Statement::If(IfStmt {
@@ -181,8 +275,7 @@ fn parse_if_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptE
 fn parse_while_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptError> {
     let mut inner = pair.into_inner();
 
-    let cond_block = parse_brace_block(inner.next().unwrap())?;
-    let condition = block_to_expression(cond_block);
+    let condition = parse_condition(inner.next().unwrap())?;
 
     let body = parse_brace_block(inner.next().unwrap())?;
 
@@ -192,30 +285,9 @@ fn parse_while_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, Scri
 fn parse_for_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptError> {
     let mut inner = pair.into_inner();
 
-    let init_block = parse_brace_block(inner.next().unwrap())?;
-    let init = Box::new(
-        init_block
-            .into_iter()
-            .next()
-            .unwrap_or(Statement::Set(SetStmt {
-                name: "_".to_string(),
-                value: Expression::Number(0.0),
-            })),
-    );
-
-    let cond_block = parse_brace_block(inner.next().unwrap())?;
-    let condition = block_to_expression(cond_block);
-
-    let incr_block = parse_brace_block(inner.next().unwrap())?;
-    let increment = Box::new(
-        incr_block
-            .into_iter()
-            .next()
-            .unwrap_or(Statement::Set(SetStmt {
-                name: "_".to_string(),
-                value: Expression::Number(0.0),
-            })),
-    );
+    let init = Box::new(parse_for_clause(inner.next().unwrap())?);
+    let condition = parse_condition(inner.next().unwrap())?;
+    let increment = Box::new(parse_for_clause(inner.next().unwrap())?);
 
     let body = parse_brace_block(inner.next().unwrap())?;
 
@@ -227,6 +299,20 @@ fn parse_for_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, Script
     }))
 }
 
+/// Parse the init or increment block of a for loop, using its first statement.
+fn parse_for_clause(pair: pest::iterators::Pair<Rule>) -> Result<Spanned<Statement>, ScriptError> {
+    let span = span_of(&pair);
+    let block = parse_brace_block(pair)?;
+    Ok(block.into_iter().next().unwrap_or(Spanned {
+        node: Statement::Set(SetStmt {
+            name: "_".to_string(),
+            value: Expression::Number(0.0),
+        }),
+        span,
+        comments: Vec::new(),
+    }))
+}
+
 fn parse_proc_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, ScriptError> {
     let mut inner = pair.into_inner();
 
@@ -266,17 +352,69 @@ fn parse_exit_stmt(pair: pest::iterators::Pair<Rule>) -> Result<Statement, Scrip
 }
 
 fn parse_brace_block(pair: pest::iterators::Pair<Rule>) -> Result<Block, ScriptError> {
-    let mut statements = Vec::new();
+    // Skip the opening brace
+    let start = pair.as_span().start() + 1;
+    parse_statements(pair, start)
+}
 
-    for inner_pair in pair.into_inner() {
-        if let Rule::statement = inner_pair.as_rule() {
-            if let Some(stmt) = parse_statement(inner_pair)? {
-                statements.push(stmt);
+fn parse_condition(pair: pest::iterators::Pair<Rule>) -> Result<Expression, ScriptError> {
+    parse_expression(pair.into_inner().next().unwrap())
+}
+
+fn parse_expression(pair: pest::iterators::Pair<Rule>) -> Result<Expression, ScriptError> {
+    match pair.as_rule() {
+        Rule::expression | Rule::primary_expr => {
+            parse_expression(pair.into_inner().next().unwrap())
+        }
+        Rule::binary_expr => {
+            let mut inner = pair.into_inner();
+            let left = parse_expression(inner.next().unwrap())?;
+            let op = match inner.next().unwrap().as_str() {
+                "+" => BinaryOperator::Add,
+                "-" => BinaryOperator::Sub,
+                "*" => BinaryOperator::Mul,
+                "/" => BinaryOperator::Div,
+                "==" => BinaryOperator::Eq,
+                "!=" => BinaryOperator::Ne,
+                "<" => BinaryOperator::Lt,
+                ">" => BinaryOperator::Gt,
+                "<=" => BinaryOperator::Le,
+                ">=" => BinaryOperator::Ge,
+                "&&" => BinaryOperator::And,
+                _ => BinaryOperator::Or,
+            };
+            let right = parse_expression(inner.next().unwrap())?;
+            Ok(Expression::BinaryOp {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            })
+        }
+        Rule::unary_expr => {
+            let mut inner = pair.into_inner();
+            let op = match inner.next().unwrap().as_str() {
+                "-" => UnaryOperator::Neg,
+                _ => UnaryOperator::Not,
+            };
+            let operand = parse_expression(inner.next().unwrap())?;
+            Ok(Expression::UnaryOp {
+                op,
+                operand: Box::new(operand),
+            })
+        }
+        Rule::number => Ok(Expression::Number(pair.as_str().parse().unwrap_or(0.0))),
+        Rule::variable => Ok(Expression::Variable(
+            pair.as_str().trim_start_matches('$').to_string(),
+        )),
+        Rule::list => {
+            let mut items = Vec::new();
+            for item in pair.into_inner() {
+                items.push(parse_expression(item)?);
             }
+            Ok(Expression::List(items))
         }
+        _ => Ok(Expression::String(parse_word(pair)?)),
     }
-
-    Ok(statements)
 }
 
 fn parse_brace_list(pair: pest::iterators::Pair<Rule>) -> Result<Vec<String>, ScriptError> {
@@ -356,14 +494,3 @@ fn parse_string_inner(s: &str) -> String {
 
     result
 }
-
-fn block_to_expression(block: Block) -> Expression {
-    // For simplicity, convert a block to an expression by evaluating the last statement
-    // In a real implementation, this would need more sophisticated handling
-    if block.is_empty() {
-        Expression::Number(1.0)
-    } else {
-        // For now, just use a placeholder - the interpreter will handle this properly
-        Expression::Number(1.0)
-    }
-}
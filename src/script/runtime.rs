@@ -1,6 +1,6 @@
 //! Runtime environment for script execution.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use crate::script::ast::PatternType;
@@ -9,10 +9,26 @@ use crate::script::error::ScriptError;
 use crate::script::value::Value;
 use crate::{Pattern, Session};
 
+/// Name of the variable holding the current spawn id, as in Expect.
+const SPAWN_ID_VAR: &str = "spawn_id";
+
+/// Variable set by `wait` to `{pid spawn_id os_error status}`, as Expect's
+/// `wait` returns.
+const WAIT_RESULT_VAR: &str = "wait_result";
+
+/// Variable set by `wait` to the exit status of the child.
+const EXIT_STATUS_VAR: &str = "exit_status";
+
 /// Runtime environment managing the session and execution context.
 pub struct Runtime {
-    /// Active session (if spawned).
-    session: Option<Session>,
+    /// Spawned sessions, keyed by spawn id.
+    sessions: HashMap<String, Session>,
+    /// Spawn ids that were closed and have not been waited for yet.
+    closed: HashSet<String>,
+    /// Spawn id of the most recently spawned session.
+    current_id: Option<String>,
+    /// Counter used to allocate spawn ids.
+    next_id: usize,
     /// Execution context (variables and procedures).
     context: Context,
     /// Session configuration.
@@ -33,7 +49,10 @@ impl Runtime {
         pty_size: Option<(u16, u16)>,
     ) -> Self {
         Self {
-            session: None,
+            sessions: HashMap::new(),
+            closed: HashSet::new(),
+            current_id: None,
+            next_id: 0,
             context: Context::new(),
             timeout,
             max_buffer_size,
@@ -54,13 +73,50 @@ impl Runtime {
     }
 
     /// Get a mutable reference to the active session, if any.
+    ///
+    /// The active session is the one named by the `spawn_id` variable, or
+    /// the most recently spawned session if that variable is not set.
     pub fn session_mut(&mut self) -> Result<&mut Session, ScriptError> {
-        self.session.as_mut().ok_or_else(|| {
-            ScriptError::RuntimeError("No active session (call spawn first)".to_string())
-        })
+        match self.active_id() {
+            Some(id) => self.session_by_id(&id),
+            None => Err(ScriptError::RuntimeError(
+                "No active session (call spawn first)".to_string(),
+            )),
+        }
+    }
+
+    /// Spawn id of the active session.
+    fn active_id(&self) -> Option<String> {
+        match self.context.get_variable(SPAWN_ID_VAR) {
+            Some(value) => Some(value.as_string()),
+            None => self.current_id.clone(),
+        }
+    }
+
+    /// Whether the active session has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.active_id().is_some_and(|id| self.closed.contains(&id))
+    }
+
+    /// Get a mutable reference to the session with the given spawn id.
+    ///
+    /// Closed sessions are rejected, since nothing can be sent to them.
+    pub fn session_by_id(&mut self, id: &str) -> Result<&mut Session, ScriptError> {
+        if self.closed.contains(id) {
+            return Err(ScriptError::RuntimeError(format!(
+                "Spawn id {} is closed",
+                id
+            )));
+        }
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| ScriptError::RuntimeError(format!("Invalid spawn id: {}", id)))
     }
 
     /// Spawn a new session with the given command.
+    ///
+    /// The new session becomes the current one and its id is stored in the
+    /// `spawn_id` variable.
     pub fn spawn(&mut self, command: &str) -> Result<(), ScriptError> {
         let mut builder = Session::builder();
 
@@ -78,22 +134,63 @@ impl Runtime {
         }
 
         let session = builder.spawn(command)?;
-        self.session = Some(session);
+
+        let id = format!("exp{}", self.next_id);
+        self.next_id += 1;
+        self.sessions.insert(id.clone(), session);
+        self.context
+            .set_variable(SPAWN_ID_VAR.to_string(), Value::String(id.clone()));
+        self.current_id = Some(id);
         Ok(())
     }
 
-    /// Close the active session.
-    pub async fn close(&mut self) -> Result<(), ScriptError> {
-        // Simply drop the session - the Drop implementation will handle cleanup
-        self.session = None;
+    /// Close the connection to a session, the active one if `id` is `None`.
+    ///
+    /// As in Expect, the process is hung up on but not reaped: a later
+    /// `wait` collects its exit status. Until then, expects on the closed
+    /// session see end of file and sends fail.
+    pub async fn close(&mut self, id: Option<String>) -> Result<(), ScriptError> {
+        // Nothing to close before the first spawn
+        let Some(id) = id.or_else(|| self.active_id()) else {
+            return Ok(());
+        };
+        self.session_by_id(&id)?.hang_up()?;
+        self.closed.insert(id);
         Ok(())
     }
 
-    /// Wait for the session to exit.
-    pub async fn wait(&mut self) -> Result<(), ScriptError> {
-        if let Some(session) = &mut self.session {
-            session.wait().await?;
+    /// Wait for a session to exit, the active one if `id` is `None`.
+    ///
+    /// The exit status is stored in the `exit_status` variable, and
+    /// `wait_result` is set to the list `{pid spawn_id 0 status}` that
+    /// Expect's `wait` returns (the pid is -1 if unknown). A closed session
+    /// is released once waited for.
+    pub async fn wait(&mut self, id: Option<String>) -> Result<(), ScriptError> {
+        // Nothing to wait for before the first spawn
+        let Some(id) = id.or_else(|| self.active_id()) else {
+            return Ok(());
+        };
+        let session = self
+            .sessions
+            .get_mut(&id)
+            .ok_or_else(|| ScriptError::RuntimeError(format!("Invalid spawn id: {}", id)))?;
+        let pid = session.pid().map_or(-1.0, f64::from);
+        let status = f64::from(session.wait().await?.exit_code());
+        if self.closed.remove(&id) {
+            self.sessions.remove(&id);
         }
+
+        self.context
+            .set_variable(EXIT_STATUS_VAR.to_string(), Value::Number(status));
+        self.context.set_variable(
+            WAIT_RESULT_VAR.to_string(),
+            Value::List(vec![
+                Value::Number(pid),
+                Value::String(id),
+                Value::Number(0.0),
+                Value::Number(status),
+            ]),
+        );
         Ok(())
     }
 
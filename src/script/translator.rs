@@ -55,6 +55,57 @@ pub fn translate_ast(ast: &Block) -> Result<GeneratedCode, TranslationError> {
     CodeGen::translate(ast)
 }
 
+/// Translate an AST block to a reusable `pub async fn` named `name`.
+///
+/// Unlike [`translate_ast`], which produces a standalone program with a
+/// `#[tokio::main]` function, the generated function can be embedded in an
+/// existing codebase. It takes a `&SessionBuilder`, which every `spawn` in
+/// the script clones, so the caller chooses timeouts and other session
+/// options. It returns the script's exit status: `exit` returns it from the
+/// function instead of ending the caller's process, and a script that runs
+/// to the end returns 0.
+///
+/// ```rust,ignore
+/// pub async fn login(builder: &SessionBuilder) -> Result<i32, Box<dyn std::error::Error>> {
+///     let mut session = builder.clone().spawn("ssh host")?;
+///     // ...
+///     Ok(0)
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns `TranslationError::Internal` if `name` is not a valid Rust
+/// identifier, and any error translating the script.
+pub fn translate_to_fn(name: &str, ast: &Block) -> Result<GeneratedCode, TranslationError> {
+    CodeGen::translate_fn(name, ast)
+}
+
+/// Translate an Expect script file to a reusable `pub async fn` named `name`.
+///
+/// See [`translate_to_fn`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use expectrust::script::translator::translate_file_to_fn;
+///
+/// let generated = translate_file_to_fn("deploy", "deploy.exp")?;
+/// std::fs::write("src/deploy.rs", generated.code)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn translate_file_to_fn<P: AsRef<Path>>(
+    name: &str,
+    path: P,
+) -> Result<GeneratedCode, TranslationError> {
+    let script_text = std::fs::read_to_string(path)
+        .map_err(|e| TranslationError::Internal(format!("File read error: {}", e)))?;
+    let ast = crate::script::parser::parse_script(&script_text)
+        .map_err(|e| TranslationError::Internal(format!("Parse error: {}", e)))?;
+
+    translate_to_fn(name, &ast)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,4 +139,125 @@ send "print('test')\n"
         let generated = result.unwrap();
         assert!(generated.code.contains("send"));
     }
+
+    #[test]
+    fn test_translate_preserves_comments() {
+        let script = r#"
+# Log in to the staging box
+spawn ssh staging
+# Wait for the prompt
+
+expect "password:" ;# the banner varies, only match the prompt
+if {1} {
+    #
+    # Retry is handled by the caller
+    send "secret\n"
+}
+"#;
+
+        let generated = translate_str(script).unwrap();
+        assert!(generated
+            .code
+            .contains("    // Log in to the staging box\n    let mut session"));
+        assert!(generated.code.contains(
+            "// Wait for the prompt\n    // the banner varies, only match the prompt\n    session.expect"
+        ));
+        let lines: Vec<&str> = generated.code.lines().map(str::trim).collect();
+        assert!(lines.windows(3).any(|w| w
+            == [
+                "//",
+                "// Retry is handled by the caller",
+                "session.send(b\"secret\\n\").await?;"
+            ]));
+    }
+
+    #[test]
+    fn test_trailing_comment_is_not_an_argument() {
+        let script = "spawn echo test  # inline comment\nexpect \"test\"\n";
+        let generated = translate_str(script).unwrap();
+        assert!(generated.code.contains(
+            "    // inline comment\n    let mut session = Session::spawn(\"echo test\")?;"
+        ));
+    }
+
+    #[test]
+    fn test_translate_to_fn() {
+        let script = r#"
+spawn echo hello
+expect "hello"
+"#;
+        let ast = crate::script::parser::parse_script(script).unwrap();
+
+        let generated = translate_to_fn("say_hello", &ast).unwrap();
+        assert!(generated.code.contains(
+            "pub async fn say_hello(builder: &SessionBuilder) -> Result<i32, Box<dyn std::error::Error>> {"
+        ));
+        assert!(generated.render(false).ends_with("    Ok(0)\n}\n"));
+        assert!(generated
+            .code
+            .contains("let mut session = builder.clone().spawn(\"echo hello\")?;"));
+        assert!(!generated.code.contains("tokio::main"));
+
+        assert!(matches!(
+            translate_to_fn("say-hello", &ast),
+            Err(TranslationError::Internal(_))
+        ));
+    }
+
+    #[test]
+    fn test_translate_to_fn_returns_exit_status() {
+        let script = r#"
+proc check {} {
+    exit 3
+}
+spawn cat
+check
+exit 1
+"#;
+        let ast = crate::script::parser::parse_script(script).unwrap();
+
+        let generated = translate_to_fn("run", &ast).unwrap();
+        assert!(!generated.code.contains("process::exit"));
+        assert!(generated.items[0]
+            .starts_with("async fn check() -> Result<Option<i32>, Box<dyn std::error::Error>> {"));
+        assert!(generated.items[0].contains("return Ok(Some(3 as i32));"));
+        assert!(generated.items[0].ends_with("    Ok(None)\n}"));
+        assert!(generated.body.contains(
+            "    if let Some(code) = check().await? {\n        return Ok(code);\n    }\n"
+        ));
+        assert!(generated.body.contains("    return Ok(1 as i32);\n"));
+
+        let standalone = translate_str(script).unwrap();
+        assert!(standalone.code.contains("std::process::exit(1 as i32);"));
+    }
+
+    #[test]
+    fn test_generated_code_sections() {
+        let script = r#"
+proc greet {} {
+    send "hi\n"
+}
+spawn cat
+greet
+"#;
+
+        let generated = translate_str(script).unwrap();
+        assert_eq!(generated.imports[0], "use expectrust::{Session, Pattern};");
+        assert_eq!(generated.items.len(), 1);
+        assert!(generated.items[0].starts_with("async fn greet()"));
+        assert!(generated.items[0].ends_with("    Ok(())\n}"));
+        assert!(generated
+            .entry
+            .ends_with("async fn main() -> Result<(), Box<dyn std::error::Error>>"));
+        assert_eq!(
+            generated.body,
+            "    let mut session = Session::spawn(\"cat\")?;\n    greet().await?;\n"
+        );
+        assert_eq!(generated.code, generated.render(true));
+
+        let plain = generated.render(false);
+        assert!(!plain.contains("// WARNING"));
+        assert!(!plain.contains("Translation warnings"));
+        assert!(plain.contains(&generated.items[0]));
+    }
 }
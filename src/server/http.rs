@@ -0,0 +1,205 @@
+//! Just enough HTTP/1.1 for the control server
+
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Largest accepted request head (request line and headers).
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Largest accepted request body.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// A parsed HTTP request.
+#[derive(Debug)]
+pub(crate) struct Request {
+    pub(crate) method: String,
+    /// Decoded path segments, e.g. `["sessions", "1", "send"]`.
+    pub(crate) segments: Vec<String>,
+    /// Decoded query parameters in order.
+    pub(crate) query: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Request {
+    /// The first value of query parameter `name`.
+    pub(crate) fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Read one request from the connection.
+pub(crate) async fn read_request<R: AsyncBufRead + Unpin>(stream: &mut R) -> io::Result<Request> {
+    let mut head_size = 0;
+    let mut request_line = String::new();
+    read_head_line(stream, &mut request_line, &mut head_size).await?;
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        let n = read_head_line(stream, &mut line, &mut head_size).await?;
+        let line = line.trim_end();
+        if n == 0 || line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("Invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(invalid("Request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await?;
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid("Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Ok(Request {
+        method: method.to_string(),
+        segments: path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(percent_decode)
+            .collect(),
+        query: query
+            .split('&')
+            .filter(|s| !s.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key), percent_decode(value))
+            })
+            .collect(),
+        body,
+    })
+}
+
+/// Read one line of the request head into `line`, without reading past
+/// [`MAX_HEAD_SIZE`] for the head as a whole.
+async fn read_head_line<R: AsyncBufRead + Unpin>(
+    stream: &mut R,
+    line: &mut String,
+    head_size: &mut usize,
+) -> io::Result<usize> {
+    let limit = (MAX_HEAD_SIZE - *head_size) as u64 + 1;
+    let n = (&mut *stream).take(limit).read_line(line).await?;
+    *head_size += n;
+    if *head_size > MAX_HEAD_SIZE {
+        return Err(invalid("Request head too large"));
+    }
+    Ok(n)
+}
+
+/// Write a complete response and close the exchange.
+pub(crate) async fn write_response(
+    stream: &mut BufReader<TcpStream>,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason(status),
+        content_type,
+        body.len()
+    );
+    let stream = stream.get_mut();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await
+}
+
+/// Write the head of a server-sent events response; events follow.
+pub(crate) async fn write_event_stream_head(stream: &mut BufReader<TcpStream>) -> io::Result<()> {
+    stream
+        .get_mut()
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        )
+        .await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Decode `%XX` escapes and `+` (as space) in a URL component.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push(high << 4 | low);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request_limits_head() {
+        let mut request: &[u8] = b"POST /sessions?a=%20 HTTP/1.1\r\nContent-Length: 3\r\n\r\ncat";
+        let request = read_request(&mut request).await.unwrap();
+        assert_eq!(request.segments, ["sessions"]);
+        assert_eq!(request.param("a"), Some(" "));
+        assert_eq!(request.body, b"cat");
+
+        let line = vec![b'x'; MAX_HEAD_SIZE * 4];
+        let mut stream = &line[..];
+        let error = read_request(&mut stream).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(stream.len(), MAX_HEAD_SIZE * 3 - 1);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b+c"), "a b c");
+        assert_eq!(percent_decode("%24%20"), "$ ");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%C3%A9%é"), "é%é");
+    }
+}
@@ -0,0 +1,462 @@
+//! HTTP control server for driving sessions from other languages
+//!
+//! [`serve`] exposes sessions over a small HTTP API, so CI systems, Python
+//! tooling and other non-Rust services can spawn processes and run
+//! expect/send exchanges remotely while this crate does the matching. The
+//! `expectrust-server` binary runs it from the command line. Requests carry
+//! plain text bodies, responses are JSON, and each session's events stream
+//! as server-sent events.
+//!
+//! | Request | Body | Response |
+//! |---|---|---|
+//! | `POST /sessions?timeout_ms=N&transcript=true` | command line, run by the platform shell | `201 {"id":1}` |
+//! | `GET /sessions` | | `200 {"sessions":[{"id":1,"command":…}]}` |
+//! | `GET /sessions/{id}` | | `200 {"id":1,"command":…,"alive":true,"buffer":…}` |
+//! | `POST /sessions/{id}/send` | bytes to send | `204` |
+//! | `POST /sessions/{id}/expect?kind=K&timeout_ms=N` | pattern | `200 {"matched":…,"before":…,"captures":[…]}` |
+//! | `GET /sessions/{id}/events` | | `text/event-stream` of the events of [`Session::serve_events`] |
+//! | `GET /sessions/{id}/transcript` | | `text/plain` [`Transcript`](crate::Transcript), if enabled at creation |
+//! | `DELETE /sessions/{id}` | | `200 {"exit_code":0}` after terminating the process |
+//!
+//! `kind` is `exact` (the default), `regex`, `glob` or `eof`; `timeout_ms`
+//! overrides the session timeout (30 seconds by default). Failures are
+//! reported as `{"error":"…"}` with status 400 for invalid requests and
+//! patterns, 404 for unknown sessions, 408 when an expect times out, 409
+//! for a transcript that was not enabled, 410 when the process closed its
+//! output first, and 500 otherwise.
+//!
+//! # Detaching
+//!
+//! Sessions belong to the server, not to a connection: a client detaches by
+//! simply going away, and any later client reattaches by session ID, so a
+//! long-running automation can be started and checked on from separate,
+//! short-lived CI steps. While no request drives a session, the server
+//! keeps reading its output into the buffer (and transcript), so the
+//! process never stalls on a full terminal and a reattaching client finds
+//! everything since its last match in `buffer`, subject to the buffer size.
+//!
+//! The server is not authenticated and runs any command it is sent: bind it
+//! to a loopback address, or put it behind an authenticating proxy.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:7878").await?;
+//! expectrust::server::serve(listener).await
+//! # }
+//! ```
+//!
+//! A client then drives a session:
+//!
+//! ```text
+//! $ curl -d 'python3 -i' localhost:7878/sessions
+//! {"id":1}
+//! $ curl -d '>>> ' localhost:7878/sessions/1/expect
+//! $ curl --data-binary $'print(6 * 7)\n' localhost:7878/sessions/1/send
+//! $ curl -d '42' 'localhost:7878/sessions/1/expect?timeout_ms=5000'
+//! $ curl -X DELETE localhost:7878/sessions/1
+//! ```
+
+mod http;
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult, PatternError};
+use crate::session::{push_json_string, Session, SessionBuilder};
+use http::Request;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Mutex;
+
+/// How long the background reader waits for output before letting
+/// requests have the session.
+const PUMP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// First delay before retrying after a transient accept error.
+const ACCEPT_RETRY_MIN: Duration = Duration::from_millis(10);
+
+/// Longest delay between accept retries.
+const ACCEPT_RETRY_MAX: Duration = Duration::from_secs(1);
+
+/// A session registered with the server.
+#[derive(Clone)]
+struct Entry {
+    command: String,
+    session: Arc<Mutex<Session>>,
+    events: broadcast::Sender<Arc<str>>,
+}
+
+/// Sessions by id.
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    entries: HashMap<u64, Entry>,
+}
+
+type SharedRegistry = Arc<std::sync::Mutex<Registry>>;
+
+/// A response.
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn text(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        let mut body = String::from("{\"error\":");
+        push_json_string(&mut body, message);
+        body.push('}');
+        Self::json(status, body)
+    }
+}
+
+impl From<ExpectError> for Reply {
+    fn from(error: ExpectError) -> Self {
+        let status = match error {
+            ExpectError::Timeout { .. } => 408,
+            ExpectError::Eof => 410,
+            ExpectError::PatternError(_) => 400,
+            _ => 500,
+        };
+        Reply::error(status, &error.to_string())
+    }
+}
+
+/// Serve the control API on `listener` until accepting a connection fails.
+///
+/// Each connection carries one request. Sessions live until they are
+/// deleted or the server stops.
+///
+/// Transient accept errors, such as running out of file descriptors or a
+/// client aborting its connection, are printed to stderr and retried after
+/// a short, growing delay.
+///
+/// # Errors
+///
+/// Returns the error that stopped the server from accepting connections.
+pub async fn serve(listener: TcpListener) -> std::io::Result<()> {
+    let registry = SharedRegistry::default();
+    let mut delay = ACCEPT_RETRY_MIN;
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) if is_transient(&e) => {
+                eprintln!("expectrust-server: accepting a connection failed: {}", e);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(ACCEPT_RETRY_MAX);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        delay = ACCEPT_RETRY_MIN;
+        tokio::spawn(handle(Arc::clone(&registry), stream));
+    }
+}
+
+/// Whether an accept error is worth retrying rather than ending the server.
+fn is_transient(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    if matches!(
+        error.kind(),
+        ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionReset
+            | ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::OutOfMemory
+    ) {
+        return true;
+    }
+    #[cfg(unix)]
+    if let Some(code) = error.raw_os_error() {
+        return matches!(
+            code,
+            libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM | libc::EPROTO
+        );
+    }
+    false
+}
+
+async fn handle(registry: SharedRegistry, stream: TcpStream) {
+    let mut stream = BufReader::new(stream);
+    let request = match http::read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            let reply = Reply::error(400, &e.to_string());
+            let _ = http::write_response(
+                &mut stream,
+                reply.status,
+                reply.content_type,
+                reply.body.as_bytes(),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+    let reply = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["sessions"]) => create(&registry, &request),
+        ("GET", ["sessions"]) => list(&registry),
+        ("GET", ["sessions", id]) => match lookup(&registry, id) {
+            Ok(entry) => state(id, &entry).await,
+            Err(reply) => Err(reply),
+        },
+        ("GET", ["sessions", id, "transcript"]) => match lookup(&registry, id) {
+            Ok(entry) => transcript(&entry).await,
+            Err(reply) => Err(reply),
+        },
+        ("POST", ["sessions", id, "send"]) => match lookup(&registry, id) {
+            Ok(entry) => send(&entry, &request).await,
+            Err(reply) => Err(reply),
+        },
+        ("POST", ["sessions", id, "expect"]) => match lookup(&registry, id) {
+            Ok(entry) => expect(&entry, &request).await,
+            Err(reply) => Err(reply),
+        },
+        ("GET", ["sessions", id, "events"]) => match lookup(&registry, id) {
+            Ok(entry) => {
+                let events = entry.events.subscribe();
+                // Holding the entry would keep the event stream open after
+                // the session is deleted
+                drop(entry);
+                stream_events(&mut stream, events).await;
+                return;
+            }
+            Err(reply) => Err(reply),
+        },
+        ("DELETE", ["sessions", id]) => delete(&registry, id).await,
+        _ => Err(Reply::error(404, "Not found")),
+    };
+
+    let reply = reply.unwrap_or_else(|reply| reply);
+    let _ = http::write_response(
+        &mut stream,
+        reply.status,
+        reply.content_type,
+        reply.body.as_bytes(),
+    )
+    .await;
+}
+
+fn lookup(registry: &SharedRegistry, id: &str) -> Result<Entry, Reply> {
+    let registry = registry
+        .lock()
+        .map_err(|_| Reply::error(500, "Registry lock poisoned"))?;
+    id.parse()
+        .ok()
+        .and_then(|id| registry.entries.get(&id).cloned())
+        .ok_or_else(|| Reply::error(404, &format!("No session {}", id)))
+}
+
+fn timeout_param(request: &Request) -> Result<Option<Duration>, Reply> {
+    request
+        .param("timeout_ms")
+        .map(|ms| {
+            ms.parse()
+                .map(Duration::from_millis)
+                .map_err(|_| Reply::error(400, "timeout_ms must be a number of milliseconds"))
+        })
+        .transpose()
+}
+
+fn create(registry: &SharedRegistry, request: &Request) -> Result<Reply, Reply> {
+    let command = std::str::from_utf8(&request.body)
+        .map_err(|_| Reply::error(400, "The command must be UTF-8"))?;
+    let mut builder = SessionBuilder::new().transcript(request.param("transcript") == Some("true"));
+    if let Some(timeout) = timeout_param(request)? {
+        builder = builder.timeout(timeout);
+    }
+    let mut session = builder.spawn_shell(command)?;
+    let events = session.event_sender();
+    let session = Arc::new(Mutex::new(session));
+    tokio::spawn(pump(Arc::downgrade(&session)));
+
+    let mut registry = registry
+        .lock()
+        .map_err(|_| Reply::error(500, "Registry lock poisoned"))?;
+    registry.next_id += 1;
+    let id = registry.next_id;
+    registry.entries.insert(
+        id,
+        Entry {
+            command: command.to_string(),
+            session,
+            events,
+        },
+    );
+    Ok(Reply::json(201, format!("{{\"id\":{}}}", id)))
+}
+
+/// Keep reading a session's output while no request drives it, until the
+/// process closes its output or the session is deleted.
+async fn pump(session: std::sync::Weak<Mutex<Session>>) {
+    while let Some(session) = session.upgrade() {
+        let mut session = session.lock().await;
+        if !matches!(session.pump(PUMP_INTERVAL).await, Ok(true)) {
+            break;
+        }
+        drop(session);
+        tokio::task::yield_now().await;
+    }
+}
+
+fn list(registry: &SharedRegistry) -> Result<Reply, Reply> {
+    let registry = registry
+        .lock()
+        .map_err(|_| Reply::error(500, "Registry lock poisoned"))?;
+    let mut ids: Vec<_> = registry.entries.keys().copied().collect();
+    ids.sort_unstable();
+
+    let mut json = String::from("{\"sessions\":[");
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        let _ = write!(json, "{{\"id\":{},\"command\":", id);
+        push_json_string(&mut json, &registry.entries[id].command);
+        json.push('}');
+    }
+    json.push_str("]}");
+    Ok(Reply::json(200, json))
+}
+
+async fn state(id: &str, entry: &Entry) -> Result<Reply, Reply> {
+    let mut session = entry.session.lock().await;
+    let alive = session.is_alive().unwrap_or(false);
+
+    let mut json = format!("{{\"id\":{},\"command\":", id);
+    push_json_string(&mut json, &entry.command);
+    let _ = write!(json, ",\"alive\":{},\"buffer\":", alive);
+    push_json_string(
+        &mut json,
+        &String::from_utf8_lossy(session.buffer().unmatched()),
+    );
+    json.push('}');
+    Ok(Reply::json(200, json))
+}
+
+async fn transcript(entry: &Entry) -> Result<Reply, Reply> {
+    match entry.session.lock().await.transcript() {
+        Some(transcript) => Ok(Reply::text(transcript.to_string())),
+        None => Err(Reply::error(
+            409,
+            "Transcript not enabled; create the session with transcript=true",
+        )),
+    }
+}
+
+async fn send(entry: &Entry, request: &Request) -> Result<Reply, Reply> {
+    entry.session.lock().await.send(&request.body).await?;
+    Ok(Reply::json(204, String::new()))
+}
+
+async fn expect(entry: &Entry, request: &Request) -> Result<Reply, Reply> {
+    let text = std::str::from_utf8(&request.body)
+        .map_err(|_| Reply::error(400, "The pattern must be UTF-8"))?;
+    let pattern = match request.param("kind").unwrap_or("exact") {
+        "exact" => Pattern::exact(text),
+        "regex" => Pattern::regex(text).map_err(|e| ExpectError::from(PatternError::from(e)))?,
+        "glob" => Pattern::glob(text),
+        "eof" => Pattern::Eof,
+        kind => {
+            return Err(Reply::error(
+                400,
+                &format!("Unknown pattern kind '{}'", kind),
+            ))
+        }
+    };
+    let timeout = timeout_param(request)?;
+
+    let mut session = entry.session.lock().await;
+    let timeout = timeout.or(session.timeout());
+    let result = session
+        .expect_any_within(std::slice::from_ref(&pattern), timeout)
+        .await?;
+    Ok(Reply::json(200, match_json(&result)))
+}
+
+fn match_json(result: &MatchResult) -> String {
+    let mut json = String::from("{\"matched\":");
+    push_json_string(&mut json, &result.matched);
+    json.push_str(",\"before\":");
+    push_json_string(&mut json, &result.before);
+    json.push_str(",\"captures\":[");
+    for (i, capture) in result.captures.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        push_json_string(&mut json, capture);
+    }
+    json.push_str("]}");
+    json
+}
+
+async fn delete(registry: &SharedRegistry, id: &str) -> Result<Reply, Reply> {
+    let entry = {
+        let mut registry = registry
+            .lock()
+            .map_err(|_| Reply::error(500, "Registry lock poisoned"))?;
+        id.parse()
+            .ok()
+            .and_then(|id| registry.entries.remove(&id))
+            .ok_or_else(|| Reply::error(404, &format!("No session {}", id)))?
+    };
+    let status = entry.session.lock().await.terminate().await?;
+    Ok(Reply::json(
+        200,
+        format!("{{\"exit_code\":{}}}", status.exit_code()),
+    ))
+}
+
+async fn stream_events(
+    stream: &mut BufReader<TcpStream>,
+    mut events: broadcast::Receiver<Arc<str>>,
+) {
+    if http::write_event_stream_head(stream).await.is_err() {
+        return;
+    }
+    loop {
+        let mut message = String::new();
+        match events.recv().await {
+            Ok(line) => {
+                let _ = write!(message, "data: {}\n\n", line.trim_end());
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                let _ = write!(message, ": {} events skipped\n\n", skipped);
+            }
+            Err(RecvError::Closed) => break,
+        }
+        if stream
+            .get_mut()
+            .write_all(message.as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    let _ = stream.get_mut().shutdown().await;
+}
@@ -0,0 +1,122 @@
+//! Request/response interface to a session for middleware composition
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::Session;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::Mutex;
+
+/// An asynchronous function from a request to a response.
+///
+/// This has the same shape as `tower::Service`, so middleware written in that
+/// style (retries, timeouts, metrics) applies directly, and an adapter to
+/// `tower` is a few lines.
+pub trait Service<Request> {
+    /// Response produced on success.
+    type Response;
+    /// Error produced on failure.
+    type Error;
+    /// Future returned by [`Service::call`].
+    type Future: Future<Output = Result<Self::Response, Self::Error>>;
+
+    /// Check whether the service is ready to accept a request.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
+
+    /// Process a request.
+    fn call(&mut self, request: Request) -> Self::Future;
+}
+
+/// A request to an [`ExpectService`].
+#[derive(Debug, Clone)]
+pub enum Request {
+    /// Wait for any of the patterns, as `Session::expect_any`.
+    Expect(Vec<Pattern>),
+    /// Send bytes, as `Session::send`.
+    Send(Vec<u8>),
+}
+
+/// The response to a [`Request`].
+#[derive(Debug, Clone)]
+pub enum Response {
+    /// The result of an `Expect` request.
+    Matched(MatchResult),
+    /// An acknowledgement of a `Send` request.
+    Sent,
+}
+
+/// A session exposed as a [`Service`].
+///
+/// Clones share the same session; requests are processed one at a time in
+/// the order they acquire the session.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::service::{ExpectService, Request, Response, Service};
+/// use expectrust::{Pattern, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut service = ExpectService::new(Session::spawn("bash")?);
+///
+/// service.call(Request::Send(b"echo hi\n".to_vec())).await?;
+/// if let Response::Matched(m) = service
+///     .call(Request::Expect(vec![Pattern::exact("hi")]))
+///     .await?
+/// {
+///     println!("Matched: {}", m.matched);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ExpectService {
+    session: Arc<Mutex<Session>>,
+}
+
+impl ExpectService {
+    /// Wrap a session.
+    pub fn new(session: Session) -> Self {
+        Self {
+            session: Arc::new(Mutex::new(session)),
+        }
+    }
+
+    /// The shared session, for operations not covered by [`Request`].
+    pub fn session(&self) -> Arc<Mutex<Session>> {
+        self.session.clone()
+    }
+
+    /// Unwrap the session.
+    ///
+    /// Returns `None` if other clones of the service still exist.
+    pub fn into_session(self) -> Option<Session> {
+        Arc::try_unwrap(self.session).ok().map(Mutex::into_inner)
+    }
+}
+
+impl Service<Request> for ExpectService {
+    type Response = Response;
+    type Error = ExpectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, ExpectError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ExpectError>> {
+        // Requests queue on the session lock instead
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let session = self.session.clone();
+        Box::pin(async move {
+            let mut session = session.lock().await;
+            match request {
+                Request::Expect(patterns) => {
+                    session.expect_any(&patterns).await.map(Response::Matched)
+                }
+                Request::Send(data) => session.send(&data).await.map(|()| Response::Sent),
+            }
+        })
+    }
+}
@@ -1,18 +1,31 @@
 //! Session builder for configuration
 
-use crate::buffer::BufferManager;
+use crate::buffer::{BufferManager, RawStream};
+use crate::config;
+use crate::history::HistoryLog;
+use crate::pattern::RegexOptions;
+use crate::quote;
 use crate::result::ExpectError;
-use crate::session::Session;
-use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use crate::screen::Screen;
+#[cfg(feature = "telnet")]
+use crate::session::telnet;
+use crate::session::{packet, DropPolicy, HumanTyping, Observers, Reader, SerialPort, Session};
+use crate::transcript::{Transcript, TranscriptLog};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 
 /// Default timeout for expect operations (in seconds)
-const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub(crate) const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
 /// Default maximum buffer size (in bytes)
-const DEFAULT_MAX_BUFFER_SIZE: usize = 8192;
+pub(crate) const DEFAULT_MAX_BUFFER_SIZE: usize = 8192;
 
 /// Default PTY rows
 const DEFAULT_PTY_ROWS: u16 = 24;
@@ -20,16 +33,45 @@ const DEFAULT_PTY_ROWS: u16 = 24;
 /// Default PTY columns
 const DEFAULT_PTY_COLS: u16 = 80;
 
+/// Default size of a single read from the PTY (in bytes)
+const DEFAULT_READ_CHUNK_SIZE: usize = 4096;
+
+/// Factor by which the read chunk may grow when the process produces output quickly
+const READ_CHUNK_GROWTH_LIMIT: usize = 16;
+
+/// Default time `terminate` waits before killing the process
+const DEFAULT_TERMINATE_GRACE: Duration = Duration::from_secs(5);
+
 /// Builder for configuring and spawning sessions.
 ///
 /// Provides a fluent interface for configuring session options before spawning a process.
 ///
 /// # Defaults
 ///
-/// - Timeout: 30 seconds
-/// - Max buffer size: 8192 bytes
-/// - ANSI stripping: disabled
+/// - Timeout: 30 seconds\*
+/// - Max buffer size: 8192 bytes\*
+/// - ANSI stripping: disabled\*
+/// - Progress-bar collapsing: disabled
 /// - PTY size: 24 rows × 80 columns
+/// - Read chunk size: 4096 bytes (growing up to 16× for fast producers)
+/// - Character delays: none
+/// - Screen model: disabled
+/// - Regex options: `regex` crate defaults
+/// - Match budget: none
+/// - Transcript: disabled
+/// - Log file: none
+/// - Log rate limit: none
+/// - History file: none
+/// - Output echo: disabled
+/// - Packet mode: disabled
+/// - Human typing: [`HumanTyping::default`]
+/// - Terminate grace period: 5 seconds
+/// - Drop policy: [`DropPolicy::HangUp`]
+/// - Environment: inherited from the parent process, including `TERM` and
+///   the locale
+/// - Working directory: the user's home directory
+///
+/// \* Process-wide defaults, see [`config`](crate::config).
 ///
 /// # Examples
 ///
@@ -52,7 +94,25 @@ pub struct SessionBuilder {
     timeout: Option<Duration>,
     max_buffer_size: usize,
     strip_ansi: bool,
+    collapse_progress: bool,
     pty_size: PtySize,
+    read_chunk_size: usize,
+    char_delays: HashMap<u8, Duration>,
+    screen: bool,
+    regex_options: RegexOptions,
+    match_budget: Option<Duration>,
+    transcript: bool,
+    log_file: Option<PathBuf>,
+    log_rate_limit: Option<(usize, Duration)>,
+    history_file: Option<PathBuf>,
+    echo_output: bool,
+    packet_mode: bool,
+    human_typing: HumanTyping,
+    terminate_grace: Duration,
+    drop_policy: DropPolicy,
+    clear_env: bool,
+    env: Vec<(OsString, Option<OsString>)>,
+    cwd: Option<PathBuf>,
 }
 
 impl Default for SessionBuilder {
@@ -64,18 +124,40 @@ impl Default for SessionBuilder {
 impl SessionBuilder {
     /// Create a new session builder with default configuration.
     ///
-    /// See the [`SessionBuilder`] documentation for default values.
+    /// See the [`SessionBuilder`] documentation for default values. The
+    /// timeout, buffer size and ANSI stripping defaults can be changed for
+    /// the whole process with [`config::set_defaults`](crate::config::set_defaults)
+    /// or environment variables.
     pub fn new() -> Self {
+        let defaults = config::defaults();
         Self {
-            timeout: Some(Duration::from_secs(DEFAULT_TIMEOUT_SECS)),
-            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
-            strip_ansi: false,
+            timeout: defaults.timeout,
+            max_buffer_size: defaults.buffer_size,
+            strip_ansi: defaults.strip_ansi,
+            collapse_progress: false,
             pty_size: PtySize {
                 rows: DEFAULT_PTY_ROWS,
                 cols: DEFAULT_PTY_COLS,
                 pixel_width: 0,
                 pixel_height: 0,
             },
+            read_chunk_size: DEFAULT_READ_CHUNK_SIZE,
+            char_delays: HashMap::new(),
+            screen: false,
+            regex_options: RegexOptions::default(),
+            match_budget: None,
+            transcript: false,
+            log_file: None,
+            log_rate_limit: None,
+            history_file: None,
+            echo_output: false,
+            packet_mode: false,
+            human_typing: HumanTyping::default(),
+            terminate_grace: DEFAULT_TERMINATE_GRACE,
+            drop_policy: DropPolicy::HangUp,
+            clear_env: false,
+            env: Vec::new(),
+            cwd: None,
         }
     }
 
@@ -141,6 +223,26 @@ impl SessionBuilder {
         self
     }
 
+    /// Enable or disable collapsing of progress bars.
+    ///
+    /// When enabled, lines that are redrawn with carriage returns (progress
+    /// bars of wget, apt, pip and similar tools) are reduced to their final
+    /// text before pattern matching, so they do not fill the buffer or break
+    /// line-oriented patterns. See
+    /// [`BufferManager::with_progress_collapsing`] for details.
+    ///
+    /// The [`transcript`](Self::transcript) and [`log_file`](Self::log_file)
+    /// are collapsed the same way. The log writes each read as it arrives,
+    /// so it only drops redraws within one read.
+    ///
+    /// # Arguments
+    ///
+    /// * `collapse` - `true` to collapse progress bars (default: `false`)
+    pub fn collapse_progress(mut self, collapse: bool) -> Self {
+        self.collapse_progress = collapse;
+        self
+    }
+
     /// Set PTY (terminal) size.
     ///
     /// This affects how the spawned process sees the terminal dimensions.
@@ -172,11 +274,428 @@ impl SessionBuilder {
         self
     }
 
+    /// Set the size of a single read from the PTY.
+    ///
+    /// When a read fills the whole chunk, the process is producing output
+    /// faster than it is being consumed, so the chunk size is doubled for
+    /// subsequent reads (up to 16× the configured size). This reduces the
+    /// number of system calls for processes that dump large amounts of output,
+    /// such as `cat bigfile`.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Initial read size in bytes (default: 4096, minimum: 1)
+    pub fn read_chunk_size(mut self, size: usize) -> Self {
+        self.read_chunk_size = size.max(1);
+        self
+    }
+
+    /// Pause for `delay` after sending `byte`.
+    ///
+    /// Some devices drop input that arrives too quickly after certain
+    /// characters, typically a line ending while they process the command.
+    /// Each configured byte makes `send()` pause after writing it, so the
+    /// device-specific pacing lives in one place instead of sleeps scattered
+    /// through the automation. Calling this again for the same byte replaces
+    /// the previous delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `byte` - The byte after which to pause
+    /// * `delay` - How long to pause
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .char_delay(b'\r', Duration::from_millis(100))
+    ///     .char_delay(b'\n', Duration::from_millis(100))
+    ///     .open_serial("/dev/ttyUSB0", 9600)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn char_delay(mut self, byte: u8, delay: Duration) -> Self {
+        self.char_delays.insert(byte, delay);
+        self
+    }
+
+    /// Enable or disable the terminal screen model.
+    ///
+    /// When enabled, all output is also fed through a VT100-style terminal
+    /// emulator of the configured PTY size, so full-screen applications can
+    /// be matched by what is displayed (see [`Session::expect_screen`] and
+    /// [`Session::screen`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to maintain a screen model (default: `false`)
+    pub fn screen(mut self, enabled: bool) -> Self {
+        self.screen = enabled;
+        self
+    }
+
+    /// Set default flags for regex patterns created with [`Session::pattern`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{RegexOptions, Session};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .regex_options(RegexOptions::new().case_insensitive(true).multi_line(true))
+    ///     .spawn("bash")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn regex_options(mut self, options: RegexOptions) -> Self {
+        self.regex_options = options;
+        self
+    }
+
+    /// Limit the total time an expect call may spend searching the buffer.
+    ///
+    /// Each expect call searches the buffer again whenever output arrives.
+    /// An expensive pattern (such as a glob, or a large regex) against a
+    /// large buffer can make these searches slow enough to stall the session.
+    /// With a budget, the call fails with `PatternError::Budget` once the
+    /// searches together exceed it. A single search is not interrupted, so
+    /// the budget may be overrun by up to one search.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .max_buffer_size(1 << 20)
+    ///     .match_budget(Duration::from_millis(500))
+    ///     .spawn("./noisy-build")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn match_budget(mut self, budget: Duration) -> Self {
+        self.match_budget = Some(budget);
+        self
+    }
+
+    /// Enable or disable recording a transcript of the session.
+    ///
+    /// When enabled, every byte read from or sent to the process is recorded
+    /// along with whether it came from the process, the automation, or the
+    /// user during [`Session::interact`]. See [`Session::transcript`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to record a transcript (default: `false`)
+    pub fn transcript(mut self, enabled: bool) -> Self {
+        self.transcript = enabled;
+        self
+    }
+
+    /// Log everything read from and sent to the process to a file.
+    ///
+    /// Equivalent to Expect's `log_file`. Each read and write is appended
+    /// to the file as soon as it happens, as a line with a timestamp, a
+    /// direction marker and the escaped data (the format of
+    /// [`Transcript`]'s `Display`). The file is created if needed and
+    /// appended to otherwise. Errors writing the log are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Spawning fails with an I/O error if the file cannot be opened.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .log_file("target/deploy-session.log")
+    ///     .spawn("./deploy.sh")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn log_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.log_file = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Limit how much process output is written to the log file.
+    ///
+    /// At most `bytes` of output are logged every `per`; the rest is
+    /// dropped, so chatty processes cannot flood the log or the sink it is
+    /// shipped to. Each gap is recorded with a line such as
+    /// `! suppressed 48213 bytes of process output`, and the total is
+    /// available from [`Session::log_suppressed`]. Data sent to the process
+    /// is always logged.
+    ///
+    /// Only affects [`log_file`](Self::log_file); the transcript and other
+    /// output layers are unaffected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .log_file("/var/log/nightly-build.log")
+    ///     .log_rate_limit(64 * 1024, Duration::from_secs(1))
+    ///     .spawn("make -j8")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn log_rate_limit(mut self, bytes: usize, per: Duration) -> Self {
+        self.log_rate_limit = Some((bytes, per));
+        self
+    }
+
+    /// Record the session's history to a file for post-mortem inspection.
+    ///
+    /// The file receives all output exactly as read, and for every expect
+    /// call its patterns, its outcome and the buffer it searched. Load it
+    /// with [`History::load`](crate::History::load) to see what the buffer
+    /// looked like at any expect call. The file is replaced if it exists.
+    /// Errors writing the history are ignored.
+    ///
+    /// The history grows with the output and with the buffer size times the
+    /// number of expect calls, so it is meant for debugging runs rather than
+    /// long-lived sessions.
+    ///
+    /// # Errors
+    ///
+    /// Spawning fails with an I/O error if the file cannot be created.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .history_file("target/deploy.history")
+    ///     .spawn("./deploy.sh")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn history_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.history_file = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Mirror output from the process to standard output (default: false).
+    ///
+    /// Like Expect's `log_user`, everything read from the process is written
+    /// to standard output as it arrives, exactly as printed, while matching
+    /// continues, so an automation can be watched in real time. Output is
+    /// only read, and so only echoed, while the session is driven. Switch it
+    /// at any time with [`Session::set_echo_output`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .echo_output(true)
+    ///     .spawn("./deploy.sh")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn echo_output(mut self, echo: bool) -> Self {
+        self.echo_output = echo;
+        self
+    }
+
+    /// Enable or disable PTY packet mode.
+    ///
+    /// In packet mode the PTY reports flow-control and flush events, such as
+    /// output being stopped by `^S` (XOFF) and restarted by `^Q` (XON), or
+    /// the process discarding pending input. These are collected with
+    /// [`Session::take_pty_events`] and do not appear in the output.
+    ///
+    /// Packet mode is supported on Linux, Android and macOS; elsewhere
+    /// spawning fails with a PTY error. It has no effect on serial or
+    /// attached sessions.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to enable packet mode (default: `false`)
+    pub fn packet_mode(mut self, enabled: bool) -> Self {
+        self.packet_mode = enabled;
+        self
+    }
+
+    /// Set the keystroke timing used by [`Session::send_human`].
+    pub fn human_typing(mut self, typing: HumanTyping) -> Self {
+        self.human_typing = typing;
+        self
+    }
+
+    /// Set how long [`Session::terminate`] waits for the process to exit
+    /// before killing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `grace` - Grace period after the polite request (default: 5 seconds)
+    pub fn terminate_grace(mut self, grace: Duration) -> Self {
+        self.terminate_grace = grace;
+        self
+    }
+
+    /// Set what happens to the process if it is still running when the
+    /// session is dropped.
+    ///
+    /// By default the terminal is hung up, which ends most interactive
+    /// programs but not ones that ignore `SIGHUP`. Services that embed
+    /// sessions usually want [`DropPolicy::Kill`], so no process outlives
+    /// its session; [`DropPolicy::Detach`] leaves a started daemon running.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{DropPolicy, Session};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .drop_policy(DropPolicy::Kill)
+    ///     .spawn("ssh admin@server")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.drop_policy = policy;
+        self
+    }
+
+    /// Set an environment variable for the spawned process.
+    ///
+    /// Applied after [`clear_env`](Self::clear_env), so variables set here
+    /// are kept even if the inherited environment is cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .env("LANG", "C")
+    ///     .env("TERM", "dumb")
+    ///     .spawn("ls -l")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.env
+            .push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    /// Set the terminal type (`TERM`) of the spawned process.
+    ///
+    /// Without this, `TERM` is inherited, so programs may print colors and
+    /// cursor movement on a developer machine but not in CI, or the other
+    /// way round. `"dumb"` makes most programs print plain text, which keeps
+    /// patterns simpler and more stable than stripping escape sequences
+    /// afterwards; `"xterm-256color"` suits full-screen applications driven
+    /// with the [screen model](Self::screen). The terminal type must be
+    /// known to the system's terminfo database.
+    ///
+    /// This is a shorthand for `env("TERM", term)`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder().term("dumb").spawn("git log")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn term(self, term: impl AsRef<OsStr>) -> Self {
+        self.env("TERM", term)
+    }
+
+    /// Run the spawned process in the given locale, such as `"C"` or
+    /// `"en_US.UTF-8"`.
+    ///
+    /// Prompts and error messages are translated according to the user's
+    /// locale, so a pattern for `"Password:"` silently stops matching when
+    /// the automation runs where it is `"Passwort:"`. Forcing a locale makes
+    /// the language predictable. `"C"` is always available; other locales
+    /// must be installed.
+    ///
+    /// Sets `LC_ALL` and `LANG`, and removes `LANGUAGE`, which GNU gettext
+    /// would otherwise prefer for messages. Windows programs do not read
+    /// these variables (except ports of Unix tools, such as those of MSYS2
+    /// and Cygwin); their language follows the system's settings.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder().force_locale("C").spawn("passwd")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn force_locale(self, locale: impl AsRef<OsStr>) -> Self {
+        let locale = locale.as_ref();
+        self.env("LC_ALL", locale)
+            .env("LANG", locale)
+            .env_remove("LANGUAGE")
+    }
+
+    /// Remove an environment variable from the spawned process.
+    pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+        self.env.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    /// Start the spawned process with an empty environment instead of
+    /// inheriting the parent's.
+    ///
+    /// Only variables set with [`env`](Self::env) are passed. Without `PATH`
+    /// the command must be given as a full path.
+    pub fn clear_env(mut self) -> Self {
+        self.clear_env = true;
+        self
+    }
+
+    /// Set the working directory of the spawned process.
+    ///
+    /// Spawning fails if the directory does not exist.
+    pub fn cwd(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
     /// Spawn a command and return a configured session.
     ///
     /// This method consumes the builder and creates a new session with the
     /// configured options.
     ///
+    /// The command is split into arguments like a shell would, honoring
+    /// quotes and backslash escapes (see [`shsplit`](crate::quote::shsplit)),
+    /// but nothing is expanded and no shell runs it. On Windows, backslashes
+    /// are kept as path separators and only double quotes group. Use
+    /// [`spawn_args`](Self::spawn_args) to pass arguments without any
+    /// parsing, or [`spawn_shell`](Self::spawn_shell) for pipes and
+    /// variables.
+    ///
     /// # Arguments
     ///
     /// * `command` - The command to spawn (e.g., "python -i", "ssh user@host")
@@ -185,6 +704,7 @@ impl SessionBuilder {
     ///
     /// Returns an error if:
     /// - The command string is empty
+    /// - A quote is not closed
     /// - The PTY cannot be created
     /// - The process cannot be spawned
     ///
@@ -197,11 +717,327 @@ impl SessionBuilder {
     /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let session = Session::builder()
     ///     .timeout(Duration::from_secs(30))
-    ///     .spawn("python -i")?;
+    ///     .spawn(r#"bash -c "echo hi there""#)?;
     /// # Ok(())
     /// # }
     /// ```
     pub fn spawn(self, command: &str) -> Result<Session, ExpectError> {
+        let parts = quote::split_words(command, !cfg!(windows)).ok_or_else(|| {
+            ExpectError::SpawnError(format!("Unterminated quote in command: {}", command))
+        })?;
+        let Some((program, args)) = parts.split_first() else {
+            return Err(ExpectError::SpawnError("Empty command".to_string()));
+        };
+
+        let mut cmd = CommandBuilder::new(program);
+        cmd.args(args);
+
+        self.spawn_pty(cmd)
+    }
+
+    /// Spawn a program with an explicit argument list and return a configured
+    /// session.
+    ///
+    /// Unlike [`spawn`](Self::spawn), nothing is parsed: each argument is
+    /// passed to the program verbatim, so arguments may contain spaces,
+    /// quotes or backslashes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program is empty, the PTY cannot be created,
+    /// or the process cannot be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::builder()
+    ///     .spawn_args("scp", ["My Documents/report.pdf", "backup:reports/"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_args<I, S>(
+        self,
+        program: impl AsRef<OsStr>,
+        args: I,
+    ) -> Result<Session, ExpectError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let program = program.as_ref();
+        if program.is_empty() {
+            return Err(ExpectError::SpawnError("Empty command".to_string()));
+        }
+
+        let mut cmd = CommandBuilder::new(program);
+        cmd.args(args);
+
+        self.spawn_pty(cmd)
+    }
+
+    /// Spawn a prepared [`CommandBuilder`] and return a configured session.
+    ///
+    /// Gives full control over the program, arguments, environment and
+    /// working directory through `portable_pty`'s own builder. Environment
+    /// and working directory settings made on this builder are applied on
+    /// top of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PTY cannot be created or the process cannot
+    /// be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{CommandBuilder, Session};
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut cmd = CommandBuilder::new("psql");
+    /// cmd.args(["--host", "db.internal", "orders"]);
+    /// cmd.env("PGCONNECT_TIMEOUT", "5");
+    /// let session = Session::builder().spawn_command(cmd)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_command(self, cmd: CommandBuilder) -> Result<Session, ExpectError> {
+        self.spawn_pty(cmd)
+    }
+
+    /// Spawn a [`std::process::Command`] and return a configured session.
+    ///
+    /// Copies the program, arguments, environment changes and working
+    /// directory, so code that already builds a `Command` can run it in a
+    /// PTY instead. Environment and working directory settings made on this
+    /// builder are applied on top of the command's.
+    ///
+    /// Standard I/O settings are ignored, since the process runs on the
+    /// PTY. A cleared environment cannot be read back from a `Command`;
+    /// use [`clear_env`](Self::clear_env) for that.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PTY cannot be created or the process cannot
+    /// be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::process::Command;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut cmd = Command::new("cargo");
+    /// cmd.arg("login").env("CARGO_TERM_COLOR", "never").current_dir("/srv/crate");
+    ///
+    /// let mut session = Session::builder()
+    ///     .timeout(Duration::from_secs(10))
+    ///     .spawn_std_command(&cmd)?;
+    /// session.expect(Pattern::exact("token")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_std_command(
+        mut self,
+        command: &std::process::Command,
+    ) -> Result<Session, ExpectError> {
+        let mut cmd = CommandBuilder::new(command.get_program());
+        cmd.args(command.get_args());
+
+        let env = command
+            .get_envs()
+            .map(|(key, value)| (key.to_owned(), value.map(OsStr::to_owned)));
+        self.env.splice(0..0, env);
+        if self.cwd.is_none() {
+            self.cwd = command.get_current_dir().map(Path::to_owned);
+        }
+        self.spawn_pty(cmd)
+    }
+
+    /// Spawn a [`tokio::process::Command`] and return a configured session.
+    ///
+    /// See [`spawn_std_command`](Self::spawn_std_command); tokio-specific
+    /// settings such as `kill_on_drop` are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PTY cannot be created or the process cannot
+    /// be spawned.
+    pub fn spawn_tokio_command(
+        self,
+        command: &tokio::process::Command,
+    ) -> Result<Session, ExpectError> {
+        self.spawn_std_command(command.as_std())
+    }
+
+    /// Run a command line through the platform shell and return a configured
+    /// session.
+    ///
+    /// Unlike [`spawn`](Self::spawn), which splits the command into
+    /// arguments and starts the program directly, the whole string is
+    /// passed to `/bin/sh -c` on Unix and `cmd /C` on Windows. Pipes,
+    /// redirections, variables and `&&` therefore work, with the syntax of
+    /// that platform's shell. The session's process is the shell itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command string is empty, the PTY cannot be
+    /// created, or the shell cannot be spawned. A command that the shell
+    /// cannot run is reported in its output, not as an error here.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().spawn_shell("dmesg | grep -i usb > usb.log && echo saved")?;
+    /// session.expect(Pattern::exact("saved")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_shell(self, command: &str) -> Result<Session, ExpectError> {
+        if command.trim().is_empty() {
+            return Err(ExpectError::SpawnError("Empty command".to_string()));
+        }
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = CommandBuilder::new("cmd");
+            cmd.arg("/C");
+            cmd
+        } else {
+            let mut cmd = CommandBuilder::new("/bin/sh");
+            cmd.arg("-c");
+            cmd
+        };
+        cmd.arg(command);
+
+        self.spawn_pty(cmd)
+    }
+
+    /// Run a program inside a running container with `docker exec` and
+    /// return a configured session.
+    ///
+    /// The program gets a TTY of the PTY size (`--interactive --tty`), and
+    /// window size changes made with [`Session::resize`] reach it. As with
+    /// [`spawn_args`](Self::spawn_args), arguments are passed verbatim, so
+    /// no shell quoting is involved.
+    ///
+    /// Environment and working directory settings apply inside the
+    /// container: variables set with [`env`](Self::env), including
+    /// [`term`](Self::term) and [`force_locale`](Self::force_locale), are
+    /// passed with `--env`, and [`cwd`](Self::cwd) becomes `--workdir`, a
+    /// path in the container. A removed variable is set to the empty value,
+    /// which programs generally treat as unset. The session's process is the
+    /// `docker` client, which exits when the program does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container or program name is empty,
+    /// [`clear_env`](Self::clear_env) was set (the container's environment
+    /// cannot be cleared), or `docker` cannot be spawned. A container that
+    /// does not exist or is not running is reported by `docker` in the
+    /// output, not as an error here.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder()
+    ///     .term("dumb")
+    ///     .docker_exec("postgres-test", "psql", ["-U", "postgres"])?;
+    /// session.expect(Pattern::exact("postgres=# ")).await?;
+    /// session.send_line("\\q").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn docker_exec<I, S>(
+        mut self,
+        container: &str,
+        program: impl AsRef<OsStr>,
+        args: I,
+    ) -> Result<Session, ExpectError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let cmd = self.docker_exec_command(container, program.as_ref(), args)?;
+        self.spawn_pty(cmd)
+    }
+
+    /// Build the `docker exec` command line, moving the environment and
+    /// working directory settings into it.
+    fn docker_exec_command<I, S>(
+        &mut self,
+        container: &str,
+        program: &OsStr,
+        args: I,
+    ) -> Result<CommandBuilder, ExpectError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        if container.is_empty() || program.is_empty() {
+            return Err(ExpectError::SpawnError(
+                "Empty container or command".to_string(),
+            ));
+        }
+        if self.clear_env {
+            return Err(ExpectError::SpawnError(
+                "Cannot clear the environment of a container".to_string(),
+            ));
+        }
+
+        let mut cmd = CommandBuilder::new("docker");
+        cmd.args(["exec", "--interactive", "--tty"]);
+        for (key, value) in std::mem::take(&mut self.env) {
+            let mut assignment = key;
+            assignment.push("=");
+            if let Some(value) = value {
+                assignment.push(value);
+            }
+            cmd.arg("--env");
+            cmd.arg(assignment);
+        }
+        if let Some(cwd) = self.cwd.take() {
+            cmd.arg("--workdir");
+            cmd.arg(cwd);
+        }
+        cmd.arg(container);
+        cmd.arg(program);
+        cmd.args(args);
+        Ok(cmd)
+    }
+
+    /// Spawn a prepared command on a new PTY.
+    fn spawn_pty(self, mut cmd: CommandBuilder) -> Result<Session, ExpectError> {
+        let logs = self.open_logs()?;
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            }
+        }
+        if let Some(cwd) = &self.cwd {
+            // portable-pty silently falls back to the home directory
+            if !cwd.is_dir() {
+                return Err(ExpectError::SpawnError(format!(
+                    "Working directory does not exist: {}",
+                    cwd.display()
+                )));
+            }
+            cmd.cwd(cwd);
+        }
+
         let pty_system = native_pty_system();
 
         // Create PTY pair
@@ -209,16 +1045,17 @@ impl SessionBuilder {
             .openpty(self.pty_size)
             .map_err(|e| ExpectError::PtyError(e.to_string()))?;
 
-        // Parse command into parts
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        if parts.is_empty() {
-            return Err(ExpectError::SpawnError("Empty command".to_string()));
-        }
-
-        // Build command
-        let mut cmd = CommandBuilder::new(parts[0]);
-        for arg in &parts[1..] {
-            cmd.arg(arg);
+        // Enable packet mode before the child can produce output
+        if self.packet_mode {
+            #[cfg(unix)]
+            let enabled = pty_pair
+                .master
+                .as_raw_fd()
+                .ok_or_else(packet::unsupported)
+                .and_then(packet::enable);
+            #[cfg(not(unix))]
+            let enabled = Err(packet::unsupported());
+            enabled.map_err(|e| ExpectError::PtyError(e.to_string()))?;
         }
 
         // Spawn child process
@@ -227,6 +1064,10 @@ impl SessionBuilder {
             .spawn_command(cmd)
             .map_err(|e| ExpectError::SpawnError(e.to_string()))?;
 
+        // Release our handle on the slave side so the master sees EOF once
+        // the child exits
+        drop(pty_pair.slave);
+
         // Get reader and writer from the master PTY
         let reader = pty_pair
             .master
@@ -240,15 +1081,374 @@ impl SessionBuilder {
             .take_writer()
             .map_err(|e| ExpectError::PtyError(e.to_string()))?;
 
+        self.connect(
+            Transport {
+                master: Some(pty_pair.master),
+                serial: None,
+                attached: None,
+                child: Some(child),
+                reader,
+                writer,
+            },
+            logs,
+        )
+    }
+
+    /// Open a serial device and return a configured session.
+    ///
+    /// The device is put into raw mode (8 data bits, no parity, no flow
+    /// control) at the given baud rate. The session has no child process, so
+    /// `is_alive()` and `wait()` return `ExpectError::ProcessExited`; the PTY
+    /// size setting does not apply.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The serial device (e.g., "/dev/ttyUSB0")
+    /// * `baud_rate` - The line speed (e.g., 115200)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The device cannot be opened or is not a terminal
+    /// - The baud rate is not supported by the platform
+    /// - The platform is not Unix
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder()
+    ///     .timeout(Duration::from_secs(10))
+    ///     .open_serial("/dev/ttyUSB0", 115200)?;
+    ///
+    /// session.send_break(Duration::from_millis(250)).await?;
+    /// session.expect(Pattern::exact("U-Boot>")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_serial(self, path: &str, baud_rate: u32) -> Result<Session, ExpectError> {
+        let logs = self.open_logs()?;
+        let opened = SerialPort::open(path, baud_rate)?;
+
+        self.connect(
+            Transport {
+                master: None,
+                serial: Some(opened.port),
+                attached: None,
+                child: None,
+                reader: opened.reader,
+                writer: opened.writer,
+            },
+            logs,
+        )
+    }
+
+    /// Connect to a telnet server and return a configured session.
+    ///
+    /// Option negotiation is handled transparently: the server may echo
+    /// and suppress go-ahead, the PTY size setting is reported as the window
+    /// size (NAWS) when the server asks, and all other options are refused.
+    /// [`Session::resize`] reports the new size to the server.
+    /// Negotiation bytes never reach the buffer, and data sent is escaped as
+    /// the protocol requires. The session has no child process, so
+    /// `is_alive()` and `wait()` return `ExpectError::ProcessExited`; EOF is
+    /// reached when the server closes the connection.
+    ///
+    /// The timeout setting also bounds establishing the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be resolved or no resolved
+    /// address accepts the connection.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder()
+    ///     .timeout(Duration::from_secs(10))
+    ///     .open_telnet("192.168.1.1:23")?;
+    ///
+    /// session.expect(Pattern::exact("Username: ")).await?;
+    /// session.send_line("admin").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "telnet")]
+    pub fn open_telnet(self, addr: impl std::net::ToSocketAddrs) -> Result<Session, ExpectError> {
+        let logs = self.open_logs()?;
+        let (reader, writer, window) =
+            telnet::connect(addr, self.timeout, self.pty_size.rows, self.pty_size.cols)?;
+
+        let mut session = self.connect(
+            Transport {
+                master: None,
+                serial: None,
+                attached: None,
+                child: None,
+                reader: Box::new(reader),
+                writer: Box::new(writer),
+            },
+            logs,
+        )?;
+        session.telnet = Some(window);
+        Ok(session)
+    }
+
+    /// Attach to an already open PTY and return a configured session.
+    ///
+    /// Use this to drive a PTY created by another library, whose master
+    /// side is handed over. The session has no child process, so
+    /// `is_alive()` and `wait()` return `ExpectError::ProcessExited`; the
+    /// process on the other side is managed by whoever started it. The PTY
+    /// size setting and packet mode do not apply, but
+    /// [`Session::resize`] works.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the PTY's reader or writer cannot be obtained,
+    /// for example because the writer was already taken.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{CommandBuilder, Pattern, Session};
+    /// use portable_pty::{native_pty_system, PtySize};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pair = native_pty_system().openpty(PtySize::default())?;
+    /// let _child = pair.slave.spawn_command(CommandBuilder::new("bash"))?;
+    ///
+    /// let mut session = Session::builder().attach_pty(pair.master)?;
+    /// session.expect(Pattern::exact("$ ")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attach_pty(self, master: Box<dyn MasterPty + Send>) -> Result<Session, ExpectError> {
+        let logs = self.open_logs()?;
+        let reader = master
+            .try_clone_reader()
+            .map_err(|e| ExpectError::PtyError(e.to_string()))?;
+        let writer = master
+            .take_writer()
+            .map_err(|e| ExpectError::PtyError(e.to_string()))?;
+
+        self.connect(
+            Transport {
+                master: Some(master),
+                serial: None,
+                attached: None,
+                child: None,
+                reader,
+                writer,
+            },
+            logs,
+        )
+    }
+
+    /// Attach to an open file descriptor and return a configured session.
+    ///
+    /// The descriptor is read from and written to directly: typically the
+    /// master side of a PTY inherited from a parent process, a socket or a
+    /// terminal device. It is closed when the session is dropped. As with
+    /// [`attach_pty`](Self::attach_pty), there is no child process and the
+    /// PTY size setting does not apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the descriptor cannot be duplicated.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::os::fd::{FromRawFd, OwnedFd};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// // Descriptor 3 was passed in by the parent process
+    /// let fd = unsafe { OwnedFd::from_raw_fd(3) };
+    /// let mut session = Session::builder().attach_fd(fd)?;
+    /// session.expect(Pattern::exact("login: ")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn attach_fd(self, fd: std::os::fd::OwnedFd) -> Result<Session, ExpectError> {
+        let logs = self.open_logs()?;
+        let file = File::from(fd);
+        let reader = Box::new(file.try_clone()?);
+        let writer = Box::new(file.try_clone()?);
+
+        self.connect(
+            Transport {
+                master: None,
+                serial: None,
+                attached: Some(file),
+                child: None,
+                reader,
+                writer,
+            },
+            logs,
+        )
+    }
+
+    /// Start reading from a transport and build the session around it.
+    fn connect(self, transport: Transport, logs: Logs) -> Result<Session, ExpectError> {
+        // Packet mode is only set up for PTYs created by spawn
+        let packet_mode = self.packet_mode && transport.child.is_some();
+        // Attached descriptors may be non-blocking
+        #[cfg(unix)]
+        let ready = {
+            use std::os::unix::io::AsRawFd;
+
+            let fd = match (&transport.master, &transport.serial, &transport.attached) {
+                (Some(master), _, _) => master.as_raw_fd(),
+                (None, Some(serial), _) => Some(serial.as_raw_fd()),
+                (None, None, Some(file)) => Some(file.as_raw_fd()),
+                (None, None, None) => None,
+            };
+            fd.map(super::reader::Readiness::new).transpose()?
+        };
+        #[cfg(not(unix))]
+        let ready = None;
+        let reader = Reader::spawn(
+            transport.reader,
+            self.read_chunk_size,
+            self.read_chunk_size.saturating_mul(READ_CHUNK_GROWTH_LIMIT),
+            packet_mode,
+            ready,
+        )?;
+
+        let screen = self.new_screen();
         Ok(Session {
-            _pty_pair: pty_pair,
-            child: Some(child),
-            master_reader: Arc::new(Mutex::new(reader)),
-            master_writer: Arc::new(Mutex::new(writer)),
-            buffer: BufferManager::new(self.max_buffer_size, self.strip_ansi),
+            _master: transport.master,
+            serial: transport.serial,
+            #[cfg(feature = "telnet")]
+            telnet: None,
+            attached: transport.attached,
+            child: transport.child,
+            reader,
+            master_writer: Arc::new(Mutex::new(transport.writer)),
+            buffer: BufferManager::new(self.max_buffer_size, self.strip_ansi)
+                .with_progress_collapsing(self.collapse_progress),
+            raw: RawStream::new(self.max_buffer_size),
             timeout: self.timeout,
             eof_reached: false,
             max_buffer_size: self.max_buffer_size,
+            char_delays: self.char_delays,
+            screen,
+            timeout_screen: None,
+            regex_options: self.regex_options,
+            match_budget: self.match_budget,
+            transcript: self
+                .transcript
+                .then(|| Transcript::new().with_progress_collapsing(self.collapse_progress)),
+            log: logs.log,
+            history: logs.history,
+            echo_output: self.echo_output,
+            observers: Observers::default(),
+            events: None,
+            auto_responses: Vec::new(),
+            critical_patterns: Vec::new(),
+            soft_timeout: None,
+            #[cfg(feature = "diagnostics")]
+            diagnostics: None,
+            prompt: None,
+            secrets: Vec::new(),
+            human_typing: self.human_typing.clone(),
+            terminate_grace: self.terminate_grace,
+            drop_policy: self.drop_policy,
         })
     }
+
+    fn open_logs(&self) -> Result<Logs, ExpectError> {
+        let log = match &self.log_file {
+            Some(path) => {
+                let log =
+                    TranscriptLog::open(path)?.with_progress_collapsing(self.collapse_progress);
+                Some(match self.log_rate_limit {
+                    Some((bytes, per)) => log.with_rate_limit(bytes, per),
+                    None => log,
+                })
+            }
+            None => None,
+        };
+        let history = match &self.history_file {
+            Some(path) => Some(HistoryLog::create(path)?),
+            None => None,
+        };
+        Ok(Logs { log, history })
+    }
+
+    fn new_screen(&self) -> Option<Screen> {
+        self.screen
+            .then(|| Screen::new(self.pty_size.rows, self.pty_size.cols))
+    }
+}
+
+/// Files a session records to, opened before connecting.
+struct Logs {
+    log: Option<TranscriptLog>,
+    history: Option<HistoryLog>,
+}
+
+/// What a session reads from, writes to and controls.
+struct Transport {
+    master: Option<Box<dyn MasterPty + Send>>,
+    serial: Option<SerialPort>,
+    attached: Option<File>,
+    child: Option<Box<dyn Child + Send>>,
+    reader: Box<dyn Read + Send>,
+    writer: Box<dyn Write + Send>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_exec_command() {
+        let mut builder = SessionBuilder::new().force_locale("C").cwd("/srv/app");
+        let cmd = builder
+            .docker_exec_command("web", OsStr::new("sh"), ["-c", "echo $LANG"])
+            .unwrap();
+        let argv: Vec<_> = cmd.get_argv().iter().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(
+            argv,
+            [
+                "docker",
+                "exec",
+                "--interactive",
+                "--tty",
+                "--env",
+                "LC_ALL=C",
+                "--env",
+                "LANG=C",
+                "--env",
+                "LANGUAGE=",
+                "--workdir",
+                "/srv/app",
+                "web",
+                "sh",
+                "-c",
+                "echo $LANG",
+            ]
+        );
+        // Nothing is left to apply to the docker client itself
+        assert!(builder.env.is_empty() && builder.cwd.is_none());
+
+        let mut builder = SessionBuilder::new().clear_env();
+        assert!(builder
+            .docker_exec_command("web", OsStr::new("sh"), [""; 0])
+            .is_err());
+        assert!(SessionBuilder::new()
+            .docker_exec_command("", OsStr::new("sh"), [""; 0])
+            .is_err());
+    }
 }
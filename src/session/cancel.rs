@@ -0,0 +1,73 @@
+//! Cancelling a pending expect from another task
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::Session;
+use std::future::Future;
+
+impl Session {
+    /// Wait for a pattern like [`expect`](Self::expect), giving up when
+    /// `cancelled` completes.
+    ///
+    /// Lets a supervisor abort a pending wait, for example on shutdown,
+    /// without dropping the session. Pass any future that completes on
+    /// cancellation, such as `token.cancelled()` for a `tokio_util`
+    /// `CancellationToken` or the receiving end of a oneshot channel.
+    ///
+    /// After cancellation, output read so far stays in the buffer and the
+    /// next expect call searches it again; the pattern consumes nothing. A
+    /// cancellation that is already complete is honored before anything is
+    /// read.
+    ///
+    /// Work the wait already did is not undone:
+    ///
+    /// - Prompts answered by [automatic responses](Self::auto_respond) stay
+    ///   answered: the trigger is removed from the buffer and the rule's
+    ///   [`count`](crate::AutoResponse::count) includes it. Cancelling
+    ///   while a response is being written can leave it partly sent.
+    /// - The cancelled wait is not recorded in the
+    ///   [history file](crate::SessionBuilder::history_file) or the
+    ///   [event stream](Self::serve_events).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::Cancelled` if `cancelled` completes first,
+    /// otherwise the same errors as [`expect`](Self::expect).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{ExpectError, Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    /// let mut session = Session::spawn("./long-job.sh")?;
+    /// tokio::spawn(async move {
+    ///     tokio::signal::ctrl_c().await.ok();
+    ///     let _ = stop.send(());
+    /// });
+    ///
+    /// let stopped = async {
+    ///     let _ = stopped.await;
+    /// };
+    /// match session.expect_cancellable(Pattern::exact("finished"), stopped).await {
+    ///     Err(ExpectError::Cancelled) => {
+    ///         session.terminate().await?;
+    ///     }
+    ///     result => println!("{}", result?),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_cancellable(
+        &mut self,
+        pattern: Pattern,
+        cancelled: impl Future<Output = ()>,
+    ) -> Result<MatchResult, ExpectError> {
+        tokio::select! {
+            biased;
+            () = cancelled => Err(ExpectError::Cancelled),
+            result = self.expect(pattern) => result,
+        }
+    }
+}
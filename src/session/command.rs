@@ -0,0 +1,319 @@
+//! Running shell commands up to the next prompt
+
+use crate::result::ExpectError;
+use crate::session::{ControlChar, ExpectOptions, Session};
+use crate::Consume;
+use std::time::{Duration, Instant};
+
+/// The result of running a command with
+/// [`Session::run_command`](crate::Session::run_command).
+///
+/// Splits what the shell printed into the echoed command line, the
+/// command's own output and the prompt that followed, so output parsers
+/// work on the same parts whatever the terminal echoes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::Session;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::spawn("bash")?;
+/// let output = session.run_command("df -h /").await?;
+/// for line in output.body.lines().skip(1) {
+///     println!("{}", line);
+/// }
+/// println!("took {:?}", output.duration);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// The command line as echoed by the terminal, if it was echoed.
+    pub echo: Option<String>,
+    /// The command's output, between the echoed command line and the
+    /// prompt.
+    pub body: String,
+    /// The prompt that followed the output.
+    pub prompt: String,
+    /// Time from sending the command to matching the prompt.
+    pub duration: Duration,
+    /// Everything received from sending the command to matching the
+    /// prompt, exactly as received and before any buffer filtering, limited
+    /// to the session's buffer size.
+    pub raw: Vec<u8>,
+    /// The command's exit status, when known.
+    ///
+    /// Only [`run_command_status`](crate::Session::run_command_status)
+    /// asks the shell for it; other ways of running a command leave it
+    /// `None`.
+    pub exit_code: Option<i32>,
+}
+
+/// Printed by the shell before the exit status in
+/// [`Session::run_command_status`].
+const STATUS_MARKER: &str = "__EXPECTRUST_STATUS__";
+
+impl Session {
+    /// Run a command in the shell and return its output.
+    ///
+    /// Sends `command` as a line and waits for the session's prompt, as
+    /// learned with [`learn_prompt`](Self::learn_prompt) or set with
+    /// [`set_prompt`](Self::set_prompt). If there is none yet, it is
+    /// learned before the command is sent. The output is what was printed
+    /// between the echoed command line and the prompt; see
+    /// [`CommandOutput`] for the other parts.
+    ///
+    /// The wait for the prompt is an expect call like any other, so
+    /// auto-responses, critical patterns, the soft timeout hook, history
+    /// and events all apply to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::PromptNotLearned` if the prompt had to be
+    /// learned and could not be, and `ExpectError::Timeout` if the prompt
+    /// does not return within the session timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("bash")?;
+    /// let kernel = session.run_command("uname -r").await?;
+    /// println!("kernel {}", kernel.body.trim());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_command(&mut self, command: &str) -> Result<CommandOutput, ExpectError> {
+        self.run_command_limited(command, None).await
+    }
+
+    /// Run a command like [`run_command`](Self::run_command), interrupting
+    /// it if it prints more than `max_bytes`.
+    ///
+    /// Protects an automation against a command that floods the session,
+    /// such as accidentally printing a multi-gigabyte file over a serial
+    /// console. Once the command's output passes `max_bytes`, Ctrl-C is sent
+    /// and the prompt awaited, so the shell is usable again when the error
+    /// is returned. The output is also limited by the buffer size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::OutputLimit` if the output exceeded
+    /// `max_bytes`, otherwise the same errors as `run_command`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{ExpectError, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::open_serial("/dev/ttyUSB0", 115200)?;
+    /// session.learn_prompt().await?;
+    /// match session.run_command_with_limit("cat /var/log/messages", 64 * 1024).await {
+    ///     Err(ExpectError::OutputLimit { received, .. }) => {
+    ///         eprintln!("interrupted after {} bytes", received)
+    ///     }
+    ///     other => println!("{}", other?.body),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_command_with_limit(
+        &mut self,
+        command: &str,
+        max_bytes: usize,
+    ) -> Result<CommandOutput, ExpectError> {
+        self.run_command_limited(command, Some(max_bytes)).await
+    }
+
+    /// Run a command like [`run_command`](Self::run_command) and capture
+    /// its exit status in [`CommandOutput::exit_code`].
+    ///
+    /// Sends `command; echo "<marker>$?"`, so the shell must be
+    /// POSIX-compatible, such as `sh`, `bash` or `zsh`. The marker line is
+    /// removed from the body, and from the echo if it was echoed. The exit
+    /// code is `None` if the marker did not appear, for example when the
+    /// command exited the shell.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`run_command`](Self::run_command).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("bash")?;
+    /// let output = session.run_command_status("test -d /srv/data").await?;
+    /// if output.exit_code != Some(0) {
+    ///     println!("no data directory");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_command_status(
+        &mut self,
+        command: &str,
+    ) -> Result<CommandOutput, ExpectError> {
+        let suffix = format!("; echo \"{}$?\"", STATUS_MARKER);
+        let line = format!("{}{}", command, suffix);
+        let mut output = self.run_command_limited(&line, None).await?;
+        if let Some(echo) = output.echo.as_mut() {
+            if echo.ends_with(&suffix) {
+                echo.truncate(echo.len() - suffix.len());
+            }
+        }
+        let (body, exit_code) = split_status(&output.body);
+        output.body = body;
+        output.exit_code = exit_code;
+        Ok(output)
+    }
+
+    async fn run_command_limited(
+        &mut self,
+        command: &str,
+        max_bytes: Option<usize>,
+    ) -> Result<CommandOutput, ExpectError> {
+        let patterns = [self.known_prompt().await?];
+        self.send_line(command).await?;
+        let first_byte = self.raw.total_len();
+        let start_time = Instant::now();
+
+        // Consume the prompt only once the output before it is taken
+        let options = ExpectOptions {
+            consume: Consume::Nothing,
+            ..Default::default()
+        };
+        let result = self
+            .find_any(&patterns, self.timeout, &options, max_bytes, true)
+            .await;
+        let output = result.as_ref().ok().map(|m| {
+            let position = self.buffer.matched_position();
+            let bytes = self.buffer.as_bytes();
+            let output = &bytes[position..m.start];
+            let (echo, body) = match strip_echo(output, command) {
+                Some(body) => (Some(command.to_owned()), body),
+                None => (None, output),
+            };
+            let received = (self.raw.total_len() - first_byte) as usize;
+            let output = CommandOutput {
+                echo,
+                body: String::from_utf8_lossy(body).into_owned(),
+                prompt: m.matched.clone(),
+                duration: start_time.elapsed(),
+                raw: self.raw.tail(received).to_vec(),
+                exit_code: None,
+            };
+            self.buffer.consume(m.end);
+            output
+        });
+        self.record_expect(&patterns, &result);
+
+        match result {
+            Ok(_) => Ok(output.expect("set for a match")),
+            Err(ExpectError::OutputLimit { limit, received }) => {
+                self.send_control(ControlChar::CtrlC).await?;
+                // Resynchronize; the limit is the error to report either way
+                let [prompt] = patterns;
+                let _ = self.expect(prompt).await;
+                Err(ExpectError::OutputLimit { limit, received })
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The output after the echoed command line, if the terminal echoed it.
+///
+/// The echo may span several rows when the command is wider than the
+/// terminal. Line breaks within it are skipped, and so is the sequence
+/// readline prints at each wrap: the first character of the new row, a
+/// carriage return, then that character again.
+fn strip_echo<'a>(output: &'a [u8], command: &str) -> Option<&'a [u8]> {
+    let command = command.as_bytes();
+    let mut matched = 0;
+    let mut i = 0;
+    while matched < command.len() {
+        match output.get(i)? {
+            &byte if byte == command[matched] => matched += 1,
+            b'\r' if matched > 0 && output.get(i + 1) == Some(&command[matched - 1]) => {
+                matched -= 1
+            }
+            b'\r' | b'\n' => {}
+            _ => return None,
+        }
+        i += 1;
+    }
+    let end = i + memchr::memchr(b'\n', &output[i..])?;
+    output[i..end]
+        .trim_ascii()
+        .is_empty()
+        .then(|| &output[end + 1..])
+}
+
+/// Remove the status marker line from `body` and parse the exit status
+/// after it.
+///
+/// The marker is only taken where digits follow it, so a marker in an
+/// unrecognized echo of the command line, followed by `$?`, is skipped.
+fn split_status(body: &str) -> (String, Option<i32>) {
+    let found = body.rmatch_indices(STATUS_MARKER).find_map(|(start, _)| {
+        let rest = &body[start + STATUS_MARKER.len()..];
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let code = rest[..digits].parse().ok()?;
+        let line_end = rest.find('\n').map_or(rest.len(), |end| end + 1);
+        Some((start, start + STATUS_MARKER.len() + line_end, code))
+    });
+    match found {
+        Some((start, end, code)) => (format!("{}{}", &body[..start], &body[end..]), Some(code)),
+        None => (body.to_owned(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_echo() {
+        let output = b"ls -l\r\ntotal 0\r\n";
+        assert_eq!(strip_echo(output, "ls -l"), Some(&b"total 0\r\n"[..]));
+        assert_eq!(strip_echo(output, "ls -a"), None);
+        assert_eq!(strip_echo(b"ls -l extra\r\n", "ls -l"), None);
+    }
+
+    #[test]
+    fn test_strip_echo_across_wraps() {
+        // As bash echoes it on a 20 column terminal after a 3 column prompt
+        let output = b"echo aaaaaaaaaa bb\rbbbbbbbbb cccccccccc \r dddd\r\nout\r\n";
+        let command = "echo aaaaaaaaaa bbbbbbbbbb cccccccccc dddd";
+        assert_eq!(strip_echo(output, command), Some(&b"out\r\n"[..]));
+
+        // Wrapped by the terminal with a line break
+        let output = b"echo aaaaaaa\r\naaa\r\nout\r\n";
+        assert_eq!(strip_echo(output, "echo aaaaaaaaaa"), Some(&b"out\r\n"[..]));
+    }
+
+    #[test]
+    fn test_split_status() {
+        let body = "one\r\n__EXPECTRUST_STATUS__2\r\n";
+        assert_eq!(split_status(body), ("one\r\n".to_owned(), Some(2)));
+    }
+
+    #[test]
+    fn test_split_status_without_trailing_newline() {
+        let body = "partial__EXPECTRUST_STATUS__0\r\n";
+        assert_eq!(split_status(body), ("partial".to_owned(), Some(0)));
+    }
+
+    #[test]
+    fn test_split_status_skips_echoed_marker() {
+        let body = "x; echo \"__EXPECTRUST_STATUS__$?\"\r\n";
+        assert_eq!(split_status(body), (body.to_owned(), None));
+    }
+}
@@ -0,0 +1,248 @@
+//! Connecting a group of sessions with retries
+
+use crate::session::{Session, SessionGroup};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+/// Retry policy with exponential backoff, used by [`SessionGroup::connect`].
+///
+/// The delay before retry `n` (counting from 1) is `initial * 2^(n-1)`,
+/// capped at `max`. With jitter enabled, a random delay between half and all
+/// of that is used instead, so many targets failing together do not retry
+/// in lockstep.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::Backoff;
+/// use std::time::Duration;
+///
+/// let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1))
+///     .max_attempts(4)
+///     .jitter(false);
+/// assert_eq!(backoff.delay(1), Duration::from_millis(100));
+/// assert_eq!(backoff.delay(3), Duration::from_millis(400));
+/// assert_eq!(backoff.delay(10), Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    max_attempts: u32,
+    jitter: bool,
+}
+
+impl Default for Backoff {
+    /// 500ms initial delay, at most 30s, 5 attempts, with jitter.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+impl Backoff {
+    /// Create a policy with the given initial and maximum delay.
+    ///
+    /// Defaults to 5 attempts with jitter.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            max_attempts: 5,
+            jitter: true,
+        }
+    }
+
+    /// Set the total number of attempts per target, including the first.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Enable or disable random jitter.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Delay before retry `retry` (1 for the first retry).
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32 << retry.saturating_sub(1).min(31);
+        let delay = self.initial.saturating_mul(factor).min(self.max);
+        if self.jitter {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u32(retry);
+            let fraction = (hasher.finish() % 1000) as f64 / 1000.0;
+            delay / 2 + (delay / 2).mul_f64(fraction)
+        } else {
+            delay
+        }
+    }
+}
+
+/// A target that could not be connected.
+#[derive(Debug)]
+pub struct Unreachable<T, E> {
+    /// The target.
+    pub target: T,
+    /// Number of attempts made.
+    pub attempts: u32,
+    /// The error of the last attempt.
+    pub error: E,
+}
+
+/// Outcome of [`SessionGroup::connect`].
+#[derive(Debug)]
+pub struct ConnectReport<T, E> {
+    /// Targets that connected, in the order of the group's sessions.
+    pub connected: Vec<T>,
+    /// Targets that failed every attempt, in the order they were given.
+    pub unreachable: Vec<Unreachable<T, E>>,
+}
+
+/// Target, number of attempts and result of the last attempt.
+type Outcome<T, E> = (T, u32, Result<Session, E>);
+
+type Attempt<'a, T, E> = Pin<Box<dyn Future<Output = Outcome<T, E>> + Send + 'a>>;
+
+impl SessionGroup {
+    /// Connect to several targets concurrently, retrying failures.
+    ///
+    /// `connect` is called with a target to open its session, e.g. spawning
+    /// `ssh` and logging in. A target whose attempt fails is retried after a
+    /// [`Backoff`] delay, independently of the others. Targets that fail
+    /// every attempt do not fail the whole run; they are listed in the
+    /// returned report, and the group holds the sessions that connected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Backoff, Credentials, LoginError, LoginSpec, Pattern, Session, SessionGroup};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let hosts = vec!["node1", "node2", "node3"];
+    /// let spec = LoginSpec::new(
+    ///     Pattern::exact("login: "),
+    ///     Pattern::exact("Password: "),
+    ///     Pattern::exact("$ "),
+    /// );
+    /// let creds = Credentials::new("admin", "secret");
+    ///
+    /// let (mut cluster, report) = SessionGroup::connect(hosts, &Backoff::default(), |host| {
+    ///     let (spec, creds) = (spec.clone(), creds.clone());
+    ///     async move {
+    ///         let mut session = Session::spawn(&format!("telnet {}", host))?;
+    ///         session.login(&spec, &creds).await?;
+    ///         Ok::<_, LoginError>(session)
+    ///     }
+    /// })
+    /// .await;
+    ///
+    /// for host in &report.unreachable {
+    ///     eprintln!("{} unreachable after {} attempts: {}", host.target, host.attempts, host.error);
+    /// }
+    /// cluster.all_expect(Pattern::exact("$ ")).await.ok();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn connect<T, E, F, Fut>(
+        targets: Vec<T>,
+        backoff: &Backoff,
+        connect: F,
+    ) -> (SessionGroup, ConnectReport<T, E>)
+    where
+        T: Clone + Send,
+        E: Send,
+        F: Fn(T) -> Fut + Sync,
+        Fut: Future<Output = Result<Session, E>> + Send,
+    {
+        let connect = &connect;
+        let mut attempts: Vec<Option<Attempt<'_, T, E>>> = targets
+            .into_iter()
+            .map(|target| -> Option<Attempt<'_, T, E>> {
+                Some(Box::pin(async move {
+                    let mut attempt = 1;
+                    loop {
+                        let result = connect(target.clone()).await;
+                        if result.is_ok() || attempt >= backoff.max_attempts {
+                            return (target, attempt, result);
+                        }
+                        tokio::time::sleep(backoff.delay(attempt)).await;
+                        attempt += 1;
+                    }
+                }))
+            })
+            .collect();
+
+        let mut outcomes: Vec<Option<Outcome<T, E>>> = (0..attempts.len()).map(|_| None).collect();
+        std::future::poll_fn(|cx| {
+            for (slot, outcome) in attempts.iter_mut().zip(outcomes.iter_mut()) {
+                if let Some(attempt) = slot {
+                    if let Poll::Ready(done) = attempt.as_mut().poll(cx) {
+                        *outcome = Some(done);
+                        *slot = None;
+                    }
+                }
+            }
+            if attempts.iter().all(Option::is_none) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        let mut sessions = Vec::new();
+        let mut report = ConnectReport {
+            connected: Vec::new(),
+            unreachable: Vec::new(),
+        };
+        for (target, attempts, result) in outcomes.into_iter().flatten() {
+            match result {
+                Ok(session) => {
+                    sessions.push(session);
+                    report.connected.push(target);
+                }
+                Err(error) => report.unreachable.push(Unreachable {
+                    target,
+                    attempts,
+                    error,
+                }),
+            }
+        }
+        (SessionGroup::new(sessions), report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+        for retry in 1..10 {
+            let full = backoff.clone().jitter(false).delay(retry);
+            let delay = backoff.delay(retry);
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "{:?} vs {:?}",
+                delay,
+                full
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_retry_count_saturates() {
+        let backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60)).jitter(false);
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(60));
+
+        // Jitter on a huge delay does not overflow
+        let backoff = Backoff::new(Duration::MAX, Duration::MAX);
+        assert!(backoff.delay(1) >= Duration::MAX / 2);
+    }
+}
@@ -0,0 +1,214 @@
+//! Sending named control characters
+
+use crate::result::ExpectError;
+use crate::session::Session;
+use std::io;
+
+/// A control character that can be sent with [`Session::send_control`].
+///
+/// | Variant | Byte | Usual effect in a terminal |
+/// |---|---|---|
+/// | `CtrlA` ... `CtrlZ` | `0x01` ... `0x1a` | `CtrlC` interrupts, `CtrlD` ends input, `CtrlZ` suspends |
+/// | `Escape` | `0x1b` | Escape (also Ctrl-`[`) |
+/// | `Delete` | `0x7f` | Backspace in most terminals (also Ctrl-`?`) |
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::ControlChar;
+///
+/// assert_eq!(ControlChar::CtrlC.byte(), 0x03);
+/// assert_eq!(ControlChar::from_char('d'), Some(ControlChar::CtrlD));
+/// assert_eq!(ControlChar::from_char('['), Some(ControlChar::Escape));
+/// assert_eq!(ControlChar::from_char('1'), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControlChar {
+    /// Ctrl-A, 0x01
+    CtrlA,
+    /// Ctrl-B, 0x02
+    CtrlB,
+    /// Ctrl-C, 0x03 (interrupt)
+    CtrlC,
+    /// Ctrl-D, 0x04 (end of input)
+    CtrlD,
+    /// Ctrl-E, 0x05
+    CtrlE,
+    /// Ctrl-F, 0x06
+    CtrlF,
+    /// Ctrl-G, 0x07
+    CtrlG,
+    /// Ctrl-H, 0x08 (backspace)
+    CtrlH,
+    /// Ctrl-I, 0x09 (tab)
+    CtrlI,
+    /// Ctrl-J, 0x0a (line feed)
+    CtrlJ,
+    /// Ctrl-K, 0x0b
+    CtrlK,
+    /// Ctrl-L, 0x0c
+    CtrlL,
+    /// Ctrl-M, 0x0d (carriage return)
+    CtrlM,
+    /// Ctrl-N, 0x0e
+    CtrlN,
+    /// Ctrl-O, 0x0f
+    CtrlO,
+    /// Ctrl-P, 0x10
+    CtrlP,
+    /// Ctrl-Q, 0x11
+    CtrlQ,
+    /// Ctrl-R, 0x12
+    CtrlR,
+    /// Ctrl-S, 0x13
+    CtrlS,
+    /// Ctrl-T, 0x14
+    CtrlT,
+    /// Ctrl-U, 0x15
+    CtrlU,
+    /// Ctrl-V, 0x16
+    CtrlV,
+    /// Ctrl-W, 0x17
+    CtrlW,
+    /// Ctrl-X, 0x18
+    CtrlX,
+    /// Ctrl-Y, 0x19
+    CtrlY,
+    /// Ctrl-Z, 0x1a (suspend)
+    CtrlZ,
+    /// Escape, 0x1b
+    Escape,
+    /// Delete, 0x7f
+    Delete,
+}
+
+impl ControlChar {
+    const LETTERS: [ControlChar; 26] = [
+        Self::CtrlA,
+        Self::CtrlB,
+        Self::CtrlC,
+        Self::CtrlD,
+        Self::CtrlE,
+        Self::CtrlF,
+        Self::CtrlG,
+        Self::CtrlH,
+        Self::CtrlI,
+        Self::CtrlJ,
+        Self::CtrlK,
+        Self::CtrlL,
+        Self::CtrlM,
+        Self::CtrlN,
+        Self::CtrlO,
+        Self::CtrlP,
+        Self::CtrlQ,
+        Self::CtrlR,
+        Self::CtrlS,
+        Self::CtrlT,
+        Self::CtrlU,
+        Self::CtrlV,
+        Self::CtrlW,
+        Self::CtrlX,
+        Self::CtrlY,
+        Self::CtrlZ,
+    ];
+
+    /// The byte sent for this control character.
+    pub fn byte(self) -> u8 {
+        match self {
+            Self::Escape => 0x1b,
+            Self::Delete => 0x7f,
+            letter => letter as u8 + 1,
+        }
+    }
+
+    /// The control character typed as Ctrl plus `c`.
+    ///
+    /// Letters are accepted in either case; `[` gives `Escape` and `?` gives
+    /// `Delete`. Returns `None` for any other character.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'a'..='z' | 'A'..='Z' => {
+                Some(Self::LETTERS[(c.to_ascii_lowercase() as u8 - b'a') as usize])
+            }
+            '[' => Some(Self::Escape),
+            '?' => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// Values accepted by [`Session::send_control`]: a [`ControlChar`], or the
+/// `char` typed with Ctrl as in [`ControlChar::from_char`].
+pub trait IntoControlChar {
+    /// The control character, or `None` if there is none for this value.
+    fn into_control_char(self) -> Option<ControlChar>;
+}
+
+impl IntoControlChar for ControlChar {
+    fn into_control_char(self) -> Option<ControlChar> {
+        Some(self)
+    }
+}
+
+impl IntoControlChar for char {
+    fn into_control_char(self) -> Option<ControlChar> {
+        ControlChar::from_char(self)
+    }
+}
+
+impl Session {
+    /// Send a control character, such as Ctrl-C to interrupt the process.
+    ///
+    /// Takes either a [`ControlChar`] or the key pressed together with Ctrl,
+    /// so `send_control('c')` and `send_control(ControlChar::CtrlC)` both
+    /// send `0x03`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::IoError` with kind `InvalidInput` if the
+    /// character has no control code, or if sending fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{ControlChar, Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("cat")?;
+    /// session.send_control('c').await?;
+    /// session.send_control(ControlChar::CtrlD).await?;
+    /// session.expect(Pattern::Eof).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_control(&mut self, key: impl IntoControlChar) -> Result<(), ExpectError> {
+        let control = key.into_control_char().ok_or_else(|| {
+            ExpectError::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No control character for this key",
+            ))
+        })?;
+        self.send(&[control.byte()]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::Key;
+
+    #[test]
+    fn test_control_bytes() {
+        assert_eq!(ControlChar::CtrlA.byte(), 0x01);
+        assert_eq!(ControlChar::CtrlZ.byte(), 0x1a);
+        assert_eq!(ControlChar::Escape.byte(), 0x1b);
+        assert_eq!(ControlChar::Delete.byte(), 0x7f);
+
+        // Agrees with the encoding used by Key::Ctrl
+        for c in ('a'..='z').chain('A'..='Z').chain(['[', '?']) {
+            let control = ControlChar::from_char(c).unwrap();
+            assert_eq!(Key::Ctrl(c).to_bytes(false), [control.byte()]);
+        }
+        assert_eq!(ControlChar::from_char('é'), None);
+    }
+}
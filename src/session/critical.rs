@@ -0,0 +1,96 @@
+//! Strict mode: failing expects on critical output
+
+use crate::pattern::{Matcher, Pattern};
+use crate::result::{ExpectError, PatternError};
+use crate::session::Session;
+
+/// A pattern whose appearance fails any expect call.
+pub(crate) struct CriticalPattern {
+    pattern: Pattern,
+    matcher: Box<dyn Matcher>,
+}
+
+impl Session {
+    /// Fail expect calls whenever `pattern` appears in the output.
+    ///
+    /// Adding critical patterns puts the session in strict mode: while any
+    /// expect call waits, output matching a critical pattern makes it fail
+    /// immediately with [`ExpectError::CriticalOutput`], whether or not its
+    /// own patterns matched or would match later. Critical output that
+    /// arrives between expect calls fails the next one. This catches panics,
+    /// tracebacks and crashes that would otherwise go unnoticed until a
+    /// timeout, or not at all when the expected text still appears.
+    ///
+    /// Output up to the end of the critical match is consumed, so the
+    /// session can be used again after handling the error. Patterns stay
+    /// active until removed with
+    /// [`clear_critical_patterns`](Self::clear_critical_patterns).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or is a special pattern
+    /// such as `Pattern::Eof`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{ExpectError, Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("cargo run --bin server")?;
+    /// session.add_critical_pattern(Pattern::exact("panicked at"))?;
+    /// session.add_critical_pattern(Pattern::exact("Segmentation fault"))?;
+    ///
+    /// match session.expect(Pattern::exact("listening")).await {
+    ///     Err(ExpectError::CriticalOutput { matched, .. }) => eprintln!("crashed: {}", matched),
+    ///     other => println!("{:?}", other?.matched),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_critical_pattern(&mut self, pattern: Pattern) -> Result<(), PatternError> {
+        let matcher = pattern.to_matcher()?;
+        self.critical_patterns
+            .push(CriticalPattern { pattern, matcher });
+        Ok(())
+    }
+
+    /// The critical patterns, in the order they were added.
+    pub fn critical_patterns(&self) -> impl Iterator<Item = &Pattern> {
+        self.critical_patterns
+            .iter()
+            .map(|critical| &critical.pattern)
+    }
+
+    /// Remove all critical patterns, leaving strict mode.
+    pub fn clear_critical_patterns(&mut self) {
+        self.critical_patterns.clear();
+    }
+
+    /// Fail if the buffer contains critical output, consuming it.
+    pub(crate) fn check_critical(&mut self) -> Result<(), ExpectError> {
+        let mut earliest: Option<(usize, usize, usize)> = None;
+        for (index, critical) in self.critical_patterns.iter().enumerate() {
+            if let Some(m) = self.buffer.find(critical.matcher.as_ref()) {
+                if earliest.is_none_or(|(_, start, _)| m.start < start) {
+                    earliest = Some((index, m.start, m.end));
+                }
+            }
+        }
+
+        let Some((index, start, end)) = earliest else {
+            return Ok(());
+        };
+        let matched = self
+            .redact_text(String::from_utf8_lossy(&self.buffer.as_bytes()[start..end]))
+            .into_owned();
+        if let Some(history) = &mut self.history {
+            history.searched(self.buffer.matched_position(), self.buffer.unmatched());
+        }
+        self.buffer.mark_matched(end);
+        Err(ExpectError::CriticalOutput {
+            pattern_index: index,
+            matched,
+        })
+    }
+}
@@ -0,0 +1,223 @@
+//! Live event stream for external viewers
+
+use crate::result::{ExpectError, MatchResult};
+use crate::session::Session;
+use crate::transcript::Origin;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// Number of events buffered for a slow viewer before it misses some.
+const EVENT_BACKLOG: usize = 1024;
+
+/// Publisher of session events to connected viewers.
+pub(crate) struct EventStream {
+    start: Instant,
+    sender: broadcast::Sender<Arc<str>>,
+    /// Task accepting viewers started by [`Session::serve_events`].
+    listener: Option<JoinHandle<()>>,
+}
+
+impl EventStream {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            sender: broadcast::channel(EVENT_BACKLOG).0,
+            listener: None,
+        }
+    }
+
+    /// Publish output read from the process.
+    pub(crate) fn output(&self, data: &[u8]) {
+        self.publish("output", |line| {
+            line.push_str(",\"data\":");
+            push_json_string(line, &String::from_utf8_lossy(data));
+        });
+    }
+
+    /// Publish data sent to the process.
+    pub(crate) fn input(&self, origin: Origin, data: &[u8]) {
+        let origin = match origin {
+            Origin::Process => "process",
+            Origin::Automation => "automation",
+            Origin::Human => "human",
+        };
+        self.publish("input", |line| {
+            let _ = write!(line, ",\"origin\":\"{}\",\"data\":", origin);
+            push_json_string(line, &String::from_utf8_lossy(data));
+        });
+    }
+
+    /// Publish the outcome of an expect call.
+    pub(crate) fn expect_result(&self, result: &Result<MatchResult, ExpectError>) {
+        match result {
+            Ok(m) => self.publish("match", |line| {
+                let _ = write!(line, ",\"pattern_index\":{},\"matched\":", m.pattern_index);
+                push_json_string(line, &m.matched);
+            }),
+            Err(e) => self.publish("error", |line| {
+                line.push_str(",\"message\":");
+                push_json_string(line, &e.to_string());
+            }),
+        }
+    }
+
+    /// Format an event as a JSON line and send it to every viewer.
+    fn publish(&self, event: &str, fields: impl FnOnce(&mut String)) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let mut line = format!(
+            "{{\"t\":{:.3},\"event\":\"{}\"",
+            self.start.elapsed().as_secs_f64(),
+            event
+        );
+        fields(&mut line);
+        line.push_str("}\n");
+        let _ = self.sender.send(line.into());
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        // Viewers are disconnected once the listener's sender is dropped too
+        if let Some(listener) = &self.listener {
+            listener.abort();
+        }
+    }
+}
+
+/// Append `text` as a JSON string literal.
+pub(crate) fn push_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl Session {
+    /// Publish the session's events to viewers connecting to a TCP socket.
+    ///
+    /// External tools such as dashboards and GUIs can attach to a running
+    /// automation and follow it live. Each connected viewer receives one
+    /// JSON object per line, from the moment it connects:
+    ///
+    /// | `event` | Fields | Published when |
+    /// |---|---|---|
+    /// | `output` | `data` | output is read from the process |
+    /// | `input` | `origin` (`automation` or `human`), `data` | data is sent to the process |
+    /// | `match` | `pattern_index`, `matched` | an expect call matches |
+    /// | `error` | `message` | an expect call fails |
+    /// | `lagged` | `skipped` | the viewer fell behind and missed events |
+    ///
+    /// Every event also has `t`, the seconds since publishing started. Data
+    /// is decoded as UTF-8, with invalid sequences (including characters
+    /// split between chunks) replaced. As with [`on_output`](Self::on_output),
+    /// events are only produced while the session is driven.
+    ///
+    /// The socket is not authenticated and the events include everything
+    /// sent, passwords too: bind to a loopback address. Calling this again
+    /// replaces the previous socket, keeping its viewers connected; dropping
+    /// the session closes it and disconnects viewers.
+    ///
+    /// Returns the address the socket is bound to, useful with port 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::IoError` if the socket cannot be bound.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("./provision.sh")?;
+    /// let addr = session.serve_events("127.0.0.1:0").await?;
+    /// println!("follow with: nc {} {}", addr.ip(), addr.port());
+    ///
+    /// session.expect(Pattern::exact("provisioned")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn serve_events(
+        &mut self,
+        addr: impl ToSocketAddrs,
+    ) -> Result<SocketAddr, ExpectError> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let events = self.events.get_or_insert_with(EventStream::new);
+        let start = events.start;
+
+        let accept_sender = events.sender.clone();
+        let listener = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut events = accept_sender.subscribe();
+                tokio::spawn(async move {
+                    loop {
+                        let line: Arc<str> = match events.recv().await {
+                            Ok(line) => line,
+                            Err(RecvError::Lagged(skipped)) => {
+                                let t = start.elapsed().as_secs_f64();
+                                format!(
+                                    "{{\"t\":{:.3},\"event\":\"lagged\",\"skipped\":{}}}\n",
+                                    t, skipped
+                                )
+                                .into()
+                            }
+                            Err(RecvError::Closed) => break,
+                        };
+                        if stream.write_all(line.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        if let Some(previous) = events.listener.replace(listener) {
+            previous.abort();
+        }
+        Ok(local_addr)
+    }
+
+    /// Sender of the session's event lines, for subscribing in-process.
+    ///
+    /// Each message is one JSON line as documented for
+    /// [`serve_events`](Self::serve_events).
+    #[cfg(feature = "server")]
+    pub(crate) fn event_sender(&mut self) -> broadcast::Sender<Arc<str>> {
+        self.events
+            .get_or_insert_with(EventStream::new)
+            .sender
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        let mut out = String::new();
+        push_json_string(&mut out, "say \"hi\"\\\r\n\x1b[0m\u{e9}");
+        assert_eq!(out, r#""say \"hi\"\\\r\n\u001b[0mé""#);
+    }
+}
@@ -0,0 +1,561 @@
+//! Synchronizing several sessions on a common pattern
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::summary::{GroupSummary, StepOutcome, StepRecord};
+use crate::session::Session;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+type ExpectFuture<'a> = Pin<Box<dyn Future<Output = Result<MatchResult, ExpectError>> + Send + 'a>>;
+type BranchFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, ExpectError>> + 'a>>;
+
+/// Error from a [`SessionGroup`] operation, with the outcome for every
+/// session.
+#[derive(Debug, Error)]
+#[error("{failed} of {total} sessions failed to match", failed = self.failures().count(), total = .results.len())]
+pub struct GroupError {
+    /// Outcome per session, in group order.
+    ///
+    /// Sessions that did not finish before the group timeout report
+    /// `ExpectError::Timeout`.
+    pub results: Vec<Result<MatchResult, ExpectError>>,
+}
+
+impl GroupError {
+    /// Indices and errors of the sessions that failed.
+    pub fn failures(&self) -> impl Iterator<Item = (usize, &ExpectError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().err().map(|e| (i, e)))
+    }
+}
+
+/// A group of sessions driven in lockstep.
+///
+/// [`all_expect`](Self::all_expect) waits for every session to print a
+/// pattern, [`any_expect`](Self::any_expect) for the first one. Sessions are
+/// waited on concurrently, under one timeout for the whole group rather than
+/// one per session.
+///
+/// Each of these operations, and each [`branch`](Self::branch), is recorded
+/// as a step; [`summary`](Self::summary) aggregates them per host.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Pattern, Session, SessionGroup};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let nodes = ["node1", "node2", "node3"]
+///     .iter()
+///     .map(|host| Session::spawn(&format!("ssh {}", host)))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// let mut cluster = SessionGroup::new(nodes);
+///
+/// cluster.all_expect(Pattern::exact("$ ")).await?;
+/// for node in cluster.iter_mut() {
+///     node.send_line("systemctl start worker").await?;
+/// }
+/// cluster.all_expect(Pattern::exact("$ ")).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SessionGroup {
+    sessions: Vec<Session>,
+    timeout: Option<Duration>,
+    labels: Vec<String>,
+    steps: Vec<StepRecord>,
+}
+
+impl SessionGroup {
+    /// Create a group from several sessions.
+    ///
+    /// The group timeout is the longest of the sessions' timeouts (none if
+    /// any session has no timeout).
+    pub fn new(sessions: Vec<Session>) -> Self {
+        let timeout = sessions
+            .iter()
+            .map(|s| s.timeout)
+            .try_fold(Duration::ZERO, |max, t| t.map(|t| max.max(t)));
+        let labels = (0..sessions.len())
+            .map(|index| format!("session {}", index))
+            .collect();
+        Self {
+            sessions,
+            timeout,
+            labels,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Name the sessions in [`summary`](Self::summary), e.g. by host.
+    ///
+    /// Labels are assigned in group order; sessions without one keep the
+    /// default `session <index>`.
+    pub fn set_labels(&mut self, labels: impl IntoIterator<Item = impl Into<String>>) {
+        for (slot, label) in self.labels.iter_mut().zip(labels) {
+            *slot = label.into();
+        }
+    }
+
+    /// Set the timeout for group operations.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Number of sessions in the group.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Whether the group has no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Access one of the sessions.
+    pub fn session_mut(&mut self, index: usize) -> Option<&mut Session> {
+        self.sessions.get_mut(index)
+    }
+
+    /// Iterate over the sessions, e.g. to send each of them input.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Session> {
+        self.sessions.iter_mut()
+    }
+
+    /// Split the group back into its sessions.
+    pub fn into_sessions(self) -> Vec<Session> {
+        self.sessions
+    }
+
+    /// Wait until every session has printed a pattern.
+    ///
+    /// Returns the match of each session, in group order.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GroupError`] with every session's outcome if any session
+    /// fails to match before the group timeout or reaches EOF. Sessions that
+    /// did match have their match consumed either way.
+    pub async fn all_expect(&mut self, pattern: Pattern) -> Result<Vec<MatchResult>, GroupError> {
+        let (results, durations) = join_all(self.expect_each(&pattern)).await;
+        self.record(
+            format!("all_expect {}", describe(&pattern)),
+            results.iter().zip(durations).map(|(result, duration)| {
+                (
+                    Some(duration),
+                    result.as_ref().err().map(ToString::to_string),
+                )
+            }),
+        );
+        if results.iter().all(Result::is_ok) {
+            Ok(results.into_iter().flatten().collect())
+        } else {
+            Err(GroupError { results })
+        }
+    }
+
+    /// Wait until any session prints a pattern.
+    ///
+    /// Returns the index of the first session to match, and its match. The
+    /// other sessions stop waiting; output they received stays buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GroupError`] if no session matches before the group
+    /// timeout, or every session reaches EOF.
+    pub async fn any_expect(
+        &mut self,
+        pattern: Pattern,
+    ) -> Result<(usize, MatchResult), GroupError> {
+        let (first, finished) = first_match(self.expect_each(&pattern)).await;
+        self.record(
+            format!("any_expect {}", describe(&pattern)),
+            finished.into_iter(),
+        );
+        first
+    }
+
+    /// Send the same data to every session.
+    ///
+    /// Useful for the shared steps of sessions that later diverge, such as
+    /// logging in to several hosts before running different commands on
+    /// each (see [`branch`](Self::branch)).
+    ///
+    /// # Errors
+    ///
+    /// Every session is sent the data; if any sends fail, the first error is
+    /// returned.
+    pub async fn broadcast_send(&mut self, data: &[u8]) -> Result<(), ExpectError> {
+        let mut first_error = None;
+        for session in &mut self.sessions {
+            if let Err(e) = session.send(data).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Send the same line to every session.
+    ///
+    /// Like [`broadcast_send`](Self::broadcast_send), with the line ending
+    /// [`Session::send_line`] uses.
+    pub async fn broadcast_send_line(&mut self, line: &str) -> Result<(), ExpectError> {
+        let mut first_error = None;
+        for session in &mut self.sessions {
+            if let Err(e) = session.send_line(line).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Run a different flow on each session, concurrently.
+    ///
+    /// `branch` is called with each session's index and the session, and the
+    /// returned futures run at the same time. This lets sessions that went
+    /// through shared steps together diverge, e.g. the same login followed
+    /// by a different upgrade path per host, then come back together.
+    ///
+    /// Returns each branch's outcome, in group order.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session, SessionGroup};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let hosts = ["canary", "stable"];
+    /// let sessions = hosts
+    ///     .iter()
+    ///     .map(|host| Session::builder().transcript(true).spawn(&format!("ssh {}", host)))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// let mut group = SessionGroup::new(sessions);
+    ///
+    /// // Shared prefix
+    /// group.all_expect(Pattern::exact("$ ")).await?;
+    /// group.broadcast_send_line("sudo -i").await?;
+    /// group.all_expect(Pattern::exact("# ")).await?;
+    ///
+    /// // Divergent paths
+    /// let results = group
+    ///     .branch(async |index, session| {
+    ///         let channel = if index == 0 { "beta" } else { "lts" };
+    ///         session.send_line(&format!("upgrade --channel {}", channel)).await?;
+    ///         session.expect(Pattern::exact("upgraded")).await
+    ///     })
+    ///     .await;
+    /// for (host, result) in hosts.iter().zip(&results) {
+    ///     println!("{}: {}", host, if result.is_ok() { "ok" } else { "failed" });
+    /// }
+    /// print!("{}", group.transcript_report());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn branch<T>(
+        &mut self,
+        branch: impl AsyncFn(usize, &mut Session) -> Result<T, ExpectError>,
+    ) -> Vec<Result<T, ExpectError>> {
+        let branch = &branch;
+        let pending = self
+            .sessions
+            .iter_mut()
+            .enumerate()
+            .map(|(index, session)| -> BranchFuture<'_, T> { Box::pin(branch(index, session)) })
+            .collect();
+        let (results, durations) = join_all(pending).await;
+        self.record(
+            "branch".to_string(),
+            results.iter().zip(durations).map(|(result, duration)| {
+                (
+                    Some(duration),
+                    result.as_ref().err().map(ToString::to_string),
+                )
+            }),
+        );
+        results
+    }
+
+    /// Aggregate the steps run on the group so far.
+    ///
+    /// Every [`all_expect`](Self::all_expect),
+    /// [`any_expect`](Self::any_expect) and [`branch`](Self::branch) call
+    /// is a step, named after the operation and numbered from 1. The summary
+    /// gives per-host pass/fail counts, the slowest steps, and failures
+    /// clustered by error and last line of output, so a run over many hosts
+    /// reads as a few distinct problems rather than one log per host.
+    /// Output in the summary is redacted like [`Session::report`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session, SessionGroup};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let hosts: Vec<String> = (1..=200).map(|n| format!("web{}", n)).collect();
+    /// let sessions = hosts
+    ///     .iter()
+    ///     .map(|host| Session::spawn(&format!("ssh {}", host)))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// let mut fleet = SessionGroup::new(sessions);
+    /// fleet.set_labels(&hosts);
+    ///
+    /// let _ = fleet.all_expect(Pattern::exact("$ ")).await;
+    /// fleet.broadcast_send_line("sudo apt-get -y upgrade").await?;
+    /// let _ = fleet.all_expect(Pattern::exact("$ ")).await;
+    ///
+    /// let summary = fleet.summary();
+    /// print!("{}", summary);
+    /// for host in summary.failed_hosts() {
+    ///     eprintln!("retry {}", host.label);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summary(&self) -> GroupSummary {
+        GroupSummary::new(&self.labels, &self.steps)
+    }
+
+    /// Forget the steps recorded so far, e.g. between runs.
+    pub fn clear_summary(&mut self) {
+        self.steps.clear();
+    }
+
+    /// The transcripts of all sessions, with their shared beginning shown
+    /// once.
+    ///
+    /// Entries that every session's transcript starts with (same origin and
+    /// data, whatever the timing) are listed first under `shared`, with the
+    /// first session's timestamps. The rest of each transcript follows
+    /// under the session's index. Sessions without a transcript (see
+    /// [`SessionBuilder::transcript`](crate::SessionBuilder::transcript))
+    /// are listed as such and do not limit the shared part.
+    pub fn transcript_report(&self) -> String {
+        let transcripts: Vec<_> = self
+            .sessions
+            .iter()
+            .map(|session| session.transcript().map(|t| t.entries()))
+            .collect();
+        let recorded: Vec<_> = transcripts.iter().flatten().collect();
+        let shared = match recorded.split_first() {
+            Some((first, others)) if !others.is_empty() => first
+                .iter()
+                .enumerate()
+                .take_while(|(i, entry)| {
+                    others.iter().all(|other| {
+                        other
+                            .get(*i)
+                            .is_some_and(|e| e.origin == entry.origin && e.data == entry.data)
+                    })
+                })
+                .count(),
+            _ => 0,
+        };
+
+        let mut report = String::new();
+        if shared > 0 {
+            let _ = writeln!(report, "shared:");
+            for entry in &recorded[0][..shared] {
+                let _ = writeln!(report, "  {}", entry);
+            }
+        }
+        for (index, transcript) in transcripts.iter().enumerate() {
+            match transcript {
+                Some(entries) => {
+                    let _ = writeln!(report, "session {}:", index);
+                    for entry in &entries[shared..] {
+                        let _ = writeln!(report, "  {}", entry);
+                    }
+                }
+                None => {
+                    let _ = writeln!(report, "session {}: no transcript", index);
+                }
+            }
+        }
+        report
+    }
+
+    /// Record a step from each session's duration and error, in group order.
+    ///
+    /// Errors are redacted with each session's secrets, since they may quote
+    /// output.
+    fn record(
+        &mut self,
+        operation: String,
+        outcomes: impl Iterator<Item = (Option<Duration>, Option<String>)>,
+    ) {
+        let outcomes = self
+            .sessions
+            .iter()
+            .zip(outcomes)
+            .map(|(session, (duration, error))| StepOutcome {
+                duration,
+                failure: error.map(|e| {
+                    (
+                        session.redact_text(e).into_owned(),
+                        session.last_output_line(),
+                    )
+                }),
+            })
+            .collect();
+        self.steps.push(StepRecord {
+            name: format!("#{} {}", self.steps.len() + 1, operation),
+            outcomes,
+        });
+    }
+
+    /// Start waiting for `pattern` on every session, sharing one deadline.
+    fn expect_each<'a>(&'a mut self, pattern: &'a Pattern) -> Vec<ExpectFuture<'a>> {
+        let timeout = self.timeout;
+        self.sessions
+            .iter_mut()
+            .map(|session| -> ExpectFuture<'a> {
+                Box::pin(session.expect_any_within(std::slice::from_ref(pattern), timeout))
+            })
+            .collect()
+    }
+}
+
+impl Session {
+    /// Wait for any of several patterns on any of several sessions, like
+    /// Expect's `expect -i $id1 ... -i $id2 ...`.
+    ///
+    /// Returns the index in `sessions` of the first session to match, and
+    /// its match. The sessions wait concurrently, under one timeout: the
+    /// longest of their timeouts (none if any has no timeout). The other
+    /// sessions stop waiting; output they received stays buffered.
+    ///
+    /// A session reaching EOF matches `Pattern::Eof` if it is given, and
+    /// otherwise drops out while the others keep waiting. `Pattern::Timeout`
+    /// matches on the first session once the timeout is reached.
+    ///
+    /// Unlike [`SessionGroup`], the sessions are only borrowed, so they can
+    /// stay in whatever structure the program keeps them in.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GroupError`] with every session's error if no session
+    /// matches.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut primary = Session::spawn("ssh db1")?;
+    /// let mut replica = Session::spawn("ssh db2")?;
+    ///
+    /// let patterns = [Pattern::exact("FAILOVER"), Pattern::exact("ERROR")];
+    /// let (index, result) =
+    ///     Session::expect_any_of(&mut [&mut primary, &mut replica], &patterns).await?;
+    /// println!("db{} printed {}", index + 1, result.matched);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_any_of(
+        sessions: &mut [&mut Session],
+        patterns: &[Pattern],
+    ) -> Result<(usize, MatchResult), GroupError> {
+        let timeout = sessions
+            .iter()
+            .map(|s| s.timeout)
+            .try_fold(Duration::ZERO, |max, t| t.map(|t| max.max(t)));
+        let pending = sessions
+            .iter_mut()
+            .map(|session| -> ExpectFuture<'_> {
+                Box::pin(session.expect_any_within(patterns, timeout))
+            })
+            .collect();
+        first_match(pending).await.0
+    }
+}
+
+/// Wait for several futures concurrently, returning their outputs in order
+/// and how long each took.
+async fn join_all<F: Future + Unpin>(mut pending: Vec<F>) -> (Vec<F::Output>, Vec<Duration>) {
+    let started = Instant::now();
+    let mut results: Vec<Option<F::Output>> = (0..pending.len()).map(|_| None).collect();
+    let mut durations = vec![Duration::ZERO; pending.len()];
+
+    std::future::poll_fn(|cx| {
+        for (index, future) in pending.iter_mut().enumerate() {
+            if results[index].is_none() {
+                if let Poll::Ready(result) = Pin::new(&mut *future).poll(cx) {
+                    results[index] = Some(result);
+                    durations[index] = started.elapsed();
+                }
+            }
+        }
+        if results.iter().all(Option::is_some) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    drop(pending);
+
+    (results.into_iter().flatten().collect(), durations)
+}
+
+/// A pattern as written, for step names: quoted source text, or the name of
+/// a special pattern.
+fn describe(pattern: &Pattern) -> String {
+    match pattern.source() {
+        Some(source) => format!("{:?}", source),
+        None => format!("{:?}", pattern),
+    }
+}
+
+/// Wait for the first of several expect calls to match.
+///
+/// Calls that fail are dropped from the race; if all fail, their errors are
+/// returned in order. Also returns, per call, how long it took if it
+/// finished and its error message if it failed.
+async fn first_match(
+    mut pending: Vec<ExpectFuture<'_>>,
+) -> (
+    Result<(usize, MatchResult), GroupError>,
+    Vec<(Option<Duration>, Option<String>)>,
+) {
+    let started = Instant::now();
+    let mut finished = vec![(None, None); pending.len()];
+    let mut errors: Vec<Option<ExpectError>> = (0..pending.len()).map(|_| None).collect();
+
+    let first = std::future::poll_fn(|cx| {
+        for (index, expect) in pending.iter_mut().enumerate() {
+            if errors[index].is_none() {
+                match expect.as_mut().poll(cx) {
+                    Poll::Ready(Ok(result)) => {
+                        finished[index].0 = Some(started.elapsed());
+                        return Poll::Ready(Some((index, result)));
+                    }
+                    Poll::Ready(Err(e)) => {
+                        finished[index] = (Some(started.elapsed()), Some(e.to_string()));
+                        errors[index] = Some(e);
+                    }
+                    Poll::Pending => {}
+                }
+            }
+        }
+        if errors.iter().all(Option::is_some) {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    drop(pending);
+
+    let first = first.ok_or_else(|| GroupError {
+        results: errors.into_iter().flatten().map(Err).collect(),
+    });
+    (first, finished)
+}
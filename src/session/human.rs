@@ -0,0 +1,171 @@
+//! Typing with human-like timing
+
+use crate::result::ExpectError;
+use crate::session::Session;
+use crate::transcript::Origin;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Keystroke timing used by [`Session::send_human`].
+///
+/// Mirrors the `send_human` variable of Expect's `send -h`. The pause after
+/// each character is drawn from a Weibull distribution with the given
+/// average and shape, then clamped to `min..=max`. Characters ending a word
+/// (whitespace) use the separate word average, since people pause longer
+/// between words.
+///
+/// The defaults are Expect's documented example, `{.1 .3 1 .05 2}`: 100ms
+/// between characters, 300ms after words, shape 1, and pauses between 50ms
+/// and 2s.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{HumanTyping, Session};
+/// use std::time::Duration;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut session = Session::builder()
+///     .human_typing(HumanTyping::new().average(Duration::from_millis(60)).shape(0.5))
+///     .spawn("bash")?;
+/// session.send_human("ls -l\n").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HumanTyping {
+    average: Duration,
+    word_average: Duration,
+    shape: f64,
+    min: Duration,
+    max: Duration,
+}
+
+impl Default for HumanTyping {
+    fn default() -> Self {
+        Self {
+            average: Duration::from_millis(100),
+            word_average: Duration::from_millis(300),
+            shape: 1.0,
+            min: Duration::from_millis(50),
+            max: Duration::from_secs(2),
+        }
+    }
+}
+
+impl HumanTyping {
+    /// Create the default timing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Average pause between characters within a word.
+    pub fn average(mut self, average: Duration) -> Self {
+        self.average = average;
+        self
+    }
+
+    /// Average pause after a character ending a word.
+    pub fn word_average(mut self, word_average: Duration) -> Self {
+        self.word_average = word_average;
+        self
+    }
+
+    /// Shape of the distribution: values below 1 give mostly quick
+    /// keystrokes with occasional long pauses, large values give nearly
+    /// constant pauses.
+    pub fn shape(mut self, shape: f64) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Shortest pause.
+    pub fn min(mut self, min: Duration) -> Self {
+        self.min = min;
+        self
+    }
+
+    /// Longest pause.
+    pub fn max(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Pause after typing `c`, for a uniform random sample `u` in `(0, 1)`.
+    fn pause(&self, c: char, u: f64) -> Duration {
+        let average = if c.is_whitespace() {
+            self.word_average
+        } else {
+            self.average
+        };
+        let sample = average.as_secs_f64() * (-u.ln()).powf(1.0 / self.shape.max(f64::EPSILON));
+        Duration::try_from_secs_f64(sample)
+            .unwrap_or(self.max)
+            .clamp(self.min, self.max.max(self.min))
+    }
+}
+
+/// A uniform random number in `(0, 1)`.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    // 53 random bits, offset by half a step to stay off 0 and 1
+    ((hasher.finish() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+impl Session {
+    /// Send text one character at a time with human-like pauses.
+    ///
+    /// The equivalent of Expect's `send -h`. Some programs, and checks meant
+    /// to detect automation, behave differently when input arrives all at
+    /// once. The timing is set with [`SessionBuilder::human_typing`](crate::SessionBuilder::human_typing)
+    /// and defaults to [`HumanTyping::default`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ssh user@host")?;
+    /// session.expect(Pattern::exact("password: ")).await?;
+    /// session.send_human("secret\n").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_human(&mut self, text: &str) -> Result<(), ExpectError> {
+        let mut chars = text.chars().peekable();
+        let mut buf = [0u8; 4];
+        while let Some(c) = chars.next() {
+            self.send_from(Origin::Automation, c.encode_utf8(&mut buf).as_bytes())
+                .await?;
+            if chars.peek().is_some() {
+                let pause = self.human_typing.pause(c, random_unit());
+                tokio::time::sleep(pause).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_bounds() {
+        let typing = HumanTyping::new();
+        // The median of an exponential distribution is ln 2 times the average
+        let median = typing.pause('a', 0.5);
+        assert!((median.as_secs_f64() - 0.1 * 2f64.ln()).abs() < 1e-9);
+        assert!(typing.pause(' ', 0.5) > median);
+        assert_eq!(typing.pause('a', 0.999_999), typing.min);
+        assert_eq!(typing.pause('a', 1e-300), typing.max);
+
+        for _ in 0..100 {
+            let u = random_unit();
+            assert!(u > 0.0 && u < 1.0);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Handing a session over to the user's terminal
+
+use crate::result::ExpectError;
+use crate::session::Session;
+use crate::teach::Recorder;
+use crate::transcript::Origin;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Byte that ends an interaction when typed by the user (Ctrl-]).
+const INTERACT_ESCAPE: u8 = 0x1d;
+
+impl Session {
+    /// Connect the process to the user's terminal.
+    ///
+    /// Everything typed is sent to the process and everything the process
+    /// prints is written to stdout, until the process reaches EOF or the user
+    /// types Ctrl-] (which is not sent). While interacting, stdin is switched
+    /// to raw mode if it is a terminal, so keys like Ctrl-C reach the process.
+    ///
+    /// Output that was buffered but not consumed by a match is printed first.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ssh user@host")?;
+    /// session.expect(Pattern::exact("password: ")).await?;
+    /// session.send_line("secret").await?;
+    /// // Let the user take over
+    /// session.interact().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn interact(&mut self) -> Result<(), ExpectError> {
+        self.interact_with(None).await
+    }
+
+    /// Interact while recording the user's input ("teach mode").
+    ///
+    /// Behaves like [`Session::interact`], and returns a [`Recorder`] holding
+    /// the typed input and the prompts that preceded it, which can generate
+    /// an Expect script or Rust program that replays the interaction.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn_args("ftp", ["example.com"])?;
+    /// let recorder = session.teach().await?;
+    /// std::fs::write("ftp.exp", recorder.to_expect_script(&["ftp", "example.com"]))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn teach(&mut self) -> Result<Recorder, ExpectError> {
+        let mut recorder = Recorder::new();
+        self.interact_with(Some(&mut recorder)).await?;
+        recorder.finish();
+        Ok(recorder)
+    }
+
+    async fn interact_with(
+        &mut self,
+        mut recorder: Option<&mut Recorder>,
+    ) -> Result<(), ExpectError> {
+        let _raw = RawMode::enable()?;
+        let mut stdin = tokio::io::stdin();
+        let mut stdout = tokio::io::stdout();
+
+        // With echo on, output was shown as it was read
+        let pending = self.buffer.unmatched().to_vec();
+        self.buffer.clear();
+        if !pending.is_empty() {
+            if !self.echo_output {
+                stdout.write_all(&pending).await?;
+                stdout.flush().await?;
+            }
+            if let Some(recorder) = recorder.as_deref_mut() {
+                recorder.record_output(&pending);
+            }
+        }
+
+        let mut input = [0u8; 1024];
+        while !self.eof_reached {
+            tokio::select! {
+                output = self.reader.read(None) => {
+                    let output = output?;
+                    if output.is_empty() {
+                        self.eof_reached = true;
+                        break;
+                    }
+                    if !self.echo_output {
+                        stdout.write_all(&output).await?;
+                        stdout.flush().await?;
+                    }
+                    self.observe(&output);
+                    if let Some(recorder) = recorder.as_deref_mut() {
+                        recorder.record_output(&output);
+                    }
+                }
+                n = stdin.read(&mut input) => {
+                    let n = n?;
+                    if n == 0 {
+                        break;
+                    }
+                    let typed = &input[..n];
+                    let (typed, done) = match typed.iter().position(|&b| b == INTERACT_ESCAPE) {
+                        Some(i) => (&typed[..i], true),
+                        None => (typed, false),
+                    };
+                    if !typed.is_empty() {
+                        self.send_from(Origin::Human, typed).await?;
+                        if let Some(recorder) = recorder.as_deref_mut() {
+                            recorder.record_input(typed);
+                        }
+                    }
+                    if done {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Guard that puts stdin into raw mode and restores it on drop.
+struct RawMode {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl RawMode {
+    #[cfg(unix)]
+    fn enable() -> std::io::Result<Self> {
+        // SAFETY: isatty/tcgetattr/tcsetattr only read and write the termios
+        // structure passed in, which is plain data
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1 {
+                return Ok(Self { original: None });
+            }
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(Self {
+                original: Some(original),
+            })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn enable() -> std::io::Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: restores settings previously read from the same descriptor
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+            }
+        }
+    }
+}
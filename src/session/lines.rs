@@ -0,0 +1,134 @@
+//! Line-oriented reading
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::{Consume, ExpectOptions, Session};
+
+/// Reader of a session's output line by line.
+///
+/// Created by [`Session::lines`]. Like tokio's `Lines`, it is advanced with
+/// [`next_line`](Self::next_line) rather than iterated.
+pub struct Lines<'a> {
+    session: &'a mut Session,
+}
+
+impl Lines<'_> {
+    /// The next line, or `None` once the process has closed its output.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Session::read_line`], except that EOF ends the lines
+    /// instead of failing.
+    pub async fn next_line(&mut self) -> Result<Option<String>, ExpectError> {
+        match self.session.read_line().await {
+            Ok(line) => Ok(Some(line)),
+            Err(ExpectError::Eof) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Session {
+    /// Read the next line of output.
+    ///
+    /// Waits for a line feed and returns the text before it, without the
+    /// line ending (`\n` and any carriage returns before it), consuming
+    /// both. Once the process has closed its output, a final line without a
+    /// line ending is returned as is. Invalid UTF-8 is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::Eof` once all output has been read, and
+    /// `ExpectError::Timeout` if no complete line arrives within the
+    /// session timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ls -1 /etc")?;
+    /// let first = session.read_line().await?;
+    /// println!("first entry: {}", first);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_line(&mut self) -> Result<String, ExpectError> {
+        let result = self
+            .expect_any_consuming(&[Pattern::exact("\n"), Pattern::Eof], Consume::Nothing)
+            .await?;
+        let start = self.buffer.matched_position();
+        let (end, consumed) = match result.pattern_index {
+            0 => (result.start, result.end),
+            _ if start == self.buffer.len() => return Err(ExpectError::Eof),
+            _ => (self.buffer.len(), self.buffer.len()),
+        };
+
+        let mut line = &self.buffer.as_bytes()[start..end];
+        // A PTY turns "\r\n" written by the process into "\r\r\n"
+        while let Some(stripped) = line.strip_suffix(b"\r") {
+            line = stripped;
+        }
+        let line = String::from_utf8_lossy(line).into_owned();
+        self.buffer.consume(consumed);
+        Ok(line)
+    }
+
+    /// Wait for a pattern within a single complete line.
+    ///
+    /// Unlike [`Session::expect`], which searches the whole buffer, the
+    /// pattern is tested against each complete line on its own, so it cannot
+    /// match across a line break and `^` and `$` anchor to the line. See
+    /// [`ExpectOptions::whole_lines`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Session::expect`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("make test")?;
+    /// let result = session
+    ///     .expect_line(Pattern::regex(r"^(\d+) passed, (\d+) failed$")?)
+    ///     .await?;
+    /// println!("failed: {}", result.captures[2]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_line(&mut self, pattern: Pattern) -> Result<MatchResult, ExpectError> {
+        let options = ExpectOptions {
+            whole_lines: true,
+            ..Default::default()
+        };
+        self.expect_opts(pattern, options).await
+    }
+
+    /// Read the output line by line.
+    ///
+    /// See [`Session::read_line`] for how lines are split.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("journalctl -f")?;
+    /// let mut lines = session.lines();
+    /// while let Some(line) = lines.next_line().await? {
+    ///     if line.contains("error") {
+    ///         println!("{}", line);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lines(&mut self) -> Lines<'_> {
+        Lines { session: self }
+    }
+}
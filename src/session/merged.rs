@@ -0,0 +1,360 @@
+//! Matching over the combined output of several sessions
+
+use crate::buffer::strip_ansi;
+use crate::pattern::{Matcher, Pattern};
+use crate::result::{ExpectError, MatchResult};
+use crate::session::Session;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// Result of a match on a [`MergedSession`].
+#[derive(Debug, Clone)]
+pub struct MergedMatch {
+    /// The match itself. Positions and `before` refer to the merged stream.
+    pub result: MatchResult,
+
+    /// Indices of the sessions whose output makes up the matched text, in
+    /// order of first appearance.
+    ///
+    /// Empty for the `Eof` and `Timeout` special patterns.
+    pub sources: Vec<usize>,
+}
+
+/// Several sessions whose output is interleaved into one matchable stream.
+///
+/// Output is appended to the merged stream chunk by chunk, in the order it
+/// arrives, and each chunk is tagged with the index of the session it came
+/// from. A single pattern can therefore span the output of several
+/// processes, and [`MergedMatch::sources`] tells which ones contributed.
+///
+/// Chunks are not split at line boundaries, so a line from one process may
+/// be interrupted by output of another. Patterns meant to match a line of a
+/// single process should not assume it arrives in one piece.
+///
+/// Output read while merged is only available through the merged stream;
+/// it does not appear in the individual session buffers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{MergedSession, Pattern, Session};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let workers = (0..3)
+///     .map(|i| Session::spawn(&format!("./worker --id {}", i)))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// let mut merged = MergedSession::new(workers);
+///
+/// // Wait until every worker printed READY once
+/// for _ in 0..3 {
+///     let found = merged.expect(Pattern::exact("READY")).await?;
+///     println!("worker {} is ready", found.sources[0]);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MergedSession {
+    sessions: Vec<Session>,
+    buffer: Vec<u8>,
+    /// Start offset in `buffer` and source index of each chunk, in order.
+    chunks: Vec<(usize, usize)>,
+    timeout: Option<Duration>,
+    max_buffer_size: usize,
+}
+
+impl MergedSession {
+    /// Merge the output of several sessions.
+    ///
+    /// The timeout is the longest of the sessions' timeouts (none if any
+    /// session has no timeout), and the buffer may hold as much as all the
+    /// session buffers together. Output already buffered in a session but not
+    /// consumed by a match is moved to the merged stream.
+    pub fn new(mut sessions: Vec<Session>) -> Self {
+        let timeout = sessions
+            .iter()
+            .map(|s| s.timeout)
+            .try_fold(Duration::ZERO, |max, t| t.map(|t| max.max(t)));
+        let max_buffer_size = sessions.iter().map(|s| s.max_buffer_size).sum();
+
+        let mut merged = Self {
+            sessions: Vec::new(),
+            buffer: Vec::new(),
+            chunks: Vec::new(),
+            timeout,
+            max_buffer_size,
+        };
+        for (index, session) in sessions.iter_mut().enumerate() {
+            let pending = session.buffer.unmatched().to_vec();
+            session.buffer.clear();
+            if !pending.is_empty() {
+                merged.append(index, &pending);
+            }
+        }
+        merged.sessions = sessions;
+        merged
+    }
+
+    /// Set the timeout for expect operations on the merged stream.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Number of merged sessions.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Whether there are no merged sessions.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Access one of the sessions, e.g. to send it input.
+    pub fn session_mut(&mut self, index: usize) -> Option<&mut Session> {
+        self.sessions.get_mut(index)
+    }
+
+    /// Split the merged session back into its sessions.
+    ///
+    /// Output left in the merged stream is discarded.
+    pub fn into_sessions(self) -> Vec<Session> {
+        self.sessions
+    }
+
+    /// The unconsumed part of the merged stream.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Wait for a pattern to appear in the merged stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the timeout is reached, or if every session
+    /// reaches EOF before the pattern matches.
+    pub async fn expect(&mut self, pattern: Pattern) -> Result<MergedMatch, ExpectError> {
+        self.expect_any(&[pattern]).await
+    }
+
+    /// Wait for any of several patterns to appear in the merged stream.
+    ///
+    /// Patterns are checked in order, as with [`Session::expect_any`].
+    /// `Pattern::Eof` matches once every session has reached EOF.
+    pub async fn expect_any(&mut self, patterns: &[Pattern]) -> Result<MergedMatch, ExpectError> {
+        let mut matchers: Vec<(usize, Box<dyn Matcher>)> = Vec::new();
+        for (idx, pattern) in patterns.iter().enumerate() {
+            if !pattern.is_special() {
+                matchers.push((idx, pattern.to_matcher()?));
+            }
+        }
+        let special = |wanted: fn(&Pattern) -> bool| patterns.iter().position(wanted);
+
+        let start_time = Instant::now();
+
+        loop {
+            for (pattern_index, matcher) in &matchers {
+                if let Some(m) = matcher.find(&self.buffer) {
+                    let sources = self.sources_in(m.start, m.end);
+                    let result = MatchResult {
+                        pattern_index: *pattern_index,
+                        matched: String::from_utf8_lossy(&self.buffer[m.start..m.end]).into_owned(),
+                        start: m.start,
+                        end: m.end,
+                        before: String::from_utf8_lossy(&self.buffer[..m.start]).into_owned(),
+                        matched_bytes: self.buffer[m.start..m.end].to_vec(),
+                        before_bytes: self.buffer[..m.start].to_vec(),
+                        captures: m.captures,
+                        captures_bytes: m.captures_bytes,
+                    };
+                    self.consume(m.end);
+                    return Ok(MergedMatch { result, sources });
+                }
+            }
+
+            if self.sessions.iter().all(|s| s.eof_reached) {
+                return match special(|p| matches!(p, Pattern::Eof)) {
+                    Some(index) => Ok(self.special_match(index)),
+                    None => Err(ExpectError::Eof),
+                };
+            }
+
+            let remaining = self.timeout.map(|t| t.saturating_sub(start_time.elapsed()));
+            match self.read_any(remaining).await {
+                Ok(()) => {}
+                Err(ExpectError::Timeout { .. }) => {
+                    return match special(|p| matches!(p, Pattern::Timeout)) {
+                        Some(index) => Ok(self.special_match(index)),
+                        None => Err(ExpectError::Timeout {
+                            duration: self.timeout.unwrap_or_default(),
+                        }),
+                    };
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Read the next chunk from whichever session produces output first.
+    async fn read_any(&mut self, timeout: Option<Duration>) -> Result<(), ExpectError> {
+        let mut reads: Vec<_> = self
+            .sessions
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, session)| !session.eof_reached)
+            .map(|(index, session)| {
+                let read: Pin<Box<dyn Future<Output = _> + Send + '_>> =
+                    Box::pin(async move { (index, session.reader.read(None).await) });
+                read
+            })
+            .collect();
+
+        // Reader::read is cancel-safe, so dropping the reads that lost the
+        // race loses no output
+        let next = std::future::poll_fn(|cx| {
+            for read in &mut reads {
+                if let Poll::Ready(result) = read.as_mut().poll(cx) {
+                    return Poll::Ready(result);
+                }
+            }
+            Poll::Pending
+        });
+        let (index, result) = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, next)
+                .await
+                .map_err(|_| ExpectError::Timeout { duration: timeout })?,
+            None => next.await,
+        };
+        drop(reads);
+
+        let chunk = result?;
+        let session = &mut self.sessions[index];
+        if chunk.is_empty() {
+            session.eof_reached = true;
+        } else {
+            session.observe(&chunk);
+        }
+        if session.buffer.strips_ansi() {
+            self.append(index, &strip_ansi(&chunk));
+        } else {
+            self.append(index, &chunk);
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, source: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        if self.chunks.last().map(|&(_, s)| s) != Some(source) {
+            self.chunks.push((self.buffer.len(), source));
+        }
+        self.buffer.extend_from_slice(data);
+
+        // Like a session buffer, keep the newest data when full
+        if self.buffer.len() > self.max_buffer_size {
+            let excess = self.buffer.len() - self.max_buffer_size;
+            self.consume(excess);
+        }
+    }
+
+    /// Drop the first `end` bytes of the stream.
+    fn consume(&mut self, end: usize) {
+        self.buffer.drain(..end);
+        // Keep the chunk that contains the new start
+        let first = self.chunks.partition_point(|&(start, _)| start <= end);
+        self.chunks.drain(..first.saturating_sub(1));
+        for (start, _) in &mut self.chunks {
+            *start = start.saturating_sub(end);
+        }
+        if self.buffer.is_empty() {
+            self.chunks.clear();
+        }
+    }
+
+    /// Sessions whose chunks overlap `start..end`, in order of appearance.
+    fn sources_in(&self, start: usize, end: usize) -> Vec<usize> {
+        let mut sources = Vec::new();
+        for (i, &(chunk_start, source)) in self.chunks.iter().enumerate() {
+            let chunk_end = self
+                .chunks
+                .get(i + 1)
+                .map_or(self.buffer.len(), |&(next, _)| next);
+            if chunk_start < end && start < chunk_end && !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+
+    fn special_match(&mut self, pattern_index: usize) -> MergedMatch {
+        let before = String::from_utf8_lossy(&self.buffer).into_owned();
+        let len = self.buffer.len();
+        MergedMatch {
+            result: MatchResult {
+                pattern_index,
+                matched: String::new(),
+                start: len,
+                end: len,
+                before,
+                matched_bytes: Vec::new(),
+                before_bytes: self.buffer.clone(),
+                captures: vec![],
+                captures_bytes: vec![],
+            },
+            sources: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(chunks: &[(usize, &str)]) -> MergedSession {
+        let mut merged = MergedSession {
+            sessions: Vec::new(),
+            buffer: Vec::new(),
+            chunks: Vec::new(),
+            timeout: None,
+            max_buffer_size: 1024,
+        };
+        for &(source, data) in chunks {
+            merged.append(source, data.as_bytes());
+        }
+        merged
+    }
+
+    #[test]
+    fn test_sources_in() {
+        let merged = merged(&[(0, "READY\n"), (2, "REA"), (1, "DY\n"), (1, "x")]);
+        assert_eq!(merged.chunks, vec![(0, 0), (6, 2), (9, 1)]);
+        assert_eq!(merged.sources_in(0, 5), vec![0]);
+        assert_eq!(merged.sources_in(6, 11), vec![2, 1]);
+        assert_eq!(merged.sources_in(3, 13), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn test_consume_keeps_tags_aligned() {
+        let mut merged = merged(&[(0, "aaaa"), (1, "bbbb"), (2, "cccc")]);
+        merged.consume(6);
+        assert_eq!(merged.buffer(), b"bbcccc");
+        assert_eq!(merged.chunks, vec![(0, 1), (2, 2)]);
+        assert_eq!(merged.sources_in(0, 3), vec![1, 2]);
+
+        merged.consume(6);
+        assert!(merged.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_overflow_keeps_newest() {
+        let mut merged = merged(&[]);
+        merged.max_buffer_size = 4;
+        merged.append(0, b"abc");
+        merged.append(1, b"def");
+        assert_eq!(merged.buffer(), b"cdef");
+        assert_eq!(merged.sources_in(0, 1), vec![0]);
+        assert_eq!(merged.sources_in(1, 4), vec![1]);
+    }
+}
@@ -1,23 +1,137 @@
 //! Session management for PTY-based process automation
 
 mod builder;
+mod cancel;
+mod command;
+mod connect;
+mod control;
+mod critical;
+mod events;
+mod group;
+mod human;
+mod interact;
+mod lines;
+mod merged;
+mod navigate;
+mod observe;
+mod packet;
+mod prompt;
+mod reader;
+mod respond;
+mod secret;
+mod serial;
+mod settle;
+mod soft_timeout;
 mod spawn;
+mod summary;
+#[cfg(feature = "telnet")]
+mod telnet;
+mod terminate;
 
 pub use builder::SessionBuilder;
+pub(crate) use builder::{DEFAULT_MAX_BUFFER_SIZE, DEFAULT_TIMEOUT_SECS};
+pub use command::CommandOutput;
+pub use connect::{Backoff, ConnectReport, Unreachable};
+pub use control::{ControlChar, IntoControlChar};
+pub use group::{GroupError, SessionGroup};
+pub use human::HumanTyping;
+pub use lines::Lines;
+pub use merged::{MergedMatch, MergedSession};
+pub use packet::PtyEvent;
+pub use respond::AutoResponse;
+pub use soft_timeout::SoftTimeout;
+pub use summary::{FailureCluster, GroupSummary, HostSummary, SlowStep};
+pub use terminate::DropPolicy;
 
-use crate::buffer::BufferManager;
-use crate::pattern::Pattern;
-use crate::result::{ExpectError, MatchResult};
-use portable_pty::{Child, ExitStatus, PtyPair};
-use std::io::{Read, Write};
+use crate::buffer::{BufferManager, RawStream};
+use crate::history::HistoryLog;
+use crate::pattern::{Pattern, PatternFactory, RegexOptions, TypedPatterns};
+#[cfg(feature = "diagnostics")]
+use crate::result::MatchDiagnostics;
+use crate::result::{ExpectError, MatchResult, PatternError, Report};
+use crate::screen::{Screen, ScreenPattern};
+use crate::transcript::{Origin, Transcript, TranscriptLog};
+use critical::CriticalPattern;
+#[cfg(feature = "server")]
+pub(crate) use events::push_json_string;
+use events::EventStream;
+use observe::Observers;
+use portable_pty::{Child, ExitStatus, MasterPty, PtySize};
+use reader::Reader;
+use serial::SerialPort;
+use soft_timeout::SoftTimeoutHook;
+use std::collections::HashMap;
+use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 
+/// Number of trailing buffer bytes shown by [`Session::report`].
+const REPORT_CONTEXT_BYTES: usize = 2048;
+
+/// How much output a successful match consumes.
+///
+/// Consumed output is no longer searched by later expect calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Consume {
+    /// Everything up to the end of the match, including the text before it.
+    #[default]
+    ThroughMatch,
+    /// Only the matched text; the text before it stays available.
+    MatchOnly,
+    /// Nothing; the same text can be matched again.
+    Nothing,
+}
+
+/// Per-call matching configuration for [`Session::expect_opts`].
+///
+/// Fields not set keep their defaults, which behave like
+/// [`Session::expect`].
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::{Consume, ExpectOptions};
+/// use std::time::Duration;
+///
+/// let options = ExpectOptions {
+///     case_insensitive: true,
+///     timeout: Some(Duration::from_secs(5)),
+///     ..Default::default()
+/// };
+/// assert_eq!(options.consume, Consume::ThroughMatch);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExpectOptions {
+    /// Timeout for this call; `None` uses the session's timeout.
+    pub timeout: Option<Duration>,
+    /// Match letters regardless of case.
+    ///
+    /// Applies to exact, regex and glob patterns. Regexes are recompiled
+    /// from their source, so only their inline flags are kept.
+    pub case_insensitive: bool,
+    /// Only search the most recent this many bytes of unconsumed output.
+    ///
+    /// Useful to match a prompt at the end of large output without scanning
+    /// all of it, or to ignore stale matches further back. Output outside
+    /// the window stays unconsumed unless the match consumes it.
+    pub search_window: Option<usize>,
+    /// Only match within complete lines.
+    ///
+    /// Each line is searched on its own, without its line ending, so a
+    /// match cannot span lines and regex anchors `^` and `$` apply to the
+    /// line. Output after the last line feed is not searched until its line
+    /// is complete.
+    pub whole_lines: bool,
+    /// How much output a match consumes.
+    pub consume: Consume,
+}
+
 /// Main session for interacting with a spawned process.
 ///
-/// A `Session` represents a running process with an attached PTY (pseudo-terminal).
-/// It provides methods to send input to the process and wait for expected output patterns.
+/// A `Session` represents a running process with an attached PTY (pseudo-terminal),
+/// or an open serial device (see [`Session::open_serial`]). It provides methods to
+/// send input to the process and wait for expected output patterns.
 ///
 /// # Examples
 ///
@@ -35,15 +149,65 @@ use tokio::sync::Mutex;
 /// # Ok(())
 /// # }
 /// ```
+///
+/// # Output layers
+///
+/// Output read from the process is fed to several independent views:
+///
+/// - the raw stream ([`Session::raw_output`]): the most recent bytes exactly
+///   as they arrived;
+/// - the matching buffer ([`Session::buffer`]): what patterns are searched
+///   in, with escape sequences stripped and progress lines collapsed if
+///   configured, and consumed by matches;
+/// - the screen model ([`Session::screen`]), if enabled;
+/// - the transcript ([`Session::transcript`]), if enabled;
+/// - the log file ([`SessionBuilder::log_file`]), if set;
+/// - standard output ([`SessionBuilder::echo_output`]), if enabled;
+/// - observers registered with [`Session::on_output`];
+/// - viewers of [`Session::serve_events`], if any are connected.
+///
+/// Filtering the matching buffer does not affect the other layers, except
+/// that progress collapsing also applies to the transcript and log file.
 pub struct Session {
-    _pty_pair: PtyPair,
+    _master: Option<Box<dyn MasterPty + Send>>,
+    serial: Option<SerialPort>,
+    /// Window size negotiation of a telnet connection.
+    #[cfg(feature = "telnet")]
+    telnet: Option<telnet::TelnetWindow>,
+    /// Descriptor handed over with [`SessionBuilder::attach_fd`].
+    attached: Option<std::fs::File>,
     child: Option<Box<dyn Child + Send>>,
-    master_reader: Arc<Mutex<Box<dyn Read + Send>>>,
+    reader: Reader,
     master_writer: Arc<Mutex<Box<dyn Write + Send>>>,
     buffer: BufferManager,
+    raw: RawStream,
     timeout: Option<Duration>,
     eof_reached: bool,
     max_buffer_size: usize,
+    char_delays: HashMap<u8, Duration>,
+    screen: Option<Screen>,
+    /// Screen contents when the last expect call timed out
+    timeout_screen: Option<String>,
+    regex_options: RegexOptions,
+    match_budget: Option<Duration>,
+    transcript: Option<Transcript>,
+    log: Option<TranscriptLog>,
+    history: Option<HistoryLog>,
+    echo_output: bool,
+    observers: Observers,
+    events: Option<EventStream>,
+    auto_responses: Vec<AutoResponse>,
+    critical_patterns: Vec<CriticalPattern>,
+    soft_timeout: Option<SoftTimeoutHook>,
+    /// Matching statistics of the last expect call
+    #[cfg(feature = "diagnostics")]
+    diagnostics: Option<MatchDiagnostics>,
+    prompt: Option<Pattern>,
+    /// Payloads sent with [`Session::send_secret`], redacted in reports.
+    secrets: Vec<String>,
+    human_typing: HumanTyping,
+    terminate_grace: Duration,
+    drop_policy: DropPolicy,
 }
 
 impl Session {
@@ -93,6 +257,144 @@ impl Session {
         SessionBuilder::new().spawn(command)
     }
 
+    /// Spawn a program with an explicit argument list (convenience method).
+    ///
+    /// This is a shorthand for `Session::builder().spawn_args(program, args)`;
+    /// see [`SessionBuilder::spawn_args`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::spawn_args("ssh", ["-o", "ConnectTimeout=5", "user@host"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_args<I, S>(
+        program: impl AsRef<std::ffi::OsStr>,
+        args: I,
+    ) -> Result<Self, ExpectError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        SessionBuilder::new().spawn_args(program, args)
+    }
+
+    /// Run a program inside a running container (convenience method).
+    ///
+    /// This is a shorthand for
+    /// `Session::builder().docker_exec(container, program, args)`; see
+    /// [`SessionBuilder::docker_exec`].
+    pub fn docker_exec<I, S>(
+        container: &str,
+        program: impl AsRef<std::ffi::OsStr>,
+        args: I,
+    ) -> Result<Self, ExpectError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        SessionBuilder::new().docker_exec(container, program, args)
+    }
+
+    /// Run a command line through the platform shell (convenience method).
+    ///
+    /// This is a shorthand for `Session::builder().spawn_shell(command)`; see
+    /// [`SessionBuilder::spawn_shell`] for how it differs from `spawn`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::spawn_shell("ls -l | sort -k5 -n")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_shell(command: &str) -> Result<Self, ExpectError> {
+        SessionBuilder::new().spawn_shell(command)
+    }
+
+    /// Open a serial device and return a session (convenience method).
+    ///
+    /// This is a shorthand for `Session::builder().open_serial(path, baud_rate)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The serial device (e.g., "/dev/ttyUSB0")
+    /// * `baud_rate` - The line speed (e.g., 115200)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::open_serial("/dev/ttyUSB0", 115200)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_serial(path: &str, baud_rate: u32) -> Result<Self, ExpectError> {
+        SessionBuilder::new().open_serial(path, baud_rate)
+    }
+
+    /// Connect to a telnet server (convenience method).
+    ///
+    /// This is a shorthand for `Session::builder().open_telnet(addr)`; see
+    /// [`SessionBuilder::open_telnet`].
+    #[cfg(feature = "telnet")]
+    pub fn open_telnet(addr: impl std::net::ToSocketAddrs) -> Result<Self, ExpectError> {
+        SessionBuilder::new().open_telnet(addr)
+    }
+
+    /// Attach to an already open PTY (convenience method).
+    ///
+    /// This is a shorthand for `Session::builder().attach_pty(master)`; see
+    /// [`SessionBuilder::attach_pty`].
+    pub fn from_pty(master: Box<dyn MasterPty + Send>) -> Result<Self, ExpectError> {
+        SessionBuilder::new().attach_pty(master)
+    }
+
+    /// Attach to an open file descriptor (convenience method).
+    ///
+    /// This is a shorthand for `Session::builder().attach_fd(fd)`; see
+    /// [`SessionBuilder::attach_fd`].
+    #[cfg(unix)]
+    pub fn from_fd(fd: std::os::fd::OwnedFd) -> Result<Self, ExpectError> {
+        SessionBuilder::new().attach_fd(fd)
+    }
+
+    /// Pattern factory applying the session's default regex options.
+    ///
+    /// See [`SessionBuilder::regex_options`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{RegexOptions, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder()
+    ///     .regex_options(RegexOptions::new().case_insensitive(true))
+    ///     .spawn("./installer")?;
+    /// let prompt = session.pattern().regex(r"continue\? \[y/n\]")?;
+    /// session.expect(prompt).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pattern(&self) -> PatternFactory {
+        PatternFactory::new(self.regex_options.clone())
+    }
+
+    /// The timeout applied to expect operations, or `None` if disabled.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
     /// Wait for a pattern to appear in the output.
     ///
     /// This method blocks until the pattern is matched, EOF is reached, or a timeout occurs.
@@ -168,7 +470,227 @@ impl Session {
     /// # }
     /// ```
     pub async fn expect_any(&mut self, patterns: &[Pattern]) -> Result<MatchResult, ExpectError> {
-        use crate::pattern::Matcher;
+        self.expect_any_within(patterns, self.timeout).await
+    }
+
+    /// Wait for any of several tagged patterns and return the matching tag.
+    ///
+    /// Behaves like [`Session::expect_any`], but identifies the pattern that
+    /// matched by its tag rather than its index. See [`TypedPatterns`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Session::expect_any`].
+    pub async fn expect_typed<T: Clone>(
+        &mut self,
+        patterns: &TypedPatterns<T>,
+    ) -> Result<(T, MatchResult), ExpectError> {
+        let result = self.expect_any(patterns.patterns()).await?;
+        let tag = patterns
+            .tag(result.pattern_index)
+            .cloned()
+            .expect("every pattern has a tag");
+        Ok((tag, result))
+    }
+
+    /// Wait for any of the given patterns, choosing how much output a match
+    /// consumes.
+    ///
+    /// By default a match consumes all output up to its end, so text before
+    /// the match can no longer be matched. This is a problem when another
+    /// pattern expected later needs interleaved content that arrived earlier;
+    /// [`Consume::MatchOnly`] removes just the matched text and
+    /// [`Consume::Nothing`] leaves the buffer untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Consume, Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("./build.sh")?;
+    /// // The warning may be printed before or after the summary
+    /// let summary = [Pattern::regex(r"\d+ tests passed")?];
+    /// session.expect_any_consuming(&summary, Consume::MatchOnly).await?;
+    /// session.expect(Pattern::exact("warning: deprecated")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_any_consuming(
+        &mut self,
+        patterns: &[Pattern],
+        consume: Consume,
+    ) -> Result<MatchResult, ExpectError> {
+        let options = ExpectOptions {
+            consume,
+            ..Default::default()
+        };
+        self.expect_any_with(patterns, self.timeout, &options).await
+    }
+
+    /// Wait for a pattern, choosing how much output the match consumes.
+    ///
+    /// See [`Session::expect_any_consuming`].
+    pub async fn expect_consuming(
+        &mut self,
+        pattern: Pattern,
+        consume: Consume,
+    ) -> Result<MatchResult, ExpectError> {
+        self.expect_any_consuming(&[pattern], consume).await
+    }
+
+    /// Wait for a pattern with per-call matching options.
+    ///
+    /// Tunes matching for a single call, such as ignoring case or limiting
+    /// the search to recent output, without changing the session or building
+    /// special patterns. See [`ExpectOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Session::expect`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{ExpectOptions, Pattern, Session};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ssh host")?;
+    /// // Matches "Password:", "password:", "PASSWORD:"
+    /// let options = ExpectOptions {
+    ///     case_insensitive: true,
+    ///     timeout: Some(Duration::from_secs(10)),
+    ///     ..Default::default()
+    /// };
+    /// session.expect_opts(Pattern::exact("password:"), options).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_opts(
+        &mut self,
+        pattern: Pattern,
+        options: ExpectOptions,
+    ) -> Result<MatchResult, ExpectError> {
+        self.expect_any_opts(&[pattern], options).await
+    }
+
+    /// Wait for any of the given patterns with per-call matching options.
+    ///
+    /// See [`Session::expect_opts`].
+    pub async fn expect_any_opts(
+        &mut self,
+        patterns: &[Pattern],
+        options: ExpectOptions,
+    ) -> Result<MatchResult, ExpectError> {
+        let timeout = options.timeout.or(self.timeout);
+        self.expect_any_with(patterns, timeout, &options).await
+    }
+
+    /// Like `expect_any`, but with an explicit timeout instead of the session's.
+    pub(crate) async fn expect_any_within(
+        &mut self,
+        patterns: &[Pattern],
+        timeout_duration: Option<Duration>,
+    ) -> Result<MatchResult, ExpectError> {
+        self.expect_any_with(patterns, timeout_duration, &ExpectOptions::default())
+            .await
+    }
+
+    async fn expect_any_with(
+        &mut self,
+        patterns: &[Pattern],
+        timeout_duration: Option<Duration>,
+        options: &ExpectOptions,
+    ) -> Result<MatchResult, ExpectError> {
+        let result = self
+            .find_any(patterns, timeout_duration, options, None, true)
+            .await;
+        self.record_expect(patterns, &result);
+        result
+    }
+
+    /// Like `expect_any_within`, but calls `interrupt` every `interval`
+    /// while waiting and gives up with `Ok(None)` when it returns true.
+    ///
+    /// The wait is recorded in history and events as one expect call, not
+    /// one per interval; an interrupted wait is not recorded. A soft timeout
+    /// hook is asked about the overall deadline, not the intervals.
+    /// `patterns` must not include `Pattern::Timeout`.
+    #[cfg(feature = "steps")]
+    pub(crate) async fn expect_any_interruptible(
+        &mut self,
+        patterns: &[Pattern],
+        timeout_duration: Option<Duration>,
+        interval: Duration,
+        mut interrupt: impl FnMut() -> bool,
+    ) -> Result<Option<MatchResult>, ExpectError> {
+        let start_time = std::time::Instant::now();
+        let options = ExpectOptions::default();
+        let mut timeout_duration = timeout_duration;
+        let mut extended = Duration::ZERO;
+        let mut output_since = self.raw.total_len();
+        let result = loop {
+            let mut slice = interval;
+            if let Some(timeout) = timeout_duration {
+                slice = slice.min(timeout.saturating_sub(start_time.elapsed()));
+            }
+            match self
+                .find_any(patterns, Some(slice), &options, None, false)
+                .await
+            {
+                Err(ExpectError::Timeout { .. }) => {}
+                result => break result,
+            }
+            if let Some(timeout) = timeout_duration.filter(|&t| start_time.elapsed() >= t) {
+                let Some(extension) =
+                    self.soft_timeout_extension(start_time.elapsed(), extended, output_since)
+                else {
+                    break Err(self.timeout_error(timeout));
+                };
+                extended += extension;
+                output_since = self.raw.total_len();
+                timeout_duration = Some(timeout.saturating_add(extension));
+            }
+            if interrupt() {
+                return Ok(None);
+            }
+        };
+        self.record_expect(patterns, &result);
+        result.map(Some)
+    }
+
+    /// Report the outcome of an expect call to events and history.
+    pub(crate) fn record_expect(
+        &mut self,
+        patterns: &[Pattern],
+        result: &Result<MatchResult, ExpectError>,
+    ) {
+        if let Some(events) = &self.events {
+            events.expect_result(result);
+        }
+        if let Some(history) = &mut self.history {
+            let position = self.buffer.matched_position();
+            history.expect(patterns, result, position, self.buffer.unmatched());
+        }
+    }
+
+    /// Wait for any of `patterns`.
+    ///
+    /// `max_output` fails the wait with `ExpectError::OutputLimit` once more
+    /// than that many bytes arrive without a match. `soft_timeout` says
+    /// whether `timeout_duration` is the caller's real deadline, which the
+    /// soft timeout hook may extend, rather than an internal polling
+    /// interval.
+    pub(crate) async fn find_any(
+        &mut self,
+        patterns: &[Pattern],
+        mut timeout_duration: Option<Duration>,
+        options: &ExpectOptions,
+        max_output: Option<usize>,
+        soft_timeout: bool,
+    ) -> Result<MatchResult, ExpectError> {
+        use crate::pattern::{LineMatcher, Matcher};
 
         // Build matchers for regular patterns
         let mut matchers: Vec<(usize, Box<dyn Matcher>)> = Vec::new();
@@ -182,47 +704,107 @@ impl Session {
                 Pattern::Timeout => has_timeout = true,
                 Pattern::FullBuffer => has_fullbuffer = true,
                 _ => {
-                    if let Ok(matcher) = pattern.to_matcher() {
+                    let matcher = if options.case_insensitive {
+                        pattern.to_matcher_ignoring_case()
+                    } else {
+                        pattern.to_matcher()
+                    };
+                    if let Ok(matcher) = matcher {
+                        let matcher: Box<dyn Matcher> = if options.whole_lines {
+                            Box::new(LineMatcher::new(matcher))
+                        } else {
+                            matcher
+                        };
                         matchers.push((idx, matcher));
                     }
                 }
             }
         }
 
-        let timeout_duration = self.timeout;
-
-        let mut read_buf = vec![0u8; 4096];
         let start_time = std::time::Instant::now();
+        let mut matching_time = Duration::ZERO;
+        let mut extended = Duration::ZERO;
+        let mut output_since = self.raw.total_len();
+        let first_byte = output_since;
+        #[cfg(feature = "diagnostics")]
+        {
+            self.diagnostics = Some(MatchDiagnostics::new(patterns.len()));
+        }
 
         loop {
+            self.run_auto_responses().await?;
+            self.check_critical()?;
+
             // Check for matches in current buffer
+            let search_start = std::time::Instant::now();
+            #[cfg(feature = "diagnostics")]
+            if let Some(diagnostics) = &mut self.diagnostics {
+                diagnostics.passes += 1;
+            }
             for (pattern_idx, matcher) in &matchers {
-                if let Some(m) = matcher.find(self.buffer.unmatched()) {
+                #[cfg(feature = "diagnostics")]
+                let attempt_start = std::time::Instant::now();
+                let found = match options.search_window {
+                    Some(window) => self.buffer.find_within(matcher.as_ref(), window),
+                    None => self.buffer.find(matcher.as_ref()),
+                };
+                #[cfg(feature = "diagnostics")]
+                if let Some(diagnostics) = &mut self.diagnostics {
+                    let stats = &mut diagnostics.patterns[*pattern_idx];
+                    stats.attempts += 1;
+                    stats.bytes_scanned += match options.search_window {
+                        Some(window) => self.buffer.window(window).len(),
+                        None => self.buffer.unmatched().len(),
+                    };
+                    stats.time += attempt_start.elapsed();
+                }
+                if let Some(m) = found {
                     // Found a match!
-                    let absolute_start = self.buffer.matched_position() + m.start;
-                    let absolute_end = self.buffer.matched_position() + m.end;
-
-                    let matched = String::from_utf8_lossy(
-                        &self.buffer.as_bytes()[absolute_start..absolute_end],
-                    )
-                    .into_owned();
+                    let absolute_start = m.start;
+                    let absolute_end = m.end;
 
-                    let before =
-                        String::from_utf8_lossy(self.buffer.before(absolute_start)).into_owned();
+                    let matched_bytes =
+                        self.buffer.as_bytes()[absolute_start..absolute_end].to_vec();
+                    let before_bytes = self.buffer.before(absolute_start).to_vec();
+                    if let Some(history) = &mut self.history {
+                        let position = self.buffer.matched_position();
+                        history.searched(position, self.buffer.unmatched());
+                    }
 
-                    self.buffer.mark_matched(absolute_end);
+                    match options.consume {
+                        Consume::ThroughMatch => self.buffer.mark_matched(absolute_end),
+                        Consume::MatchOnly => self.buffer.remove(absolute_start, absolute_end),
+                        Consume::Nothing => {}
+                    }
 
                     return Ok(MatchResult {
                         pattern_index: *pattern_idx,
-                        matched,
+                        matched: String::from_utf8_lossy(&matched_bytes).into_owned(),
                         start: absolute_start,
                         end: absolute_end,
-                        before,
+                        before: String::from_utf8_lossy(&before_bytes).into_owned(),
+                        matched_bytes,
+                        before_bytes,
                         captures: m.captures,
+                        captures_bytes: m.captures_bytes,
                     });
                 }
             }
 
+            matching_time += search_start.elapsed();
+            if let Some(budget) = self.match_budget.filter(|&b| matching_time > b) {
+                return Err(PatternError::Budget {
+                    budget,
+                    elapsed: matching_time,
+                }
+                .into());
+            }
+
+            let received = self.raw.total_len() - first_byte;
+            if let Some(limit) = max_output.filter(|&limit| received > limit as u64) {
+                return Err(ExpectError::OutputLimit { limit, received });
+            }
+
             // Check special patterns
             if self.eof_reached && has_eof {
                 let pattern_idx = patterns
@@ -235,7 +817,10 @@ impl Session {
                     start: self.buffer.len(),
                     end: self.buffer.len(),
                     before: self.buffer.as_str().to_owned(),
+                    matched_bytes: Vec::new(),
+                    before_bytes: self.buffer.as_bytes().to_vec(),
                     captures: vec![],
+                    captures_bytes: vec![],
                 });
             }
 
@@ -248,6 +833,21 @@ impl Session {
             // Check timeout
             if let Some(timeout) = timeout_duration {
                 if start_time.elapsed() >= timeout {
+                    if let Some(extension) = soft_timeout
+                        .then(|| {
+                            self.soft_timeout_extension(
+                                start_time.elapsed(),
+                                extended,
+                                output_since,
+                            )
+                        })
+                        .flatten()
+                    {
+                        extended += extension;
+                        output_since = self.raw.total_len();
+                        timeout_duration = Some(timeout.saturating_add(extension));
+                        continue;
+                    }
                     if has_timeout {
                         let pattern_idx = patterns
                             .iter()
@@ -259,10 +859,13 @@ impl Session {
                             start: self.buffer.len(),
                             end: self.buffer.len(),
                             before: self.buffer.as_str().to_owned(),
+                            matched_bytes: Vec::new(),
+                            before_bytes: self.buffer.as_bytes().to_vec(),
                             captures: vec![],
+                            captures_bytes: vec![],
                         });
                     } else {
-                        return Err(ExpectError::Timeout { duration: timeout });
+                        return Err(self.timeout_error(timeout));
                     }
                 }
             }
@@ -271,24 +874,21 @@ impl Session {
             let remaining_timeout =
                 timeout_duration.map(|t| t.saturating_sub(start_time.elapsed()));
 
-            match self
-                .read_with_timeout(&mut read_buf, remaining_timeout)
-                .await
-            {
-                Ok(0) => {
+            match self.reader.read(remaining_timeout).await {
+                Ok(chunk) if chunk.is_empty() => {
                     // EOF
                     self.eof_reached = true;
                     if !has_eof {
                         return Err(ExpectError::Eof);
                     }
                 }
-                Ok(n) => {
-                    self.buffer.append(&read_buf[..n])?;
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // No data available, continue loop
-                    tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(chunk) => {
+                    self.ingest(&chunk)?;
                 }
+                // The deadline passed; handled above, where a soft timeout
+                // hook may extend it
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::TimedOut && timeout_duration.is_some() => {}
                 Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
                     // Timeout from read operation
                     if has_timeout {
@@ -302,10 +902,11 @@ impl Session {
                             start: self.buffer.len(),
                             end: self.buffer.len(),
                             before: self.buffer.as_str().to_owned(),
+                            matched_bytes: Vec::new(),
+                            before_bytes: self.buffer.as_bytes().to_vec(),
                             captures: vec![],
+                            captures_bytes: vec![],
                         });
-                    } else if let Some(timeout) = timeout_duration {
-                        return Err(ExpectError::Timeout { duration: timeout });
                     } else {
                         return Err(ExpectError::IoError(e));
                     }
@@ -315,32 +916,452 @@ impl Session {
         }
     }
 
-    /// Read with timeout
-    async fn read_with_timeout(
+    /// Assert that the process produces no output for the given duration.
+    ///
+    /// Any output that arrives within the window causes an error. Reaching EOF
+    /// counts as silence. This is useful after cleanup steps in tests, to check
+    /// that a process is not still printing errors after `exit`.
+    ///
+    /// Output that arrived before the call is not considered, even if it has
+    /// not been read into the buffer yet; it is added to the buffer first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::UnexpectedOutput` with the received text if any
+    /// bytes arrive before the window elapses.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut session = Session::spawn("bash")?;
+    /// session.send_line("exit").await?;
+    /// session.assert_no_output(Duration::from_millis(500)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn assert_no_output(&mut self, duration: Duration) -> Result<(), ExpectError> {
+        self.ingest_available()?;
+        let start_time = std::time::Instant::now();
+
+        while !self.eof_reached {
+            let remaining = duration.saturating_sub(start_time.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+
+            match self.reader.read(Some(remaining)).await {
+                Ok(chunk) if chunk.is_empty() => self.eof_reached = true,
+                Ok(chunk) => {
+                    self.ingest(&chunk)?;
+                    return Err(ExpectError::UnexpectedOutput {
+                        output: self
+                            .redact_text(String::from_utf8_lossy(&chunk))
+                            .into_owned(),
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => break,
+                Err(e) => return Err(ExpectError::IoError(e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for a pattern to appear in a region of the screen.
+    ///
+    /// Requires the screen model to be enabled with
+    /// [`SessionBuilder::screen`]. The pattern is matched against the text
+    /// currently displayed in the region (see [`ScreenPattern`]) each time
+    /// new output arrives. The output buffer is not consumed.
+    ///
+    /// In the returned `MatchResult`, positions are byte offsets into the
+    /// region text and `before` is the region text preceding the match.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the screen model is disabled, the pattern is
+    /// invalid, or the timeout or EOF is reached before the region matches.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Rect, ScreenPattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().screen(true).spawn("top")?;
+    /// let header = ScreenPattern::at(Rect::rows(0, 5), Pattern::regex(r"Tasks: \d+")?);
+    /// let result = session.expect_screen(&header).await?;
+    /// println!("{}", result.matched);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_screen(
         &mut self,
-        buf: &mut [u8],
-        timeout: Option<Duration>,
-    ) -> std::io::Result<usize> {
-        let reader = self.master_reader.clone();
-        let buf_len = buf.len();
-
-        let read_future = tokio::task::spawn_blocking(move || {
-            let mut reader = reader.blocking_lock();
-            let mut temp_buf = vec![0u8; buf_len];
-            reader.read(&mut temp_buf).map(|n| (n, temp_buf))
-        });
-
-        let result = if let Some(timeout) = timeout {
-            tokio::time::timeout(timeout, read_future)
-                .await
-                .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "Read timeout"))??
-        } else {
-            read_future.await.map_err(std::io::Error::other)?
-        }?;
+        pattern: &ScreenPattern,
+    ) -> Result<MatchResult, ExpectError> {
+        let matcher = pattern.pattern.to_matcher()?;
+        let start_time = std::time::Instant::now();
+
+        loop {
+            let screen = self.screen.as_ref().ok_or(ExpectError::ScreenDisabled)?;
+            let text = screen.region_text(pattern.region);
+            if let Some(m) = matcher.find(text.as_bytes()) {
+                let bytes = text.as_bytes();
+                return Ok(MatchResult {
+                    pattern_index: 0,
+                    matched: String::from_utf8_lossy(&bytes[m.start..m.end]).into_owned(),
+                    start: m.start,
+                    end: m.end,
+                    before: String::from_utf8_lossy(&bytes[..m.start]).into_owned(),
+                    matched_bytes: bytes[m.start..m.end].to_vec(),
+                    before_bytes: bytes[..m.start].to_vec(),
+                    captures: m.captures,
+                    captures_bytes: m.captures_bytes,
+                });
+            }
+
+            let remaining = self.timeout.map(|t| t.saturating_sub(start_time.elapsed()));
+            self.read_screen_update(remaining).await?;
+        }
+    }
+
+    /// Read one chunk of output into the buffer and screen model.
+    ///
+    /// Fails with `Eof` once the process has closed its output, or `Timeout`
+    /// if nothing arrives within `remaining`.
+    async fn read_screen_update(&mut self, remaining: Option<Duration>) -> Result<(), ExpectError> {
+        if self.eof_reached {
+            return Err(ExpectError::Eof);
+        }
+
+        match self.reader.read(remaining).await {
+            Ok(chunk) if chunk.is_empty() => {
+                self.eof_reached = true;
+                Err(ExpectError::Eof)
+            }
+            Ok(chunk) => self.ingest(&chunk),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                Err(self.timeout_error(self.timeout.unwrap_or_default()))
+            }
+            Err(e) => Err(ExpectError::IoError(e)),
+        }
+    }
+
+    /// The emulated terminal screen, if enabled with [`SessionBuilder::screen`].
+    pub fn screen(&self) -> Option<&Screen> {
+        self.screen.as_ref()
+    }
+
+    /// The transcript recorded so far, if enabled with
+    /// [`SessionBuilder::transcript`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Origin, Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().transcript(true).spawn("bash")?;
+    /// session.send_line("make deploy").await?;
+    /// session.interact().await?;
+    ///
+    /// let transcript = session.transcript().unwrap();
+    /// println!("{transcript}");
+    /// let typed = transcript.bytes_from(Origin::Human);
+    /// println!("user typed: {}", String::from_utf8_lossy(&typed));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_ref()
+    }
+
+    /// Number of bytes of output left out of the log file so far by
+    /// [`SessionBuilder::log_rate_limit`].
+    pub fn log_suppressed(&self) -> u64 {
+        self.log.as_ref().map_or(0, TranscriptLog::suppressed)
+    }
+
+    /// The matching buffer: the output that patterns are searched in.
+    ///
+    /// Escape sequences are stripped and progress lines collapsed here if
+    /// configured, and matches consume it. See [`Session::raw_output`] for
+    /// the unfiltered output.
+    pub fn buffer(&self) -> &BufferManager {
+        &self.buffer
+    }
+
+    /// The output not yet consumed by a match, without consuming it.
+    ///
+    /// Output that has already arrived is added to the buffer first, without
+    /// waiting for more, so the result is what the next expect call would
+    /// start searching. Useful to log or branch on pending output before
+    /// committing to a pattern. Invalid UTF-8 is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the output fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("./installer")?;
+    /// session.send_line("y").await?;
+    /// if session.peek()?.contains("reboot required") {
+    ///     session.expect(Pattern::exact("Reboot now? ")).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn peek(&mut self) -> Result<String, ExpectError> {
+        self.ingest_available()?;
+        Ok(String::from_utf8_lossy(self.buffer.unmatched()).into_owned())
+    }
+
+    /// Number of bytes in the buffer not yet consumed by a match.
+    ///
+    /// Output that arrived since the last expect call is not counted until
+    /// something reads it, such as [`Session::peek`].
+    pub fn buffer_len(&self) -> usize {
+        self.buffer.unmatched().len()
+    }
+
+    /// Discard all output received so far.
+    ///
+    /// Output that has already arrived is discarded too, so the next expect
+    /// call only sees output produced from now on. Use this between phases
+    /// of an interaction, such as after logging in, so later patterns cannot
+    /// match stale text like a login banner.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the output fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ssh admin@host")?;
+    /// session.expect(Pattern::exact("$ ")).await?;
+    /// // The banner mentions "error"; don't let it match later
+    /// session.clear_buffer()?;
+    /// session.send_line("./check.sh").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clear_buffer(&mut self) -> Result<(), ExpectError> {
+        self.ingest_available()?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Remove and return all output not yet consumed by a match.
+    ///
+    /// Like [`Session::peek`] followed by [`Session::clear_buffer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the output fails.
+    pub fn take_buffer(&mut self) -> Result<String, ExpectError> {
+        let pending = self.peek()?;
+        self.buffer.clear();
+        Ok(pending)
+    }
+
+    /// Add output that has already arrived to the buffer, without waiting.
+    fn ingest_available(&mut self) -> Result<(), ExpectError> {
+        while let Some(next) = self.reader.try_read() {
+            let chunk = next?;
+            if chunk.is_empty() {
+                self.eof_reached = true;
+                break;
+            }
+            self.ingest(&chunk)?;
+        }
+        Ok(())
+    }
+
+    /// The most recent output exactly as the process printed it.
+    ///
+    /// Retains up to the maximum buffer size, regardless of
+    /// [`SessionBuilder::strip_ansi`] or matches consuming the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().strip_ansi(true).spawn("ls --color=always")?;
+    /// session.expect(Pattern::Eof).await?;
+    /// println!("{:?}", String::from_utf8_lossy(session.raw_output().as_bytes()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn raw_output(&self) -> &RawStream {
+        &self.raw
+    }
+
+    /// The last `n` bytes of output exactly as the process printed them.
+    ///
+    /// Shorthand for `raw_output().tail(n)`. Useful when a pattern does not
+    /// match because of escape sequences that `strip_ansi` or the terminal
+    /// hide.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Escaped, ExpectError, Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().strip_ansi(true).spawn("./installer")?;
+    /// if let Err(ExpectError::Timeout { .. }) = session.expect(Pattern::exact("Done")).await {
+    ///     eprintln!("last output: {}", Escaped(session.raw_tail(200)));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn raw_tail(&self, n: usize) -> &[u8] {
+        self.raw.tail(n)
+    }
+
+    /// A readable report of an error from this session, showing the end of
+    /// the buffer as context.
+    ///
+    /// Payloads sent with [`send_secret`](Self::send_secret) are replaced
+    /// with `****` in the error message, output and screen shown.
+    ///
+    /// See [`ExpectError::report`] and [`ExpectError::exit_code`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("./deploy.sh")?;
+    /// if let Err(e) = session.expect(Pattern::exact("deployed")).await {
+    ///     eprint!("{}", session.report(&e));
+    ///     std::process::exit(e.exit_code().into());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn report<'a>(&'a self, error: &'a ExpectError) -> Report<'a> {
+        let bytes = self.buffer.as_bytes();
+        let context = &bytes[bytes.len().saturating_sub(REPORT_CONTEXT_BYTES)..];
+        let report = error
+            .report(context)
+            .message(self.redact_text(error.to_string()).into_owned())
+            .output(self.redact(context));
+        // A screenshot taken when the timeout occurred is more accurate than
+        // the current screen
+        match (&self.screen, &self.timeout_screen, error) {
+            (_, Some(screen), ExpectError::Timeout { .. }) => {
+                report.screen(self.redact_text(screen.as_str()))
+            }
+            (None, _, _) => report,
+            (Some(screen), _, _) => report.screen(self.redact_text(screen.contents())),
+        }
+    }
+
+    /// The screen contents when the last expect call timed out.
+    ///
+    /// Taken at the moment the wait gave up, so a failing full-screen
+    /// automation shows what was actually displayed even if the screen has
+    /// changed since. [`report`](Self::report) shows it for timeout errors.
+    /// Secrets sent with [`send_secret`](Self::send_secret) are replaced
+    /// with `****`. Returns `None` if the screen model is disabled or no
+    /// call has timed out.
+    pub fn timeout_screen(&self) -> Option<&str> {
+        self.timeout_screen.as_deref()
+    }
+
+    /// Statistics about the matching work done by the last expect call.
+    ///
+    /// Covers calls that search the session buffer, whether they matched or
+    /// not; screen matches are not counted. Returns `None` before the first
+    /// such call.
+    #[cfg(feature = "diagnostics")]
+    pub fn match_diagnostics(&self) -> Option<&MatchDiagnostics> {
+        self.diagnostics.as_ref()
+    }
 
-        let (n, temp_buf) = result;
-        buf[..n].copy_from_slice(&temp_buf[..n]);
-        Ok(n)
+    /// A timeout error, taking a screenshot if the screen model is enabled.
+    pub(crate) fn timeout_error(&mut self, duration: Duration) -> ExpectError {
+        self.timeout_screen = self
+            .screen
+            .as_ref()
+            .map(|screen| self.redact_text(screen.contents()).into_owned());
+        ExpectError::Timeout { duration }
+    }
+
+    /// Feed output from the process to every layer except the matching
+    /// buffer.
+    fn observe(&mut self, chunk: &[u8]) {
+        self.raw.append(chunk);
+        if let Some(screen) = &mut self.screen {
+            screen.process(chunk);
+        }
+        if let Some(transcript) = &mut self.transcript {
+            transcript.record(Origin::Process, chunk);
+        }
+        if let Some(log) = &mut self.log {
+            log.record(Origin::Process, chunk);
+        }
+        if let Some(history) = &mut self.history {
+            history.output(chunk);
+        }
+        if self.echo_output {
+            // Errors are ignored, as for the log file
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(chunk);
+            let _ = stdout.flush();
+        }
+        self.observers.output(chunk);
+        if let Some(events) = &self.events {
+            events.output(chunk);
+        }
+    }
+
+    /// Read output arriving within `wait` into the buffer without matching
+    /// anything, answering automatic responses.
+    ///
+    /// Returns `false` once the process has closed its output.
+    #[cfg(feature = "server")]
+    pub(crate) async fn pump(&mut self, wait: Duration) -> Result<bool, ExpectError> {
+        self.run_auto_responses().await?;
+        if self.eof_reached {
+            return Ok(false);
+        }
+        match self.reader.read(Some(wait)).await {
+            Ok(chunk) if chunk.is_empty() => {
+                self.eof_reached = true;
+                Ok(false)
+            }
+            Ok(chunk) => {
+                self.ingest(&chunk)?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(true),
+            Err(e) => Err(ExpectError::IoError(e)),
+        }
+    }
+
+    /// Feed output from the process to all layers.
+    fn ingest(&mut self, chunk: &[u8]) -> Result<(), ExpectError> {
+        self.observe(chunk);
+        self.buffer.append(chunk)?;
+        Ok(())
     }
 
     /// Send data to the process.
@@ -415,7 +1436,85 @@ impl Session {
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// # Character Delays
+    ///
+    /// If character delays are configured (see [`SessionBuilder::char_delay`]),
+    /// the data is written up to and including each delayed byte, followed by
+    /// a pause of the configured length before the rest is written.
     pub async fn send(&mut self, data: &[u8]) -> Result<(), ExpectError> {
+        self.send_from(Origin::Automation, data).await
+    }
+
+    /// Send data to the process, recording it in the transcript as coming
+    /// from `origin`.
+    async fn send_from(&mut self, origin: Origin, data: &[u8]) -> Result<(), ExpectError> {
+        self.send_recorded(origin, data, data).await
+    }
+
+    /// Send `data` to the process, recording `recorded` in its place.
+    async fn send_recorded(
+        &mut self,
+        origin: Origin,
+        data: &[u8],
+        recorded: &[u8],
+    ) -> Result<(), ExpectError> {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.record(origin, recorded);
+        }
+        if let Some(log) = &mut self.log {
+            log.record(origin, recorded);
+        }
+        self.observers.input(recorded);
+        if let Some(events) = &self.events {
+            events.input(origin, recorded);
+        }
+        if self.char_delays.is_empty() {
+            return self.write_all(data).await;
+        }
+
+        let mut rest = data;
+        while !rest.is_empty() {
+            let split = rest
+                .iter()
+                .position(|b| self.char_delays.contains_key(b))
+                .map_or(rest.len(), |i| i + 1);
+            let (chunk, tail) = rest.split_at(split);
+            self.write_all(chunk).await?;
+            if let Some(delay) = self.char_delays.get(&chunk[chunk.len() - 1]) {
+                tokio::time::sleep(*delay).await;
+            }
+            rest = tail;
+        }
+
+        Ok(())
+    }
+
+    /// Set the pause after sending a specific byte, or remove it with `None`.
+    ///
+    /// See [`SessionBuilder::char_delay`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::open_serial("/dev/ttyUSB0", 9600)?;
+    /// session.set_char_delay(b'\r', Some(Duration::from_millis(100)));
+    /// session.send(b"reset\r").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_char_delay(&mut self, byte: u8, delay: Option<Duration>) {
+        match delay {
+            Some(delay) => self.char_delays.insert(byte, delay),
+            None => self.char_delays.remove(&byte),
+        };
+    }
+
+    async fn write_all(&mut self, data: &[u8]) -> Result<(), ExpectError> {
         let writer = self.master_writer.clone();
         let data = data.to_vec();
 
@@ -430,6 +1529,218 @@ impl Session {
         Ok(())
     }
 
+    /// Send a break signal on a serial session.
+    ///
+    /// Holds the line in the break condition for `duration`, which many
+    /// bootloaders and consoles interpret as an attention signal (for example
+    /// the SysRq prefix on Linux serial consoles).
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Unsupported` I/O error if the session is not a serial session.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::open_serial("/dev/ttyUSB0", 115200)?;
+    /// session.send_break(Duration::from_millis(250)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_break(&mut self, duration: Duration) -> Result<(), ExpectError> {
+        self.serial_port()?.set_break(true)?;
+        tokio::time::sleep(duration).await;
+        self.serial_port()?.set_break(false)?;
+        Ok(())
+    }
+
+    /// Assert (`true`) or clear (`false`) the DTR line of a serial session.
+    ///
+    /// Toggling DTR and RTS is the usual way to reset a board or force it
+    /// into its bootloader.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Unsupported` I/O error if the session is not a serial session.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::open_serial("/dev/ttyUSB0", 115200)?;
+    /// // Pulse reset
+    /// session.set_dtr(false)?;
+    /// tokio::time::sleep(Duration::from_millis(100)).await;
+    /// session.set_dtr(true)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_dtr(&mut self, level: bool) -> Result<(), ExpectError> {
+        self.serial_port()?.set_dtr(level)?;
+        Ok(())
+    }
+
+    /// Assert (`true`) or clear (`false`) the RTS line of a serial session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Unsupported` I/O error if the session is not a serial session.
+    pub fn set_rts(&mut self, level: bool) -> Result<(), ExpectError> {
+        self.serial_port()?.set_rts(level)?;
+        Ok(())
+    }
+
+    /// Change the baud rate of a serial session.
+    ///
+    /// Bootloaders often start at a low speed and switch to a faster one on
+    /// request; this changes the local side to follow.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session is not a serial session or the rate is
+    /// not supported by the platform.
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), ExpectError> {
+        self.serial_port()?.set_baud_rate(baud_rate)?;
+        Ok(())
+    }
+
+    /// The file descriptor of the PTY master, or of the device for a serial
+    /// session.
+    ///
+    /// This is for integrating with code the session does not cover: `ioctl`
+    /// calls such as `TIOCSTI` or `TIOCPKT`, terminal attributes, or
+    /// registering the descriptor with another event loop. The descriptor
+    /// stays owned by the session and is closed when it is dropped.
+    ///
+    /// The session reads from this descriptor on a background thread, so
+    /// reading from it directly races with the session and steals output
+    /// from the buffer. Data written to it directly bypasses character
+    /// delays and the transcript.
+    ///
+    /// Returns `None` if the PTY implementation does not expose a
+    /// descriptor. There is no Windows equivalent, since the ConPTY handle is
+    /// not exposed by the PTY backend.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session = Session::spawn("bash")?;
+    /// if let Some(fd) = session.as_raw_fd() {
+    ///     let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    ///     unsafe { libc::tcgetattr(fd, &mut termios) };
+    ///     println!("echo enabled: {}", termios.c_lflag & libc::ECHO != 0);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn as_raw_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        use std::os::unix::io::AsRawFd;
+
+        match (&self._master, &self.serial, &self.attached) {
+            (Some(master), _, _) => master.as_raw_fd(),
+            (None, Some(serial), _) => Some(serial.as_raw_fd()),
+            (None, None, Some(file)) => Some(file.as_raw_fd()),
+            (None, None, None) => None,
+        }
+    }
+
+    /// Change the terminal size of the PTY.
+    ///
+    /// The process is notified with `SIGWINCH` on Unix, so full-screen
+    /// applications can redraw for the new size. On telnet sessions the new
+    /// size is reported to the server (NAWS) if it asked for the window
+    /// size. If the screen model is enabled it is resized to match.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::PtyError` for serial sessions, which have no
+    /// terminal size, or if the resize fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().screen(true).spawn("htop")?;
+    /// session.resize(50, 160)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resize(&mut self, rows: u16, cols: u16) -> Result<(), ExpectError> {
+        self.resize_terminal(rows, cols)?;
+        if let Some(screen) = &mut self.screen {
+            screen.resize(rows, cols);
+        }
+        Ok(())
+    }
+
+    /// Resize the PTY, or report the size to a telnet server.
+    fn resize_terminal(&self, rows: u16, cols: u16) -> Result<(), ExpectError> {
+        #[cfg(feature = "telnet")]
+        if let Some(telnet) = &self.telnet {
+            return Ok(telnet.resize(rows, cols)?);
+        }
+
+        let master = self
+            ._master
+            .as_ref()
+            .ok_or_else(|| ExpectError::PtyError("Session has no PTY to resize".to_string()))?;
+        master
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| ExpectError::PtyError(e.to_string()))
+    }
+
+    /// Remove and return the PTY control events reported since the last call.
+    ///
+    /// Events are only reported when packet mode is enabled with
+    /// [`SessionBuilder::packet_mode`]; otherwise this is always empty. They
+    /// arrive as the PTY reports them, independently of the output buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{PtyEvent, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().packet_mode(true).spawn("less big.log")?;
+    /// session.send(b"\x13").await?; // ^S
+    /// # tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    /// if session.take_pty_events().contains(&PtyEvent::Stop) {
+    ///     println!("output is stopped");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take_pty_events(&mut self) -> Vec<PtyEvent> {
+        self.reader.take_events()
+    }
+
+    fn serial_port(&self) -> Result<&SerialPort, ExpectError> {
+        self.serial.as_ref().ok_or_else(|| {
+            ExpectError::IoError(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Not a serial session",
+            ))
+        })
+    }
+
     /// Send a line to the process (appends newline).
     ///
     /// Convenience method that sends the given string followed by a newline character.
@@ -487,6 +1798,14 @@ impl Session {
         }
     }
 
+    /// The process ID of the spawned process.
+    ///
+    /// Returns `None` for serial sessions, after [`wait`](Self::wait), or if
+    /// the platform does not report it.
+    pub fn pid(&self) -> Option<u32> {
+        self.child.as_ref().and_then(|child| child.process_id())
+    }
+
     /// Wait for the process to exit and return its exit status.
     ///
     /// This method blocks until the process exits. After calling this method,
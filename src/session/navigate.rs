@@ -0,0 +1,113 @@
+//! Key presses and cursor navigation for full-screen applications
+
+use crate::result::ExpectError;
+use crate::screen::Key;
+use crate::session::Session;
+use std::collections::HashSet;
+use std::time::Instant;
+
+impl Session {
+    /// Send a key `count` times.
+    ///
+    /// When the screen model is enabled, arrow keys follow the cursor key
+    /// mode the application selected; otherwise the normal mode encoding is
+    /// used.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Key, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().screen(true).spawn("./menu")?;
+    /// session.press(Key::Down, 2).await?;
+    /// session.press(Key::Enter, 1).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn press(&mut self, key: Key, count: usize) -> Result<(), ExpectError> {
+        let application_cursor = self
+            .screen
+            .as_ref()
+            .is_some_and(|screen| screen.application_cursor());
+        let data = key.to_bytes(application_cursor).repeat(count);
+        self.send(&data).await
+    }
+
+    /// Cursor position on the emulated screen as 0-based `(row, col)`.
+    ///
+    /// Reflects the output received so far; call after an `expect` or
+    /// [`Session::expect_screen`] to see the effect of recent input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::ScreenDisabled` if the screen model is not
+    /// enabled.
+    pub fn cursor(&self) -> Result<(u16, u16), ExpectError> {
+        self.screen
+            .as_ref()
+            .map(|screen| screen.cursor())
+            .ok_or(ExpectError::ScreenDisabled)
+    }
+
+    /// Move the cursor to `(row, col)` with the arrow keys.
+    ///
+    /// Presses one arrow key at a time, rows first, and waits for the cursor
+    /// to move before pressing the next. This suits menus and forms where the
+    /// application places the cursor on the selected item, such as installers
+    /// built on dialog or newt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The screen model is not enabled
+    /// - The cursor does not move within the session timeout
+    /// - The cursor returns to a position it already visited, meaning the
+    ///   target cannot be reached (`ExpectError::NavigationFailed`)
+    /// - The process exits
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Key, Pattern, ScreenPattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().screen(true).spawn("./installer")?;
+    /// let menu = ScreenPattern::anywhere(Pattern::exact("Install"));
+    /// session.expect_screen(&menu).await?;
+    /// session.navigate_to(12, 20).await?;
+    /// session.press(Key::Enter, 1).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn navigate_to(&mut self, row: u16, col: u16) -> Result<(), ExpectError> {
+        let start_time = Instant::now();
+        let mut visited = HashSet::new();
+
+        loop {
+            let cursor = self.cursor()?;
+            if cursor == (row, col) {
+                return Ok(());
+            }
+            if !visited.insert(cursor) {
+                return Err(ExpectError::NavigationFailed { row, col, cursor });
+            }
+
+            let key = if cursor.0 < row {
+                Key::Down
+            } else if cursor.0 > row {
+                Key::Up
+            } else if cursor.1 < col {
+                Key::Right
+            } else {
+                Key::Left
+            };
+            self.press(key, 1).await?;
+
+            while self.cursor()? == cursor {
+                let remaining = self.timeout.map(|t| t.saturating_sub(start_time.elapsed()));
+                self.read_screen_update(remaining).await?;
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Callbacks observing the data exchanged with the process
+
+use crate::session::Session;
+
+type Observer = Box<dyn FnMut(&[u8]) + Send>;
+
+/// Callbacks registered with [`Session::on_output`] and [`Session::on_input`].
+#[derive(Default)]
+pub(crate) struct Observers {
+    output: Vec<Observer>,
+    input: Vec<Observer>,
+}
+
+impl Observers {
+    /// Pass output read from the process to the output observers.
+    pub(crate) fn output(&mut self, data: &[u8]) {
+        for observer in &mut self.output {
+            observer(data);
+        }
+    }
+
+    /// Pass input sent to the process to the input observers.
+    pub(crate) fn input(&mut self, data: &[u8]) {
+        for observer in &mut self.input {
+            observer(data);
+        }
+    }
+}
+
+impl Session {
+    /// Call `observer` with every chunk of output read from the process.
+    ///
+    /// Output is passed as it is read, while expect calls (or
+    /// [`interact`](Self::interact)) drive the session, exactly as the
+    /// process printed it: before ANSI stripping or progress collapsing, and
+    /// regardless of what matches consume. Chunk boundaries are arbitrary,
+    /// so a line may arrive in several calls. Nothing is read, and so
+    /// nothing observed, while the session is idle.
+    ///
+    /// The observer runs on the task driving the session and should return
+    /// quickly; forward the data to a channel for slow consumers.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    /// let mut session = Session::spawn("./build.sh")?;
+    /// session.on_output(move |bytes| {
+    ///     let _ = tx.send(bytes.to_vec());
+    /// });
+    /// tokio::spawn(async move {
+    ///     while let Some(bytes) = rx.recv().await {
+    ///         print!("{}", String::from_utf8_lossy(&bytes));
+    ///     }
+    /// });
+    ///
+    /// session.expect(Pattern::exact("BUILD SUCCESSFUL")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_output(&mut self, observer: impl FnMut(&[u8]) + Send + 'static) {
+        self.observers.output.push(Box::new(observer));
+    }
+
+    /// Start or stop mirroring output to standard output.
+    ///
+    /// See [`SessionBuilder::echo_output`](crate::SessionBuilder::echo_output).
+    pub fn set_echo_output(&mut self, echo: bool) {
+        self.echo_output = echo;
+    }
+
+    /// Whether output is mirrored to standard output.
+    pub fn echo_output(&self) -> bool {
+        self.echo_output
+    }
+
+    /// Call `observer` with all data sent to the process.
+    ///
+    /// This includes data sent by the program, such as with
+    /// [`send`](Self::send) and automatic responses, and input typed by the
+    /// user during [`interact`](Self::interact). See
+    /// [`on_output`](Self::on_output).
+    pub fn on_input(&mut self, observer: impl FnMut(&[u8]) + Send + 'static) {
+        self.observers.input.push(Box::new(observer));
+    }
+
+    /// Remove all observers registered with [`on_output`](Self::on_output)
+    /// and [`on_input`](Self::on_input).
+    pub fn clear_observers(&mut self) {
+        self.observers = Observers::default();
+    }
+}
@@ -0,0 +1,94 @@
+//! PTY packet mode and the control events it reports
+
+use std::io;
+
+/// Control event reported by the PTY in packet mode.
+///
+/// Enabled with [`SessionBuilder::packet_mode`](crate::SessionBuilder::packet_mode)
+/// and collected with [`Session::take_pty_events`](crate::Session::take_pty_events).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PtyEvent {
+    /// The process discarded its unread input.
+    FlushRead,
+    /// The process discarded its unsent output.
+    FlushWrite,
+    /// Output was stopped, e.g. by a `^S` (XOFF).
+    Stop,
+    /// Output was restarted, e.g. by a `^Q` (XON).
+    Start,
+    /// The process turned off XON/XOFF flow control, or changed the
+    /// stop/start characters away from `^S`/`^Q`.
+    NoStop,
+    /// The process turned on XON/XOFF flow control with `^S`/`^Q`.
+    DoStop,
+}
+
+/// Status bits of a packet mode control byte, in the order events are reported.
+const STATUS_BITS: [(u8, PtyEvent); 6] = [
+    (0x01, PtyEvent::FlushRead),
+    (0x02, PtyEvent::FlushWrite),
+    (0x04, PtyEvent::Stop),
+    (0x08, PtyEvent::Start),
+    (0x10, PtyEvent::NoStop),
+    (0x20, PtyEvent::DoStop),
+];
+
+/// Split a packet read in packet mode into its data and control events.
+///
+/// Each read returns one packet: a status byte that is zero for data,
+/// followed by the data; or a non-zero status byte of event bits alone.
+pub(crate) fn parse_packet(packet: &[u8]) -> (&[u8], Vec<PtyEvent>) {
+    match packet.split_first() {
+        Some((0, data)) => (data, Vec::new()),
+        Some((&status, _)) => {
+            let events = STATUS_BITS
+                .iter()
+                .filter(|(bit, _)| status & bit != 0)
+                .map(|&(_, event)| event)
+                .collect();
+            (&[], events)
+        }
+        None => (&[], Vec::new()),
+    }
+}
+
+/// Put the PTY master referred to by `fd` into packet mode.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+pub(crate) fn enable(fd: std::os::unix::io::RawFd) -> io::Result<()> {
+    let on: libc::c_int = 1;
+    // SAFETY: TIOCPKT reads a single int from the pointer, which is valid
+    if unsafe { libc::ioctl(fd, libc::TIOCPKT as _, &on) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "linux", target_os = "android", target_os = "macos"))
+))]
+pub(crate) fn enable(_fd: std::os::unix::io::RawFd) -> io::Result<()> {
+    Err(unsupported())
+}
+
+pub(crate) fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "PTY packet mode is not supported on this platform",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_packet() {
+        assert_eq!(parse_packet(b"\0hello"), (&b"hello"[..], vec![]));
+        assert_eq!(parse_packet(b"\0"), (&b""[..], vec![]));
+        assert_eq!(
+            parse_packet(&[0x04 | 0x01]),
+            (&b""[..], vec![PtyEvent::FlushRead, PtyEvent::Stop])
+        );
+    }
+}
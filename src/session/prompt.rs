@@ -0,0 +1,201 @@
+//! Learning and matching the shell prompt
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, MatchResult};
+use crate::session::{Session, DEFAULT_TIMEOUT_SECS};
+use std::time::Duration;
+
+/// Silence after which a prompt sample is complete.
+const SAMPLE_QUIET: Duration = Duration::from_millis(300);
+
+impl Session {
+    /// Learn the shell prompt and make it the session's prompt.
+    ///
+    /// Run this once the shell is ready, typically right after login.
+    /// Pending output is discarded, then a bare newline is sent twice and
+    /// the last line printed each time is taken as a sample of the prompt.
+    /// Identical samples are matched exactly; where they differ, such as a
+    /// history counter or the time, any text is allowed. The learned
+    /// pattern only matches the prompt at the end of the output.
+    ///
+    /// This replaces hard-coded patterns like `"$ "` that break on a
+    /// customized `PS1`. Use [`expect_prompt`](Self::expect_prompt)
+    /// afterwards, which also learns the prompt itself when first called.
+    ///
+    /// # Returns
+    ///
+    /// The learned prompt pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::PromptNotLearned` if the samples show no
+    /// common prompt, and `ExpectError::Timeout` if output does not settle
+    /// within the session timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Credentials, LoginSpec, Session};
+    ///
+    /// # async fn example(spec: LoginSpec) -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ssh admin@server")?;
+    /// session.login(&spec, &Credentials::new("admin", "secret")).await?;
+    /// session.learn_prompt().await?;
+    ///
+    /// session.send_line("uptime").await?;
+    /// let result = session.expect_prompt().await?;
+    /// println!("{}", result.before);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn learn_prompt(&mut self) -> Result<Pattern, ExpectError> {
+        let max_wait = self
+            .timeout
+            .unwrap_or(Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+        self.settle(max_wait, SAMPLE_QUIET).await?;
+        self.send_line("").await?;
+        let first = self.settle(max_wait, SAMPLE_QUIET).await?;
+        self.send_line("").await?;
+        let second = self.settle(max_wait, SAMPLE_QUIET).await?;
+
+        let Some(prompt) = prompt_from_samples(&first, &second) else {
+            let samples = [first, second]
+                .into_iter()
+                .map(|sample| self.redact_text(sample).into_owned())
+                .collect();
+            return Err(ExpectError::PromptNotLearned { samples });
+        };
+        self.prompt = Some(prompt.clone());
+        Ok(prompt)
+    }
+
+    /// Set the session's prompt, as used by
+    /// [`expect_prompt`](Self::expect_prompt).
+    pub fn set_prompt(&mut self, prompt: Pattern) {
+        self.prompt = Some(prompt);
+    }
+
+    /// The session's prompt, if learned or set.
+    pub fn prompt(&self) -> Option<&Pattern> {
+        self.prompt.as_ref()
+    }
+
+    /// Wait for the session's prompt, learning it first if needed.
+    ///
+    /// If no prompt was learned with [`learn_prompt`](Self::learn_prompt)
+    /// or set with [`set_prompt`](Self::set_prompt), it is learned now and
+    /// a bare newline is sent to bring up a fresh prompt to match; the
+    /// output before the first call is discarded while learning. Make the
+    /// first call while the shell is idle, typically right after login.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::PromptNotLearned` if the prompt had to be
+    /// learned and could not be, otherwise the same errors as
+    /// [`Session::expect`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("bash")?;
+    /// session.expect_prompt().await?;
+    ///
+    /// session.send_line("make install").await?;
+    /// let result = session.expect_prompt().await?;
+    /// println!("{}", result.before);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn expect_prompt(&mut self) -> Result<MatchResult, ExpectError> {
+        let prompt = match self.prompt.clone() {
+            Some(prompt) => prompt,
+            None => {
+                let prompt = self.learn_prompt().await?;
+                self.send_line("").await?;
+                prompt
+            }
+        };
+        self.expect(prompt).await
+    }
+
+    /// The session's prompt, learned now if it is not known yet.
+    pub(crate) async fn known_prompt(&mut self) -> Result<Pattern, ExpectError> {
+        match self.prompt.clone() {
+            Some(prompt) => Ok(prompt),
+            None => self.learn_prompt().await,
+        }
+    }
+}
+
+/// The prompt pattern shared by two samples, each ending with a prompt.
+fn prompt_from_samples(first: &str, second: &str) -> Option<Pattern> {
+    let first = last_line(first);
+    let second = last_line(second);
+    if first.trim().is_empty() || second.trim().is_empty() {
+        return None;
+    }
+    if first == second {
+        return Pattern::regex(&format!("{}$", regex::escape(first))).ok();
+    }
+
+    let prefix_len = common_len(first.chars(), second.chars());
+    let (first_rest, second_rest) = (&first[prefix_len..], &second[prefix_len..]);
+    let suffix_len = common_len(first_rest.chars().rev(), second_rest.chars().rev());
+    // Digits next to the difference belong to it, as in "[9]" and "[10]"
+    let prefix = first[..prefix_len].trim_end_matches(|c: char| c.is_ascii_digit());
+    let suffix = first_rest[first_rest.len() - suffix_len..]
+        .trim_start_matches(|c: char| c.is_ascii_digit());
+    if prefix.trim().is_empty() && suffix.trim().is_empty() {
+        return None;
+    }
+    Pattern::regex(&format!(
+        r"{}[^\r\n]*{}$",
+        regex::escape(prefix),
+        regex::escape(suffix)
+    ))
+    .ok()
+}
+
+/// The text after the last line break.
+fn last_line(sample: &str) -> &str {
+    let line = sample.rsplit('\n').next().unwrap_or(sample);
+    line.rsplit('\r').next().unwrap_or(line)
+}
+
+/// Length in bytes of the common start of two character sequences.
+fn common_len(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> usize {
+    a.zip(b)
+        .take_while(|(x, y)| x == y)
+        .map(|(c, _)| c.len_utf8())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &Pattern, text: &str) -> bool {
+        pattern
+            .to_matcher()
+            .unwrap()
+            .find(text.as_bytes())
+            .is_some()
+    }
+
+    #[test]
+    fn test_prompt_from_samples() {
+        let prompt = prompt_from_samples("\r\nadmin@web1:~$ ", "\r\nadmin@web1:~$ ").unwrap();
+        assert!(matches(&prompt, "uptime\r\n up 3 days\r\nadmin@web1:~$ "));
+        assert!(!matches(&prompt, "admin@web1:~$ uptime\r\n"));
+
+        let counter = prompt_from_samples("\r\n[9] db> ", "\r\n[10] db> ").unwrap();
+        assert!(matches(&counter, "ok\r\n[11] db> "));
+        assert!(!matches(&counter, "ok\r\n[11] web> "));
+
+        assert!(prompt_from_samples("\r\n", "\r\n$ ").is_none());
+        assert!(prompt_from_samples("\r\n1", "\r\n2").is_none());
+    }
+}
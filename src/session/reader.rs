@@ -0,0 +1,294 @@
+//! Background reader draining the PTY into a channel
+
+use super::packet::{self, PtyEvent};
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Number of chunks that may be queued before the reader thread blocks.
+///
+/// When the queue is full the thread stops reading, so the PTY applies its
+/// own flow control to the child instead of memory growing without bound.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Readiness of a non-blocking source: a duplicate of its descriptor, polled
+/// when a read would block.
+#[cfg(unix)]
+pub(crate) struct Readiness(std::os::fd::OwnedFd);
+
+/// Readiness of a non-blocking source; sources are always blocking off Unix.
+#[cfg(not(unix))]
+pub(crate) enum Readiness {}
+
+#[cfg(unix)]
+impl Readiness {
+    /// Watch the open descriptor `fd` of a source.
+    pub(crate) fn new(fd: std::os::fd::RawFd) -> io::Result<Self> {
+        // SAFETY: the caller passes a descriptor that is open for the
+        // duration of this call; it is duplicated before being kept
+        let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+        Ok(Self(fd.try_clone_to_owned()?))
+    }
+
+    /// Block until the source has data, has hung up or has failed.
+    fn wait(&self) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        let mut pollfd = libc::pollfd {
+            fd: self.0.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        loop {
+            // SAFETY: pollfd points to one valid pollfd structure
+            if unsafe { libc::poll(&mut pollfd, 1, -1) } >= 0 {
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Readiness {
+    fn wait(&self) -> io::Result<()> {
+        match *self {}
+    }
+}
+
+/// Receiving end of the background reader.
+///
+/// A dedicated thread performs blocking reads on the PTY and forwards each
+/// chunk through a channel. Waiting for output is therefore just awaiting the
+/// channel, which costs no CPU while the process is idle and is cancel-safe:
+/// a timeout never loses data that was already read.
+pub(crate) struct Reader {
+    rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    eof: bool,
+    events: Arc<Mutex<Vec<PtyEvent>>>,
+}
+
+impl Reader {
+    /// Start a reader thread for the given PTY reader.
+    ///
+    /// `chunk_size` is the initial read size; it doubles whenever a read fills
+    /// the whole chunk, up to `max_chunk_size`, and shrinks back after short
+    /// reads (see `next_chunk_size`). With `packet_mode`, each read
+    /// is a PTY packet: control events are collected separately and only the
+    /// data is forwarded.
+    ///
+    /// A non-blocking source needs `ready`, which is waited on whenever a
+    /// read would block; without it, a read that would block is an error.
+    pub(crate) fn spawn(
+        mut source: Box<dyn Read + Send>,
+        chunk_size: usize,
+        max_chunk_size: usize,
+        packet_mode: bool,
+        ready: Option<Readiness>,
+    ) -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let thread_events = Arc::clone(&events);
+
+        std::thread::Builder::new()
+            .name("expectrust-reader".to_string())
+            .spawn(move || {
+                let mut buf = vec![0u8; chunk_size];
+                loop {
+                    let message = match source.read(&mut buf) {
+                        Ok(n) => {
+                            let chunk = if packet_mode && n > 0 {
+                                let (data, events) = packet::parse_packet(&buf[..n]);
+                                if let Ok(mut queue) = thread_events.lock() {
+                                    queue.extend(events);
+                                }
+                                data.to_vec()
+                            } else {
+                                buf[..n].to_vec()
+                            };
+                            let new_len = next_chunk_size(buf.len(), n, chunk_size, max_chunk_size);
+                            if new_len != buf.len() {
+                                buf.resize(new_len, 0);
+                                buf.shrink_to_fit();
+                            }
+                            if chunk.is_empty() && n > 0 {
+                                // A control packet, or an empty data packet
+                                continue;
+                            }
+                            Ok(chunk)
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => match &ready {
+                            Some(ready) => match ready.wait() {
+                                Ok(()) => continue,
+                                Err(e) => Err(e),
+                            },
+                            None => Err(e),
+                        },
+                        Err(e) if is_eof_error(&e) => Ok(Vec::new()),
+                        Err(e) => Err(e),
+                    };
+
+                    let done = !matches!(&message, Ok(chunk) if !chunk.is_empty());
+                    if tx.blocking_send(message).is_err() || done {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            rx,
+            eof: false,
+            events,
+        })
+    }
+
+    /// Remove and return the packet mode events reported so far.
+    pub(crate) fn take_events(&self) -> Vec<PtyEvent> {
+        self.events
+            .lock()
+            .map(|mut events| std::mem::take(&mut *events))
+            .unwrap_or_default()
+    }
+
+    /// Wait for the next chunk of output.
+    ///
+    /// Returns an empty vector once the stream has reached EOF, and an error
+    /// of kind `TimedOut` if no data arrives within `timeout`.
+    pub(crate) async fn read(&mut self, timeout: Option<Duration>) -> io::Result<Vec<u8>> {
+        if self.eof {
+            return Ok(Vec::new());
+        }
+
+        let next = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.rx.recv())
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Read timeout"))?,
+            None => self.rx.recv().await,
+        };
+
+        match next {
+            Some(Ok(chunk)) => {
+                self.eof = chunk.is_empty();
+                Ok(chunk)
+            }
+            Some(Err(e)) => {
+                self.eof = true;
+                Err(e)
+            }
+            None => {
+                self.eof = true;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Take the next chunk of output if one has already arrived.
+    ///
+    /// Returns `None` if no output is waiting, otherwise like
+    /// [`read`](Self::read).
+    pub(crate) fn try_read(&mut self) -> Option<io::Result<Vec<u8>>> {
+        if self.eof {
+            return Some(Ok(Vec::new()));
+        }
+        let next = match self.rx.try_recv() {
+            Ok(next) => next,
+            Err(mpsc::error::TryRecvError::Empty) => return None,
+            Err(mpsc::error::TryRecvError::Disconnected) => Ok(Vec::new()),
+        };
+        self.eof = next.as_ref().map_or(true, |chunk| chunk.is_empty());
+        Some(next)
+    }
+}
+
+/// The read size to use after reading `n` bytes into a buffer of `len`.
+///
+/// Doubles after a read that filled the buffer, up to `max`, and halves
+/// after one that used a quarter of it or less, down to `min`, so a burst of
+/// output does not keep a large buffer for the rest of the session.
+fn next_chunk_size(len: usize, n: usize, min: usize, max: usize) -> usize {
+    if n == len && len < max {
+        (len * 2).min(max)
+    } else if n <= len / 4 && len > min {
+        (len / 2).max(min)
+    } else {
+        len
+    }
+}
+
+/// Whether a read error means the other side of the PTY has gone away.
+///
+/// Linux reports a closed PTY slave as `EIO` rather than a zero-length read,
+/// and Windows reports a closed ConPTY pipe as a broken pipe.
+fn is_eof_error(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(libc::EIO) {
+        return true;
+    }
+    e.kind() == io::ErrorKind::BrokenPipe
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A source counting the reads made on it.
+    struct Counting(std::fs::File, Arc<AtomicUsize>);
+
+    impl Read for Counting {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_chunk_size_follows_output() {
+        // Grows through a burst
+        assert_eq!(next_chunk_size(4096, 4096, 4096, 65536), 8192);
+        assert_eq!(next_chunk_size(65536, 65536, 4096, 65536), 65536);
+        // and shrinks back once reads are short again
+        assert_eq!(next_chunk_size(65536, 100, 4096, 65536), 32768);
+        assert_eq!(next_chunk_size(8192, 100, 4096, 65536), 4096);
+        assert_eq!(next_chunk_size(4096, 100, 4096, 65536), 4096);
+        // A read using more than a quarter keeps the size
+        assert_eq!(next_chunk_size(65536, 20000, 4096, 65536), 65536);
+    }
+
+    #[tokio::test]
+    async fn test_idle_nonblocking_source_does_not_spin() {
+        let mut fds = [0; 2];
+        // SAFETY: fds has room for the two descriptors pipe returns
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        // SAFETY: pipe returned two new descriptors owned by nobody else
+        let (read_end, write_end) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        // SAFETY: read_end is an open descriptor
+        unsafe { libc::fcntl(read_end.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) };
+
+        let reads = Arc::new(AtomicUsize::new(0));
+        let ready = Readiness::new(read_end.as_raw_fd()).unwrap();
+        let source = Counting(std::fs::File::from(read_end), Arc::clone(&reads));
+        let mut reader = Reader::spawn(Box::new(source), 64, 64, false, Some(ready)).unwrap();
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(reads.load(Ordering::SeqCst) <= 1);
+
+        std::fs::File::from(write_end).write_all(b"hello").unwrap();
+        let chunk = reader.read(Some(Duration::from_secs(5))).await.unwrap();
+        assert_eq!(chunk, b"hello");
+        // The write end is closed, so the reader reaches EOF
+        assert!(reader
+            .read(Some(Duration::from_secs(5)))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Automatic responses to recurring prompts
+
+use crate::pattern::{Matcher, Pattern};
+use crate::result::{ExpectError, PatternError};
+use crate::session::Session;
+
+/// A rule that answers a prompt whenever it appears.
+///
+/// Created with [`Session::auto_respond`], which returns a reference for
+/// setting a limit on how many times the rule fires.
+pub struct AutoResponse {
+    pattern: Pattern,
+    matcher: Box<dyn Matcher>,
+    response: Vec<u8>,
+    limit: Option<usize>,
+    count: usize,
+}
+
+impl AutoResponse {
+    /// Stop responding after `limit` responses.
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The pattern this rule responds to.
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Number of times this rule has responded.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Whether the rule has reached its limit.
+    pub fn is_exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.count >= limit)
+    }
+}
+
+impl std::fmt::Debug for AutoResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AutoResponse")
+            .field("pattern", &self.pattern)
+            .field("response", &String::from_utf8_lossy(&self.response))
+            .field("limit", &self.limit)
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+impl Session {
+    /// Answer a prompt automatically whenever it appears.
+    ///
+    /// While the session waits in an expect call, any output matching
+    /// `pattern` is answered by sending `response`, before the expected
+    /// patterns are checked. The matched text is removed from the buffer,
+    /// so expects never see it and the prompt is answered once per
+    /// appearance. When several rules match, the earliest match in the
+    /// output is answered first.
+    ///
+    /// This suits prompts that may or may not appear, such as "Press ENTER
+    /// to continue" in the middle of paged output, without adding them to
+    /// every expect. Rules stay active until removed with
+    /// [`clear_auto_responses`](Self::clear_auto_responses) or until they
+    /// reach their [`limit`](AutoResponse::limit).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or is a special pattern
+    /// such as `Pattern::Eof`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("ssh user@host")?;
+    /// session
+    ///     .auto_respond(Pattern::exact("(yes/no)?"), "yes\n")?
+    ///     .limit(1);
+    /// session.auto_respond(Pattern::exact("Press ENTER to continue"), "\n")?;
+    ///
+    /// session.expect(Pattern::exact("$ ")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn auto_respond(
+        &mut self,
+        pattern: Pattern,
+        response: impl AsRef<[u8]>,
+    ) -> Result<&mut AutoResponse, PatternError> {
+        let matcher = pattern.to_matcher()?;
+        self.auto_responses.push(AutoResponse {
+            pattern,
+            matcher,
+            response: response.as_ref().to_vec(),
+            limit: None,
+            count: 0,
+        });
+        Ok(self.auto_responses.last_mut().unwrap())
+    }
+
+    /// The automatic response rules, in the order they were added.
+    pub fn auto_responses(&self) -> &[AutoResponse] {
+        &self.auto_responses
+    }
+
+    /// Remove all automatic response rules.
+    pub fn clear_auto_responses(&mut self) {
+        self.auto_responses.clear();
+    }
+
+    /// Answer any prompts in the buffer that have automatic responses.
+    pub(crate) async fn run_auto_responses(&mut self) -> Result<(), ExpectError> {
+        loop {
+            let mut earliest: Option<(usize, usize, usize)> = None;
+            for (index, rule) in self.auto_responses.iter().enumerate() {
+                if rule.is_exhausted() {
+                    continue;
+                }
+                if let Some(m) = self.buffer.find(rule.matcher.as_ref()) {
+                    let earlier = earliest.is_none_or(|(_, start, _)| m.start < start);
+                    if m.end > m.start && earlier {
+                        earliest = Some((index, m.start, m.end));
+                    }
+                }
+            }
+
+            let Some((index, start, end)) = earliest else {
+                return Ok(());
+            };
+            self.buffer.remove(start, end);
+            let rule = &mut self.auto_responses[index];
+            rule.count += 1;
+            let response = rule.response.clone();
+            self.send(&response).await?;
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Sending passwords and other sensitive input
+
+use crate::result::ExpectError;
+use crate::session::Session;
+use crate::transcript::Origin;
+use std::borrow::Cow;
+
+/// What a secret is replaced with wherever it would be recorded.
+const REDACTED: &str = "****";
+
+impl Session {
+    /// Send a secret followed by a newline, like
+    /// [`send_line`](Self::send_line), without recording it.
+    ///
+    /// The transcript, the log file, input observers and event viewers see
+    /// `****` in place of the secret. The session also remembers it and
+    /// replaces it with `****` should the process echo it back in the
+    /// output that errors carry (`UnexpectedOutput`, `CriticalOutput` and
+    /// `PromptNotLearned`), the [`timeout_screen`](Self::timeout_screen),
+    /// the error message, output and screen shown by
+    /// [`report`](Self::report), and the errors and last lines of output
+    /// listed by [`SessionGroup::summary`](crate::SessionGroup::summary).
+    /// Use this for passwords, tokens and passphrases.
+    ///
+    /// Output from the process is otherwise recorded as received: if the
+    /// process echoes the secret, it appears in the transcript, log file,
+    /// history file, event stream, [`echo_output`](crate::SessionBuilder::echo_output)
+    /// and the matching buffer. Programs normally turn off echo while
+    /// reading a password.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder().transcript(true).spawn("ssh admin@server")?;
+    /// session.expect(Pattern::exact("password: ")).await?;
+    /// session.send_secret("hunter2").await?;
+    /// assert!(!session.transcript().unwrap().to_string().contains("hunter2"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_secret(&mut self, secret: &str) -> Result<(), ExpectError> {
+        if !secret.is_empty() && !self.secrets.iter().any(|known| known == secret) {
+            self.secrets.push(secret.to_string());
+        }
+        let line = format!("{}\n", secret);
+        let recorded = format!("{}\n", REDACTED);
+        self.send_recorded(Origin::Automation, line.as_bytes(), recorded.as_bytes())
+            .await
+    }
+
+    /// `bytes` with every secret sent so far replaced with `****`.
+    pub(crate) fn redact<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut redacted = Cow::Borrowed(bytes);
+        for secret in &self.secrets {
+            if memchr::memmem::find(&redacted, secret.as_bytes()).is_some() {
+                redacted = Cow::Owned(replace(&redacted, secret.as_bytes()));
+            }
+        }
+        redacted
+    }
+
+    /// `text` with every secret sent so far replaced with `****`.
+    pub(crate) fn redact_text<'a>(&self, text: impl Into<Cow<'a, str>>) -> Cow<'a, str> {
+        let mut text = text.into();
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+            }
+        }
+        text
+    }
+}
+
+/// `haystack` with every occurrence of `needle` replaced with `****`.
+fn replace(haystack: &[u8], needle: &[u8]) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(haystack.len());
+    let mut last = 0;
+    for start in memchr::memmem::find_iter(haystack, needle) {
+        if start < last {
+            continue;
+        }
+        replaced.extend_from_slice(&haystack[last..start]);
+        replaced.extend_from_slice(REDACTED.as_bytes());
+        last = start + needle.len();
+    }
+    replaced.extend_from_slice(&haystack[last..]);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_secret() {
+        assert_eq!(
+            replace(b"pw=abc, again abc", b"abc"),
+            b"pw=****, again ****"
+        );
+        assert_eq!(replace(b"aaaa", b"aa"), b"********");
+        assert_eq!(replace(b"aaa", b"aa"), b"****a");
+        assert_eq!(replace(b"nothing", b"abc"), b"nothing");
+    }
+}
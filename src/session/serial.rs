@@ -0,0 +1,275 @@
+//! Serial port transport with break and modem-control line support
+
+use std::fs::File;
+use std::io;
+
+/// An open serial device.
+///
+/// The session reads and writes through clones of the same file descriptor;
+/// this handle is kept for line control (break, DTR/RTS, baud rate).
+pub(crate) struct SerialPort {
+    file: File,
+}
+
+/// Handles returned when opening a serial device.
+pub(crate) struct OpenedSerial {
+    pub(crate) port: SerialPort,
+    pub(crate) reader: Box<dyn io::Read + Send>,
+    pub(crate) writer: Box<dyn io::Write + Send>,
+}
+
+#[cfg(unix)]
+impl SerialPort {
+    /// Open a serial device in raw mode at the given baud rate.
+    pub(crate) fn open(path: &str, baud_rate: u32) -> io::Result<OpenedSerial> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)?;
+
+        let port = SerialPort { file };
+        port.configure(|termios| {
+            // SAFETY: termios is a valid, initialized structure
+            unsafe { libc::cfmakeraw(termios) };
+            termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            set_speed(termios, baud_rate)
+        })?;
+
+        let reader = Box::new(port.file.try_clone()?);
+        let writer = Box::new(port.file.try_clone()?);
+        Ok(OpenedSerial {
+            port,
+            reader,
+            writer,
+        })
+    }
+
+    /// The file descriptor of the device.
+    pub(crate) fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        use std::os::unix::io::AsRawFd;
+
+        self.file.as_raw_fd()
+    }
+
+    /// Start (`true`) or end (`false`) the break condition on the line.
+    pub(crate) fn set_break(&self, active: bool) -> io::Result<()> {
+        let request = if active {
+            libc::TIOCSBRK
+        } else {
+            libc::TIOCCBRK
+        };
+        self.ioctl(request as _, std::ptr::null())
+    }
+
+    /// Assert or clear the DTR (data terminal ready) line.
+    pub(crate) fn set_dtr(&self, level: bool) -> io::Result<()> {
+        self.set_modem_line(libc::TIOCM_DTR, level)
+    }
+
+    /// Assert or clear the RTS (request to send) line.
+    pub(crate) fn set_rts(&self, level: bool) -> io::Result<()> {
+        self.set_modem_line(libc::TIOCM_RTS, level)
+    }
+
+    /// Change the baud rate of the open port.
+    pub(crate) fn set_baud_rate(&self, baud_rate: u32) -> io::Result<()> {
+        self.configure(|termios| set_speed(termios, baud_rate))
+    }
+
+    fn set_modem_line(&self, line: libc::c_int, level: bool) -> io::Result<()> {
+        let request = if level {
+            libc::TIOCMBIS
+        } else {
+            libc::TIOCMBIC
+        };
+        self.ioctl(request as _, &line)
+    }
+
+    fn ioctl(&self, request: libc::c_ulong, arg: *const libc::c_int) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        // SAFETY: the descriptor is open for the lifetime of `self`, and every
+        // request used here takes either no argument or a pointer to c_int
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, arg) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Read the current terminal settings, modify them, and apply them.
+    fn configure<F>(&self, modify: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut libc::termios) -> io::Result<()>,
+    {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = self.file.as_raw_fd();
+        // SAFETY: termios is plain data and fully initialized by tcgetattr
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        modify(&mut termios)?;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_speed(termios: &mut libc::termios, baud_rate: u32) -> io::Result<()> {
+    let speed = baud_constant(baud_rate).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unsupported baud rate: {}", baud_rate),
+        )
+    })?;
+    // SAFETY: termios is a valid structure owned by the caller
+    if unsafe { libc::cfsetspeed(termios, speed) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Map a numeric baud rate to the platform's speed constant.
+#[cfg(unix)]
+fn baud_constant(baud_rate: u32) -> Option<libc::speed_t> {
+    let speed = match baud_rate {
+        50 => libc::B50,
+        75 => libc::B75,
+        110 => libc::B110,
+        134 => libc::B134,
+        150 => libc::B150,
+        200 => libc::B200,
+        300 => libc::B300,
+        600 => libc::B600,
+        1200 => libc::B1200,
+        1800 => libc::B1800,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        460800 => libc::B460800,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        500000 => libc::B500000,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        921600 => libc::B921600,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        1000000 => libc::B1000000,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        1500000 => libc::B1500000,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        2000000 => libc::B2000000,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        3000000 => libc::B3000000,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        4000000 => libc::B4000000,
+        _ => return None,
+    };
+    Some(speed)
+}
+
+#[cfg(not(unix))]
+impl SerialPort {
+    pub(crate) fn open(_path: &str, _baud_rate: u32) -> io::Result<OpenedSerial> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_break(&self, _active: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_dtr(&self, _level: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_rts(&self, _level: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_baud_rate(&self, _baud_rate: u32) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(not(unix))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Serial ports are only supported on Unix",
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::os::unix::io::FromRawFd;
+
+    /// Open a PTY pair and return the master and the slave device path.
+    fn open_pty() -> (File, String) {
+        let mut master = 0;
+        let mut slave = 0;
+        let mut name = [0 as libc::c_char; 128];
+        // SAFETY: all out-pointers are valid for the duration of the call
+        let rc = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                name.as_mut_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(rc, 0, "openpty failed");
+        // SAFETY: openpty returned valid descriptors and a NUL-terminated name
+        unsafe { libc::close(slave) };
+        let path = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        (unsafe { File::from_raw_fd(master) }, path)
+    }
+
+    #[test]
+    fn test_round_trip_over_tty() {
+        let (mut master, path) = open_pty();
+        let mut serial = SerialPort::open(&path, 115200).unwrap();
+
+        serial.writer.write_all(b"ping").unwrap();
+        let mut buf = [0u8; 4];
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        master.write_all(b"pong").unwrap();
+        serial.reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+
+    #[test]
+    fn test_line_control() {
+        let (_master, path) = open_pty();
+        let serial = SerialPort::open(&path, 9600).unwrap();
+
+        serial.port.set_baud_rate(19200).unwrap();
+        serial.port.set_break(true).unwrap();
+        serial.port.set_break(false).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_baud_rate() {
+        let (_master, path) = open_pty();
+        let err = SerialPort::open(&path, 12345).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,112 @@
+//! Waiting for startup output to settle
+
+use crate::pattern::{Matcher, Pattern};
+use crate::result::ExpectError;
+use crate::session::Session;
+use std::time::{Duration, Instant};
+
+impl Session {
+    /// Read and discard output until the process has been quiet for
+    /// `quiet_for`.
+    ///
+    /// Interactive programs often print banners, a message of the day or
+    /// shell startup noise of unpredictable length before they are ready.
+    /// Settling skips all of it, including output already buffered, without
+    /// a fixed sleep: it returns as soon as no output arrived for
+    /// `quiet_for`, or when the process reaches EOF. The next expect only
+    /// sees output printed afterwards.
+    ///
+    /// Returns the discarded output (up to the buffer size).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::Timeout` if output keeps arriving for
+    /// `max_wait`. What was read is still discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("bash -i")?;
+    /// session
+    ///     .settle(Duration::from_secs(5), Duration::from_millis(200))
+    ///     .await?;
+    /// session.send_line("echo ready").await?;
+    /// session.expect(Pattern::exact("ready")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn settle(
+        &mut self,
+        max_wait: Duration,
+        quiet_for: Duration,
+    ) -> Result<String, ExpectError> {
+        self.settle_with(None, max_wait, quiet_for).await
+    }
+
+    /// Like [`settle`](Self::settle), but also stop as soon as `prompt`
+    /// appears.
+    ///
+    /// Output up to and including the prompt is discarded; anything after
+    /// it stays buffered. Use this when the prompt is known but may be
+    /// preceded by noise that itself contains prompt-like text, or when
+    /// waiting for silence would be slow.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `prompt` is invalid or a special pattern, or
+    /// `ExpectError::Timeout` as for `settle`.
+    pub async fn settle_until(
+        &mut self,
+        prompt: &Pattern,
+        max_wait: Duration,
+        quiet_for: Duration,
+    ) -> Result<String, ExpectError> {
+        self.settle_with(Some(prompt), max_wait, quiet_for).await
+    }
+
+    async fn settle_with(
+        &mut self,
+        prompt: Option<&Pattern>,
+        max_wait: Duration,
+        quiet_for: Duration,
+    ) -> Result<String, ExpectError> {
+        let matcher: Option<Box<dyn Matcher>> = prompt.map(Pattern::to_matcher).transpose()?;
+        let start_time = Instant::now();
+
+        loop {
+            if let Some(m) = matcher.as_ref().and_then(|m| self.buffer.find(m.as_ref())) {
+                let discarded = String::from_utf8_lossy(self.buffer.before(m.end)).into_owned();
+                self.buffer.mark_matched(m.end);
+                return Ok(discarded);
+            }
+            if self.eof_reached {
+                break;
+            }
+
+            let remaining = max_wait.saturating_sub(start_time.elapsed());
+            if remaining.is_zero() {
+                self.buffer.clear();
+                return Err(self.timeout_error(max_wait));
+            }
+
+            match self.reader.read(Some(quiet_for.min(remaining))).await {
+                Ok(chunk) if chunk.is_empty() => self.eof_reached = true,
+                Ok(chunk) => self.ingest(&chunk)?,
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    if remaining >= quiet_for {
+                        break;
+                    }
+                }
+                Err(e) => return Err(ExpectError::IoError(e)),
+            }
+        }
+
+        let discarded = String::from_utf8_lossy(self.buffer.unmatched()).into_owned();
+        self.buffer.clear();
+        Ok(discarded)
+    }
+}
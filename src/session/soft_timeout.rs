@@ -0,0 +1,104 @@
+//! Extending expect deadlines while the process makes progress
+
+use crate::session::Session;
+use std::time::Duration;
+
+type Hook = Box<dyn FnMut(&SoftTimeout<'_>) -> Option<Duration> + Send>;
+
+/// The state of an expect call whose deadline has passed, as seen by a hook
+/// registered with [`Session::on_soft_timeout`].
+#[derive(Debug, Clone, Copy)]
+pub struct SoftTimeout<'a> {
+    /// Time spent waiting so far in this expect call.
+    pub elapsed: Duration,
+    /// Total extension already granted in this expect call.
+    pub extended: Duration,
+    /// Output received since the call started or was last extended, exactly
+    /// as the process printed it (limited to the buffer size).
+    pub output: &'a [u8],
+}
+
+/// Hook registered with [`Session::on_soft_timeout`].
+pub(crate) struct SoftTimeoutHook {
+    max_extension: Duration,
+    hook: Hook,
+}
+
+impl Session {
+    /// Let `hook` extend an expect call's deadline when it passes.
+    ///
+    /// Fixed timeouts are either too short for a slow but healthy run or too
+    /// long for a hung one. When an expect call reaches its deadline, `hook`
+    /// is given the output since the call started (or was last extended)
+    /// and returns how much longer to wait, or `None` to time out as usual.
+    /// Extensions within one call add up to at most `max_extension`, so a
+    /// process that keeps printing cannot postpone the timeout forever.
+    ///
+    /// Applies to all expect calls of the session. Registering a hook
+    /// replaces the previous one.
+    ///
+    /// # Examples
+    ///
+    /// Keep waiting as long as a progress percentage is shown:
+    ///
+    /// ```no_run
+    /// use expectrust::{Pattern, Session};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder()
+    ///     .timeout(Duration::from_secs(30))
+    ///     .spawn("./upgrade.sh")?;
+    /// session.on_soft_timeout(Duration::from_secs(600), |soft| {
+    ///     let advancing = soft.output.contains(&b'%');
+    ///     advancing.then_some(Duration::from_secs(30))
+    /// });
+    /// session.expect(Pattern::exact("Upgrade complete")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_soft_timeout(
+        &mut self,
+        max_extension: Duration,
+        hook: impl FnMut(&SoftTimeout<'_>) -> Option<Duration> + Send + 'static,
+    ) {
+        self.soft_timeout = Some(SoftTimeoutHook {
+            max_extension,
+            hook: Box::new(hook),
+        });
+    }
+
+    /// Remove the hook registered with
+    /// [`on_soft_timeout`](Self::on_soft_timeout).
+    pub fn clear_soft_timeout(&mut self) {
+        self.soft_timeout = None;
+    }
+
+    /// Ask the soft timeout hook how much to extend a passed deadline.
+    ///
+    /// `output_since` is the raw stream position the output shown to the
+    /// hook starts at. Returns `None` if there is no hook, it declines, or
+    /// the cap is reached.
+    pub(crate) fn soft_timeout_extension(
+        &mut self,
+        elapsed: Duration,
+        extended: Duration,
+        output_since: u64,
+    ) -> Option<Duration> {
+        let soft_timeout = self.soft_timeout.as_mut()?;
+        let allowed = soft_timeout.max_extension.saturating_sub(extended);
+        if allowed.is_zero() {
+            return None;
+        }
+        let received = self.raw.total_len().saturating_sub(output_since);
+        let state = SoftTimeout {
+            elapsed,
+            extended,
+            output: self
+                .raw
+                .tail(usize::try_from(received).unwrap_or(usize::MAX)),
+        };
+        let extension = (soft_timeout.hook)(&state)?.min(allowed);
+        (!extension.is_zero()).then_some(extension)
+    }
+}
@@ -0,0 +1,263 @@
+//! Aggregate reporting for session groups
+
+use crate::buffer::Escaped;
+use crate::session::Session;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Number of slowest steps listed in a [`GroupSummary`].
+const SLOWEST_STEPS: usize = 5;
+
+/// Number of hosts named per failure cluster when displayed.
+const CLUSTER_HOSTS_SHOWN: usize = 10;
+
+/// Number of buffered bytes searched for the last line of output.
+const TAIL_BYTES: usize = 1024;
+
+/// One group operation as seen by each session.
+#[derive(Debug, Clone)]
+pub(crate) struct StepRecord {
+    pub(crate) name: String,
+    pub(crate) outcomes: Vec<StepOutcome>,
+}
+
+/// How one session fared in a step.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StepOutcome {
+    /// Time until the session finished the step; `None` if it was stopped
+    /// because another session matched first.
+    pub(crate) duration: Option<Duration>,
+    /// The error and the last line of output, if the session failed.
+    pub(crate) failure: Option<(String, String)>,
+}
+
+/// Summary of the steps run on a [`SessionGroup`](crate::SessionGroup),
+/// returned by [`SessionGroup::summary`](crate::SessionGroup::summary).
+///
+/// Turns a run over many hosts into what needs attention: which hosts
+/// failed, which steps were slow, and which failures are the same failure.
+/// `Display` prints it as a readable report.
+#[derive(Debug, Clone)]
+pub struct GroupSummary {
+    /// Outcome per host, in group order.
+    pub hosts: Vec<HostSummary>,
+    /// The slowest step runs, slowest first.
+    pub slowest: Vec<SlowStep>,
+    /// Failures grouped by error and last line of output, largest group
+    /// first.
+    pub failure_clusters: Vec<FailureCluster>,
+}
+
+/// Outcome of the group's steps for one host.
+#[derive(Debug, Clone)]
+pub struct HostSummary {
+    /// The host's label.
+    pub label: String,
+    /// Number of steps the host completed successfully.
+    pub passed: usize,
+    /// Number of steps the host failed.
+    pub failed: usize,
+    /// Name of the first step the host failed.
+    pub first_failure: Option<String>,
+}
+
+/// How long one host took for one step.
+#[derive(Debug, Clone)]
+pub struct SlowStep {
+    /// The step's name.
+    pub step: String,
+    /// The host's label.
+    pub host: String,
+    /// Time the host took.
+    pub duration: Duration,
+}
+
+/// Hosts that failed with the same error after the same output.
+#[derive(Debug, Clone)]
+pub struct FailureCluster {
+    /// The error message.
+    pub error: String,
+    /// The last line of output before the failure, escaped.
+    pub tail: String,
+    /// Labels of the hosts that failed this way.
+    pub hosts: Vec<String>,
+}
+
+impl GroupSummary {
+    /// Summarize `steps` for hosts labeled `labels`.
+    pub(crate) fn new(labels: &[String], steps: &[StepRecord]) -> Self {
+        let mut hosts: Vec<HostSummary> = labels
+            .iter()
+            .map(|label| HostSummary {
+                label: label.clone(),
+                passed: 0,
+                failed: 0,
+                first_failure: None,
+            })
+            .collect();
+        let mut slowest = Vec::new();
+        let mut clusters: Vec<FailureCluster> = Vec::new();
+        let mut cluster_index: HashMap<(&str, &str), usize> = HashMap::new();
+
+        for step in steps {
+            for (host, outcome) in hosts.iter_mut().zip(&step.outcomes) {
+                if let Some(duration) = outcome.duration {
+                    slowest.push(SlowStep {
+                        step: step.name.clone(),
+                        host: host.label.clone(),
+                        duration,
+                    });
+                }
+                let Some((error, tail)) = &outcome.failure else {
+                    if outcome.duration.is_some() {
+                        host.passed += 1;
+                    }
+                    continue;
+                };
+                host.failed += 1;
+                host.first_failure.get_or_insert_with(|| step.name.clone());
+                let index = *cluster_index
+                    .entry((error.as_str(), tail.as_str()))
+                    .or_insert_with(|| {
+                        clusters.push(FailureCluster {
+                            error: error.clone(),
+                            tail: tail.clone(),
+                            hosts: Vec::new(),
+                        });
+                        clusters.len() - 1
+                    });
+                clusters[index].hosts.push(host.label.clone());
+            }
+        }
+
+        slowest.sort_by_key(|slow| std::cmp::Reverse(slow.duration));
+        slowest.truncate(SLOWEST_STEPS);
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.hosts.len()));
+        Self {
+            hosts,
+            slowest,
+            failure_clusters: clusters,
+        }
+    }
+
+    /// Hosts that failed at least one step.
+    pub fn failed_hosts(&self) -> impl Iterator<Item = &HostSummary> {
+        self.hosts.iter().filter(|host| host.failed > 0)
+    }
+}
+
+impl Session {
+    /// The last non-blank line of unmatched output, redacted and escaped.
+    pub(crate) fn last_output_line(&self) -> String {
+        let bytes = self.buffer.as_bytes();
+        let tail = self.redact(&bytes[bytes.len().saturating_sub(TAIL_BYTES)..]);
+        tail.split(|&b| b == b'\n')
+            .map(<[u8]>::trim_ascii)
+            .rfind(|line| !line.is_empty())
+            .map(|line| Escaped(line).to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for GroupSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed = self.failed_hosts().count();
+        writeln!(
+            f,
+            "{} of {} hosts passed, {} failed",
+            self.hosts.len() - failed,
+            self.hosts.len(),
+            failed
+        )?;
+        for host in self.failed_hosts() {
+            writeln!(
+                f,
+                "  {}: failed {} of {} steps, first at {}",
+                host.label,
+                host.failed,
+                host.passed + host.failed,
+                host.first_failure.as_deref().unwrap_or("?")
+            )?;
+        }
+
+        if !self.slowest.is_empty() {
+            writeln!(f, "slowest steps:")?;
+            for slow in &self.slowest {
+                writeln!(f, "  {:>8.2?}  {}  {}", slow.duration, slow.host, slow.step)?;
+            }
+        }
+
+        if !self.failure_clusters.is_empty() {
+            writeln!(f, "failures:")?;
+            for cluster in &self.failure_clusters {
+                writeln!(f, "  {} hosts: {}", cluster.hosts.len(), cluster.error)?;
+                if !cluster.tail.is_empty() {
+                    writeln!(f, "    | {}", cluster.tail)?;
+                }
+                let shown = &cluster.hosts[..cluster.hosts.len().min(CLUSTER_HOSTS_SHOWN)];
+                write!(f, "    {}", shown.join(", "))?;
+                if cluster.hosts.len() > shown.len() {
+                    write!(f, " and {} more", cluster.hosts.len() - shown.len())?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(millis: u64) -> StepOutcome {
+        StepOutcome {
+            duration: Some(Duration::from_millis(millis)),
+            failure: None,
+        }
+    }
+
+    fn failed(millis: u64, error: &str, tail: &str) -> StepOutcome {
+        StepOutcome {
+            duration: Some(Duration::from_millis(millis)),
+            failure: Some((error.to_string(), tail.to_string())),
+        }
+    }
+
+    #[test]
+    fn test_summary_clusters_identical_failures() {
+        let labels: Vec<String> = ["web1", "web2", "web3", "db1"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let steps = [
+            StepRecord {
+                name: "login".to_string(),
+                outcomes: vec![ok(100), ok(900), ok(120), ok(80)],
+            },
+            StepRecord {
+                name: "upgrade".to_string(),
+                outcomes: vec![
+                    ok(300),
+                    failed(5000, "Timeout", "E: Could not get lock"),
+                    failed(5000, "Timeout", "E: Could not get lock"),
+                    failed(10, "EOF", "Connection closed"),
+                ],
+            },
+        ];
+        let summary = GroupSummary::new(&labels, &steps);
+
+        assert_eq!(summary.failed_hosts().count(), 3);
+        assert_eq!(summary.hosts[0].passed, 2);
+        assert_eq!(summary.hosts[1].first_failure.as_deref(), Some("upgrade"));
+        assert_eq!(summary.slowest[0].host, "web2");
+        assert_eq!(summary.slowest.len(), SLOWEST_STEPS);
+        assert_eq!(summary.failure_clusters[0].hosts, ["web2", "web3"]);
+        assert_eq!(summary.failure_clusters[1].hosts, ["db1"]);
+
+        let text = summary.to_string();
+        assert!(text.starts_with("1 of 4 hosts passed, 3 failed\n"));
+        assert!(text.contains("  2 hosts: Timeout\n    | E: Could not get lock\n    web2, web3\n"));
+    }
+}
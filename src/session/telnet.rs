@@ -0,0 +1,379 @@
+//! Telnet client transport
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Interpret as command
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+/// Start of subnegotiation
+const SB: u8 = 250;
+/// End of subnegotiation
+const SE: u8 = 240;
+
+const OPT_ECHO: u8 = 1;
+const OPT_SUPPRESS_GO_AHEAD: u8 = 3;
+const OPT_NAWS: u8 = 31;
+
+/// Connect to a telnet server.
+///
+/// Returns the reader and writer of the connection, and a handle for
+/// changing the window size. The reader answers option negotiation itself,
+/// reporting a window of `rows` × `cols`.
+pub(crate) fn connect(
+    addr: impl ToSocketAddrs,
+    timeout: Option<Duration>,
+    rows: u16,
+    cols: u16,
+) -> io::Result<(TelnetReader, TelnetWriter, TelnetWindow)> {
+    let mut last_error = None;
+    let mut stream = None;
+    for addr in addr.to_socket_addrs()? {
+        let attempt = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match attempt {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let stream = stream.ok_or_else(|| {
+        last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Address resolved to nothing")
+        })
+    })?;
+    stream.set_nodelay(true)?;
+
+    let out = Arc::new(Mutex::new(stream.try_clone()?));
+    let negotiator = Arc::new(Mutex::new(Negotiator::new(rows, cols)));
+    Ok((
+        TelnetReader {
+            stream,
+            out: Arc::clone(&out),
+            negotiator: Arc::clone(&negotiator),
+            raw: Vec::new(),
+        },
+        TelnetWriter {
+            out: Arc::clone(&out),
+            after_cr: false,
+        },
+        TelnetWindow { out, negotiator },
+    ))
+}
+
+/// Reading side of a telnet connection, yielding only data.
+pub(crate) struct TelnetReader {
+    stream: TcpStream,
+    out: Arc<Mutex<TcpStream>>,
+    negotiator: Arc<Mutex<Negotiator>>,
+    raw: Vec<u8>,
+}
+
+impl Read for TelnetReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Data never takes more room than the bytes carrying it, so one
+        // read's worth of data always fits
+        self.raw.resize(buf.len(), 0);
+        loop {
+            let n = self.stream.read(&mut self.raw)?;
+            if n == 0 {
+                return Ok(0);
+            }
+
+            let (data, replies) = self
+                .negotiator
+                .lock()
+                .map_err(|_| lock_error())?
+                .process(&self.raw[..n]);
+            if !replies.is_empty() {
+                let mut out = self.out.lock().map_err(|_| lock_error())?;
+                out.write_all(&replies)?;
+            }
+            if !data.is_empty() {
+                buf[..data.len()].copy_from_slice(&data);
+                return Ok(data.len());
+            }
+        }
+    }
+}
+
+/// Writing side of a telnet connection, escaping data as the protocol
+/// requires.
+pub(crate) struct TelnetWriter {
+    out: Arc<Mutex<TcpStream>>,
+    /// The last byte written was a carriage return.
+    after_cr: bool,
+}
+
+impl Write for TelnetWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let escaped = escape(buf, &mut self.after_cr);
+        let mut out = self.out.lock().map_err(|_| lock_error())?;
+        out.write_all(&escaped)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.lock().map_err(|_| lock_error())?.flush()
+    }
+}
+
+/// Window size of a telnet connection.
+pub(crate) struct TelnetWindow {
+    out: Arc<Mutex<TcpStream>>,
+    negotiator: Arc<Mutex<Negotiator>>,
+}
+
+impl TelnetWindow {
+    /// Change the window size, reporting it to the server if it asked for
+    /// NAWS; otherwise the size is reported if it asks later.
+    pub(crate) fn resize(&self, rows: u16, cols: u16) -> io::Result<()> {
+        let mut negotiator = self.negotiator.lock().map_err(|_| lock_error())?;
+        negotiator.rows = rows;
+        negotiator.cols = cols;
+        if negotiator.local[OPT_NAWS as usize] {
+            let mut report = Vec::new();
+            negotiator.window_size(&mut report);
+            self.out
+                .lock()
+                .map_err(|_| lock_error())?
+                .write_all(&report)?;
+        }
+        Ok(())
+    }
+}
+
+fn lock_error() -> io::Error {
+    io::Error::other("Telnet connection lock poisoned")
+}
+
+/// Escape data for sending: `IAC` is doubled, and a carriage return not
+/// followed by a line feed is followed by NUL, as the network virtual
+/// terminal requires.
+///
+/// `after_cr` carries a carriage return at the end of `data` over to the
+/// next call, which sends the NUL first unless it starts with a line feed.
+fn escape(data: &[u8], after_cr: &mut bool) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len() + 1);
+    for &byte in data {
+        if std::mem::take(after_cr) && byte != b'\n' {
+            escaped.push(0);
+        }
+        escaped.push(byte);
+        match byte {
+            IAC => escaped.push(IAC),
+            b'\r' => *after_cr = true,
+            _ => {}
+        }
+    }
+    escaped
+}
+
+/// Where the parser is within a command sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Data,
+    /// Data, just after a carriage return.
+    Cr,
+    Iac,
+    Option(u8),
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+/// Telnet protocol state: separates data from commands and answers option
+/// negotiation.
+///
+/// The client lets the server echo and suppress go-ahead, reports its window
+/// size (NAWS) when asked, and refuses every other option. Replies are only
+/// sent when an option changes state, so negotiation cannot loop.
+#[derive(Debug)]
+struct Negotiator {
+    state: State,
+    /// Options enabled on the server side.
+    remote: [bool; 256],
+    /// Options enabled on our side.
+    local: [bool; 256],
+    rows: u16,
+    cols: u16,
+}
+
+impl Negotiator {
+    fn new(rows: u16, cols: u16) -> Self {
+        Self {
+            state: State::Data,
+            remote: [false; 256],
+            local: [false; 256],
+            rows,
+            cols,
+        }
+    }
+
+    /// Split received bytes into data and the replies to send back.
+    fn process(&mut self, input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut data = Vec::with_capacity(input.len());
+        let mut replies = Vec::new();
+
+        for &byte in input {
+            self.state = match (self.state, byte) {
+                (State::Data | State::Cr, IAC) => State::Iac,
+                // NUL only marks the carriage return as not ending a line
+                (State::Cr, 0) => State::Data,
+                (State::Data | State::Cr, b'\r') => {
+                    data.push(byte);
+                    State::Cr
+                }
+                (State::Data | State::Cr, _) => {
+                    data.push(byte);
+                    State::Data
+                }
+                (State::Iac, IAC) => {
+                    data.push(IAC);
+                    State::Data
+                }
+                (State::Iac, WILL | WONT | DO | DONT) => State::Option(byte),
+                (State::Iac, SB) => State::Subnegotiation,
+                // NOP, go-ahead and other commands carry no data
+                (State::Iac, _) => State::Data,
+                (State::Option(command), option) => {
+                    self.negotiate(command, option, &mut replies);
+                    State::Data
+                }
+                (State::Subnegotiation, IAC) => State::SubnegotiationIac,
+                (State::Subnegotiation, _) => State::Subnegotiation,
+                (State::SubnegotiationIac, SE) => State::Data,
+                (State::SubnegotiationIac, _) => State::Subnegotiation,
+            };
+        }
+
+        (data, replies)
+    }
+
+    fn negotiate(&mut self, command: u8, option: u8, replies: &mut Vec<u8>) {
+        let index = option as usize;
+        match command {
+            WILL if !matches!(option, OPT_ECHO | OPT_SUPPRESS_GO_AHEAD) => {
+                replies.extend_from_slice(&[IAC, DONT, option]);
+            }
+            WILL if !self.remote[index] => {
+                self.remote[index] = true;
+                replies.extend_from_slice(&[IAC, DO, option]);
+            }
+            WONT if self.remote[index] => {
+                self.remote[index] = false;
+                replies.extend_from_slice(&[IAC, DONT, option]);
+            }
+            DO if !matches!(option, OPT_NAWS | OPT_SUPPRESS_GO_AHEAD) => {
+                replies.extend_from_slice(&[IAC, WONT, option]);
+            }
+            DO if !self.local[index] => {
+                self.local[index] = true;
+                replies.extend_from_slice(&[IAC, WILL, option]);
+                if option == OPT_NAWS {
+                    self.window_size(replies);
+                }
+            }
+            DONT if self.local[index] => {
+                self.local[index] = false;
+                replies.extend_from_slice(&[IAC, WONT, option]);
+            }
+            // Already in the requested state
+            _ => {}
+        }
+    }
+
+    /// Append a NAWS subnegotiation reporting the window size.
+    fn window_size(&self, replies: &mut Vec<u8>) {
+        replies.extend_from_slice(&[IAC, SB, OPT_NAWS]);
+        for byte in [self.cols.to_be_bytes(), self.rows.to_be_bytes()].concat() {
+            replies.push(byte);
+            if byte == IAC {
+                replies.push(IAC);
+            }
+        }
+        replies.extend_from_slice(&[IAC, SE]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separates_data_from_commands() {
+        let mut negotiator = Negotiator::new(24, 80);
+        let (data, replies) = negotiator.process(&[
+            b'a', IAC, 241, b'b', IAC, IAC, IAC, SB, 24, 1, IAC, IAC, IAC, SE, b'c',
+        ]);
+        assert_eq!(data, [b'a', b'b', IAC, b'c']);
+        assert!(replies.is_empty());
+    }
+
+    #[test]
+    fn test_commands_split_across_reads() {
+        let mut negotiator = Negotiator::new(24, 80);
+        let (data, replies) = negotiator.process(&[b'x', IAC]);
+        assert_eq!(data, b"x");
+        assert!(replies.is_empty());
+        let (data, replies) = negotiator.process(&[WILL]);
+        assert!(data.is_empty() && replies.is_empty());
+        let (data, replies) = negotiator.process(&[OPT_ECHO, b'y']);
+        assert_eq!(data, b"y");
+        assert_eq!(replies, [IAC, DO, OPT_ECHO]);
+    }
+
+    #[test]
+    fn test_negotiation_replies() {
+        let mut negotiator = Negotiator::new(24, 255);
+        let (_, replies) = negotiator.process(&[IAC, DO, OPT_NAWS, IAC, DO, 24, IAC, WILL, 5]);
+        assert_eq!(
+            replies,
+            [
+                IAC, WILL, OPT_NAWS, IAC, SB, OPT_NAWS, 0, IAC, IAC, 0, 24, IAC, SE, IAC, WONT, 24,
+                IAC, DONT, 5
+            ]
+        );
+
+        // Repeated requests for an enabled option are not answered again
+        let (_, replies) = negotiator.process(&[IAC, DO, OPT_NAWS, IAC, WILL, OPT_ECHO]);
+        assert_eq!(replies, [IAC, DO, OPT_ECHO]);
+        let (_, replies) = negotiator.process(&[IAC, WILL, OPT_ECHO, IAC, WONT, OPT_ECHO]);
+        assert_eq!(replies, [IAC, DONT, OPT_ECHO]);
+    }
+
+    #[test]
+    fn test_drops_nul_after_carriage_return() {
+        let mut negotiator = Negotiator::new(24, 80);
+        let (data, _) = negotiator.process(b"a\r\0b\r\nc\0\r");
+        assert_eq!(data, b"a\rb\r\nc\0\r");
+        // Split across reads
+        let (data, _) = negotiator.process(b"\0d");
+        assert_eq!(data, b"d");
+    }
+
+    #[test]
+    fn test_escape() {
+        let mut after_cr = false;
+        assert_eq!(escape(b"a\r\nb\rc", &mut after_cr), b"a\r\nb\r\0c");
+        assert_eq!(escape(&[1, IAC, 2], &mut after_cr), [1, IAC, IAC, 2]);
+
+        // A carriage return ending one write waits for the next
+        assert_eq!(escape(b"x\r", &mut after_cr), b"x\r");
+        assert_eq!(escape(b"\ny", &mut after_cr), b"\ny");
+        assert_eq!(escape(b"\r", &mut after_cr), b"\r");
+        assert_eq!(escape(b"z", &mut after_cr), b"\0z");
+        assert!(!after_cr);
+    }
+}
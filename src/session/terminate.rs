@@ -0,0 +1,300 @@
+//! Waiting for and stopping the spawned process
+
+use crate::result::ExpectError;
+use crate::session::Session;
+#[cfg(not(unix))]
+use portable_pty::ChildKiller;
+use portable_pty::{Child, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// How often `terminate` and `wait_timeout` check whether the process has
+/// exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What happens to a process that is still running when its [`Session`] is
+/// dropped.
+///
+/// Set with [`SessionBuilder::drop_policy`](crate::SessionBuilder::drop_policy).
+/// Sessions without a process, such as serial sessions, are not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Hang up the terminal, as closing it does: the process receives
+    /// `SIGHUP` on Unix, and the pseudo console is closed on Windows. Most
+    /// interactive programs exit; a process ignoring the hangup keeps
+    /// running. The process is not waited for, but is reaped in the
+    /// background once it exits.
+    #[default]
+    HangUp,
+    /// Kill the process immediately, as [`Session::kill`] does, and reap it.
+    Kill,
+    /// Leave the process running with its terminal open. Nothing reads its
+    /// output or writes its input any more, so a process that keeps
+    /// writing eventually blocks, and one reading input waits forever. The
+    /// terminal stays open for the life of the program, and the process is
+    /// reaped in the background if it exits.
+    Detach,
+}
+
+impl Session {
+    /// Set what happens to the process if it is still running when the
+    /// session is dropped.
+    ///
+    /// See [`SessionBuilder::drop_policy`](crate::SessionBuilder::drop_policy).
+    pub fn set_drop_policy(&mut self, policy: DropPolicy) {
+        self.drop_policy = policy;
+    }
+
+    /// Return the exit status if the process has exited, without waiting.
+    ///
+    /// Unlike [`wait`](Self::wait), the process handle is kept: this can be
+    /// called repeatedly, and returns the same status once the process has
+    /// exited. [`wait`](Self::wait), [`terminate`](Self::terminate) and
+    /// [`kill`](Self::kill) still work afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::ProcessExited` if there is no process, as in a
+    /// serial session or after [`wait`](Self::wait), or an I/O error if
+    /// checking fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("./build.sh")?;
+    /// if let Some(status) = session.try_wait()? {
+    ///     println!("build finished: {}", status.exit_code());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>, ExpectError> {
+        let child = self.child.as_mut().ok_or(ExpectError::ProcessExited)?;
+        Ok(child.try_wait()?)
+    }
+
+    /// Wait up to `timeout` for the process to exit.
+    ///
+    /// Returns the exit status, or `None` if the process is still running
+    /// when the time is up. Like [`try_wait`](Self::try_wait), the process
+    /// handle is kept either way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::ProcessExited` if there is no process, or an I/O
+    /// error if checking fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::spawn("./migrate.sh")?;
+    /// match session.wait_timeout(Duration::from_secs(60)).await? {
+    ///     Some(status) => println!("migrated: {}", status.success()),
+    ///     None => {
+    ///         session.terminate().await?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<ExitStatus>, ExpectError> {
+        // A timeout too long to represent has no deadline
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(Some(status));
+            }
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => EXIT_POLL_INTERVAL,
+            };
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            tokio::time::sleep(remaining.min(EXIT_POLL_INTERVAL)).await;
+        }
+    }
+
+    /// Stop the process gracefully, killing it if it does not exit in time.
+    ///
+    /// Asks the process to exit (`SIGTERM` on Unix; on Windows the pseudo
+    /// console is closed), waits up to the grace period set with
+    /// [`SessionBuilder::terminate_grace`](crate::SessionBuilder::terminate_grace),
+    /// then kills it as [`kill`](Self::kill) does. The process is always
+    /// reaped, so no zombie is left behind.
+    ///
+    /// Returns the exit status. If the process had already exited, its
+    /// status is returned without sending anything.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::ProcessExited` if there is no process, as in a
+    /// serial session or after [`wait`](Self::wait), or an I/O error if
+    /// signalling or waiting fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::builder()
+    ///     .terminate_grace(Duration::from_secs(2))
+    ///     .spawn("./server")?;
+    /// // ...
+    /// let status = session.terminate().await?;
+    /// println!("server stopped: {:?}", status);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn terminate(&mut self) -> Result<ExitStatus, ExpectError> {
+        if let Some(status) = self.try_reap()? {
+            return Ok(status);
+        }
+
+        #[cfg(unix)]
+        if let Some(pid) = self.pid() {
+            signal(pid, libc::SIGTERM)?;
+        }
+        #[cfg(not(unix))]
+        {
+            self._master = None;
+        }
+
+        // A grace period too long to represent has no deadline
+        let deadline = Instant::now().checked_add(self.terminate_grace);
+        while deadline.is_none_or(|deadline| Instant::now() < deadline) {
+            tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+            if let Some(status) = self.try_reap()? {
+                return Ok(status);
+            }
+        }
+
+        self.kill().await
+    }
+
+    /// Kill the process immediately and wait for it to exit.
+    ///
+    /// Sends `SIGKILL` on Unix and terminates the process on Windows. Use
+    /// [`terminate`](Self::terminate) to give the process a chance to clean
+    /// up first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExpectError::ProcessExited` if there is no process, or an I/O
+    /// error if killing or waiting fails.
+    pub async fn kill(&mut self) -> Result<ExitStatus, ExpectError> {
+        if let Some(status) = self.try_reap()? {
+            return Ok(status);
+        }
+
+        #[cfg(unix)]
+        if let Some(pid) = self.pid() {
+            signal(pid, libc::SIGKILL)?;
+        }
+        #[cfg(not(unix))]
+        if let Some(child) = &mut self.child {
+            child.kill()?;
+        }
+
+        self.wait().await
+    }
+
+    /// Hang up the terminal, as closing the PTY does, without waiting.
+    ///
+    /// The process receives `SIGHUP` on Unix; on Windows the pseudo console
+    /// is closed.
+    #[cfg(feature = "script")]
+    pub(crate) fn hang_up(&mut self) -> Result<(), ExpectError> {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid() {
+            signal(pid, libc::SIGHUP)?;
+        }
+        #[cfg(not(unix))]
+        {
+            self._master = None;
+        }
+        Ok(())
+    }
+
+    /// Take the exit status if the process has exited.
+    fn try_reap(&mut self) -> Result<Option<ExitStatus>, ExpectError> {
+        let child = self.child.as_mut().ok_or(ExpectError::ProcessExited)?;
+        let status = child.try_wait()?;
+        if status.is_some() {
+            self.child = None;
+        }
+        Ok(status)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+
+        match self.drop_policy {
+            DropPolicy::HangUp => {
+                #[cfg(unix)]
+                if let Some(pid) = child.process_id() {
+                    let _ = signal(pid, libc::SIGHUP);
+                }
+                reap_in_background(child);
+            }
+            DropPolicy::Kill => {
+                #[cfg(unix)]
+                if let Some(pid) = child.process_id() {
+                    let _ = signal(pid, libc::SIGKILL);
+                }
+                #[cfg(not(unix))]
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            DropPolicy::Detach => {
+                // Closing the PTY would hang up the process, and dropping
+                // the writer sends it end of file
+                std::mem::forget(self._master.take());
+                std::mem::forget(self.master_writer.clone());
+                reap_in_background(child);
+            }
+        }
+    }
+}
+
+/// Wait for a process on a separate thread so it does not linger as a
+/// zombie after its session is gone.
+fn reap_in_background(mut child: Box<dyn Child + Send>) {
+    let _ = std::thread::Builder::new()
+        .name("expectrust-reaper".to_string())
+        .spawn(move || {
+            let _ = child.wait();
+        });
+}
+
+#[cfg(unix)]
+fn signal(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    // The process exited since it was last checked; waiting reaps it
+    if err.raw_os_error() == Some(libc::ESRCH) {
+        return Ok(());
+    }
+    Err(err)
+}
@@ -0,0 +1,285 @@
+//! Expect steps loaded from TOML files, with hot reloading
+
+use crate::pattern::Pattern;
+use crate::result::{ExpectError, PatternError};
+use crate::session::Session;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// How often a running steps file is checked for changes.
+const RELOAD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A list of expect/send steps.
+///
+/// Steps are written in TOML as an array of `[[step]]` tables:
+///
+/// ```toml
+/// [[step]]
+/// name = "boot"
+/// expect = "login: "
+/// send = "root\n"
+/// timeout = 1200
+///
+/// [[step]]
+/// regex = '[$#] $'
+/// send = "uname -a\n"
+/// ```
+///
+/// Each step waits for at most one of `expect` (exact text), `regex` or
+/// `glob`, then sends `send` if present. `timeout` (in seconds) overrides
+/// the session timeout for that step; it must not be negative.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Steps {
+    /// The steps, in execution order.
+    #[serde(rename = "step", default)]
+    pub steps: Vec<Step>,
+}
+
+/// A single step in a [`Steps`] list.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Optional name, used in error messages.
+    pub name: Option<String>,
+    /// Exact text to wait for.
+    pub expect: Option<String>,
+    /// Regular expression to wait for.
+    pub regex: Option<String>,
+    /// Glob pattern to wait for.
+    pub glob: Option<String>,
+    /// Text to send once the pattern matched.
+    pub send: Option<String>,
+    /// Timeout for this step in seconds.
+    pub timeout: Option<f64>,
+}
+
+impl Step {
+    /// The pattern this step waits for, if any.
+    pub fn pattern(&self) -> Result<Option<Pattern>, ExpectError> {
+        match (&self.expect, &self.regex, &self.glob) {
+            (None, None, None) => Ok(None),
+            (Some(text), None, None) => Ok(Some(Pattern::exact(text))),
+            (None, Some(regex), None) => {
+                Ok(Some(Pattern::regex(regex).map_err(PatternError::from)?))
+            }
+            (None, None, Some(glob)) => Ok(Some(Pattern::glob(glob))),
+            _ => Err(invalid(&format!(
+                "step {} has more than one of expect, regex and glob",
+                self.label()
+            ))),
+        }
+    }
+
+    /// The timeout for this step, if it overrides the session's.
+    fn timeout(&self) -> Result<Option<Duration>, ExpectError> {
+        self.timeout
+            .map(|secs| {
+                Duration::try_from_secs_f64(secs).map_err(|_| {
+                    invalid(&format!(
+                        "step {} has invalid timeout {}",
+                        self.label(),
+                        secs
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("'{}'", name),
+            None => "without name".to_string(),
+        }
+    }
+}
+
+impl Steps {
+    /// Load steps from a TOML file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ExpectError> {
+        let content = std::fs::read_to_string(path)?;
+        content.parse()
+    }
+}
+
+impl FromStr for Steps {
+    type Err = ExpectError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps: Steps = toml::from_str(s).map_err(|e| invalid(&e.to_string()))?;
+        // Catch bad patterns and timeouts up front rather than in the
+        // middle of a run
+        for step in &steps.steps {
+            step.pattern()?;
+            step.timeout()?;
+        }
+        Ok(steps)
+    }
+}
+
+impl Session {
+    /// Run the steps in a TOML file, reloading it whenever it changes.
+    ///
+    /// The file is checked for modifications while a step waits for its
+    /// pattern. When it changes, the step at the current position is
+    /// re-read from the new file and its wait restarts, keeping any output
+    /// already received; completed steps are not re-run. This allows
+    /// adjusting patterns during a long run (such as waiting for a device to
+    /// boot) without restarting the target. An edit that does not parse is
+    /// ignored until the file changes again.
+    ///
+    /// Each step's wait is one expect call in the session's history and
+    /// events, however often the file is checked.
+    ///
+    /// See [`Steps`] for the file format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded initially, if a step
+    /// times out or fails, or if a reloaded file no longer has a step at the
+    /// current position.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use expectrust::Session;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut session = Session::open_serial("/dev/ttyUSB0", 115200)?;
+    /// session.run_steps_file("boot.toml").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_steps_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ExpectError> {
+        let path = path.as_ref();
+        let mut modified = modified_time(path);
+        let mut steps = Steps::load(path)?;
+        let mut index = 0;
+
+        while index < steps.steps.len() {
+            let step = steps.steps[index].clone();
+            if let Some(pattern) = step.pattern()? {
+                let timeout = step.timeout()?.or(self.timeout());
+                let mut reloaded = None;
+                let interrupted = self
+                    .expect_any_interruptible(
+                        std::slice::from_ref(&pattern),
+                        timeout,
+                        RELOAD_POLL_INTERVAL,
+                        || {
+                            let current = modified_time(path);
+                            if current != modified {
+                                modified = current;
+                                reloaded = Steps::load(path).ok();
+                            }
+                            reloaded.is_some()
+                        },
+                    )
+                    .await?
+                    .is_none();
+
+                if let (true, Some(reloaded)) = (interrupted, reloaded) {
+                    // Re-run the step at this position from the reloaded file
+                    if reloaded.steps.len() <= index {
+                        return Err(invalid(&format!(
+                            "reloaded file has {} steps, but step {} was running",
+                            reloaded.steps.len(),
+                            index + 1
+                        )));
+                    }
+                    steps = reloaded;
+                    continue;
+                }
+            }
+
+            if let Some(data) = &step.send {
+                self.send(data.as_bytes()).await?;
+            }
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Run a list of steps.
+    ///
+    /// Like [`Session::run_steps_file`], without reloading.
+    pub async fn run_steps(&mut self, steps: &Steps) -> Result<(), ExpectError> {
+        for step in &steps.steps {
+            if let Some(pattern) = step.pattern()? {
+                let timeout = step.timeout()?.or(self.timeout());
+                self.expect_any_within(&[pattern], timeout).await?;
+            }
+            if let Some(data) = &step.send {
+                self.send(data.as_bytes()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn invalid(message: &str) -> ExpectError {
+    ExpectError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid steps: {}", message),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_steps() {
+        let steps: Steps = r#"
+            [[step]]
+            name = "login"
+            expect = "login: "
+            send = "root\n"
+            timeout = 1.5
+
+            [[step]]
+            regex = '[$#] $'
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(steps.steps.len(), 2);
+        assert_eq!(steps.steps[0].name.as_deref(), Some("login"));
+        assert_eq!(steps.steps[0].send.as_deref(), Some("root\n"));
+        assert_eq!(steps.steps[0].timeout, Some(1.5));
+        assert!(matches!(
+            steps.steps[1].pattern().unwrap(),
+            Some(Pattern::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_conflicting_patterns() {
+        let result = "[[step]]\nexpect = \"a\"\nglob = \"b*\"\n".parse::<Steps>();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_reject_unknown_field() {
+        assert!("[[step]]\nexpext = \"a\"\n".parse::<Steps>().is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_timeout() {
+        assert!("[[step]]\ntimeout = -1.0\n".parse::<Steps>().is_err());
+        assert!("[[step]]\ntimeout = nan\n".parse::<Steps>().is_err());
+        assert!("[[step]]\ntimeout = 1e300\n".parse::<Steps>().is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_regex() {
+        assert!("[[step]]\nregex = \"(\"\n".parse::<Steps>().is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Teach mode: record an interactive session and generate a replay script
+
+use crate::buffer::strip_ansi;
+use std::fmt::Write;
+
+/// Maximum length of a generated prompt pattern, in characters.
+const MAX_PROMPT_LEN: usize = 32;
+
+/// A single recorded exchange: the prompt that was showing and what the
+/// operator typed in response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedStep {
+    /// Text chosen from the output preceding the input, used as the pattern
+    /// to wait for on replay. Empty if there was no output to wait for.
+    pub prompt: String,
+    /// The bytes typed by the operator, including the line terminator.
+    pub input: Vec<u8>,
+}
+
+/// Recorder of operator input and the output that prompted it.
+///
+/// Filled in by [`Session::teach`](crate::Session::teach), then turned into an
+/// Expect script with [`Recorder::to_expect_script`] or a Rust program with
+/// [`Recorder::to_rust`] — the equivalent of Expect's `autoexpect`.
+///
+/// Input is grouped into steps that end at a line terminator. For each step
+/// the prompt pattern is the tail of the last non-empty output line seen
+/// before the operator started typing, with escape sequences removed.
+/// Output that arrives while typing is treated as echo and ignored.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::Recorder;
+///
+/// let mut recorder = Recorder::new();
+/// recorder.record_output(b"Welcome\r\nlogin: ");
+/// recorder.record_input(b"admin\r");
+///
+/// let script = recorder.to_expect_script(&["telnet", "router"]);
+/// assert!(script.contains("expect \"login: \""));
+/// assert!(script.contains("send -- \"admin\\r\""));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    steps: Vec<RecordedStep>,
+    output: Vec<u8>,
+    input: Vec<u8>,
+}
+
+impl Recorder {
+    /// Create an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record output from the process.
+    pub fn record_output(&mut self, data: &[u8]) {
+        // Output while the operator is mid-line is echo
+        if self.input.is_empty() {
+            self.output.extend_from_slice(data);
+        }
+    }
+
+    /// Record input typed by the operator.
+    pub fn record_input(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.input.push(byte);
+            if byte == b'\r' || byte == b'\n' {
+                self.finish_step();
+            }
+        }
+    }
+
+    /// Finish recording, keeping any partially typed input as a final step.
+    pub fn finish(&mut self) {
+        if !self.input.is_empty() {
+            self.finish_step();
+        }
+    }
+
+    /// The recorded steps.
+    pub fn steps(&self) -> &[RecordedStep] {
+        &self.steps
+    }
+
+    /// Generate an Expect script replaying the recorded steps.
+    ///
+    /// `command` is the program and its arguments, quoted as needed in the
+    /// script's `spawn` line.
+    pub fn to_expect_script<S: AsRef<str>>(&self, command: &[S]) -> String {
+        let mut script = String::new();
+        script.push_str("#!/usr/bin/env expect\n");
+        script.push_str("# Generated by expectrust teach mode\n\n");
+        script.push_str("set timeout 30\n");
+        script.push_str("spawn");
+        for arg in command {
+            let arg = arg.as_ref();
+            let plain = !arg.is_empty()
+                && arg
+                    .bytes()
+                    .all(|b| b.is_ascii_graphic() && !b"\\\"$[]{};".contains(&b));
+            if plain {
+                let _ = write!(script, " {}", arg);
+            } else {
+                let _ = write!(script, " \"{}\"", tcl_escape(arg.as_bytes()));
+            }
+        }
+        script.push('\n');
+        for step in &self.steps {
+            if !step.prompt.is_empty() {
+                let _ = writeln!(script, "expect \"{}\"", tcl_escape(step.prompt.as_bytes()));
+            }
+            let _ = writeln!(script, "send -- \"{}\"", tcl_escape(&step.input));
+        }
+        script.push_str("expect eof\n");
+        script
+    }
+
+    /// Generate a Rust program replaying the recorded steps.
+    ///
+    /// `command` is the program and its arguments, passed to
+    /// [`Session::spawn_args`](crate::Session::spawn_args) as written.
+    pub fn to_rust<S: AsRef<str>>(&self, command: &[S]) -> String {
+        let mut code = String::new();
+        code.push_str("// Generated by expectrust teach mode\n");
+        code.push_str("use expectrust::{Pattern, Session};\n\n");
+        code.push_str("#[tokio::main]\n");
+        code.push_str("async fn main() -> Result<(), Box<dyn std::error::Error>> {\n");
+        let (program, args) = command
+            .split_first()
+            .map_or(("", &[][..]), |(program, args)| (program.as_ref(), args));
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| format!("{:?}", arg.as_ref()))
+            .collect();
+        let _ = writeln!(
+            code,
+            "    let mut session = Session::spawn_args({:?}, [{}])?;",
+            program,
+            args.join(", ")
+        );
+        for step in &self.steps {
+            if !step.prompt.is_empty() {
+                let _ = writeln!(
+                    code,
+                    "    session.expect(Pattern::exact({:?})).await?;",
+                    step.prompt
+                );
+            }
+            let _ = writeln!(
+                code,
+                "    session.send(b\"{}\").await?;",
+                rust_escape(&step.input)
+            );
+        }
+        code.push_str("    session.expect(Pattern::Eof).await?;\n");
+        code.push_str("    Ok(())\n");
+        code.push_str("}\n");
+        code
+    }
+
+    fn finish_step(&mut self) {
+        self.steps.push(RecordedStep {
+            prompt: choose_prompt(&self.output),
+            input: std::mem::take(&mut self.input),
+        });
+        self.output.clear();
+    }
+}
+
+/// Pick a stable pattern from the output that preceded some input.
+///
+/// Uses the tail of the last non-empty line, which is usually the prompt,
+/// and skips escape sequences that vary between runs.
+fn choose_prompt(output: &[u8]) -> String {
+    let stripped = strip_ansi(output);
+    let text = String::from_utf8_lossy(&stripped);
+    let line = text
+        .split(['\r', '\n'])
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("")
+        .trim_start();
+
+    let skip = line.chars().count().saturating_sub(MAX_PROMPT_LEN);
+    line.chars().skip(skip).collect()
+}
+
+/// Escape bytes for a double-quoted Tcl string.
+fn tcl_escape(data: &[u8]) -> String {
+    let mut out = String::new();
+    for &byte in data {
+        match byte {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'$' => out.push_str("\\$"),
+            b'[' => out.push_str("\\["),
+            b']' => out.push_str("\\]"),
+            b'\r' => out.push_str("\\r"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", byte);
+            }
+        }
+    }
+    out
+}
+
+/// Escape bytes for a Rust byte string literal.
+fn rust_escape(data: &[u8]) -> String {
+    let mut out = String::new();
+    for &byte in data {
+        match byte {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\r' => out.push_str("\\r"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", byte);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_split_on_line_terminator() {
+        let mut recorder = Recorder::new();
+        recorder.record_output(b"banner\r\nuser: ");
+        recorder.record_input(b"bob\r");
+        recorder.record_output(b"bob\r\npass: ");
+        recorder.record_input(b"pw");
+        recorder.record_output(b"echo while typing");
+        recorder.record_input(b"\r");
+        recorder.record_output(b"\r\n$ ");
+        recorder.record_input(&[0x04]);
+        recorder.finish();
+
+        let steps = recorder.steps();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].prompt, "user: ");
+        assert_eq!(steps[0].input, b"bob\r");
+        assert_eq!(steps[1].prompt, "pass: ");
+        assert_eq!(steps[1].input, b"pw\r");
+        assert_eq!(steps[2].prompt, "$ ");
+        assert_eq!(steps[2].input, [0x04]);
+    }
+
+    #[test]
+    fn test_choose_prompt() {
+        assert_eq!(choose_prompt(b"line one\r\n> "), "> ");
+        assert_eq!(choose_prompt(b"prompt\r\n\r\n"), "prompt");
+        assert_eq!(choose_prompt(b"\x1b[1;32mhost$ \x1b[0m"), "host$ ");
+        assert_eq!(choose_prompt(b""), "");
+        let long = "x".repeat(100);
+        assert_eq!(choose_prompt(long.as_bytes()).len(), MAX_PROMPT_LEN);
+    }
+
+    #[test]
+    fn test_generated_script() {
+        let mut recorder = Recorder::new();
+        recorder.record_output(b"Price [$]: ");
+        recorder.record_input(b"\x03\r");
+
+        let script = recorder.to_expect_script(&["shop", "--name", "my shop"]);
+        assert!(script.contains("spawn shop --name \"my shop\"\n"));
+        assert!(script.contains("expect \"Price \\[\\$\\]: \"\n"));
+        assert!(script.contains("send -- \"\\x03\\r\"\n"));
+
+        let code = recorder.to_rust(&["shop", "--name", "my shop"]);
+        assert!(code.contains("Session::spawn_args(\"shop\", [\"--name\", \"my shop\"])?;"));
+        assert!(code.contains("session.expect(Pattern::exact(\"Price [$]: \")).await?;"));
+        assert!(code.contains("session.send(b\"\\x03\\r\").await?;"));
+    }
+}
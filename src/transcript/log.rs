@@ -0,0 +1,204 @@
+//! Streaming a transcript to a file as it is recorded
+
+use crate::buffer::ProgressCollapser;
+use crate::transcript::{Origin, TranscriptEntry};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Log file receiving each read and write as it happens, in the format of
+/// [`Transcript`](crate::Transcript)'s `Display` implementation.
+///
+/// Unlike a [`Transcript`](crate::Transcript), nothing is kept in memory and
+/// every line is written immediately, so the log is complete up to the
+/// moment a process hangs or the program is killed.
+#[derive(Debug)]
+pub(crate) struct TranscriptLog {
+    start: Instant,
+    file: File,
+    limit: Option<RateLimit>,
+    /// Collapses progress lines in process output, if enabled.
+    progress: Option<ProgressCollapser>,
+}
+
+/// Budget for process output written to the log.
+#[derive(Debug)]
+struct RateLimit {
+    bytes: usize,
+    per: Duration,
+    /// Start of the current period, relative to the log's start.
+    period_start: Duration,
+    /// Bytes written in the current period.
+    used: usize,
+    /// Bytes suppressed and not yet reported in the log.
+    pending: u64,
+    /// Bytes suppressed since the log was opened.
+    total: u64,
+}
+
+impl TranscriptLog {
+    /// Open `path` for appending, creating it if needed.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            start: Instant::now(),
+            file,
+            limit: None,
+            progress: None,
+        })
+    }
+
+    /// Collapse lines of process output redrawn with carriage returns.
+    ///
+    /// Each read is written as it arrives, so only redraws within one read
+    /// are dropped from the log.
+    pub(crate) fn with_progress_collapsing(mut self, enabled: bool) -> Self {
+        self.progress = enabled.then(ProgressCollapser::default);
+        self
+    }
+
+    /// Write at most `bytes` of process output every `per`, counting the
+    /// rest as suppressed.
+    pub(crate) fn with_rate_limit(mut self, bytes: usize, per: Duration) -> Self {
+        self.limit = Some(RateLimit {
+            bytes,
+            per,
+            period_start: Duration::ZERO,
+            used: 0,
+            pending: 0,
+            total: 0,
+        });
+        self
+    }
+
+    /// Number of bytes of process output left out of the log so far.
+    pub(crate) fn suppressed(&self) -> u64 {
+        self.limit.as_ref().map_or(0, |limit| limit.total)
+    }
+
+    /// Write one line for bytes from the given origin.
+    ///
+    /// Write errors are ignored: losing the log must not fail the
+    /// automation it is meant to help debug.
+    pub(crate) fn record(&mut self, origin: Origin, data: &[u8]) {
+        let mut collapsed = Vec::new();
+        let mut data = data;
+        if let (Origin::Process, Some(progress)) = (origin, &mut self.progress) {
+            let Ok(()) = progress.feed(data, &mut collapsed);
+            data = &collapsed;
+        }
+        if data.is_empty() {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        let mut suppressed = 0;
+        if let (Origin::Process, Some(limit)) = (origin, &mut self.limit) {
+            if elapsed.saturating_sub(limit.period_start) >= limit.per {
+                limit.period_start = elapsed;
+                limit.used = 0;
+            }
+            let allowed = (limit.bytes - limit.used).min(data.len());
+            limit.used += allowed;
+            suppressed = (data.len() - allowed) as u64;
+            data = &data[..allowed];
+        }
+
+        // Sent data is never suppressed. Suppressed output is noted before
+        // the next line, so the log shows where it is missing.
+        if !data.is_empty() {
+            self.write_suppressed(elapsed);
+            let entry = TranscriptEntry {
+                origin,
+                elapsed,
+                data: data.to_vec(),
+            };
+            let _ = writeln!(self.file, "{}", entry);
+        }
+        if let Some(limit) = &mut self.limit {
+            limit.pending += suppressed;
+            limit.total += suppressed;
+        }
+    }
+
+    /// Note output suppressed since the last note, if any.
+    fn write_suppressed(&mut self, elapsed: Duration) {
+        let Some(limit) = self.limit.as_mut().filter(|limit| limit.pending > 0) else {
+            return;
+        };
+        let _ = writeln!(
+            self.file,
+            "[{:>9.3}s] ! suppressed {} bytes of process output",
+            elapsed.as_secs_f64(),
+            limit.pending
+        );
+        limit.pending = 0;
+    }
+}
+
+impl Drop for TranscriptLog {
+    fn drop(&mut self) {
+        self.write_suppressed(self.start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appends_entries_with_direction() {
+        let path = std::env::temp_dir().join(format!(
+            "expectrust-transcript-log-{}.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let mut log = TranscriptLog::open(&path).unwrap();
+        log.record(Origin::Process, b"login: ");
+        log.record(Origin::Automation, b"admin\r");
+        log.record(Origin::Process, b"");
+        drop(log);
+        let mut log = TranscriptLog::open(&path).unwrap();
+        log.record(Origin::Human, b"exit\r");
+        drop(log);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("< process \"login: \""));
+        assert!(lines[1].ends_with("> automation \"admin\\r\""));
+        assert!(lines[2].ends_with("> human \"exit\\r\""));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let path = std::env::temp_dir().join(format!(
+            "expectrust-transcript-log-limit-{}.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let mut log = TranscriptLog::open(&path)
+            .unwrap()
+            .with_rate_limit(8, Duration::from_secs(3600));
+        log.record(Origin::Process, b"12345");
+        log.record(Origin::Process, b"67890");
+        log.record(Origin::Process, b"abc");
+        log.record(Origin::Automation, b"q\r");
+        log.record(Origin::Process, b"def");
+        assert_eq!(log.suppressed(), 8);
+        drop(log);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].ends_with("< process \"12345\""));
+        assert!(lines[1].ends_with("< process \"678\""));
+        assert!(lines[2].ends_with("! suppressed 5 bytes of process output"));
+        assert!(lines[3].ends_with("> automation \"q\\r\""));
+        assert!(lines[4].ends_with("! suppressed 3 bytes of process output"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Transcript of the bytes exchanged with a process, with their origin
+
+mod log;
+
+pub(crate) use log::TranscriptLog;
+
+use crate::buffer::{LineSink, ProgressCollapser};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Which way data travelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Written to the process.
+    Sent,
+    /// Read from the process.
+    Received,
+}
+
+/// Where data in a transcript came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    /// Output printed by the process.
+    Process,
+    /// Input sent by the program, e.g. with [`Session::send`](crate::Session::send).
+    Automation,
+    /// Input typed by the user during [`Session::interact`](crate::Session::interact).
+    Human,
+}
+
+impl Origin {
+    /// The direction of data with this origin.
+    pub fn direction(self) -> Direction {
+        match self {
+            Origin::Process => Direction::Received,
+            Origin::Automation | Origin::Human => Direction::Sent,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Origin::Process => "< process",
+            Origin::Automation => "> automation",
+            Origin::Human => "> human",
+        }
+    }
+}
+
+/// A run of bytes from one origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    /// Where the bytes came from.
+    pub origin: Origin,
+    /// Time since the transcript started when the first byte was recorded.
+    pub elapsed: Duration,
+    /// The bytes, exactly as read or written, apart from progress lines
+    /// collapsed by [`SessionBuilder::collapse_progress`](crate::SessionBuilder::collapse_progress).
+    pub data: Vec<u8>,
+}
+
+impl TranscriptEntry {
+    /// Which way the bytes travelled.
+    pub fn direction(&self) -> Direction {
+        self.origin.direction()
+    }
+}
+
+impl fmt::Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>9.3}s] {} {:?}",
+            self.elapsed.as_secs_f64(),
+            self.origin.label(),
+            String::from_utf8_lossy(&self.data)
+        )
+    }
+}
+
+/// Record of everything sent to and received from a process.
+///
+/// Enabled with [`SessionBuilder::transcript`](crate::SessionBuilder::transcript)
+/// and read back with [`Session::transcript`](crate::Session::transcript).
+/// Each entry is tagged with its [`Origin`], so in sessions that mix
+/// automation and [`interact`](crate::Session::interact) it shows who typed
+/// what. Consecutive data from the same origin is merged into one entry.
+///
+/// The `Display` implementation prints one line per entry with a timestamp,
+/// a direction marker and the escaped data.
+///
+/// # Examples
+///
+/// ```
+/// use expectrust::{Origin, Transcript};
+///
+/// let mut transcript = Transcript::new();
+/// transcript.record(Origin::Process, b"login: ");
+/// transcript.record(Origin::Automation, b"admin\r");
+/// transcript.record(Origin::Human, b"ls\r");
+///
+/// assert_eq!(transcript.bytes_from(Origin::Human), b"ls\r");
+/// assert!(transcript.to_string().contains("> human \"ls\\r\""));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    start: Instant,
+    entries: Vec<TranscriptEntry>,
+    /// Collapses progress lines in process output, if enabled.
+    progress: Option<ProgressCollapser>,
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transcript {
+    /// Create an empty transcript starting now.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Vec::new(),
+            progress: None,
+        }
+    }
+
+    /// Collapse lines of process output redrawn with carriage returns, as
+    /// the matching buffer does.
+    pub(crate) fn with_progress_collapsing(mut self, enabled: bool) -> Self {
+        self.progress = enabled.then(ProgressCollapser::default);
+        self
+    }
+
+    /// Record bytes from the given origin.
+    pub fn record(&mut self, origin: Origin, data: &[u8]) {
+        match self.progress.filter(|_| origin == Origin::Process) {
+            Some(mut progress) => {
+                let Ok(()) = progress.feed(data, &mut ProcessOutput(self));
+                self.progress = Some(progress);
+            }
+            None => self.append(origin, data),
+        }
+    }
+
+    fn append(&mut self, origin: Origin, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        match self.entries.last_mut() {
+            Some(last) if last.origin == origin => last.data.extend_from_slice(data),
+            _ => self.entries.push(TranscriptEntry {
+                origin,
+                elapsed: self.start.elapsed(),
+                data: data.to_vec(),
+            }),
+        }
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    /// All bytes from the given origin, concatenated.
+    pub fn bytes_from(&self, origin: Origin) -> Vec<u8> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.origin == origin)
+            .flat_map(|entry| entry.data.iter().copied())
+            .collect()
+    }
+
+    /// All bytes travelling in the given direction, concatenated.
+    pub fn bytes_in(&self, direction: Direction) -> Vec<u8> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.direction() == direction)
+            .flat_map(|entry| entry.data.iter().copied())
+            .collect()
+    }
+
+    /// Remove all entries. Timestamps stay relative to the original start.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// The process output at the end of a transcript, as progress collapsing
+/// sees it.
+struct ProcessOutput<'a>(&'a mut Transcript);
+
+impl LineSink for ProcessOutput<'_> {
+    type Error = std::convert::Infallible;
+
+    fn push(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.0.append(Origin::Process, data);
+        Ok(())
+    }
+
+    /// Drop the current line from the last entry, if it is process output.
+    fn rewind_line(&mut self) {
+        let entries = &mut self.0.entries;
+        let Some(last) = entries.last_mut().filter(|e| e.origin == Origin::Process) else {
+            return;
+        };
+        let line_start = memchr::memrchr(b'\n', &last.data).map_or(0, |i| i + 1);
+        last.data.truncate(line_start);
+        if last.data.is_empty() {
+            entries.pop();
+        }
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merges_consecutive_origin() {
+        let mut transcript = Transcript::new();
+        transcript.record(Origin::Process, b"pass");
+        transcript.record(Origin::Process, b"word: ");
+        transcript.record(Origin::Automation, b"secret\r");
+        transcript.record(Origin::Human, b"");
+        transcript.record(Origin::Process, b"$ ");
+
+        let origins: Vec<_> = transcript.entries().iter().map(|e| e.origin).collect();
+        assert_eq!(
+            origins,
+            [Origin::Process, Origin::Automation, Origin::Process]
+        );
+        assert_eq!(transcript.entries()[0].data, b"password: ");
+        assert_eq!(transcript.bytes_in(Direction::Received), b"password: $ ");
+        assert_eq!(transcript.bytes_in(Direction::Sent), b"secret\r");
+    }
+
+    #[test]
+    fn test_collapses_process_progress() {
+        let mut transcript = Transcript::new().with_progress_collapsing(true);
+        transcript.record(Origin::Process, b"get\r\n 10%\r");
+        transcript.record(Origin::Process, b" 50%\r");
+        transcript.record(Origin::Automation, b"\r");
+        transcript.record(Origin::Process, b"100%\r\n");
+
+        let entries = transcript.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].data, b"get\r\n 50%\r");
+        // A redraw does not reach back past input
+        assert_eq!(entries[1].data, b"\r");
+        assert_eq!(entries[2].data, b"100%\r\n");
+    }
+}
@@ -0,0 +1,586 @@
+//! Drivers for the interactive `sftp` and `ftp` clients
+
+use crate::login::{Credentials, LoginError, LoginSpec};
+use crate::pattern::Pattern;
+use crate::result::ExpectError;
+use crate::session::{Consume, Session};
+use regex::Regex;
+use thiserror::Error;
+
+/// Lines of `sftp` output reporting a failed command.
+const SFTP_ERRORS: &str = r"^(Couldn't |Can't |remote |dest |stat |File .* not found)|: (No such file or directory|Permission denied|Failure)$";
+
+/// Lines of `sftp ls` output reporting a failure. Narrower than
+/// [`SFTP_ERRORS`], since any other line is a listed name, whatever it
+/// looks like.
+const SFTP_LS_ERRORS: &str = r#"^(Can't ls: |Couldn't (read directory|stat remote file|get handle): |File ".*" not found\.$)"#;
+
+/// Lines of `ftp` output reporting a failed command: error replies and
+/// local failures.
+const FTP_ERRORS: &str =
+    r"^([45]\d\d[ -]|Not connected)|: (No such file or directory|Permission denied)$";
+
+/// An `sftp` progress meter update, such as
+/// `file.tar  45%  512KB  1.0MB/s   00:01 ETA`, ended by the carriage return
+/// starting the next update.
+const SFTP_PROGRESS: &str =
+    r"[^\r\n]*?(\d{1,3})%\s+(\S+B)\s+(\S+/s)\s+(?:(\S+) ETA|\S+)[ \t]*[\r\n]";
+
+/// The summary `ftp` prints after a transfer.
+const FTP_SUMMARY: &str = r"^(\d+) bytes (?:sent|received) in .*\(([^)]+)\)";
+
+/// Progress of a file transfer, as reported by the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    /// Percentage transferred, from 0 to 100.
+    pub percent: u8,
+    /// Amount transferred so far, as printed (`"512KB"`, `"1234 bytes"`).
+    pub transferred: String,
+    /// Transfer rate, as printed (`"1.0MB/s"`).
+    pub rate: String,
+    /// Estimated time remaining, while the transfer is in progress.
+    pub eta: Option<String>,
+}
+
+/// Reasons a file transfer command did not succeed.
+#[derive(Error, Debug)]
+pub enum TransferError {
+    /// The client reported an error for the command.
+    #[error("{command} failed: {message}")]
+    Failed {
+        /// The command sent to the client
+        command: String,
+        /// The client's error message
+        message: String,
+    },
+
+    /// Logging in to the server failed.
+    #[error(transparent)]
+    Login(#[from] LoginError),
+
+    /// Any other session error, such as the client exiting.
+    #[error(transparent)]
+    Expect(#[from] ExpectError),
+}
+
+/// Callback receiving progress updates.
+type ProgressCallback = Box<dyn FnMut(&TransferProgress) + Send>;
+
+/// Command loop shared by the drivers.
+struct Client {
+    session: Session,
+    prompt: Pattern,
+    errors: Regex,
+    progress: Option<Pattern>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Client {
+    fn new(session: Session, prompt: &str, errors: &str, progress: Option<&str>) -> Self {
+        Self {
+            session,
+            prompt: Pattern::exact(prompt),
+            errors: Regex::new(errors).expect("valid error regex"),
+            progress: progress.map(|p| Pattern::regex(p).expect("valid progress regex")),
+            on_progress: None,
+        }
+    }
+
+    /// Run a command and return its output lines, without the echoed
+    /// command and progress updates.
+    async fn run(
+        &mut self,
+        command: &str,
+    ) -> Result<(Vec<String>, Option<TransferProgress>), TransferError> {
+        let errors = self.errors.clone();
+        self.run_checking(command, &errors).await
+    }
+
+    /// Run a command like [`run`](Self::run), reporting the lines matching
+    /// `errors` as failures.
+    async fn run_checking(
+        &mut self,
+        command: &str,
+        errors: &Regex,
+    ) -> Result<(Vec<String>, Option<TransferProgress>), TransferError> {
+        self.session.send_line(command).await?;
+        // Progress first: the last update often arrives with the prompt
+        let mut patterns: Vec<Pattern> = self.progress.iter().cloned().collect();
+        patterns.push(self.prompt.clone());
+
+        // Removing only the matches leaves the rest of the output in place
+        let mut last = None;
+        loop {
+            let result = self
+                .session
+                .expect_any_consuming(&patterns, Consume::MatchOnly)
+                .await?;
+            if result.pattern_index == patterns.len() - 1 {
+                break;
+            }
+            last = sftp_progress(&result.captures);
+            if let (Some(progress), Some(callback)) = (&last, &mut self.on_progress) {
+                callback(progress);
+            }
+        }
+
+        let output = self.session.take_buffer()?;
+        let lines: Vec<String> = output
+            .split('\n')
+            .skip(1)
+            .map(|line| {
+                line.trim_matches(|c: char| c == '\r' || c == ' ')
+                    .to_string()
+            })
+            .filter(|line| !line.is_empty())
+            .collect();
+        if let Some(error) = lines.iter().find(|line| errors.is_match(line)) {
+            return Err(TransferError::Failed {
+                command: command.to_string(),
+                message: error.clone(),
+            });
+        }
+        Ok((lines, last))
+    }
+
+    /// Wait for the client to exit after `command`.
+    async fn quit(mut self, command: &str) -> Result<(), TransferError> {
+        self.session.send_line(command).await?;
+        self.session.expect(Pattern::Eof).await?;
+        Ok(())
+    }
+}
+
+/// Progress from the captures of [`SFTP_PROGRESS`].
+fn sftp_progress(captures: &[String]) -> Option<TransferProgress> {
+    Some(TransferProgress {
+        percent: captures.get(1)?.parse().ok()?,
+        transferred: captures.get(2)?.clone(),
+        rate: captures.get(3)?.clone(),
+        eta: captures.get(4).cloned(),
+    })
+}
+
+/// Progress from the transfer summary among `ftp` output lines.
+fn ftp_summary(lines: &[String]) -> Option<TransferProgress> {
+    let summary = Regex::new(FTP_SUMMARY).expect("valid summary regex");
+    let captures = lines.iter().find_map(|line| summary.captures(line))?;
+    Some(TransferProgress {
+        percent: 100,
+        transferred: format!("{} bytes", &captures[1]),
+        rate: captures[2].to_string(),
+        eta: None,
+    })
+}
+
+/// Quote a path for the `sftp` command line when needed.
+fn sftp_quote(path: &str) -> String {
+    if !path.contains([' ', '\t', '"', '\\', '\'']) {
+        return path.to_string();
+    }
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Driver for the interactive OpenSSH `sftp` client.
+///
+/// For environments where only the interactive client is available, the
+/// driver runs `put`, `get` and `ls` commands and turns the client's output
+/// into results: progress meter updates are parsed into
+/// [`TransferProgress`] and error messages into [`TransferError::Failed`].
+///
+/// Each wait for the client uses the session timeout, and every progress
+/// update restarts it, so long transfers only time out when they stall.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::SftpDriver;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut sftp = SftpDriver::spawn("deploy@backup.example.com")?;
+/// sftp.connect(None).await?;
+/// sftp.on_progress(|p| eprintln!("{}% at {}", p.percent, p.rate));
+/// sftp.put("release.tar.gz", "/srv/releases/").await?;
+/// for name in sftp.ls("/srv/releases").await? {
+///     println!("{}", name);
+/// }
+/// sftp.quit().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SftpDriver {
+    client: Client,
+}
+
+impl SftpDriver {
+    /// Drive an `sftp` client already running in `session`.
+    pub fn new(session: Session) -> Self {
+        Self {
+            client: Client::new(session, "sftp> ", SFTP_ERRORS, Some(SFTP_PROGRESS)),
+        }
+    }
+
+    /// Spawn `sftp` for a destination such as `user@host`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client cannot be started.
+    pub fn spawn(destination: &str) -> Result<Self, ExpectError> {
+        Ok(Self::new(Session::spawn_args("sftp", [destination])?))
+    }
+
+    /// Wait until the client is connected, answering a password prompt
+    /// with `password` if one appears.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LoginError::Rejected`] if the password is asked for again,
+    /// and [`TransferError::Failed`] with the client's last message if it
+    /// exits or a password is needed but none was given.
+    pub async fn connect(&mut self, password: Option<&str>) -> Result<(), TransferError> {
+        let patterns = [
+            self.client.prompt.clone(),
+            Pattern::regex(r"(?i)password: ?$").expect("valid password regex"),
+            Pattern::Eof,
+        ];
+        let mut sent = false;
+        loop {
+            let result = self.client.session.expect_any(&patterns).await?;
+            match (result.pattern_index, password) {
+                (0, _) => return Ok(()),
+                (1, _) if sent => {
+                    return Err(LoginError::Rejected {
+                        attempts: 1,
+                        failure: None,
+                        output: result.matched,
+                    }
+                    .into())
+                }
+                (1, Some(password)) => {
+                    self.client.session.send_secret(password).await?;
+                    sent = true;
+                }
+                (1, None) => return Err(failed("connect", "password required")),
+                _ => {
+                    let output = result.before.trim().lines().last().unwrap_or("");
+                    return Err(failed("connect", output.trim()));
+                }
+            }
+        }
+    }
+
+    /// Call `callback` with every progress update of `put` and `get`.
+    pub fn on_progress(&mut self, callback: impl FnMut(&TransferProgress) + Send + 'static) {
+        self.client.on_progress = Some(Box::new(callback));
+    }
+
+    /// Upload `local` to `remote`.
+    ///
+    /// # Returns
+    ///
+    /// The last progress update, or `None` if the client showed none.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn put(
+        &mut self,
+        local: &str,
+        remote: &str,
+    ) -> Result<Option<TransferProgress>, TransferError> {
+        let command = format!("put {} {}", sftp_quote(local), sftp_quote(remote));
+        Ok(self.client.run(&command).await?.1)
+    }
+
+    /// Download `remote` to `local`.
+    ///
+    /// # Returns
+    ///
+    /// The last progress update, or `None` if the client showed none.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn get(
+        &mut self,
+        remote: &str,
+        local: &str,
+    ) -> Result<Option<TransferProgress>, TransferError> {
+        let command = format!("get {} {}", sftp_quote(remote), sftp_quote(local));
+        Ok(self.client.run(&command).await?.1)
+    }
+
+    /// List a remote directory, one entry per name as printed by `ls -1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports that the
+    /// listing failed. Names that merely look like error messages are
+    /// listed.
+    pub async fn ls(&mut self, path: &str) -> Result<Vec<String>, TransferError> {
+        let command = format!("ls -1 {}", sftp_quote(path));
+        let errors = Regex::new(SFTP_LS_ERRORS).expect("valid error regex");
+        Ok(self.client.run_checking(&command, &errors).await?.0)
+    }
+
+    /// Change the remote directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn cd(&mut self, path: &str) -> Result<(), TransferError> {
+        let command = format!("cd {}", sftp_quote(path));
+        self.client.run(&command).await.map(|_| ())
+    }
+
+    /// Run any other command and return its output lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn command(&mut self, command: &str) -> Result<Vec<String>, TransferError> {
+        Ok(self.client.run(command).await?.0)
+    }
+
+    /// The underlying session.
+    pub fn session(&mut self) -> &mut Session {
+        &mut self.client.session
+    }
+
+    /// Exit the client and wait for it to close.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client does not exit in time.
+    pub async fn quit(self) -> Result<(), TransferError> {
+        self.client.quit("bye").await
+    }
+}
+
+/// Driver for the interactive `ftp` client.
+///
+/// Works like [`SftpDriver`]. Error replies (4xx and 5xx) and local errors
+/// become [`TransferError::Failed`]. The client shows no progress meter, so
+/// `put` and `get` report the summary printed after the transfer, and a
+/// large transfer needs a session timeout long enough to complete it.
+/// Paths cannot contain spaces.
+///
+/// # Examples
+///
+/// ```no_run
+/// use expectrust::{Credentials, FtpDriver};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut ftp = FtpDriver::spawn("ftp.example.com")?;
+/// ftp.login(&Credentials::new("anonymous", "guest@example.com")).await?;
+/// ftp.command("binary").await?;
+/// let summary = ftp.get("pub/README", "README").await?;
+/// println!("{:?}", summary);
+/// ftp.quit().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FtpDriver {
+    client: Client,
+}
+
+impl FtpDriver {
+    /// Drive an `ftp` client already running in `session`.
+    pub fn new(session: Session) -> Self {
+        Self {
+            client: Client::new(session, "ftp> ", FTP_ERRORS, None),
+        }
+    }
+
+    /// Spawn `ftp` for a host.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client cannot be started.
+    pub fn spawn(host: &str) -> Result<Self, ExpectError> {
+        Ok(Self::new(Session::spawn_args("ftp", [host])?))
+    }
+
+    /// Log in and wait for the command prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LoginError::Rejected`] if the server rejects the
+    /// credentials.
+    pub async fn login(&mut self, credentials: &Credentials) -> Result<(), TransferError> {
+        // The client shows its prompt after success and failure alike
+        let spec = LoginSpec::new(
+            Pattern::regex(r"Name \(.*\): ").expect("valid name regex"),
+            Pattern::exact("Password:"),
+            Pattern::exact("230 "),
+        )
+        .failure(Pattern::exact("530 "))
+        .max_retries(0);
+        self.client.session.login(&spec, credentials).await?;
+        self.client
+            .session
+            .expect(self.client.prompt.clone())
+            .await?;
+        Ok(())
+    }
+
+    /// Upload `local` to `remote`.
+    ///
+    /// # Returns
+    ///
+    /// The transfer summary, or `None` if the client printed none.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn put(
+        &mut self,
+        local: &str,
+        remote: &str,
+    ) -> Result<Option<TransferProgress>, TransferError> {
+        self.transfer(&format!("put {} {}", local, remote)).await
+    }
+
+    /// Download `remote` to `local`.
+    ///
+    /// # Returns
+    ///
+    /// The transfer summary, or `None` if the client printed none.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn get(
+        &mut self,
+        remote: &str,
+        local: &str,
+    ) -> Result<Option<TransferProgress>, TransferError> {
+        self.transfer(&format!("get {} {}", remote, local)).await
+    }
+
+    async fn transfer(&mut self, command: &str) -> Result<Option<TransferProgress>, TransferError> {
+        let (lines, _) = self.client.run(command).await?;
+        let summary = ftp_summary(&lines);
+        if let (Some(progress), Some(callback)) = (&summary, &mut self.client.on_progress) {
+            callback(progress);
+        }
+        Ok(summary)
+    }
+
+    /// Call `callback` with the summary of every `put` and `get`.
+    pub fn on_progress(&mut self, callback: impl FnMut(&TransferProgress) + Send + 'static) {
+        self.client.on_progress = Some(Box::new(callback));
+    }
+
+    /// List a remote directory, one entry per name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn ls(&mut self, path: &str) -> Result<Vec<String>, TransferError> {
+        let reply = Regex::new(r"^\d{3}[ -]").expect("valid reply regex");
+        let (lines, _) = self.client.run(&format!("nlist {}", path)).await?;
+        Ok(lines.into_iter().filter(|l| !reply.is_match(l)).collect())
+    }
+
+    /// Change the remote directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn cd(&mut self, path: &str) -> Result<(), TransferError> {
+        self.client.run(&format!("cd {}", path)).await.map(|_| ())
+    }
+
+    /// Run any other command and return its output lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TransferError::Failed`] if the client reports an error.
+    pub async fn command(&mut self, command: &str) -> Result<Vec<String>, TransferError> {
+        Ok(self.client.run(command).await?.0)
+    }
+
+    /// The underlying session.
+    pub fn session(&mut self) -> &mut Session {
+        &mut self.client.session
+    }
+
+    /// Exit the client and wait for it to close.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client does not exit in time.
+    pub async fn quit(self) -> Result<(), TransferError> {
+        self.client.quit("bye").await
+    }
+}
+
+fn failed(command: &str, message: &str) -> TransferError {
+    TransferError::Failed {
+        command: command.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress() {
+        let matcher = Pattern::regex(SFTP_PROGRESS).unwrap().to_matcher().unwrap();
+        let update = matcher
+            .find(b"\rrelease.tar  45%  512KB   1.0MB/s   00:01 ETA\r")
+            .unwrap();
+        let progress = sftp_progress(&update.captures).unwrap();
+        assert_eq!(progress.percent, 45);
+        assert_eq!(progress.transferred, "512KB");
+        assert_eq!(progress.rate, "1.0MB/s");
+        assert_eq!(progress.eta.as_deref(), Some("00:01"));
+
+        let done = matcher
+            .find(b"\rrelease.tar 100% 1024KB   1.0MB/s   00:01    \r\n")
+            .unwrap();
+        assert_eq!(sftp_progress(&done.captures).unwrap().eta, None);
+        // An update is only complete once the next one starts
+        assert!(matcher
+            .find(b"\rrelease.tar  45%  512KB   1.0MB/s   00:0")
+            .is_none());
+
+        let lines = vec![
+            "226 Transfer complete.".to_string(),
+            "1234 bytes received in 0.00 secs (4.5 MB/s)".to_string(),
+        ];
+        let summary = ftp_summary(&lines).unwrap();
+        assert_eq!(summary.transferred, "1234 bytes");
+        assert_eq!(summary.rate, "4.5 MB/s");
+    }
+
+    #[test]
+    fn test_error_lines() {
+        let sftp = Regex::new(SFTP_ERRORS).unwrap();
+        assert!(sftp.is_match("stat missing.txt: No such file or directory"));
+        assert!(sftp.is_match("File \"/srv/x\" not found."));
+        assert!(sftp.is_match("Can't ls: \"/nope\" not found"));
+        assert!(!sftp.is_match("Uploading a.txt to /srv/a.txt"));
+
+        // Listed names are only errors if ls itself failed
+        let sftp_ls = Regex::new(SFTP_LS_ERRORS).unwrap();
+        assert!(sftp_ls.is_match("Can't ls: \"/nope\" not found"));
+        assert!(sftp_ls.is_match("File \"/srv/*.bak\" not found."));
+        for name in ["remote notes.txt", "stat report.pdf", "log: Failure"] {
+            assert!(sftp.is_match(name));
+            assert!(!sftp_ls.is_match(name));
+        }
+
+        let ftp = Regex::new(FTP_ERRORS).unwrap();
+        assert!(ftp.is_match("550 Failed to open file."));
+        assert!(ftp.is_match("local: missing: No such file or directory"));
+        assert!(!ftp.is_match("local: a.txt remote: a.txt"));
+        assert!(!ftp.is_match("226 Transfer complete."));
+
+        assert_eq!(sftp_quote("/srv/a.txt"), "/srv/a.txt");
+        assert_eq!(sftp_quote("my \"file\""), "\"my \\\"file\\\"\"");
+    }
+}
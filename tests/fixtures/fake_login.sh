@@ -0,0 +1,15 @@
+#!/bin/sh
+# Minimal login dialog used by the login tests
+while true; do
+    printf 'login: '
+    read -r user
+    printf 'Password: '
+    read -r pass
+    if [ "$user" = "admin" ] && [ "$pass" = "secret" ]; then
+        echo "Welcome $user"
+        printf '$ '
+        read -r cmd
+        exit 0
+    fi
+    echo "Login incorrect"
+done
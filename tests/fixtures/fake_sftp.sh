@@ -0,0 +1,26 @@
+#!/bin/sh
+# Minimal sftp client used by the transfer tests
+echo "Connected to fake."
+while printf 'sftp> ' && read -r cmd arg1 arg2; do
+    case "$cmd" in
+        put)
+            if [ "$arg1" = "missing.txt" ]; then
+                echo "stat $arg1: No such file or directory"
+                continue
+            fi
+            echo "Uploading $arg1 to $arg2"
+            printf '%s   50%%  512KB   1.0MB/s   00:01 ETA' "$arg1"
+            sleep 0.1
+            printf '\r%s  100%% 1024KB   1.0MB/s   00:01    \n' "$arg1"
+            ;;
+        ls)
+            printf 'a.txt\nb.txt\n'
+            ;;
+        bye)
+            exit 0
+            ;;
+        *)
+            echo "Invalid command."
+            ;;
+    esac
+done
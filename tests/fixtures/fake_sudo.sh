@@ -0,0 +1,15 @@
+#!/bin/sh
+# Minimal sudo used by the prompt classifier tests: asks until the password
+# is right, then stays cached
+printf '[sudo] password for admin: '
+read -r pass
+while [ "$pass" != "secret" ]; do
+    echo "Sorry, try again."
+    printf '[sudo] password for admin: '
+    read -r pass
+done
+while true; do
+    echo "done"
+    printf '$ '
+    read -r cmd
+done
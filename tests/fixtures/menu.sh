@@ -0,0 +1,24 @@
+#!/bin/bash
+# Minimal full-screen menu used by the navigation tests: the arrow keys
+# move the cursor between items and Enter selects one
+items=(alpha beta gamma)
+row=0
+stty -echo -icanon min 1 time 0
+printf '\033[2J'
+for i in "${!items[@]}"; do
+    printf '\033[%d;3H%s' $((i + 2)) "${items[$i]}"
+done
+printf '\033[2;3H'
+while IFS= read -rsn1 key; do
+    if [[ $key == $'\033' ]]; then
+        read -rsn2 rest
+        case $rest in
+            '[A'|'OA') ((row > 0)) && row=$((row - 1)) ;;
+            '[B'|'OB') ((row < ${#items[@]} - 1)) && row=$((row + 1)) ;;
+        esac
+        printf '\033[%d;3H' $((row + 2))
+    elif [[ -z $key ]]; then
+        printf '\033[6;1Hselected %s\r\n' "${items[$row]}"
+        exit 0
+    fi
+done
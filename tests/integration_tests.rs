@@ -1,17 +1,13 @@
 //! Integration tests for ExpectRust
 
-use expectrust::{ExpectError, Pattern, Session};
+use expectrust::{cmd, Credentials, ExpectError, LoginError, LoginSpec, Pattern, Session};
 use std::time::Duration;
 
 #[tokio::test]
 async fn test_basic_command_execution() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Hello World"
-        } else {
-            "echo Hello World"
-        })
+        .spawn(&cmd::echo("Hello World"))
         .expect("Failed to spawn command");
 
     let result = session
@@ -26,11 +22,7 @@ async fn test_basic_command_execution() {
 async fn test_exact_pattern_matching() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Testing exact pattern"
-        } else {
-            "echo Testing exact pattern"
-        })
+        .spawn(&cmd::echo("Testing exact pattern"))
         .expect("Failed to spawn");
 
     let result = session
@@ -45,11 +37,7 @@ async fn test_exact_pattern_matching() {
 async fn test_regex_pattern_matching() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Number: 12345"
-        } else {
-            "echo Number: 12345"
-        })
+        .spawn(&cmd::echo("Number: 12345"))
         .expect("Failed to spawn");
 
     let result = session
@@ -65,11 +53,7 @@ async fn test_regex_pattern_matching() {
 async fn test_multiple_patterns() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo SUCCESS message"
-        } else {
-            "echo SUCCESS message"
-        })
+        .spawn(&cmd::echo("SUCCESS message"))
         .expect("Failed to spawn");
 
     let patterns = [
@@ -91,11 +75,7 @@ async fn test_multiple_patterns() {
 async fn test_timeout_error() {
     let mut session = Session::builder()
         .timeout(Duration::from_millis(100))
-        .spawn(if cfg!(windows) {
-            "cmd /C timeout /t 2"
-        } else {
-            "sleep 2"
-        })
+        .spawn(&cmd::sleep(2))
         .expect("Failed to spawn");
 
     let result = session.expect(Pattern::exact("NEVER_APPEARS")).await;
@@ -116,11 +96,7 @@ async fn test_timeout_error() {
 async fn test_eof_pattern() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Quick"
-        } else {
-            "echo Quick"
-        })
+        .spawn(&cmd::echo("Quick"))
         .expect("Failed to spawn");
 
     let patterns = [Pattern::exact("Quick"), Pattern::Eof];
@@ -168,11 +144,7 @@ async fn test_session_builder() {
         .max_buffer_size(4096)
         .strip_ansi(false)
         .pty_size(24, 80)
-        .spawn(if cfg!(windows) {
-            "cmd /C echo test"
-        } else {
-            "echo test"
-        });
+        .spawn(&cmd::echo("test"));
 
     assert!(session.is_ok());
 }
@@ -181,11 +153,7 @@ async fn test_session_builder() {
 async fn test_is_alive() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo alive"
-        } else {
-            "echo alive"
-        })
+        .spawn(&cmd::echo("alive"))
         .expect("Failed to spawn");
 
     // Process should be alive initially or finish quickly
@@ -197,11 +165,7 @@ async fn test_is_alive() {
 async fn test_pattern_at_buffer_start() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo START of line"
-        } else {
-            "echo START of line"
-        })
+        .spawn(&cmd::echo("START of line"))
         .expect("Failed to spawn");
 
     let result = session
@@ -216,11 +180,7 @@ async fn test_pattern_at_buffer_start() {
 async fn test_utf8_support() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Hello 世界"
-        } else {
-            "echo Hello 世界"
-        })
+        .spawn(&cmd::echo("Hello 世界"))
         .expect("Failed to spawn");
 
     let result = session
@@ -235,11 +195,7 @@ async fn test_utf8_support() {
 async fn test_regex_with_captures() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Email: test@example.com"
-        } else {
-            "echo Email: test@example.com"
-        })
+        .spawn(&cmd::echo("Email: test@example.com"))
         .expect("Failed to spawn");
 
     let result = session
@@ -283,11 +239,7 @@ async fn test_ansi_stripping() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
         .strip_ansi(true)
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Test"
-        } else {
-            "echo Test"
-        })
+        .spawn(&cmd::echo("Test"))
         .expect("Failed to spawn");
 
     let result = session
@@ -302,11 +254,7 @@ async fn test_ansi_stripping() {
 async fn test_timeout_pattern() {
     let mut session = Session::builder()
         .timeout(Duration::from_millis(100))
-        .spawn(if cfg!(windows) {
-            "cmd /C timeout /t 2"
-        } else {
-            "sleep 2"
-        })
+        .spawn(&cmd::sleep(2))
         .expect("Failed to spawn");
 
     let patterns = [Pattern::exact("NEVER"), Pattern::Timeout, Pattern::Eof];
@@ -322,11 +270,7 @@ async fn test_timeout_pattern() {
 
 #[tokio::test]
 async fn test_convenience_spawn() {
-    let session = Session::spawn(if cfg!(windows) {
-        "cmd /C echo convenience"
-    } else {
-        "echo convenience"
-    });
+    let session = Session::spawn(&cmd::echo("convenience"));
 
     assert!(session.is_ok());
 }
@@ -335,11 +279,7 @@ async fn test_convenience_spawn() {
 async fn test_case_insensitive_regex() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo HELLO world"
-        } else {
-            "echo HELLO world"
-        })
+        .spawn(&cmd::echo("HELLO world"))
         .expect("Failed to spawn");
 
     let result = session
@@ -354,11 +294,7 @@ async fn test_case_insensitive_regex() {
 async fn test_before_field() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo BEFORE_TEXT MARKER AFTER_TEXT"
-        } else {
-            "echo BEFORE_TEXT MARKER AFTER_TEXT"
-        })
+        .spawn(&cmd::echo("BEFORE_TEXT MARKER AFTER_TEXT"))
         .expect("Failed to spawn");
 
     let result = session
@@ -453,11 +389,7 @@ async fn test_wait_for_process() {
 
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo done"
-        } else {
-            "echo done"
-        })
+        .spawn(&cmd::echo("done"))
         .expect("Failed to spawn");
 
     // Wait for the process to complete
@@ -480,8 +412,11 @@ async fn test_sequential_commands() {
         .spawn("bash -i")
         .expect("Failed to spawn bash");
 
-    // Wait for prompt (can be $ or bash-version info)
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    // Skip the prompt and any startup output
+    session
+        .settle(Duration::from_secs(5), Duration::from_millis(300))
+        .await
+        .expect("Startup output did not settle");
 
     // Send first command
     session
@@ -515,11 +450,7 @@ async fn test_sequential_commands() {
 async fn test_pattern_position_info() {
     let mut session = Session::builder()
         .timeout(Duration::from_secs(5))
-        .spawn(if cfg!(windows) {
-            "cmd /C echo Position test"
-        } else {
-            "echo Position test"
-        })
+        .spawn(&cmd::echo("Position test"))
         .expect("Failed to spawn");
 
     let result = session
@@ -536,11 +467,7 @@ async fn test_pattern_position_info() {
 async fn test_no_timeout() {
     let mut session = Session::builder()
         .no_timeout()
-        .spawn(if cfg!(windows) {
-            "cmd /C echo No timeout test"
-        } else {
-            "echo No timeout test"
-        })
+        .spawn(&cmd::echo("No timeout test"))
         .expect("Failed to spawn");
 
     // Should work even with no timeout set
@@ -585,3 +512,2582 @@ async fn test_spawn_invalid_command() {
     // Should fail to spawn non-existent command
     assert!(result.is_err());
 }
+
+#[tokio::test]
+async fn test_assert_no_output_silent() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn("sleep 2")
+        .expect("Failed to spawn");
+
+    session
+        .assert_no_output(Duration::from_millis(200))
+        .await
+        .expect("Silent process reported output");
+}
+
+#[tokio::test]
+async fn test_assert_no_output_chatter() {
+    if cfg!(windows) {
+        return;
+    }
+
+    // Printed after the window starts; earlier output would not count
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn_shell("sleep 0.3; echo chatter")
+        .expect("Failed to spawn");
+
+    match session.assert_no_output(Duration::from_secs(2)).await {
+        Err(ExpectError::UnexpectedOutput { output }) => assert!(output.contains("chatter")),
+        other => panic!("Expected UnexpectedOutput, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_assert_no_output_ignores_earlier_output() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn_shell("echo early; sleep 2")
+        .expect("Failed to spawn");
+    // Let the output reach the reader without reading it into the buffer
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    session
+        .assert_no_output(Duration::from_millis(200))
+        .await
+        .expect("Output from before the call was counted");
+    assert!(session.buffer().as_str().contains("early"));
+}
+
+#[tokio::test]
+async fn test_small_read_chunk_size() {
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .read_chunk_size(1)
+        .spawn(&cmd::echo("chunked output"))
+        .expect("Failed to spawn");
+
+    let result = session
+        .expect(Pattern::exact("output"))
+        .await
+        .expect("Pattern not found with tiny read chunks");
+
+    assert!(result.before.contains("chunked"));
+}
+
+#[tokio::test]
+async fn test_eof_after_exit() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn("echo finished")
+        .expect("Failed to spawn");
+
+    let result = session
+        .expect(Pattern::Eof)
+        .await
+        .expect("EOF not detected after process exit");
+
+    assert!(result.before.contains("finished"));
+}
+
+#[tokio::test]
+async fn test_serial_control_on_pty_session() {
+    let mut session = Session::spawn(&cmd::echo("serial")).expect("Failed to spawn");
+
+    match session.send_break(Duration::from_millis(1)).await {
+        Err(ExpectError::IoError(e)) => assert_eq!(e.kind(), std::io::ErrorKind::Unsupported),
+        other => panic!("Expected Unsupported, got {:?}", other),
+    }
+    assert!(session.set_dtr(true).is_err());
+    assert!(session.set_baud_rate(9600).is_err());
+}
+
+#[tokio::test]
+async fn test_open_serial_missing_device() {
+    let result = Session::open_serial("/nonexistent/serial-device", 115200);
+    assert!(matches!(result, Err(ExpectError::IoError(_))));
+}
+
+#[tokio::test]
+async fn test_char_delay() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .char_delay(b'\n', Duration::from_millis(100))
+        .spawn("cat")
+        .expect("Failed to spawn");
+
+    let start = std::time::Instant::now();
+    session.send(b"one\ntwo\n").await.expect("Failed to send");
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    session
+        .expect(Pattern::exact("two"))
+        .await
+        .expect("Failed to match delayed output");
+
+    session.set_char_delay(b'\n', None);
+    let start = std::time::Instant::now();
+    session.send(b"three\n").await.expect("Failed to send");
+    assert!(start.elapsed() < Duration::from_millis(100));
+}
+
+/// Command running the fake login fixture (children start in the home directory).
+fn fake_login_command() -> String {
+    format!(
+        "sh {}/tests/fixtures/fake_login.sh",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+#[tokio::test]
+async fn test_login_success() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let spec = LoginSpec::new(
+        Pattern::exact("login: "),
+        Pattern::exact("Password: "),
+        Pattern::exact("$ "),
+    )
+    .failure(Pattern::exact("Login incorrect"));
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&fake_login_command())
+        .expect("Failed to spawn");
+
+    let result = session
+        .login(&spec, &Credentials::new("admin", "secret"))
+        .await
+        .expect("Login failed");
+    assert!(result.before.contains("Welcome admin"));
+}
+
+#[tokio::test]
+async fn test_login_rejected() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let spec = LoginSpec::new(
+        Pattern::exact("login: "),
+        Pattern::exact("Password: "),
+        Pattern::exact("$ "),
+    )
+    .failure(Pattern::exact("Login incorrect"))
+    .max_retries(1);
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&fake_login_command())
+        .expect("Failed to spawn");
+
+    match session
+        .login(&spec, &Credentials::new("admin", "wrong"))
+        .await
+    {
+        Err(LoginError::Rejected {
+            attempts, failure, ..
+        }) => {
+            assert_eq!(attempts, 2);
+            assert_eq!(failure, Some(0));
+        }
+        other => panic!("Expected Rejected, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_login_unrecognized_rejection() {
+    if cfg!(windows) {
+        return;
+    }
+
+    // Without a failure pattern, a new prompt after the password is a rejection
+    let spec = LoginSpec::new(
+        Pattern::exact("login: "),
+        Pattern::exact("Password: "),
+        Pattern::exact("$ "),
+    )
+    .max_retries(0);
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&fake_login_command())
+        .expect("Failed to spawn");
+
+    match session
+        .login(&spec, &Credentials::new("admin", "wrong"))
+        .await
+    {
+        Err(LoginError::Rejected {
+            attempts, failure, ..
+        }) => {
+            assert_eq!(attempts, 1);
+            assert_eq!(failure, None);
+        }
+        other => panic!("Expected Rejected, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_expect_service() {
+    use expectrust::service::{ExpectService, Request, Response, Service};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    /// Middleware counting the requests passed to the inner service.
+    struct Counted<S> {
+        inner: S,
+        count: usize,
+    }
+
+    impl<S: Service<Request>> Service<Request> for Counted<S> {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(
+            &mut self,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            self.inner.poll_ready(cx)
+        }
+
+        fn call(&mut self, request: Request) -> Self::Future {
+            self.count += 1;
+            self.inner.call(request)
+        }
+    }
+
+    let session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn("cat")
+        .expect("Failed to spawn");
+    let mut service = Counted {
+        inner: ExpectService::new(session),
+        count: 0,
+    };
+
+    let sent = service
+        .call(Request::Send(b"service\n".to_vec()))
+        .await
+        .expect("Failed to send");
+    assert!(matches!(sent, Response::Sent));
+
+    match service
+        .call(Request::Expect(vec![Pattern::exact("service")]))
+        .await
+        .expect("Failed to match")
+    {
+        Response::Matched(m) => assert_eq!(m.matched, "service"),
+        other => panic!("Expected Matched, got {:?}", other),
+    }
+    assert_eq!(service.count, 2);
+    assert!(service.inner.into_session().is_some());
+}
+
+#[cfg(feature = "steps")]
+#[tokio::test]
+async fn test_run_steps_file_reloads() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let path = std::env::temp_dir().join(format!("expectrust-steps-{}.toml", std::process::id()));
+    std::fs::write(&path, "[[step]]\nexpect = \"never printed\"\n").unwrap();
+
+    let history = path.with_extension("history");
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(10))
+        .history_file(&history)
+        .spawn("cat")
+        .expect("Failed to spawn");
+    session.send(b"ready\n").await.unwrap();
+
+    let edit_path = path.clone();
+    let editor = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        std::fs::write(
+            &edit_path,
+            "[[step]]\nexpect = \"ready\"\nsend = \"done\\n\"\n\n[[step]]\nexpect = \"done\"\n",
+        )
+        .unwrap();
+    });
+
+    let result = session.run_steps_file(&path).await;
+    editor.await.unwrap();
+    result.expect("Steps did not complete after reload");
+
+    // One record per completed step, not one per reload check
+    drop(session);
+    let recorded = expectrust::History::load(&history).unwrap();
+    std::fs::remove_file(&history).ok();
+    assert_eq!(recorded.expects().len(), 2);
+
+    // A reload that removes the running step is an error
+    std::fs::write(&path, "[[step]]\nexpect = \"never printed\"\n").unwrap();
+    let mut session = Session::spawn("cat").expect("Failed to spawn");
+    let edit_path = path.clone();
+    let editor = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        std::fs::write(&edit_path, "").unwrap();
+    });
+    let result = session.run_steps_file(&path).await;
+    editor.await.unwrap();
+    std::fs::remove_file(&path).ok();
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_expect_screen_region() {
+    use expectrust::{Rect, ScreenPattern};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .screen(true)
+        .spawn(r"printf '\033[3;5Hready\033[1;1Htitle'")
+        .expect("Failed to spawn");
+
+    let result = session
+        .expect_screen(&ScreenPattern::at(Rect::row(2), Pattern::exact("ready")))
+        .await
+        .expect("Failed to match screen region");
+    assert_eq!(result.before, "    ");
+
+    // "ready" is on the screen, but not in the first row
+    let result = session
+        .expect_screen(&ScreenPattern::at(Rect::row(0), Pattern::exact("ready")))
+        .await;
+    assert!(matches!(result, Err(ExpectError::Eof)));
+
+    let screen = session.screen().expect("Screen model enabled");
+    assert_eq!(screen.row_text(0), "title");
+}
+
+#[tokio::test]
+async fn test_expect_screen_disabled() {
+    use expectrust::ScreenPattern;
+
+    let mut session = Session::spawn(&cmd::echo("hi")).expect("Failed to spawn");
+
+    let result = session
+        .expect_screen(&ScreenPattern::anywhere(Pattern::exact("hi")))
+        .await;
+    assert!(matches!(result, Err(ExpectError::ScreenDisabled)));
+}
+
+#[tokio::test]
+async fn test_navigate_menu() {
+    use expectrust::{Key, Rect, ScreenPattern};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .screen(true)
+        .spawn(&format!(
+            "bash {}/tests/fixtures/menu.sh",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("Failed to spawn");
+
+    // The menu is drawn once the cursor is parked on the first item
+    session
+        .expect(Pattern::exact("gamma\x1b[2;3H"))
+        .await
+        .expect("Menu not drawn");
+    session
+        .expect_screen(&ScreenPattern::at(Rect::row(3), Pattern::exact("gamma")))
+        .await
+        .expect("Menu not on screen");
+    assert_eq!(session.cursor().unwrap(), (1, 2));
+
+    session.navigate_to(3, 2).await.expect("Failed to navigate");
+    assert_eq!(session.cursor().unwrap(), (3, 2));
+
+    session.navigate_to(2, 2).await.expect("Failed to navigate");
+    session.press(Key::Enter, 1).await.unwrap();
+    session
+        .expect(Pattern::exact("selected beta"))
+        .await
+        .expect("Wrong item selected");
+}
+
+#[tokio::test]
+async fn test_merged_session() {
+    use expectrust::MergedSession;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let sessions = ["a", "b", "c"]
+        .iter()
+        .map(|name| Session::spawn(&format!("echo READY-{}", name)))
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to spawn");
+    let mut merged = MergedSession::new(sessions);
+    assert_eq!(merged.len(), 3);
+
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+        let found = merged
+            .expect(Pattern::regex(r"READY-(\w)").unwrap())
+            .await
+            .expect("Failed to match merged output");
+        assert_eq!(found.sources.len(), 1);
+        let expected = ["a", "b", "c"][found.sources[0]];
+        assert_eq!(found.result.captures[1], expected);
+        seen.push(found.sources[0]);
+    }
+    seen.sort();
+    assert_eq!(seen, vec![0, 1, 2]);
+
+    let result = merged.expect_any(&[Pattern::Eof]).await.unwrap();
+    assert!(result.sources.is_empty());
+}
+
+#[tokio::test]
+async fn test_session_group_all_expect() {
+    use expectrust::SessionGroup;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let sessions = ["one", "two", "three"]
+        .iter()
+        .map(|name| Session::spawn(&format!("echo ready {}", name)))
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to spawn");
+    let mut group = SessionGroup::new(sessions);
+
+    let results = group
+        .all_expect(Pattern::regex(r"ready \w+").unwrap())
+        .await
+        .expect("Not all sessions matched");
+    let matched: Vec<_> = results.iter().map(|r| r.matched.as_str()).collect();
+    assert_eq!(matched, vec!["ready one", "ready two", "ready three"]);
+}
+
+#[tokio::test]
+async fn test_session_group_reports_failures() {
+    use expectrust::SessionGroup;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let sessions = vec![
+        Session::spawn("echo ready").unwrap(),
+        Session::spawn("sleep 5").unwrap(),
+    ];
+    let mut group = SessionGroup::new(sessions);
+    group.set_timeout(Some(Duration::from_millis(300)));
+
+    let start = std::time::Instant::now();
+    let err = group
+        .all_expect(Pattern::exact("ready"))
+        .await
+        .expect_err("The sleeping session cannot match");
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(err.results[0].is_ok());
+    let failures: Vec<_> = err.failures().map(|(i, _)| i).collect();
+    assert_eq!(failures, vec![1]);
+    assert_eq!(err.to_string(), "1 of 2 sessions failed to match");
+
+    let (index, _) = group
+        .any_expect(Pattern::Eof)
+        .await
+        .expect("The echo session has exited");
+    assert_eq!(index, 0);
+}
+
+#[tokio::test]
+async fn test_session_group_connect_with_backoff() {
+    use expectrust::{Backoff, SessionGroup};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let calls = AtomicU32::new(0);
+    let backoff =
+        Backoff::new(Duration::from_millis(10), Duration::from_millis(50)).max_attempts(3);
+    let (mut group, report) =
+        SessionGroup::connect(vec!["echo ready", "echo broken"], &backoff, |command| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let mut session = Session::spawn(command)?;
+                session.expect(Pattern::exact("ready")).await?;
+                Ok::<_, ExpectError>(session)
+            }
+        })
+        .await;
+
+    assert_eq!(group.len(), 1);
+    assert_eq!(report.connected, vec!["echo ready"]);
+    assert_eq!(report.unreachable.len(), 1);
+    assert_eq!(report.unreachable[0].target, "echo broken");
+    assert_eq!(report.unreachable[0].attempts, 3);
+    assert!(matches!(report.unreachable[0].error, ExpectError::Eof));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+    group.any_expect(Pattern::Eof).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_collapse_progress() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .collapse_progress(true)
+        .spawn(r"printf '10%%\r50%%\r100%%\ndone\n'")
+        .expect("Failed to spawn");
+
+    let result = session
+        .expect(Pattern::exact("done"))
+        .await
+        .expect("Failed to match");
+    assert_eq!(result.before, "100%\r\n");
+}
+
+#[tokio::test]
+async fn test_collapse_progress_keeps_final_carriage_return() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .collapse_progress(true)
+        .transcript(true)
+        .spawn(r"printf '10%%\r50%%\r'")
+        .expect("Failed to spawn");
+
+    let result = session.expect(Pattern::Eof).await.unwrap();
+    assert_eq!(result.before, "50%\r");
+    let transcript = session.transcript().unwrap();
+    assert_eq!(transcript.bytes_from(expectrust::Origin::Process), b"50%\r");
+}
+
+#[tokio::test]
+async fn test_expect_consume_granularity() {
+    use expectrust::Consume;
+
+    let mut session = Session::spawn(&cmd::echo("alpha beta gamma")).expect("Failed to spawn");
+
+    // Nothing consumed: the same text matches again
+    session
+        .expect_consuming(Pattern::exact("gamma"), Consume::Nothing)
+        .await
+        .unwrap();
+    let result = session
+        .expect_consuming(Pattern::exact("gamma"), Consume::MatchOnly)
+        .await
+        .unwrap();
+    assert!(result.before.ends_with("alpha beta "));
+
+    // Text before the match is still available, the match itself is not
+    session.expect(Pattern::exact("alpha")).await.unwrap();
+    let result = session
+        .expect_any(&[Pattern::exact("gamma"), Pattern::exact("beta")])
+        .await
+        .unwrap();
+    assert_eq!(result.pattern_index, 1);
+}
+
+#[tokio::test]
+async fn test_session_regex_options() {
+    use expectrust::RegexOptions;
+
+    let mut session = Session::builder()
+        .regex_options(RegexOptions::new().case_insensitive(true))
+        .spawn(&cmd::echo("HELLO"))
+        .expect("Failed to spawn");
+
+    let pattern = session.pattern().regex(r"hel+o").unwrap();
+    let result = session.expect(pattern).await.expect("Failed to match");
+    assert_eq!(result.matched, "HELLO");
+}
+
+#[tokio::test]
+async fn test_match_budget() {
+    use expectrust::PatternError;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(10))
+        .max_buffer_size(1 << 20)
+        .match_budget(Duration::from_millis(1))
+        .spawn("seq 1 50000")
+        .expect("Failed to spawn");
+
+    // Rescanning the growing buffer for a pattern that never matches
+    let result = session
+        .expect(Pattern::regex(r"(\d+\s+){3}x").unwrap())
+        .await;
+    assert!(
+        matches!(
+            result,
+            Err(ExpectError::PatternError(PatternError::Budget { .. }))
+        ),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn test_expect_typed() {
+    use expectrust::TypedPatterns;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Outcome {
+        Failed,
+        Done,
+        Exited,
+    }
+
+    let mut session = Session::spawn(&cmd::echo("done")).expect("Failed to spawn");
+
+    let patterns = TypedPatterns::new()
+        .with(Outcome::Failed, Pattern::exact("failed"))
+        .with(Outcome::Done, Pattern::exact("done"))
+        .with(Outcome::Exited, Pattern::Eof);
+
+    let (outcome, result) = session.expect_typed(&patterns).await.unwrap();
+    assert_eq!(outcome, Outcome::Done);
+    assert_eq!(result.matched, "done");
+
+    let (outcome, _) = session.expect_typed(&patterns).await.unwrap();
+    assert_eq!(outcome, Outcome::Exited);
+}
+
+#[tokio::test]
+async fn test_spawn_shell() {
+    let mut session = Session::spawn_shell(if cfg!(windows) {
+        "echo one && echo two"
+    } else {
+        "echo one | tr a-z A-Z && echo 'two  words'"
+    })
+    .expect("Failed to spawn shell");
+
+    if cfg!(windows) {
+        session.expect(Pattern::exact("two")).await.unwrap();
+    } else {
+        session.expect(Pattern::exact("ONE")).await.unwrap();
+        session.expect(Pattern::exact("two  words")).await.unwrap();
+    }
+}
+
+#[test]
+fn test_spawn_shell_empty() {
+    assert!(matches!(
+        Session::spawn_shell("  "),
+        Err(ExpectError::SpawnError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_transcript_records_origin() {
+    use expectrust::{Direction, Origin};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .transcript(true)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+
+    session.send_line("hello").await.unwrap();
+    session.expect(Pattern::exact("hello")).await.unwrap();
+
+    let transcript = session.transcript().expect("Transcript is enabled");
+    let first = &transcript.entries()[0];
+    assert_eq!(first.origin, Origin::Automation);
+    assert_eq!(first.direction(), Direction::Sent);
+    assert_eq!(transcript.bytes_from(Origin::Automation), b"hello\n");
+    assert!(transcript.bytes_from(Origin::Human).is_empty());
+    assert!(String::from_utf8_lossy(&transcript.bytes_in(Direction::Received)).contains("hello"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_as_raw_fd() {
+    let session = Session::spawn("cat").expect("Failed to spawn cat");
+    let fd = session.as_raw_fd().expect("PTY master has a descriptor");
+
+    // SAFETY: the descriptor is owned by the live session
+    assert_eq!(unsafe { libc::isatty(fd) }, 1);
+}
+
+#[tokio::test]
+async fn test_packet_mode_flow_control_events() {
+    use expectrust::PtyEvent;
+
+    if !cfg!(any(target_os = "linux", target_os = "macos")) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .packet_mode(true)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+
+    // ^S stops output, ^Q restarts it
+    session.send(b"\x13").await.unwrap();
+    session.send_line("flowing").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    session.send(b"\x11").await.unwrap();
+
+    // Control packets must not leak into the output
+    let result = session.expect(Pattern::exact("flowing")).await.unwrap();
+    assert!(!result.before.contains(['\0', '\u{4}', '\u{8}']));
+
+    let events = session.take_pty_events();
+    let stop = events.iter().position(|e| *e == PtyEvent::Stop);
+    let start = events.iter().position(|e| *e == PtyEvent::Start);
+    assert!(stop.is_some() && start.is_some(), "events: {events:?}");
+    assert!(stop < start);
+    assert!(session.take_pty_events().is_empty());
+}
+
+#[cfg(feature = "diagnostics")]
+#[tokio::test]
+async fn test_match_diagnostics() {
+    let mut session = Session::spawn(&cmd::echo("alpha beta")).expect("Failed to spawn");
+
+    let patterns = [
+        Pattern::exact("never printed"),
+        Pattern::Eof,
+        Pattern::exact("beta"),
+    ];
+    let result = session.expect_any(&patterns).await.unwrap();
+    assert_eq!(result.pattern_index, 2);
+
+    let diagnostics = session
+        .match_diagnostics()
+        .expect("Diagnostics are collected");
+    assert!(diagnostics.passes >= 1);
+    assert_eq!(diagnostics.patterns.len(), 3);
+    assert_eq!(diagnostics.patterns[0].attempts, diagnostics.passes);
+    assert!(diagnostics.patterns[0].bytes_scanned > 0);
+    assert_eq!(diagnostics.patterns[1].attempts, 0);
+    assert_eq!(diagnostics.patterns[2].attempts, diagnostics.passes);
+}
+
+#[cfg(feature = "diagnostics")]
+#[tokio::test]
+async fn test_match_diagnostics_count_search_window() {
+    use expectrust::ExpectOptions;
+
+    let mut session = Session::spawn(&cmd::echo("alpha beta")).expect("Failed to spawn");
+    session.expect(Pattern::Eof).await.unwrap();
+
+    // Only the window is scanned, however much output is unconsumed
+    let options = ExpectOptions {
+        search_window: Some(4),
+        ..Default::default()
+    };
+    let result = session
+        .expect_any_opts(&[Pattern::exact("never printed"), Pattern::Eof], options)
+        .await
+        .unwrap();
+    assert_eq!(result.pattern_index, 1);
+    let diagnostics = session.match_diagnostics().unwrap();
+    assert_eq!(diagnostics.patterns[0].attempts, 1);
+    assert_eq!(diagnostics.patterns[0].bytes_scanned, 4);
+}
+
+#[tokio::test]
+async fn test_auto_respond() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let script = concat!(
+        "for i in 1 2 3; do printf 'page %s Press ENTER to continue' $i; read x; done; ",
+        "printf 'Continue? (yes/no) '; read answer; echo \"answer=$answer\""
+    );
+    let mut session = Session::spawn_shell(script).expect("Failed to spawn");
+    session
+        .auto_respond(Pattern::exact("Press ENTER to continue"), "\n")
+        .unwrap()
+        .limit(2);
+    session
+        .auto_respond(Pattern::exact("(yes/no)"), "yes\n")
+        .unwrap();
+
+    // The third page exceeds the limit and is left for the foreground expect
+    let result = session.expect(Pattern::exact("page 3")).await.unwrap();
+    assert!(!result.before.contains("Press ENTER"));
+    session.send_line("").await.unwrap();
+
+    session.expect(Pattern::exact("answer=yes")).await.unwrap();
+    assert_eq!(session.auto_responses()[0].count(), 2);
+    assert!(session.auto_responses()[0].is_exhausted());
+    assert_eq!(session.auto_responses()[1].count(), 1);
+}
+
+#[tokio::test]
+async fn test_raw_output_layer() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .strip_ansi(true)
+        .spawn("printf '\\033[1mbold\\033[0m'")
+        .expect("Failed to spawn");
+
+    let result = session.expect(Pattern::Eof).await.unwrap();
+    assert!(result.before.contains("bold"));
+    assert!(!session.buffer().as_bytes().contains(&0x1b));
+
+    let raw = String::from_utf8_lossy(session.raw_output().as_bytes()).into_owned();
+    assert!(raw.contains("\x1b[1mbold\x1b[0m"), "raw: {raw:?}");
+    assert_eq!(session.raw_tail(4), b"\x1b[0m");
+}
+
+#[tokio::test]
+async fn test_send_human() {
+    use expectrust::HumanTyping;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let typing = HumanTyping::new()
+        .average(Duration::from_millis(20))
+        .word_average(Duration::from_millis(40))
+        .min(Duration::from_millis(10))
+        .max(Duration::from_millis(50));
+    let mut session = Session::builder()
+        .human_typing(typing)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+
+    let start = std::time::Instant::now();
+    session.send_human("hi there\n").await.unwrap();
+    // Eight pauses of at least 10ms each
+    assert!(start.elapsed() >= Duration::from_millis(80));
+
+    session.expect(Pattern::exact("hi there")).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_send_control() {
+    use expectrust::ControlChar;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::spawn("cat").expect("Failed to spawn cat");
+    let err = session.send_control('1').await.unwrap_err();
+    assert!(matches!(err, ExpectError::IoError(_)));
+
+    session.send_line("hello").await.unwrap();
+    session.expect(Pattern::exact("hello")).await.unwrap();
+    session.send_control(ControlChar::CtrlD).await.unwrap();
+    session.expect(Pattern::Eof).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_terminate_and_kill() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::spawn(&cmd::sleep(10)).expect("Failed to spawn sleep");
+    let start = std::time::Instant::now();
+    let status = session.terminate().await.unwrap();
+    assert!(!status.success());
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(matches!(
+        session.kill().await,
+        Err(ExpectError::ProcessExited)
+    ));
+
+    // A process ignoring SIGTERM is killed after the grace period
+    let mut session = Session::builder()
+        .terminate_grace(Duration::from_millis(300))
+        .spawn_shell("trap '' TERM; echo ready; sleep 10")
+        .expect("Failed to spawn shell");
+    session.expect(Pattern::exact("ready")).await.unwrap();
+    let start = std::time::Instant::now();
+    let status = session.terminate().await.unwrap();
+    assert!(!status.success());
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // An unrepresentable grace period waits without a deadline
+    let mut session = Session::builder()
+        .terminate_grace(Duration::MAX)
+        .spawn(&cmd::sleep(10))
+        .expect("Failed to spawn sleep");
+    let status = session.terminate().await.unwrap();
+    assert!(!status.success());
+}
+
+#[tokio::test]
+async fn test_try_wait_and_wait_timeout() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::spawn_shell("sleep 0.5; exit 3").expect("Failed to spawn shell");
+    assert!(session.try_wait().unwrap().is_none());
+    let start = std::time::Instant::now();
+    assert!(session
+        .wait_timeout(Duration::from_millis(100))
+        .await
+        .unwrap()
+        .is_none());
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // An unrepresentable timeout waits without a deadline
+    let status = session.wait_timeout(Duration::MAX).await.unwrap();
+    assert_eq!(status.unwrap().exit_code(), 3);
+    // The handle is kept, so the status can be read again
+    assert_eq!(session.try_wait().unwrap().unwrap().exit_code(), 3);
+    assert_eq!(session.wait().await.unwrap().exit_code(), 3);
+    assert!(matches!(
+        session.try_wait(),
+        Err(ExpectError::ProcessExited)
+    ));
+}
+
+#[tokio::test]
+async fn test_drop_policy() {
+    use expectrust::DropPolicy;
+
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+
+    // Killed and reaped despite ignoring the hangup
+    let mut session = Session::builder()
+        .drop_policy(DropPolicy::Kill)
+        .spawn_shell("trap '' HUP; echo ready; exec sleep 30")
+        .expect("Failed to spawn shell");
+    session.expect(Pattern::exact("ready")).await.unwrap();
+    let pid = session.pid().unwrap();
+    drop(session);
+    assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+
+    let session = Session::builder()
+        .drop_policy(DropPolicy::Detach)
+        .spawn(&cmd::sleep(30))
+        .expect("Failed to spawn sleep");
+    let pid = session.pid().unwrap();
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+    assert!(stat.contains(") S "), "{}", stat);
+    std::process::Command::new("kill")
+        .arg(pid.to_string())
+        .status()
+        .unwrap();
+
+    // A detached process reading its input is not sent end of file
+    let session = Session::builder()
+        .drop_policy(DropPolicy::Detach)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    let pid = session.pid().unwrap();
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+    assert!(stat.contains(") S "), "{}", stat);
+    std::process::Command::new("kill")
+        .arg(pid.to_string())
+        .status()
+        .unwrap();
+
+    // A hung up process is reaped rather than left as a zombie
+    let session = Session::spawn("cat").expect("Failed to spawn cat");
+    let pid = session.pid().unwrap();
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+}
+
+#[tokio::test]
+async fn test_resize() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .screen(true)
+        .spawn_shell("trap 'stty size' WINCH; echo ready; while :; do sleep 0.05; done")
+        .expect("Failed to spawn shell");
+    session.expect(Pattern::exact("ready")).await.unwrap();
+
+    session.resize(30, 100).unwrap();
+    assert_eq!(session.screen().unwrap().size(), (30, 100));
+    session.expect(Pattern::exact("30 100")).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_env_and_cwd() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let dir = std::env::temp_dir();
+    let mut session = Session::builder()
+        .clear_env()
+        .env("PATH", "/usr/bin:/bin")
+        .env("GREETING", "hello")
+        .env("REMOVED", "x")
+        .env_remove("REMOVED")
+        .cwd(&dir)
+        .spawn_shell("echo \"[$GREETING|${HOME:-none}|${REMOVED:-gone}]\"; pwd")
+        .expect("Failed to spawn shell");
+    session
+        .expect(Pattern::exact("[hello|none|gone]"))
+        .await
+        .unwrap();
+    let dir = dir.canonicalize().unwrap();
+    session
+        .expect(Pattern::exact(dir.to_str().unwrap()))
+        .await
+        .unwrap();
+
+    let result = Session::builder()
+        .cwd("/nonexistent/directory")
+        .spawn(&cmd::echo("hi"));
+    assert!(matches!(result, Err(ExpectError::SpawnError(_))));
+}
+
+#[tokio::test]
+async fn test_spawn_args_verbatim() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::spawn_args("printf", ["[%s]\\n", "two  words", "\"quoted\""])
+        .expect("Failed to spawn printf");
+    session
+        .expect(Pattern::exact("[two  words]"))
+        .await
+        .unwrap();
+    session
+        .expect(Pattern::exact("[\"quoted\"]"))
+        .await
+        .unwrap();
+
+    let empty: [&str; 0] = [];
+    assert!(matches!(
+        Session::spawn_args("", empty),
+        Err(ExpectError::SpawnError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_spawn_command() {
+    use expectrust::CommandBuilder;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut cmd = CommandBuilder::new("sh");
+    cmd.args(["-c", "echo \"$FROM_CMD $FROM_BUILDER\""]);
+    cmd.env("FROM_CMD", "command");
+    let mut session = Session::builder()
+        .env("FROM_BUILDER", "builder")
+        .spawn_command(cmd)
+        .expect("Failed to spawn command");
+    session
+        .expect(Pattern::exact("command builder"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_log_file() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let path = std::env::temp_dir().join(format!("expectrust-log-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut session = Session::builder()
+        .log_file(&path)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    session.send_line("logged").await.unwrap();
+    session.expect(Pattern::exact("logged")).await.unwrap();
+    drop(session);
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(log.contains("> automation \"logged\""));
+    assert!(log.contains("< process \"logged"));
+
+    let missing = std::env::temp_dir().join("expectrust-no-such-dir/session.log");
+    assert!(matches!(
+        Session::builder().log_file(missing).spawn("cat"),
+        Err(ExpectError::IoError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_output_and_input_observers() {
+    use std::sync::{Arc, Mutex};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let input = Arc::new(Mutex::new(Vec::new()));
+    let mut session = Session::builder()
+        .strip_ansi(true)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    let seen = output.clone();
+    session.on_output(move |bytes| seen.lock().unwrap().extend_from_slice(bytes));
+    let sent = input.clone();
+    session.on_input(move |bytes| sent.lock().unwrap().extend_from_slice(bytes));
+
+    session.send_line("observed").await.unwrap();
+    session.expect(Pattern::exact("observed")).await.unwrap();
+    assert_eq!(*input.lock().unwrap(), b"observed\n");
+    assert!(String::from_utf8_lossy(&output.lock().unwrap()).contains("observed"));
+
+    session.clear_observers();
+    session.send_line("ignored").await.unwrap();
+    session.expect(Pattern::exact("ignored")).await.unwrap();
+    assert_eq!(*input.lock().unwrap(), b"observed\n");
+    assert!(!String::from_utf8_lossy(&output.lock().unwrap()).contains("ignored"));
+}
+
+#[tokio::test]
+async fn test_expect_any_of_sessions() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut quiet = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    let mut talker = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    talker.send_line("status: ERROR").await.unwrap();
+
+    let patterns = [Pattern::exact("OK"), Pattern::exact("ERROR")];
+    let (index, result) = Session::expect_any_of(&mut [&mut quiet, &mut talker], &patterns)
+        .await
+        .expect("One session matches");
+    assert_eq!(index, 1);
+    assert_eq!(result.pattern_index, 1);
+
+    // A session reaching EOF drops out unless Eof is expected
+    let mut done = Session::spawn("echo bye").expect("Failed to spawn echo");
+    quiet.send_line("OK").await.unwrap();
+    let (index, _) = Session::expect_any_of(&mut [&mut done, &mut quiet], &patterns)
+        .await
+        .expect("The cat session matches");
+    assert_eq!(index, 1);
+}
+
+#[tokio::test]
+async fn test_attach_existing_pty() {
+    use expectrust::CommandBuilder;
+    use portable_pty::{native_pty_system, PtySize};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let pair = native_pty_system()
+        .openpty(PtySize::default())
+        .expect("Failed to open PTY");
+    let mut child = pair
+        .slave
+        .spawn_command(CommandBuilder::new("cat"))
+        .expect("Failed to spawn cat");
+    drop(pair.slave);
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .attach_pty(pair.master)
+        .expect("Failed to attach");
+    session.send_line("attached").await.unwrap();
+    session.expect(Pattern::exact("attached")).await.unwrap();
+    assert!(matches!(
+        session.wait().await,
+        Err(ExpectError::ProcessExited)
+    ));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_attach_fd() {
+    use std::io::{Read, Write};
+    use std::os::fd::OwnedFd;
+    use std::os::unix::net::UnixStream;
+
+    let (ours, mut theirs) = UnixStream::pair().unwrap();
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .attach_fd(OwnedFd::from(ours))
+        .expect("Failed to attach");
+    assert!(session.as_raw_fd().is_some());
+
+    theirs.write_all(b"login: ").unwrap();
+    session.expect(Pattern::exact("login: ")).await.unwrap();
+    session.send(b"admin\n").await.unwrap();
+    let mut typed = [0u8; 6];
+    theirs.read_exact(&mut typed).unwrap();
+    assert_eq!(&typed, b"admin\n");
+
+    drop(theirs);
+    session.expect(Pattern::Eof).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_settle() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn_shell("echo banner one; echo banner two; sleep 0.2; echo late; exec cat")
+        .expect("Failed to spawn shell");
+    let discarded = session
+        .settle(Duration::from_secs(5), Duration::from_millis(400))
+        .await
+        .unwrap();
+    assert!(discarded.contains("banner two"));
+    assert!(discarded.contains("late"));
+
+    session.send_line("after").await.unwrap();
+    let result = session.expect(Pattern::exact("after")).await.unwrap();
+    assert!(!result.before.contains("banner"));
+
+    let mut session = Session::spawn_shell("echo motd; echo '$ '; echo rest; exec cat")
+        .expect("Failed to spawn shell");
+    let discarded = session
+        .settle_until(
+            &Pattern::exact("$ "),
+            Duration::from_secs(5),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert!(discarded.starts_with("motd"));
+    assert!(discarded.ends_with("$ "));
+    session.expect(Pattern::exact("rest")).await.unwrap();
+
+    let mut noisy = Session::spawn_shell("yes noise").expect("Failed to spawn yes");
+    assert!(matches!(
+        noisy
+            .settle(Duration::from_millis(300), Duration::from_millis(100))
+            .await,
+        Err(ExpectError::Timeout { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_term() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .env("TERM", "vt100")
+        .term("dumb")
+        .spawn_shell("echo \"[$TERM]\"")
+        .expect("Failed to spawn shell");
+    session.expect(Pattern::exact("[dumb]")).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_force_locale() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .env("LANGUAGE", "de")
+        .force_locale("C")
+        .spawn_shell("echo \"[$LC_ALL|$LANG|${LANGUAGE:-unset}]\"")
+        .expect("Failed to spawn shell");
+    session.expect(Pattern::exact("[C|C|unset]")).await.unwrap();
+}
+
+#[cfg(feature = "telnet")]
+#[tokio::test]
+async fn test_telnet() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    const IAC: u8 = 255;
+    const WILL: u8 = 251;
+    const DO: u8 = 253;
+    const SB: u8 = 250;
+    const SE: u8 = 240;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .write_all(&[IAC, WILL, 1, IAC, DO, 31, b'l', b'o', b'g', IAC, IAC])
+            .unwrap();
+        stream.write_all(b"in: ").unwrap();
+
+        let mut received = Vec::new();
+        let mut chunk = [0u8; 64];
+        while !received.ends_with(b"done\n") {
+            let n = stream.read(&mut chunk).unwrap();
+            assert!(n > 0, "connection closed early");
+            received.extend_from_slice(&chunk[..n]);
+        }
+        received
+    });
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .pty_size(30, 100)
+        .open_telnet(addr)
+        .expect("Failed to connect");
+    let m = session.expect(Pattern::exact("in: ")).await.unwrap();
+    assert_eq!(m.before, "log\u{fffd}");
+    session.send_line("admin").await.unwrap();
+    // The server asked for the window size, so it hears about the change
+    session.resize(40, 120).unwrap();
+    session.send_line("done").await.unwrap();
+
+    let received = server.join().unwrap();
+    let negotiation = [
+        IAC, DO, 1, IAC, WILL, 31, IAC, SB, 31, 0, 100, 0, 30, IAC, SE,
+    ];
+    assert_eq!(&received[..negotiation.len()], negotiation);
+    assert_eq!(
+        &received[negotiation.len()..],
+        [
+            &b"admin\n"[..],
+            &[IAC, SB, 31, 0, 120, 0, 40, IAC, SE],
+            b"done\n"
+        ]
+        .concat()
+    );
+
+    session.expect(Pattern::Eof).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_timeout_screenshot() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(300))
+        .screen(true)
+        .spawn_shell("printf '\\033[2J\\033[H  Main Menu\\r\\n\\r\\n> Settings'; exec cat")
+        .expect("Failed to spawn shell");
+    session.expect(Pattern::exact("Settings")).await.unwrap();
+
+    let error = session.expect(Pattern::exact("Quit")).await.unwrap_err();
+    assert!(matches!(error, ExpectError::Timeout { .. }));
+    assert_eq!(
+        session.timeout_screen().unwrap().trim_end(),
+        "  Main Menu\n\n> Settings"
+    );
+    let report = session.report(&error).to_string();
+    assert!(report.contains("  screen:\n    |   Main Menu\n"));
+
+    // Without the screen model there is nothing to show
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(100))
+        .spawn("cat")
+        .expect("Failed to spawn");
+    let error = session.expect(Pattern::exact("Quit")).await.unwrap_err();
+    assert!(matches!(error, ExpectError::Timeout { .. }));
+    assert!(session.timeout_screen().is_none());
+    assert!(!session.report(&error).to_string().contains("screen"));
+}
+
+#[tokio::test]
+async fn test_serve_events() {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn("cat")
+        .expect("Failed to spawn");
+    let addr = session.serve_events("127.0.0.1:0").await.unwrap();
+    let viewer = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut lines = BufReader::new(viewer).lines();
+    // Let the viewer subscribe before anything happens
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    session.send(b"say \"hi\"\n").await.unwrap();
+    session.expect(Pattern::exact("hi\"")).await.unwrap();
+
+    let mut events = Vec::new();
+    while !events
+        .last()
+        .is_some_and(|e: &String| e.contains("\"match\""))
+    {
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        events.push(line);
+    }
+    assert!(events[0].contains(r#""event":"input","origin":"automation","data":"say \"hi\"\n""#));
+    assert!(events.iter().any(|e| e.contains(r#""event":"output""#)));
+    assert!(events
+        .last()
+        .unwrap()
+        .ends_with(r#""event":"match","pattern_index":0,"matched":"hi\""}"#));
+
+    // Dropping the session disconnects viewers
+    drop(session);
+    let end = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(end, None);
+}
+
+#[test]
+fn test_blocking_session() {
+    use expectrust::blocking;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = blocking::Session::new(
+        Session::builder()
+            .timeout(Duration::from_secs(5))
+            .spawn("cat")
+            .expect("Failed to spawn"),
+    )
+    .unwrap();
+    assert!(session.is_alive().unwrap());
+
+    session.send_line("hello blocking").unwrap();
+    let result = session.expect(Pattern::exact("blocking")).unwrap();
+    assert!(result.before.contains("hello"));
+
+    session.send_control('d').unwrap();
+    session.expect(Pattern::Eof).unwrap();
+    assert!(session.wait().unwrap().success());
+}
+
+#[cfg(feature = "server")]
+#[tokio::test]
+async fn test_control_server() {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpStream;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    async fn request(
+        addr: std::net::SocketAddr,
+        method: &str,
+        target: &str,
+        body: &str,
+    ) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let head = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            method,
+            target,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(body.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(expectrust::server::serve(listener));
+
+    let (status, body) = request(addr, "POST", "/sessions?timeout_ms=5000", "cat").await;
+    assert_eq!((status, body.as_str()), (201, r#"{"id":1}"#));
+
+    // Follow the session's events
+    let mut events = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    events
+        .get_mut()
+        .write_all(b"GET /sessions/1/events HTTP/1.1\r\n\r\n")
+        .await
+        .unwrap();
+    let mut line = String::new();
+    events.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "HTTP/1.1 200 OK\r\n");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let (status, _) = request(addr, "POST", "/sessions/1/send", "hello 42\n").await;
+    assert_eq!(status, 204);
+    let (status, body) = request(
+        addr,
+        "POST",
+        "/sessions/1/expect?kind=regex",
+        r"hello (\d+)",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body.contains(r#""captures":["hello 42","42"]"#), "{}", body);
+
+    let (status, body) = request(addr, "POST", "/sessions/1/expect?timeout_ms=100", "never").await;
+    assert_eq!(status, 408);
+    assert!(body.starts_with(r#"{"error":"Timeout"#));
+    let (status, _) = request(addr, "POST", "/sessions/1/expect?kind=regex", "(").await;
+    assert_eq!(status, 400);
+
+    let mut seen = String::new();
+    while !seen.contains(r#""event":"match""#) {
+        line.clear();
+        events.read_line(&mut line).await.unwrap();
+        seen.push_str(&line);
+    }
+    assert!(seen.contains(r#"data: {"t":"#));
+    assert!(seen.contains(r#""event":"input","origin":"automation","data":"hello 42\n""#));
+
+    // Output produced while no client is attached is collected for the
+    // next one
+    let (status, body) = request(
+        addr,
+        "POST",
+        "/sessions?transcript=true",
+        "sleep 0.3; echo finished while detached; exec cat",
+    )
+    .await;
+    assert_eq!((status, body.as_str()), (201, r#"{"id":2}"#));
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    let (status, body) = request(addr, "GET", "/sessions/2", "").await;
+    assert_eq!(status, 200);
+    assert!(body.contains(r#""alive":true"#), "{}", body);
+    assert!(body.contains("finished while detached"), "{}", body);
+    let (status, body) = request(addr, "GET", "/sessions/2/transcript", "").await;
+    assert_eq!(status, 200);
+    assert!(
+        body.contains("< process \"finished while detached"),
+        "{}",
+        body
+    );
+    let (status, _) = request(addr, "GET", "/sessions/1/transcript", "").await;
+    assert_eq!(status, 409);
+    let (status, body) = request(addr, "GET", "/sessions", "").await;
+    assert_eq!(status, 200);
+    assert!(body.starts_with(r#"{"sessions":[{"id":1,"command":"cat"},{"id":2,"#));
+
+    let (status, body) = request(addr, "DELETE", "/sessions/1", "").await;
+    assert_eq!(status, 200);
+    assert!(body.starts_with(r#"{"exit_code":"#));
+    let (status, _) = request(addr, "POST", "/sessions/1/send", "x").await;
+    assert_eq!(status, 404);
+
+    // The event stream ends with the session
+    let mut rest = String::new();
+    tokio::time::timeout(Duration::from_secs(5), events.read_to_string(&mut rest))
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_expect_opts() {
+    use expectrust::{Consume, ExpectOptions};
+
+    let mut session =
+        Session::spawn(&cmd::echo("Login OK: READY then ready")).expect("Failed to spawn");
+
+    let ignore_case = ExpectOptions {
+        case_insensitive: true,
+        consume: Consume::Nothing,
+        ..Default::default()
+    };
+    let result = session
+        .expect_opts(Pattern::exact("ready"), ignore_case)
+        .await
+        .unwrap();
+    assert_eq!(result.matched, "READY");
+    let result = session
+        .expect_opts(Pattern::glob("login*:"), ignore_case)
+        .await
+        .unwrap();
+    assert_eq!(result.matched, "Login OK:");
+
+    // Only the end of the output is searched
+    session
+        .expect_consuming(Pattern::exact("ready"), Consume::Nothing)
+        .await
+        .unwrap();
+    let recent = ExpectOptions {
+        search_window: Some(8),
+        timeout: Some(Duration::from_millis(200)),
+        case_insensitive: true,
+        ..Default::default()
+    };
+    // "OK" is further back, so the search ends at EOF or the timeout
+    let result = session.expect_opts(Pattern::exact("ok"), recent).await;
+    assert!(result.is_err());
+    let result = session
+        .expect_opts(Pattern::exact("ready"), recent)
+        .await
+        .unwrap();
+    assert_eq!(result.matched, "ready");
+}
+
+#[tokio::test]
+async fn test_log_rate_limit() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let path =
+        std::env::temp_dir().join(format!("expectrust-log-limit-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut session = Session::builder()
+        .log_file(&path)
+        .log_rate_limit(100, Duration::from_secs(3600))
+        .spawn_shell("seq 1 2000; echo finished")
+        .expect("Failed to spawn");
+    session.expect(Pattern::exact("finished")).await.unwrap();
+    let suppressed = session.log_suppressed();
+    assert!(suppressed > 8000, "suppressed {} bytes", suppressed);
+    drop(session);
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(log.contains("< process \"1\\r\\n2\\r\\n"));
+    assert!(!log.contains("finished"));
+    assert!(log.contains(" bytes of process output"));
+}
+
+#[tokio::test]
+async fn test_peek() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::spawn("cat").expect("Failed to spawn cat");
+    session.send_line("pending").await.unwrap();
+    session.expect(Pattern::exact("pending")).await.unwrap();
+    session.send_line("more output").await.unwrap();
+
+    // Output arrives without an expect call draining it
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while !session.peek().unwrap().contains("more output") {
+        assert!(std::time::Instant::now() < deadline, "output never arrived");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // Peeking consumes nothing
+    assert_eq!(session.peek().unwrap(), session.peek().unwrap());
+    let result = session.expect(Pattern::exact("output")).await.unwrap();
+    assert!(result.before.contains("more "));
+}
+
+#[tokio::test]
+async fn test_buffer_control() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(500))
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    session.send_line("banner error").await.unwrap();
+    session.expect(Pattern::exact("banner")).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Stale output, read or not, no longer matches
+    session.clear_buffer().unwrap();
+    assert_eq!(session.buffer_len(), 0);
+    assert!(session.expect(Pattern::exact("error")).await.is_err());
+
+    session.send_line("fresh").await.unwrap();
+    session.expect(Pattern::exact("fresh")).await.unwrap();
+    let pending = session.take_buffer().unwrap();
+    assert!(!pending.contains("banner"), "{:?}", pending);
+    assert_eq!(session.buffer_len(), 0);
+}
+
+#[tokio::test]
+async fn test_critical_patterns() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session =
+        Session::spawn_shell("echo starting; echo 'thread main panicked at x'; echo listening")
+            .expect("Failed to spawn");
+    session
+        .add_critical_pattern(Pattern::exact("Segmentation fault"))
+        .unwrap();
+    session
+        .add_critical_pattern(Pattern::regex(r"panicked at \w+").unwrap())
+        .unwrap();
+    assert!(session.add_critical_pattern(Pattern::Eof).is_err());
+    assert_eq!(session.critical_patterns().count(), 2);
+
+    // The expected text follows the panic, but the panic wins
+    let error = session
+        .expect(Pattern::exact("listening"))
+        .await
+        .unwrap_err();
+    match &error {
+        ExpectError::CriticalOutput {
+            pattern_index,
+            matched,
+        } => {
+            assert_eq!(*pattern_index, 1);
+            assert_eq!(matched, "panicked at x");
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(error.exit_code(), 8);
+
+    // The critical output was consumed
+    session.expect(Pattern::exact("listening")).await.unwrap();
+    session.clear_critical_patterns();
+    assert_eq!(session.critical_patterns().count(), 0);
+}
+
+#[tokio::test]
+async fn test_match_result_bytes() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::spawn_shell(r"printf '\377\376BOOT>\200'").expect("Failed to spawn");
+    let result = session.expect(Pattern::exact("BOOT>")).await.unwrap();
+    assert!(result.before_bytes.ends_with(&[0xff, 0xfe]));
+    assert!(result.before.ends_with("\u{fffd}\u{fffd}"));
+    assert_eq!(result.matched_bytes, b"BOOT>");
+    let debug = format!("{:?}", result);
+    assert!(debug.contains(r#"before: "\xff\xfe""#), "{}", debug);
+    assert_eq!(
+        result.to_string(),
+        format!("\"BOOT>\" at {}..{}", result.start, result.end)
+    );
+
+    let result = session.expect(Pattern::Eof).await.unwrap();
+    assert!(result.before_bytes.contains(&0x80));
+    assert!(format!("{:?}", result).contains(r"\x80"));
+}
+
+#[tokio::test]
+async fn test_regex_bytes_captures() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session =
+        Session::spawn_shell(r"printf '\377HDR\001\376\002END'").expect("Failed to spawn");
+    let frame = Pattern::regex_bytes(r"(?-u)HDR(.)(.)(.)END").unwrap();
+    let result = session.expect(frame).await.unwrap();
+    assert_eq!(result.captures_bytes[1], [0x01]);
+    assert_eq!(result.captures_bytes[2], [0xfe]);
+    assert_eq!(result.captures_bytes[3], [0x02]);
+    assert_eq!(result.before_bytes, [0xff]);
+}
+
+#[tokio::test]
+async fn test_history_file() {
+    use expectrust::{ExpectOutcome, History};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let path = std::env::temp_dir().join(format!("expectrust-{}.history", std::process::id()));
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(300))
+        .history_file(&path)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+    session.send_line("one two").await.unwrap();
+    session.expect(Pattern::exact("two")).await.unwrap();
+    session.expect(Pattern::exact("three")).await.unwrap_err();
+    drop(session);
+
+    let history = History::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(history.expects().len(), 2);
+
+    let first = history.expect_call(1).unwrap();
+    assert_eq!(first.patterns, ["Exact(\"two\")"]);
+    let ExpectOutcome::Matched { range, .. } = &first.outcome else {
+        panic!("first expect failed: {:?}", first.outcome);
+    };
+    assert_eq!(&first.buffer[range.clone()], b"two");
+    assert!(first.buffer.starts_with(b"one two"));
+
+    let second = history.expect_call(2).unwrap();
+    assert!(
+        matches!(&second.outcome, ExpectOutcome::Failed(message) if message.contains("Timeout"))
+    );
+    assert!(!second.buffer.starts_with(b"one"));
+    assert!(history.output_at(1).unwrap().len() <= history.output().len());
+    assert!(history.output().starts_with(b"one two"));
+}
+
+#[tokio::test]
+async fn test_session_group_branches() {
+    use expectrust::SessionGroup;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let sessions = (0..2)
+        .map(|_| Session::builder().transcript(true).spawn("cat"))
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Failed to spawn cat");
+    let mut group = SessionGroup::new(sessions);
+
+    group.broadcast_send_line("login").await.unwrap();
+    group.all_expect(Pattern::exact("login")).await.unwrap();
+
+    let results = group
+        .branch(async |index, session| {
+            let path = if index == 0 { "path-a" } else { "path-b" };
+            session.send_line(&format!("upgrade {}", path)).await?;
+            session.expect(Pattern::exact(path)).await?;
+            Ok(path)
+        })
+        .await;
+    let paths: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(paths, ["path-a", "path-b"]);
+
+    let report = group.transcript_report();
+    assert!(report.starts_with("shared:\n"), "{}", report);
+    assert_eq!(
+        report.matches("> automation \"login").count(),
+        1,
+        "{}",
+        report
+    );
+    let (first, second) = report.split_once("session 1:").unwrap();
+    assert!(first.contains("upgrade path-a") && !first.contains("path-b"));
+    assert!(second.contains("upgrade path-b"));
+}
+
+#[tokio::test]
+async fn test_read_lines() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session =
+        Session::spawn("printf 'first\\r\\nsecond\\nthird'").expect("Failed to spawn printf");
+    assert_eq!(session.read_line().await.unwrap(), "first");
+
+    let mut lines = session.lines();
+    assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("second"));
+    assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("third"));
+    assert_eq!(lines.next_line().await.unwrap(), None);
+    assert!(matches!(session.read_line().await, Err(ExpectError::Eof)));
+}
+
+#[tokio::test]
+async fn test_sftp_driver() {
+    use expectrust::{SftpDriver, TransferError};
+    use std::sync::{Arc, Mutex};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&format!(
+            "sh {}/tests/fixtures/fake_sftp.sh",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("Failed to spawn");
+    let mut sftp = SftpDriver::new(session);
+    sftp.connect(None).await.unwrap();
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let seen = updates.clone();
+    sftp.on_progress(move |p| seen.lock().unwrap().push(p.percent));
+    let last = sftp.put("a.txt", "/srv/a.txt").await.unwrap().unwrap();
+    assert_eq!(last.percent, 100);
+    assert_eq!(last.transferred, "1024KB");
+    assert_eq!(*updates.lock().unwrap(), [50, 100]);
+
+    match sftp.put("missing.txt", "/srv/").await {
+        Err(TransferError::Failed { message, .. }) => {
+            assert_eq!(message, "stat missing.txt: No such file or directory")
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    assert_eq!(sftp.ls("/srv").await.unwrap(), ["a.txt", "b.txt"]);
+    sftp.quit().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_classify_prompt() {
+    use expectrust::{PromptClassifier, PromptEvent};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let classifier = PromptClassifier::password()
+        .without_prompt(Pattern::exact("$ "))
+        .unwrap();
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(&format!(
+            "sh {}/tests/fixtures/fake_sudo.sh",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("Failed to spawn");
+
+    let event = session.classify_prompt(&classifier).await.unwrap();
+    assert_eq!(event, PromptEvent::Password);
+    session.send_line("wrong").await.unwrap();
+    let event = session.classify_prompt(&classifier).await.unwrap();
+    assert_eq!(event, PromptEvent::Retry);
+    session.send_line("secret").await.unwrap();
+    let event = session.classify_prompt(&classifier).await.unwrap();
+    assert_eq!(event, PromptEvent::NoPrompt);
+
+    // Cached: the next command asks for nothing
+    session.send_line("sudo ls").await.unwrap();
+    let event = session.classify_prompt(&classifier).await.unwrap();
+    assert_eq!(event, PromptEvent::NoPrompt);
+}
+
+#[tokio::test]
+async fn test_expect_line() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(500))
+        .spawn("printf 'ready:\\nok\\nstatus:\\x20ok\\npartial\\x20ok'")
+        .expect("Failed to spawn printf");
+
+    // "ready:\nok" would match across lines; the anchored regex only fits
+    // a whole line
+    let result = session
+        .expect_line(Pattern::regex(r"^\w+: ok$").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(result.matched, "status: ok");
+    // The last line has no line feed, so it is never complete
+    assert!(session
+        .expect_line(Pattern::exact("partial"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_echo_output() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .echo_output(true)
+        .spawn("echo mirrored")
+        .expect("Failed to spawn echo");
+    assert!(session.echo_output());
+    // Echoing does not take output away from matching
+    session.expect(Pattern::exact("mirrored")).await.unwrap();
+    session.set_echo_output(false);
+    assert!(!session.echo_output());
+    session.expect(Pattern::Eof).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_learn_prompt() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .env("PS1", "custom-prompt% ")
+        .spawn("sh -i")
+        .expect("Failed to spawn sh");
+    assert!(session.prompt().is_none());
+
+    session.learn_prompt().await.unwrap();
+    session.send_line("echo learned").await.unwrap();
+    let result = session.expect_prompt().await.unwrap();
+    assert_eq!(result.matched, "custom-prompt% ");
+    assert!(result.before.contains("learned"));
+}
+
+#[tokio::test]
+async fn test_expect_prompt_learns_prompt() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .env("PS1", "[1] auto> ")
+        .spawn("sh -i")
+        .expect("Failed to spawn sh");
+    let result = session.expect_prompt().await.unwrap();
+    assert_eq!(result.matched, "[1] auto> ");
+    assert!(session.prompt().is_some());
+
+    let output = session.run_command("echo again").await.unwrap();
+    assert_eq!(output.body.trim(), "again");
+}
+
+#[tokio::test]
+async fn test_shell_command_quoting() {
+    use expectrust::shell_command;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let hostile = "a  b; echo injected";
+    let mut session = Session::spawn_shell(&shell_command!("printf '[%s]\\n' {}", hostile))
+        .expect("Failed to spawn shell");
+    let result = session.expect(Pattern::Eof).await.unwrap();
+    assert!(result.before.contains("[a  b; echo injected]"));
+    assert!(!result.before.contains("\ninjected"));
+}
+
+#[tokio::test]
+async fn test_run_command_with_limit() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .env("PS1", "limit% ")
+        .spawn("sh -i")
+        .expect("Failed to spawn sh");
+    session.learn_prompt().await.unwrap();
+
+    let output = session.run_command("echo small").await.unwrap();
+    assert_eq!(output.echo.as_deref(), Some("echo small"));
+    assert_eq!(output.body.trim(), "small");
+    assert_eq!(output.prompt, "limit% ");
+    assert!(String::from_utf8_lossy(&output.raw).ends_with("small\r\nlimit% "));
+
+    let result = session.run_command_with_limit("yes flood", 10_000).await;
+    match result {
+        Err(ExpectError::OutputLimit { limit, received }) => {
+            assert_eq!(limit, 10_000);
+            assert!(received > 10_000);
+        }
+        other => panic!("expected OutputLimit, got {:?}", other.map(|_| ())),
+    }
+
+    // Interrupted and back at the prompt
+    let output = session.run_command("echo after").await.unwrap();
+    assert_eq!(output.body.trim(), "after");
+}
+
+#[tokio::test]
+async fn test_run_command_wider_than_terminal() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .env("PS1", "w$ ")
+        .env("TERM", "xterm")
+        .pty_size(24, 20)
+        .spawn("bash --norc --noprofile -i")
+        .expect("Failed to spawn bash");
+    session.learn_prompt().await.unwrap();
+
+    // bash redraws a character around each wrap of the echoed line
+    let command = "echo aaaaaaaaaa bbbbbbbbbb cccccccccc dddd";
+    let output = session.run_command(command).await.unwrap();
+    assert_eq!(output.echo.as_deref(), Some(command));
+    assert!(output
+        .body
+        .ends_with("aaaaaaaaaa bbbbbbbbbb cccccccccc dddd\r\n"));
+    assert!(!output.body.contains("echo"));
+}
+
+#[tokio::test]
+async fn test_run_command_soft_timeout() {
+    use expectrust::ExpectOptions;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(300))
+        .env("PS1", "soft% ")
+        .spawn("sh -i")
+        .expect("Failed to spawn sh");
+    let startup = ExpectOptions {
+        timeout: Some(Duration::from_secs(5)),
+        ..Default::default()
+    };
+    let prompt = Pattern::exact("soft% ");
+    session.expect_opts(prompt.clone(), startup).await.unwrap();
+    session.set_prompt(prompt);
+
+    // The command outlasts the timeout but keeps printing, so the hook
+    // extends the wait for the prompt
+    session.on_soft_timeout(Duration::from_secs(5), |soft| {
+        soft.output
+            .contains(&b'%')
+            .then_some(Duration::from_millis(300))
+    });
+    let output = session
+        .run_command("for i in 1 2 3 4 5; do echo $i%; sleep 0.15; done")
+        .await
+        .unwrap();
+    assert!(output.body.contains("5%"));
+}
+
+#[tokio::test]
+async fn test_run_command_status() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .env("PS1", "status$ ")
+        // Keep readline from wrapping output in bracketed paste sequences
+        .env("TERM", "dumb")
+        .spawn("bash --norc --noprofile -i")
+        .expect("Failed to spawn bash");
+    session.learn_prompt().await.unwrap();
+
+    let output = session.run_command_status("echo ok; false").await.unwrap();
+    assert_eq!(output.exit_code, Some(1));
+    assert_eq!(output.echo.as_deref(), Some("echo ok; false"));
+    assert_eq!(output.body.trim(), "ok");
+
+    let output = session.run_command_status("true").await.unwrap();
+    assert_eq!(output.exit_code, Some(0));
+    assert_eq!(output.body, "");
+
+    let output = session.run_command_status("(exit 42)").await.unwrap();
+    assert_eq!(output.exit_code, Some(42));
+
+    // Plain run_command does not ask for the status
+    let output = session.run_command("true").await.unwrap();
+    assert_eq!(output.exit_code, None);
+}
+
+#[tokio::test]
+async fn test_send_secret() {
+    use expectrust::{ExpectError, Origin};
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .transcript(true)
+        .spawn(&format!(
+            "sh {}/tests/fixtures/fake_sudo.sh",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("Failed to spawn");
+
+    session
+        .expect(Pattern::exact("password for admin: "))
+        .await
+        .unwrap();
+    session.send_secret("secret").await.unwrap();
+    session.expect(Pattern::exact("done")).await.unwrap();
+
+    let transcript = session.transcript().unwrap();
+    assert_eq!(transcript.bytes_from(Origin::Automation), b"****\n");
+
+    // The terminal echoed the password; the report hides it
+    let report = session.report(&ExpectError::Eof).to_string();
+    assert!(report.contains("****"), "{}", report);
+    assert!(!report.contains("secret"), "{}", report);
+
+    // Including when the error message quotes the output
+    let error = ExpectError::UnexpectedOutput {
+        output: "secret\r\n".to_string(),
+    };
+    let report = session.report(&error).to_string();
+    assert!(!report.contains("secret"), "{}", report);
+}
+
+#[tokio::test]
+async fn test_send_secret_redacts_errors() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(500))
+        .screen(true)
+        .spawn("cat")
+        .expect("Failed to spawn cat");
+
+    // The terminal echoes the secret and cat prints it again
+    session.send_secret("hunter2").await.unwrap();
+    match session.assert_no_output(Duration::from_secs(2)).await {
+        Err(ExpectError::UnexpectedOutput { output }) => {
+            assert!(output.contains("****"), "{:?}", output);
+            assert!(!output.contains("hunter2"), "{:?}", output);
+        }
+        other => panic!("expected UnexpectedOutput, got {:?}", other),
+    }
+
+    let result = session.expect(Pattern::exact("never")).await;
+    assert!(matches!(result, Err(ExpectError::Timeout { .. })));
+    let screen = session.timeout_screen().unwrap();
+    assert!(screen.contains("****"), "{}", screen);
+    assert!(!screen.contains("hunter2"), "{}", screen);
+
+    session
+        .add_critical_pattern(Pattern::exact("hunter2"))
+        .unwrap();
+    session.send_secret("hunter2").await.unwrap();
+    match session.expect(Pattern::exact("never")).await {
+        Err(ExpectError::CriticalOutput { matched, .. }) => assert_eq!(matched, "****"),
+        other => panic!("expected CriticalOutput, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_spawn_std_command() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut cmd = std::process::Command::new("sh");
+    cmd.args(["-c", "echo \"$GREETING from $(pwd)\""])
+        .env("GREETING", "hello")
+        .current_dir("/");
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn_std_command(&cmd)
+        .expect("Failed to start command");
+    session
+        .expect(Pattern::exact("hello from /\r"))
+        .await
+        .unwrap();
+
+    let mut cmd = tokio::process::Command::new("printenv");
+    cmd.arg("GREETING").env("GREETING", "from tokio");
+    let mut session = Session::builder()
+        .spawn_tokio_command(&cmd)
+        .expect("Failed to start command");
+    session.expect(Pattern::exact("from tokio")).await.unwrap();
+
+    // Builder settings apply on top of the command's
+    let mut cmd = std::process::Command::new("printenv");
+    cmd.arg("GREETING").env("GREETING", "from command");
+    let mut session = Session::builder()
+        .env("GREETING", "from builder")
+        .spawn_std_command(&cmd)
+        .expect("Failed to start command");
+    session
+        .expect(Pattern::exact("from builder"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_spawn_honors_quotes() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(5))
+        .spawn(r#"sh -c "echo hi   there; echo 'single  quoted' it\'s""#)
+        .expect("Failed to spawn sh");
+    session
+        .expect(Pattern::exact("hi there\r\n"))
+        .await
+        .unwrap();
+    session
+        .expect(Pattern::exact("single  quoted it's"))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        Session::spawn("echo 'unclosed"),
+        Err(ExpectError::SpawnError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_soft_timeout_extends_while_progressing() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(300))
+        .spawn_shell(
+            "for i in 10 20 30 40 50 60; do echo $i%; sleep 0.15; done; echo done; sleep 5",
+        )
+        .expect("Failed to spawn shell");
+    session.on_soft_timeout(Duration::from_secs(5), |soft| {
+        soft.output
+            .contains(&b'%')
+            .then_some(Duration::from_millis(300))
+    });
+    session.expect(Pattern::exact("done")).await.unwrap();
+
+    // Silence is not progress: the hook declines
+    let start = std::time::Instant::now();
+    let result = session.expect(Pattern::exact("never")).await;
+    assert!(matches!(result, Err(ExpectError::Timeout { .. })));
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // Extensions stop at the cap
+    session.on_soft_timeout(Duration::from_millis(400), |_| {
+        Some(Duration::from_millis(300))
+    });
+    let start = std::time::Instant::now();
+    let result = session.expect(Pattern::exact("never")).await;
+    assert!(matches!(result, Err(ExpectError::Timeout { .. })));
+    assert!(start.elapsed() >= Duration::from_millis(700));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_soft_timeout_without_cap() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_millis(200))
+        .spawn_shell("sleep 0.5; echo done")
+        .expect("Failed to spawn shell");
+    session.on_soft_timeout(Duration::MAX, |_| Some(Duration::MAX));
+    session.expect(Pattern::exact("done")).await.unwrap();
+}
+
+#[cfg(feature = "steps")]
+#[tokio::test]
+async fn test_soft_timeout_in_steps_uses_step_deadline() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let path = std::env::temp_dir().join(format!("expectrust-soft-{}.toml", std::process::id()));
+    std::fs::write(&path, "[[step]]\nexpect = \"never\"\ntimeout = 1.2\n").unwrap();
+
+    let mut session = Session::spawn("cat").expect("Failed to spawn");
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    session.on_soft_timeout(Duration::from_secs(5), move |soft| {
+        assert!(soft.elapsed >= Duration::from_millis(1200));
+        counted.fetch_add(1, Ordering::SeqCst);
+        None
+    });
+    let result = session.run_steps_file(&path).await;
+    std::fs::remove_file(&path).ok();
+    assert!(matches!(result, Err(ExpectError::Timeout { .. })));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_expect_cancellable() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(10))
+        .spawn_shell("echo partial; sleep 10")
+        .expect("Failed to spawn shell");
+
+    let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let _ = cancel.send(());
+    });
+    let start = std::time::Instant::now();
+    let result = session
+        .expect_cancellable(Pattern::exact("never"), async {
+            let _ = cancelled.await;
+        })
+        .await;
+    assert!(matches!(result, Err(ExpectError::Cancelled)));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    // Output read before cancelling is still there
+    let result = session
+        .expect_cancellable(Pattern::exact("partial"), std::future::pending())
+        .await
+        .unwrap();
+    assert_eq!(result.matched, "partial");
+}
+
+#[tokio::test]
+async fn test_expect_cancellable_keeps_auto_responses() {
+    if cfg!(windows) {
+        return;
+    }
+
+    let mut session = Session::builder()
+        .timeout(Duration::from_secs(10))
+        .spawn_shell("printf 'Continue? '; read answer; echo \"got $answer\"; sleep 10")
+        .expect("Failed to spawn shell");
+    session
+        .auto_respond(Pattern::exact("Continue? "), "yes\n")
+        .unwrap();
+
+    let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let _ = cancel.send(());
+    });
+    let result = session
+        .expect_cancellable(Pattern::exact("never"), async {
+            let _ = cancelled.await;
+        })
+        .await;
+    assert!(matches!(result, Err(ExpectError::Cancelled)));
+
+    // The prompt was answered during the cancelled wait and stays answered
+    assert_eq!(session.auto_responses()[0].count(), 1);
+    assert!(!session.peek().unwrap().contains("Continue? "));
+    session.clear_auto_responses();
+    session.expect(Pattern::exact("got yes")).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_session_group_summary() {
+    use expectrust::SessionGroup;
+
+    if cfg!(windows) {
+        return;
+    }
+
+    let sessions = vec![
+        Session::spawn("echo ready").unwrap(),
+        Session::spawn("echo 'E: Could not get lock'").unwrap(),
+        Session::spawn("echo 'E: Could not get lock'").unwrap(),
+    ];
+    let mut group = SessionGroup::new(sessions);
+    group.set_labels(["web1", "web2", "web3"]);
+    group.set_timeout(Some(Duration::from_millis(500)));
+
+    let _ = group.all_expect(Pattern::exact("ready")).await;
+
+    let summary = group.summary();
+    assert_eq!(summary.hosts[0].passed, 1);
+    assert_eq!(summary.hosts[0].failed, 0);
+    let failed: Vec<_> = summary.failed_hosts().map(|h| h.label.as_str()).collect();
+    assert_eq!(failed, ["web2", "web3"]);
+    assert_eq!(
+        summary.hosts[1].first_failure.as_deref(),
+        Some("#1 all_expect \"ready\"")
+    );
+    assert_eq!(summary.failure_clusters.len(), 1);
+    assert_eq!(summary.failure_clusters[0].hosts, ["web2", "web3"]);
+    assert_eq!(summary.failure_clusters[0].tail, "E: Could not get lock");
+    assert!(summary
+        .to_string()
+        .starts_with("1 of 3 hosts passed, 2 failed\n"));
+
+    group.clear_summary();
+    assert_eq!(group.summary().failed_hosts().count(), 0);
+
+    // Errors quoting a secret are redacted
+    group
+        .session_mut(0)
+        .unwrap()
+        .send_secret("hunter2")
+        .await
+        .unwrap();
+    group
+        .branch(async |_, _| -> Result<(), ExpectError> {
+            Err(ExpectError::SpawnError("bad password hunter2".to_string()))
+        })
+        .await;
+    // Only web1 was given the secret, so only its error is redacted
+    let summary = group.summary();
+    let web1 = summary
+        .failure_clusters
+        .iter()
+        .find(|cluster| cluster.hosts == ["web1"])
+        .unwrap();
+    assert_eq!(web1.error, "Failed to spawn process: bad password ****");
+}